@@ -1,23 +1,38 @@
 // 配置管理相关命令
 
 use serde_json::Value;
-use std::fs;
+use std::sync::Arc;
+use tauri::State;
 
 use super::proxy_commands::{ProxyManagerState, TransparentProxyState};
-use super::types::ActiveConfig;
+use super::types::{ActiveConfig, ConfigSnapshots, SaveSettingsError};
 use ::duckcoding::services::config::{
     CodexSettingsPayload, GeminiEnvPayload, GeminiSettingsPayload,
 };
+use ::duckcoding::services::profile_bundle::{
+    bundle_info, BundleImportReport, BundleInfo, ConflictStrategy, ProfileRef,
+};
+use ::duckcoding::services::profile_health::{self, ProfileCheckResult};
+use ::duckcoding::services::profile_store::ProfileStore;
 use ::duckcoding::services::proxy::{ProxyConfig, TransparentProxyConfigService};
 use ::duckcoding::utils::config::{
     apply_proxy_if_configured, read_global_config, write_global_config,
 };
+use ::duckcoding::utils::vault::{Vault, VaultStatus};
+use ::duckcoding::utils::{write_atomic_with, SnapshotStore};
 use ::duckcoding::ConfigService;
 use ::duckcoding::GlobalConfig;
 use ::duckcoding::Tool;
+use ::duckcoding::{ConfigDrift, ConfigWatcher};
+use ::duckcoding::{ProfileDetails, ProfileHealth, ProfileRecord};
 
 // ==================== 类型定义 ====================
 
+// 配置文件监听器状态
+pub struct ConfigWatcherState {
+    pub watcher: Arc<ConfigWatcher>,
+}
+
 #[derive(serde::Deserialize, Debug)]
 struct TokenData {
     id: i64,
@@ -48,6 +63,11 @@ fn build_reqwest_client() -> Result<reqwest::Client, String> {
     ::duckcoding::http_client::build_client()
 }
 
+/// 默认配置档案存储（~/.duckcoding/profiles）
+fn profile_store() -> Result<ProfileStore, String> {
+    ProfileStore::default_store().map_err(|e| format!("打开配置档案存储失败: {e}"))
+}
+
 fn mask_api_key(key: &str) -> String {
     if key.len() <= 8 {
         return "****".to_string();
@@ -57,190 +77,6 @@ fn mask_api_key(key: &str) -> String {
     format!("{prefix}...{suffix}")
 }
 
-fn detect_profile_name(
-    tool: &str,
-    active_api_key: &str,
-    active_base_url: &str,
-    home_dir: &std::path::Path,
-) -> Option<String> {
-    let config_dir = match tool {
-        "claude-code" => home_dir.join(".claude"),
-        "codex" => home_dir.join(".codex"),
-        "gemini-cli" => home_dir.join(".gemini"),
-        _ => return None,
-    };
-
-    if !config_dir.exists() {
-        return None;
-    }
-
-    // 遍历配置目录，查找匹配的备份文件
-    if let Ok(entries) = fs::read_dir(&config_dir) {
-        for entry in entries.flatten() {
-            let file_name = entry.file_name();
-            let file_name_str = file_name.to_string_lossy();
-
-            // 根据工具类型匹配不同的备份文件格式
-            let profile_name = match tool {
-                "claude-code" => {
-                    // 匹配 settings.{profile}.json
-                    if file_name_str.starts_with("settings.")
-                        && file_name_str.ends_with(".json")
-                        && file_name_str != "settings.json"
-                    {
-                        file_name_str
-                            .strip_prefix("settings.")
-                            .and_then(|s| s.strip_suffix(".json"))
-                    } else {
-                        None
-                    }
-                }
-                "codex" => {
-                    // 匹配 config.{profile}.toml
-                    if file_name_str.starts_with("config.")
-                        && file_name_str.ends_with(".toml")
-                        && file_name_str != "config.toml"
-                    {
-                        file_name_str
-                            .strip_prefix("config.")
-                            .and_then(|s| s.strip_suffix(".toml"))
-                    } else {
-                        None
-                    }
-                }
-                "gemini-cli" => {
-                    // 匹配 .env.{profile}
-                    if file_name_str.starts_with(".env.") && file_name_str != ".env" {
-                        file_name_str.strip_prefix(".env.")
-                    } else {
-                        None
-                    }
-                }
-                _ => None,
-            };
-
-            if let Some(profile) = profile_name {
-                // 读取备份文件并比较内容
-                let is_match = match tool {
-                    "claude-code" => {
-                        if let Ok(content) = fs::read_to_string(entry.path()) {
-                            if let Ok(config) = serde_json::from_str::<Value>(&content) {
-                                let env_api_key = config
-                                    .get("env")
-                                    .and_then(|env| env.get("ANTHROPIC_AUTH_TOKEN"))
-                                    .and_then(|v| v.as_str());
-                                let env_base_url = config
-                                    .get("env")
-                                    .and_then(|env| env.get("ANTHROPIC_BASE_URL"))
-                                    .and_then(|v| v.as_str());
-
-                                let flat_api_key =
-                                    config.get("ANTHROPIC_AUTH_TOKEN").and_then(|v| v.as_str());
-                                let flat_base_url =
-                                    config.get("ANTHROPIC_BASE_URL").and_then(|v| v.as_str());
-
-                                let backup_api_key = env_api_key.or(flat_api_key).unwrap_or("");
-                                let backup_base_url = env_base_url.or(flat_base_url).unwrap_or("");
-
-                                backup_api_key == active_api_key
-                                    && backup_base_url == active_base_url
-                            } else {
-                                false
-                            }
-                        } else {
-                            false
-                        }
-                    }
-                    "codex" => {
-                        // 需要同时检查 config.toml 和 auth.json
-                        let auth_backup = config_dir.join(format!("auth.{profile}.json"));
-
-                        let mut api_key_matches = false;
-                        if let Ok(auth_content) = fs::read_to_string(&auth_backup) {
-                            if let Ok(auth) = serde_json::from_str::<Value>(&auth_content) {
-                                let backup_api_key = auth
-                                    .get("OPENAI_API_KEY")
-                                    .and_then(|v| v.as_str())
-                                    .unwrap_or("");
-
-                                api_key_matches = backup_api_key == active_api_key;
-                            }
-                        }
-
-                        if !api_key_matches {
-                            false
-                        } else {
-                            // API Key 匹配，继续检查 base_url
-                            if let Ok(config_content) = fs::read_to_string(entry.path()) {
-                                if let Ok(toml::Value::Table(table)) =
-                                    toml::from_str::<toml::Value>(&config_content)
-                                {
-                                    if let Some(toml::Value::Table(providers)) =
-                                        table.get("model_providers")
-                                    {
-                                        let mut url_matches = false;
-                                        for (_, provider) in providers {
-                                            if let toml::Value::Table(p) = provider {
-                                                if let Some(toml::Value::String(url)) =
-                                                    p.get("base_url")
-                                                {
-                                                    if url == active_base_url {
-                                                        url_matches = true;
-                                                        break;
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        url_matches
-                                    } else {
-                                        false
-                                    }
-                                } else {
-                                    false
-                                }
-                            } else {
-                                false
-                            }
-                        }
-                    }
-                    "gemini-cli" => {
-                        if let Ok(content) = fs::read_to_string(entry.path()) {
-                            let mut backup_api_key = "";
-                            let mut backup_base_url = "";
-
-                            for line in content.lines() {
-                                let line = line.trim();
-                                if line.is_empty() || line.starts_with('#') {
-                                    continue;
-                                }
-
-                                if let Some((key, value)) = line.split_once('=') {
-                                    match key.trim() {
-                                        "GEMINI_API_KEY" => backup_api_key = value.trim(),
-                                        "GOOGLE_GEMINI_BASE_URL" => backup_base_url = value.trim(),
-                                        _ => {}
-                                    }
-                                }
-                            }
-
-                            backup_api_key == active_api_key && backup_base_url == active_base_url
-                        } else {
-                            false
-                        }
-                    }
-                    _ => false,
-                };
-
-                if is_match {
-                    return Some(profile.to_string());
-                }
-            }
-        }
-    }
-
-    None
-}
-
 // ==================== Tauri 命令 ====================
 
 #[tauri::command]
@@ -259,13 +95,20 @@ pub async fn configure_api(
 
     // 获取 base_url，根据工具类型使用不同的默认值
     let base_url_str = base_url.unwrap_or_else(|| match tool.as_str() {
-        "codex" => "https://jp.duckcoding.com/v1".to_string(),
+        // OpenAI SDK / @ai-sdk/anthropic 以 base_url 直接拼接接口路径，需要带 /v1
+        "codex" | "qwen-code" | "opencode" => "https://jp.duckcoding.com/v1".to_string(),
         _ => "https://jp.duckcoding.com".to_string(),
     });
 
     // 使用 ConfigService 应用配置
-    ConfigService::apply_config(&tool_obj, &api_key, &base_url_str, profile_name.as_deref())
-        .map_err(|e| e.to_string())?;
+    ConfigService::apply_config(
+        &profile_store()?,
+        &tool_obj,
+        &api_key,
+        &base_url_str,
+        profile_name.as_deref(),
+    )
+    .map_err(|e| e.to_string())?;
 
     Ok(())
 }
@@ -279,7 +122,7 @@ pub async fn list_profiles(tool: String) -> Result<Vec<String>, String> {
     let tool_obj = Tool::by_id(&tool).ok_or_else(|| format!("❌ 未知的工具: {tool}"))?;
 
     // 使用 ConfigService 列出配置
-    ConfigService::list_profiles(&tool_obj).map_err(|e| e.to_string())
+    ConfigService::list_profiles(&profile_store()?, &tool_obj).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -316,120 +159,13 @@ pub async fn switch_profile(
     };
 
     if proxy_enabled {
-        // 代理模式：直接从备份文件读取配置，不修改当前配置文件
+        // 代理模式：直接从配置档案读取配置，不修改当前配置文件
         let mut global_config = global_config_opt.ok_or("全局配置不存在")?;
 
-        // 从备份文件读取真实配置
-        let (new_api_key, new_base_url) = match tool.as_str() {
-            "claude-code" => {
-                let backup_path = tool_obj.backup_path(&profile);
-                if !backup_path.exists() {
-                    return Err(format!("配置文件不存在: {backup_path:?}"));
-                }
-                let content = fs::read_to_string(&backup_path)
-                    .map_err(|e| format!("读取备份配置失败: {e}"))?;
-                let backup_data: Value =
-                    serde_json::from_str(&content).map_err(|e| format!("解析备份配置失败: {e}"))?;
-
-                // 兼容新旧格式
-                let api_key = backup_data
-                    .get("ANTHROPIC_AUTH_TOKEN")
-                    .and_then(|v| v.as_str())
-                    .or_else(|| {
-                        backup_data
-                            .get("env")
-                            .and_then(|env| env.get("ANTHROPIC_AUTH_TOKEN"))
-                            .and_then(|v| v.as_str())
-                    })
-                    .ok_or("备份配置缺少 API Key")?
-                    .to_string();
-
-                let base_url = backup_data
-                    .get("ANTHROPIC_BASE_URL")
-                    .and_then(|v| v.as_str())
-                    .or_else(|| {
-                        backup_data
-                            .get("env")
-                            .and_then(|env| env.get("ANTHROPIC_BASE_URL"))
-                            .and_then(|v| v.as_str())
-                    })
-                    .ok_or("备份配置缺少 Base URL")?
-                    .to_string();
-
-                (api_key, base_url)
-            }
-            "codex" => {
-                // 读取备份的 auth.json
-                let backup_auth = tool_obj.config_dir.join(format!("auth.{profile}.json"));
-                let backup_config = tool_obj.config_dir.join(format!("config.{profile}.toml"));
-
-                if !backup_auth.exists() {
-                    return Err(format!("配置文件不存在: {backup_auth:?}"));
-                }
-
-                let auth_content = fs::read_to_string(&backup_auth)
-                    .map_err(|e| format!("读取备份 auth.json 失败: {e}"))?;
-                let auth_data: Value = serde_json::from_str(&auth_content)
-                    .map_err(|e| format!("解析备份 auth.json 失败: {e}"))?;
-                let api_key = auth_data
-                    .get("OPENAI_API_KEY")
-                    .and_then(|v| v.as_str())
-                    .ok_or("备份配置缺少 API Key")?
-                    .to_string();
-
-                // 读取备份的 config.toml
-                let base_url = if backup_config.exists() {
-                    let config_content = fs::read_to_string(&backup_config)
-                        .map_err(|e| format!("读取备份 config.toml 失败: {e}"))?;
-                    let config: toml::Value = toml::from_str(&config_content)
-                        .map_err(|e| format!("解析备份 config.toml 失败: {e}"))?;
-                    let provider = config
-                        .get("model_provider")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("custom");
-                    config
-                        .get("model_providers")
-                        .and_then(|mp| mp.get(provider))
-                        .and_then(|p| p.get("base_url"))
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("")
-                        .to_string()
-                } else {
-                    return Err(format!("配置文件不存在: {backup_config:?}"));
-                };
-
-                (api_key, base_url)
-            }
-            "gemini-cli" => {
-                let backup_env = tool_obj.config_dir.join(format!(".env.{profile}"));
-                if !backup_env.exists() {
-                    return Err(format!("配置文件不存在: {backup_env:?}"));
-                }
-
-                let content = fs::read_to_string(&backup_env)
-                    .map_err(|e| format!("读取备份 .env 失败: {e}"))?;
-                let mut api_key = String::new();
-                let mut base_url = String::new();
-
-                for line in content.lines() {
-                    let trimmed = line.trim();
-                    if let Some((key, value)) = trimmed.split_once('=') {
-                        match key.trim() {
-                            "GEMINI_API_KEY" => api_key = value.trim().to_string(),
-                            "GOOGLE_GEMINI_BASE_URL" => base_url = value.trim().to_string(),
-                            _ => {}
-                        }
-                    }
-                }
-
-                if api_key.is_empty() || base_url.is_empty() {
-                    return Err("备份配置缺少必要字段".to_string());
-                }
-
-                (api_key, base_url)
-            }
-            _ => return Err(format!("未知工具: {tool}")),
-        };
+        // 从配置档案读取真实配置
+        let (new_api_key, new_base_url) =
+            ConfigService::read_profile_api_config(&profile_store()?, &tool_obj, &profile)
+                .map_err(|e| format!("读取配置失败: {e}"))?;
 
         if !new_api_key.is_empty() && !new_base_url.is_empty() {
             // 更新保存的真实配置
@@ -464,7 +200,7 @@ pub async fn switch_profile(
 
                     manager_state
                         .manager
-                        .update_config(&tool, updated_config)
+                        .update_config(&tool, updated_config, &global_config)
                         .await
                         .map_err(|e| format!("更新代理配置失败: {e}"))?;
 
@@ -503,7 +239,8 @@ pub async fn switch_profile(
         }
     } else {
         // 非代理模式：正常激活配置
-        ConfigService::activate_profile(&tool_obj, &profile).map_err(|e| e.to_string())?;
+        ConfigService::activate_profile(&profile_store()?, &tool_obj, &profile)
+            .map_err(|e| e.to_string())?;
         tracing::info!(
             tool = %tool,
             profile = %profile,
@@ -527,7 +264,8 @@ pub async fn delete_profile(tool: String, profile: String) -> Result<(), String>
     let tool_obj = Tool::by_id(&tool).ok_or_else(|| format!("❌ 未知的工具: {tool}"))?;
 
     // 使用 ConfigService 删除配置
-    ConfigService::delete_profile(&tool_obj, &profile).map_err(|e| e.to_string())?;
+    ConfigService::delete_profile(&profile_store()?, &tool_obj, &profile)
+        .map_err(|e| e.to_string())?;
 
     #[cfg(debug_assertions)]
     tracing::debug!(profile = %profile, "配置文件删除成功");
@@ -535,181 +273,177 @@ pub async fn delete_profile(tool: String, profile: String) -> Result<(), String>
     Ok(())
 }
 
+/// 列出配置档案（含模型、备注、标签等详情）
 #[tauri::command]
-pub async fn get_active_config(tool: String) -> Result<ActiveConfig, String> {
-    let home_dir = dirs::home_dir().ok_or("❌ 无法获取用户主目录")?;
-
-    match tool.as_str() {
-        "claude-code" => {
-            let config_path = home_dir.join(".claude").join("settings.json");
-            if !config_path.exists() {
-                return Ok(ActiveConfig {
-                    api_key: "未配置".to_string(),
-                    base_url: "未配置".to_string(),
-                    profile_name: None,
-                });
-            }
+pub async fn list_profile_records(tool: String) -> Result<Vec<ProfileRecord>, String> {
+    let tool_obj = Tool::by_id(&tool).ok_or_else(|| format!("❌ 未知的工具: {tool}"))?;
+    ConfigService::list_profile_records(&profile_store()?, &tool_obj)
+        .map_err(|e| format!("读取配置失败: {e}"))
+}
 
-            let content =
-                fs::read_to_string(&config_path).map_err(|e| format!("❌ 读取配置失败: {e}"))?;
-            let config: Value =
-                serde_json::from_str(&content).map_err(|e| format!("❌ 解析配置失败: {e}"))?;
-
-            let raw_api_key = config
-                .get("env")
-                .and_then(|env| env.get("ANTHROPIC_AUTH_TOKEN"))
-                .and_then(|v| v.as_str())
-                .unwrap_or("");
-
-            let api_key = if raw_api_key.is_empty() {
-                "未配置".to_string()
-            } else {
-                mask_api_key(raw_api_key)
-            };
-
-            let base_url = config
-                .get("env")
-                .and_then(|env| env.get("ANTHROPIC_BASE_URL"))
-                .and_then(|v| v.as_str())
-                .unwrap_or("未配置");
-
-            // 检测配置名称
-            let profile_name = if !raw_api_key.is_empty() && base_url != "未配置" {
-                detect_profile_name("claude-code", raw_api_key, base_url, &home_dir)
-            } else {
-                None
-            };
-
-            Ok(ActiveConfig {
-                api_key,
-                base_url: base_url.to_string(),
-                profile_name,
-            })
-        }
-        "codex" => {
-            let auth_path = home_dir.join(".codex").join("auth.json");
-            let config_path = home_dir.join(".codex").join("config.toml");
-
-            let mut raw_api_key = String::new();
-            let mut api_key = "未配置".to_string();
-            let mut base_url = "未配置".to_string();
-
-            // 读取 auth.json
-            if auth_path.exists() {
-                let content = fs::read_to_string(&auth_path)
-                    .map_err(|e| format!("❌ 读取认证文件失败: {e}"))?;
-                let auth: Value = serde_json::from_str(&content)
-                    .map_err(|e| format!("❌ 解析认证文件失败: {e}"))?;
-
-                if let Some(key) = auth.get("OPENAI_API_KEY").and_then(|v| v.as_str()) {
-                    raw_api_key = key.to_string();
-                    api_key = mask_api_key(key);
-                }
+/// 重命名配置（代理模式下同步更新记录的配置名称）
+#[tauri::command]
+pub async fn rename_profile(
+    tool: String,
+    profile: String,
+    new_name: String,
+) -> Result<ProfileRecord, String> {
+    let tool_obj = Tool::by_id(&tool).ok_or_else(|| format!("❌ 未知的工具: {tool}"))?;
+    let record = ConfigService::rename_profile(&profile_store()?, &tool_obj, &profile, &new_name)
+        .map_err(|e| format!("重命名配置失败: {e}"))?;
+
+    if let Some(mut global_config) = read_global_config()? {
+        if let Some(proxy_config) = global_config.get_proxy_config_mut(&tool) {
+            if proxy_config.real_profile_name.as_deref() == Some(profile.as_str()) {
+                proxy_config.real_profile_name = Some(new_name);
+                write_global_config(&global_config)?;
             }
+        }
+    }
 
-            // 读取 config.toml
-            if config_path.exists() {
-                let content = fs::read_to_string(&config_path)
-                    .map_err(|e| format!("❌ 读取配置文件失败: {e}"))?;
-                let config: toml::Value =
-                    toml::from_str(&content).map_err(|e| format!("❌ 解析TOML失败: {e}"))?;
-
-                if let toml::Value::Table(table) = config {
-                    let selected_provider = table
-                        .get("model_provider")
-                        .and_then(|value| value.as_str())
-                        .map(|s| s.to_string());
-
-                    if let Some(toml::Value::Table(providers)) = table.get("model_providers") {
-                        if let Some(provider_name) = selected_provider.as_deref() {
-                            if let Some(toml::Value::Table(provider_table)) =
-                                providers.get(provider_name)
-                            {
-                                if let Some(toml::Value::String(url)) =
-                                    provider_table.get("base_url")
-                                {
-                                    base_url = url.clone();
-                                }
-                            }
-                        }
-
-                        if base_url == "未配置" {
-                            for (_, provider) in providers {
-                                if let toml::Value::Table(p) = provider {
-                                    if let Some(toml::Value::String(url)) = p.get("base_url") {
-                                        base_url = url.clone();
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    Ok(record)
+}
 
-            // 检测配置名称
-            let profile_name = if !raw_api_key.is_empty() && base_url != "未配置" {
-                detect_profile_name("codex", &raw_api_key, &base_url, &home_dir)
-            } else {
-                None
-            };
-
-            Ok(ActiveConfig {
-                api_key,
-                base_url,
-                profile_name,
-            })
-        }
-        "gemini-cli" => {
-            let env_path = home_dir.join(".gemini").join(".env");
-            if !env_path.exists() {
-                return Ok(ActiveConfig {
-                    api_key: "未配置".to_string(),
-                    base_url: "未配置".to_string(),
-                    profile_name: None,
-                });
-            }
+/// 复制配置
+#[tauri::command]
+pub async fn duplicate_profile(
+    tool: String,
+    profile: String,
+    new_name: String,
+) -> Result<ProfileRecord, String> {
+    let tool_obj = Tool::by_id(&tool).ok_or_else(|| format!("❌ 未知的工具: {tool}"))?;
+    ConfigService::duplicate_profile(&profile_store()?, &tool_obj, &profile, &new_name)
+        .map_err(|e| format!("复制配置失败: {e}"))
+}
 
-            let content = fs::read_to_string(&env_path)
-                .map_err(|e| format!("❌ 读取环境变量配置失败: {e}"))?;
+/// 更新配置的模型、备注和标签
+#[tauri::command]
+pub async fn update_profile_details(
+    tool: String,
+    profile: String,
+    details: ProfileDetails,
+) -> Result<ProfileRecord, String> {
+    let tool_obj = Tool::by_id(&tool).ok_or_else(|| format!("❌ 未知的工具: {tool}"))?;
+    ConfigService::update_profile_details(&profile_store()?, &tool_obj, &profile, details)
+        .map_err(|e| format!("更新配置失败: {e}"))
+}
 
-            let mut raw_api_key = String::new();
-            let mut api_key = "未配置".to_string();
-            let mut base_url = "未配置".to_string();
+/// 搜索配置（不指定工具时搜索所有工具）
+#[tauri::command]
+pub async fn search_profiles(
+    tool: Option<String>,
+    query: String,
+    tag: Option<String>,
+) -> Result<Vec<ProfileRecord>, String> {
+    let tool_obj = match tool {
+        Some(tool) => Some(Tool::by_id(&tool).ok_or_else(|| format!("❌ 未知的工具: {tool}"))?),
+        None => None,
+    };
+    ConfigService::search_profiles(&profile_store()?, tool_obj.as_ref(), &query, tag.as_deref())
+        .map_err(|e| format!("搜索配置失败: {e}"))
+}
 
-            for line in content.lines() {
-                let line = line.trim();
-                if line.is_empty() || line.starts_with('#') {
-                    continue;
-                }
+/// 检查配置的连通性和 API Key 是否有效（结果写回配置档案）
+#[tauri::command]
+pub async fn check_profile_health(tool: String, profile: String) -> Result<ProfileHealth, String> {
+    let tool_obj = Tool::by_id(&tool).ok_or_else(|| format!("❌ 未知的工具: {tool}"))?;
+    profile_health::check_profile(&tool_obj, &profile)
+        .await
+        .map_err(|e| format!("检查配置失败: {e}"))
+}
 
-                if let Some((key, value)) = line.split_once('=') {
-                    match key.trim() {
-                        "GEMINI_API_KEY" => {
-                            raw_api_key = value.trim().to_string();
-                            api_key = mask_api_key(value.trim());
-                        }
-                        "GOOGLE_GEMINI_BASE_URL" => base_url = value.trim().to_string(),
-                        _ => {}
-                    }
-                }
-            }
+/// 并发检查所有工具的全部配置
+#[tauri::command]
+pub async fn check_all_profiles_health() -> Result<Vec<ProfileCheckResult>, String> {
+    profile_health::check_all_profiles()
+        .await
+        .map_err(|e| format!("检查配置失败: {e}"))
+}
 
-            // 检测配置名称
-            let profile_name = if !raw_api_key.is_empty() && base_url != "未配置" {
-                detect_profile_name("gemini-cli", &raw_api_key, &base_url, &home_dir)
-            } else {
-                None
-            };
-
-            Ok(ActiveConfig {
-                api_key,
-                base_url,
-                profile_name,
-            })
-        }
-        _ => Err(format!("❌ 未知的工具: {tool}")),
+/// 导出配置到导出包文件（profiles 为空时导出全部配置），返回导出数量
+#[tauri::command]
+pub async fn export_profile_bundle(
+    path: String,
+    profiles: Vec<ProfileRef>,
+    passphrase: Option<String>,
+) -> Result<usize, String> {
+    let content =
+        ConfigService::export_profiles(&profile_store()?, &profiles, passphrase.as_deref())
+            .map_err(|e| format!("导出配置失败: {e}"))?;
+    let count = bundle_info(&content)
+        .ok()
+        .and_then(|info| info.profiles)
+        .unwrap_or(profiles.len());
+
+    // 导出包含明文或口令加密的 API Key，不保存快照并限制为仅本人可读
+    let path = std::path::Path::new(&path);
+    write_atomic_with(path, content, None).map_err(|e| format!("写入导出包失败: {e}"))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("设置导出包权限失败: {e}"))?;
     }
+
+    tracing::info!(path = ?path, count, "配置已导出");
+    Ok(count)
+}
+
+/// 读取导出包概要（导入前判断是否需要口令）
+#[tauri::command]
+pub async fn inspect_profile_bundle(path: String) -> Result<BundleInfo, String> {
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("读取导出包失败: {e}"))?;
+    bundle_info(&content).map_err(|e| e.to_string())
+}
+
+/// 从导出包导入配置
+#[tauri::command]
+pub async fn import_profile_bundle(
+    path: String,
+    passphrase: Option<String>,
+    strategy: ConflictStrategy,
+) -> Result<BundleImportReport, String> {
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("读取导出包失败: {e}"))?;
+    let report = ConfigService::import_profiles(
+        &profile_store()?,
+        &content,
+        passphrase.as_deref(),
+        strategy,
+    )
+    .map_err(|e| format!("导入配置失败: {e}"))?;
+    tracing::info!(
+        imported = report.imported(),
+        total = report.outcomes.len(),
+        "配置导入完成"
+    );
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn get_active_config(tool: String) -> Result<ActiveConfig, String> {
+    let tool_obj = Tool::by_id(&tool).ok_or_else(|| format!("❌ 未知的工具: {tool}"))?;
+    let (raw_api_key, base_url) = ConfigService::read_api_config(&tool_obj).unwrap_or_default();
+
+    // 检测配置名称
+    let profile_name = if !raw_api_key.is_empty() && !base_url.is_empty() {
+        ConfigService::find_matching_profile(&profile_store()?, &tool_obj, &raw_api_key, &base_url)
+    } else {
+        None
+    };
+
+    Ok(ActiveConfig {
+        api_key: if raw_api_key.is_empty() {
+            "未配置".to_string()
+        } else {
+            mask_api_key(&raw_api_key)
+        },
+        base_url: if base_url.is_empty() {
+            "未配置".to_string()
+        } else {
+            base_url
+        },
+        profile_name,
+    })
 }
 
 #[tauri::command]
@@ -848,8 +582,8 @@ pub fn get_claude_settings() -> Result<Value, String> {
 }
 
 #[tauri::command]
-pub fn save_claude_settings(settings: Value) -> Result<(), String> {
-    ConfigService::save_claude_settings(&settings).map_err(|e| e.to_string())
+pub fn save_claude_settings(settings: Value) -> Result<(), SaveSettingsError> {
+    Ok(ConfigService::save_claude_settings(&settings)?)
 }
 
 #[tauri::command]
@@ -863,8 +597,11 @@ pub fn get_codex_settings() -> Result<CodexSettingsPayload, String> {
 }
 
 #[tauri::command]
-pub fn save_codex_settings(settings: Value, auth_token: Option<String>) -> Result<(), String> {
-    ConfigService::save_codex_settings(&settings, auth_token).map_err(|e| e.to_string())
+pub fn save_codex_settings(
+    settings: Value,
+    auth_token: Option<String>,
+) -> Result<(), SaveSettingsError> {
+    Ok(ConfigService::save_codex_settings(&settings, auth_token)?)
 }
 
 #[tauri::command]
@@ -878,8 +615,11 @@ pub fn get_gemini_settings() -> Result<GeminiSettingsPayload, String> {
 }
 
 #[tauri::command]
-pub fn save_gemini_settings(settings: Value, env: GeminiEnvPayload) -> Result<(), String> {
-    ConfigService::save_gemini_settings(&settings, &env).map_err(|e| e.to_string())
+pub fn save_gemini_settings(
+    settings: Value,
+    env: GeminiEnvPayload,
+) -> Result<(), SaveSettingsError> {
+    Ok(ConfigService::save_gemini_settings(&settings, &env)?)
 }
 
 #[tauri::command]
@@ -892,49 +632,94 @@ pub fn get_gemini_schema() -> Result<Value, String> {
 pub async fn get_profile_config(tool: String, profile: String) -> Result<ActiveConfig, String> {
     let tool_obj = Tool::by_id(&tool).ok_or_else(|| format!("未知的工具: {tool}"))?;
 
-    match tool.as_str() {
-        "claude-code" => {
-            let backup_path = tool_obj.backup_path(&profile);
-            if !backup_path.exists() {
-                return Err(format!("配置文件不存在: {profile}"));
-            }
+    let (api_key, base_url) =
+        ConfigService::read_profile_api_config(&profile_store()?, &tool_obj, &profile)
+            .map_err(|e| format!("读取配置失败: {e}"))?;
+    Ok(ActiveConfig {
+        api_key,
+        base_url,
+        profile_name: Some(profile),
+    })
+}
 
-            // 读取备份配置文件
-            let backup_content =
-                fs::read_to_string(&backup_path).map_err(|e| format!("读取配置文件失败: {e}"))?;
-            let backup_data: Value = serde_json::from_str(&backup_content)
-                .map_err(|e| format!("解析配置文件失败: {e}"))?;
-
-            // 兼容新旧格式读取 API Key
-            let api_key = backup_data
-                .get("ANTHROPIC_AUTH_TOKEN")
-                .and_then(|v| v.as_str())
-                .or_else(|| {
-                    backup_data
-                        .get("env")
-                        .and_then(|env| env.get("ANTHROPIC_AUTH_TOKEN"))
-                        .and_then(|v| v.as_str())
-                })
-                .ok_or_else(|| "配置文件格式错误：缺少 API Key".to_string())?;
-
-            // 兼容新旧格式读取 Base URL
-            let base_url = backup_data
-                .get("ANTHROPIC_BASE_URL")
-                .and_then(|v| v.as_str())
-                .or_else(|| {
-                    backup_data
-                        .get("env")
-                        .and_then(|env| env.get("ANTHROPIC_BASE_URL"))
-                        .and_then(|v| v.as_str())
-                })
-                .ok_or_else(|| "配置文件格式错误：缺少 Base URL".to_string())?;
-
-            Ok(ActiveConfig {
-                api_key: api_key.to_string(),
-                base_url: base_url.to_string(),
-                profile_name: Some(profile),
+/// 列出工具配置文件的快照（每次写入前自动保存）
+#[tauri::command]
+pub async fn list_config_snapshots(tool: String) -> Result<Vec<ConfigSnapshots>, String> {
+    let tool_obj = Tool::by_id(&tool).ok_or_else(|| format!("未知的工具: {tool}"))?;
+    let store = SnapshotStore::default_store().ok_or("无法获取快照目录")?;
+
+    tool_obj
+        .config_files()
+        .into_iter()
+        .map(|path| {
+            let snapshots = store
+                .list(&path)
+                .map_err(|e| format!("读取配置快照失败: {e}"))?;
+            Ok(ConfigSnapshots {
+                file: config_file_name(&path),
+                path: path.to_string_lossy().to_string(),
+                snapshots,
             })
-        }
-        _ => Err(format!("暂不支持的工具: {tool}")),
-    }
+        })
+        .collect()
+}
+
+/// 用快照恢复工具配置文件
+#[tauri::command]
+pub async fn restore_config_snapshot(
+    tool: String,
+    file: String,
+    snapshot_id: String,
+) -> Result<(), String> {
+    let tool_obj = Tool::by_id(&tool).ok_or_else(|| format!("未知的工具: {tool}"))?;
+    let store = SnapshotStore::default_store().ok_or("无法获取快照目录")?;
+
+    // 只允许恢复工具自身的配置文件
+    let path = tool_obj
+        .config_files()
+        .into_iter()
+        .find(|path| config_file_name(path) == file)
+        .ok_or_else(|| format!("{} 没有配置文件: {file}", tool_obj.name))?;
+
+    store
+        .restore(&path, &snapshot_id)
+        .map_err(|e| format!("恢复配置快照失败: {e}"))
+}
+
+fn config_file_name(path: &std::path::Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// 获取未处理的配置偏移（工具配置被外部修改）
+#[tauri::command]
+pub async fn get_config_drifts(
+    watcher_state: State<'_, ConfigWatcherState>,
+) -> Result<Vec<ConfigDrift>, String> {
+    Ok(watcher_state.watcher.drifts())
+}
+
+/// 一键修复配置偏移
+#[tauri::command]
+pub async fn reconcile_config_drift(
+    tool: String,
+    watcher_state: State<'_, ConfigWatcherState>,
+    manager_state: State<'_, ProxyManagerState>,
+) -> Result<ConfigDrift, String> {
+    let tool_obj = Tool::by_id(&tool).ok_or_else(|| format!("未知的工具: {tool}"))?;
+
+    watcher_state
+        .watcher
+        .reconcile(&tool_obj, &manager_state.manager)
+        .await
+        .map_err(|e| format!("修复配置偏移失败: {e}"))
+}
+
+/// 获取密钥库状态（无法打开时 locked 为 true，配置中的密钥保持 vault: 引用）
+#[tauri::command]
+pub async fn get_vault_status() -> Result<VaultStatus, String> {
+    Vault::default_vault()
+        .map(|vault| vault.status())
+        .map_err(|e| format!("读取密钥库失败: {e}"))
 }
@@ -5,11 +5,14 @@ use std::sync::Arc;
 use tauri::State;
 use tokio::sync::Mutex as TokioMutex;
 
+use ::duckcoding::services::proxy::capture::{self, CapturedExchange, ReplayResult};
+use ::duckcoding::services::proxy::local_keys;
+use ::duckcoding::services::proxy::tls::{self, TlsCertificateInfo};
 use ::duckcoding::services::proxy::{
-    ProxyManager, TransparentProxyConfigService, TransparentProxyService,
+    ProxyInstanceStatus, ProxyManager, TransparentProxyConfigService, TransparentProxyService,
 };
 use ::duckcoding::utils::config::{read_global_config, write_global_config};
-use ::duckcoding::{GlobalConfig, ProxyConfig, Tool};
+use ::duckcoding::{GlobalConfig, LocalApiKey, ProxyConfig, Tool, ToolProxyConfig, UpstreamConfig};
 
 // ==================== 类型定义 ====================
 
@@ -26,8 +29,9 @@ pub struct ProxyManagerState {
 // 透明代理相关的 Tauri Commands
 #[derive(serde::Serialize)]
 pub struct TransparentProxyStatus {
-    running: bool,
     port: u16,
+    #[serde(flatten)]
+    status: ProxyInstanceStatus,
 }
 
 #[derive(serde::Deserialize)]
@@ -188,7 +192,13 @@ pub async fn get_transparent_proxy_status(
     let service = state.service.lock().await;
     let running = service.is_running().await;
 
-    Ok(TransparentProxyStatus { running, port })
+    Ok(TransparentProxyStatus {
+        port,
+        status: ProxyInstanceStatus {
+            running,
+            ..Default::default()
+        },
+    })
 }
 
 #[tauri::command]
@@ -356,13 +366,9 @@ pub async fn start_tool_proxy(
         .ok_or_else(|| "全局配置不存在，请先配置用户信息".to_string())?;
 
     // 确保工具的代理配置存在
-    let default_ports: HashMap<&str, u16> =
-        [("claude-code", 8787), ("codex", 8788), ("gemini-cli", 8789)]
-            .iter()
-            .cloned()
-            .collect();
-
-    let default_port = default_ports.get(tool_id.as_str()).copied().unwrap_or(8790);
+    let default_port = Tool::by_id(&tool_id)
+        .map(|tool| tool.default_port)
+        .ok_or_else(|| format!("未知工具: {tool_id}"))?;
     config.ensure_proxy_config(&tool_id, default_port);
 
     // 获取工具的代理配置
@@ -426,7 +432,7 @@ pub async fn start_tool_proxy(
     // 启动代理
     manager_state
         .manager
-        .start_proxy(&tool_id, updated_config)
+        .start_proxy(&tool_id, updated_config, &config)
         .await
         .map_err(|e| format!("启动代理失败: {e}"))?;
 
@@ -474,27 +480,217 @@ pub async fn get_all_proxy_status(
 ) -> Result<HashMap<String, TransparentProxyStatus>, String> {
     let config = get_global_config().await.ok().flatten();
 
+    let instance_status = manager_state.manager.get_all_status().await;
     let mut status_map = HashMap::new();
 
-    for tool_id in &["claude-code", "codex", "gemini-cli"] {
+    for tool in Tool::all() {
         let port = config
             .as_ref()
-            .and_then(|c| c.get_proxy_config(tool_id))
+            .and_then(|c| c.get_proxy_config(&tool.id))
             .map(|tc| tc.port)
-            .unwrap_or_else(|| match *tool_id {
-                "claude-code" => 8787,
-                "codex" => 8788,
-                "gemini-cli" => 8789,
-                _ => 8790,
-            });
-
-        let running = manager_state.manager.is_running(tool_id).await;
-
-        status_map.insert(
-            tool_id.to_string(),
-            TransparentProxyStatus { running, port },
-        );
+            .unwrap_or(tool.default_port);
+
+        let status = instance_status.get(&tool.id).cloned().unwrap_or_default();
+
+        status_map.insert(tool.id, TransparentProxyStatus { port, status });
     }
 
     Ok(status_map)
 }
+
+// ==================== 本地 API Key 管理 ====================
+
+/// 修改工具的命名本地密钥并保存；代理运行中时同步到实例（无需重启）
+async fn modify_tool_proxy_config<T, F>(
+    tool_id: &str,
+    manager_state: &ProxyManagerState,
+    modify: F,
+) -> Result<T, String>
+where
+    F: FnOnce(&mut ToolProxyConfig) -> anyhow::Result<T>,
+{
+    let mut config = get_global_config()
+        .await
+        .map_err(|e| format!("读取配置失败: {e}"))?
+        .ok_or_else(|| "全局配置不存在".to_string())?;
+
+    let tool_config = config
+        .get_proxy_config_mut(tool_id)
+        .ok_or_else(|| format!("{tool_id} 尚未配置透明代理"))?;
+    let result = modify(tool_config).map_err(|e| e.to_string())?;
+    let updated_config = tool_config.clone();
+
+    save_global_config(config.clone())
+        .await
+        .map_err(|e| format!("保存配置失败: {e}"))?;
+
+    if manager_state.manager.is_running(tool_id).await {
+        manager_state
+            .manager
+            .update_config(tool_id, updated_config, &config)
+            .await
+            .map_err(|e| format!("更新代理配置失败: {e}"))?;
+    }
+
+    Ok(result)
+}
+
+/// 获取工具的命名本地密钥列表
+#[tauri::command]
+pub async fn list_local_api_keys(tool_id: String) -> Result<Vec<LocalApiKey>, String> {
+    let config = get_global_config()
+        .await
+        .map_err(|e| format!("读取配置失败: {e}"))?;
+
+    Ok(config
+        .as_ref()
+        .and_then(|c| c.get_proxy_config(&tool_id))
+        .map(|tc| tc.local_api_keys.clone())
+        .unwrap_or_default())
+}
+
+/// 创建命名本地密钥
+#[tauri::command]
+pub async fn create_local_api_key(
+    tool_id: String,
+    owner: String,
+    expires_at: Option<i64>,
+    manager_state: State<'_, ProxyManagerState>,
+) -> Result<LocalApiKey, String> {
+    let now = chrono::Utc::now().timestamp();
+    let key = modify_tool_proxy_config(&tool_id, &manager_state, |config| {
+        local_keys::create_key(config, &tool_id, &owner, expires_at, now)
+    })
+    .await
+    .map_err(|e| format!("创建本地密钥失败: {e}"))?;
+
+    tracing::info!(tool_id = %tool_id, key_id = %key.id, owner = %key.owner, "已创建本地密钥");
+    Ok(key)
+}
+
+/// 吊销命名本地密钥
+#[tauri::command]
+pub async fn revoke_local_api_key(
+    tool_id: String,
+    key_id: String,
+    manager_state: State<'_, ProxyManagerState>,
+) -> Result<LocalApiKey, String> {
+    let key = modify_tool_proxy_config(&tool_id, &manager_state, |config| {
+        local_keys::revoke_key(config, &key_id)
+    })
+    .await
+    .map_err(|e| format!("吊销本地密钥失败: {e}"))?;
+
+    tracing::info!(tool_id = %tool_id, key_id = %key.id, "已吊销本地密钥");
+    Ok(key)
+}
+
+/// 轮换命名本地密钥（旧密钥立即失效）
+#[tauri::command]
+pub async fn rotate_local_api_key(
+    tool_id: String,
+    key_id: String,
+    manager_state: State<'_, ProxyManagerState>,
+) -> Result<LocalApiKey, String> {
+    let key = modify_tool_proxy_config(&tool_id, &manager_state, |config| {
+        local_keys::rotate_key(config, &tool_id, &key_id)
+    })
+    .await
+    .map_err(|e| format!("轮换本地密钥失败: {e}"))?;
+
+    tracing::info!(tool_id = %tool_id, key_id = %key.id, "已轮换本地密钥");
+    Ok(key)
+}
+
+// ==================== TLS 证书 ====================
+
+/// 获取工具代理的 TLS 证书信息（指纹与 PEM，供客户端固定证书）
+///
+/// 使用自签名证书且尚未生成时会先生成
+#[tauri::command]
+pub async fn get_proxy_tls_certificate(tool_id: String) -> Result<TlsCertificateInfo, String> {
+    let config = get_global_config()
+        .await
+        .map_err(|e| format!("读取配置失败: {e}"))?;
+    let tls_config = config
+        .as_ref()
+        .and_then(|c| c.get_proxy_config(&tool_id))
+        .map(|tc| tc.tls.clone())
+        .unwrap_or_default();
+
+    tls::certificate_info(&tls_config).map_err(|e| format!("读取 TLS 证书失败: {e}"))
+}
+
+// ==================== 请求抓包 ====================
+
+/// 启用或关闭工具代理的请求抓包（运行中的代理立即生效）
+#[tauri::command]
+pub async fn set_proxy_capture_enabled(
+    tool_id: String,
+    enabled: bool,
+    manager_state: State<'_, ProxyManagerState>,
+) -> Result<(), String> {
+    modify_tool_proxy_config(&tool_id, &manager_state, |config| {
+        config.capture.enabled = enabled;
+        Ok(())
+    })
+    .await
+}
+
+/// 读取抓包内容（请求记录中的 capture_id）
+#[tauri::command]
+pub async fn get_proxy_capture(
+    tool_id: String,
+    capture_id: String,
+) -> Result<CapturedExchange, String> {
+    capture::load_capture(&tool_id, &capture_id).map_err(|e| format!("读取抓包失败: {e}"))
+}
+
+/// 重放抓包请求并与原始响应对比
+///
+/// 未指定 base_url 时使用当前配置的上游；指定 base_url 但未提供 api_key 时，
+/// 复用配置中相同地址上游的密钥
+#[tauri::command]
+pub async fn replay_proxy_capture(
+    tool_id: String,
+    capture_id: String,
+    base_url: Option<String>,
+    api_key: Option<String>,
+) -> Result<ReplayResult, String> {
+    let upstream = match base_url.filter(|url| !url.trim().is_empty()) {
+        None => None,
+        Some(base_url) => {
+            let base_url = base_url.trim().trim_end_matches('/').to_string();
+            let api_key = match api_key.filter(|key| !key.is_empty()) {
+                Some(key) => key,
+                None => get_global_config()
+                    .await
+                    .map_err(|e| format!("读取配置失败: {e}"))?
+                    .as_ref()
+                    .and_then(|c| c.get_proxy_config(&tool_id))
+                    .and_then(|tc| {
+                        tc.upstream_candidates()
+                            .into_iter()
+                            .find(|u| u.base_url.trim_end_matches('/') == base_url)
+                    })
+                    .map(|u| u.api_key)
+                    .ok_or_else(|| format!("请提供上游 {base_url} 的 API Key"))?,
+            };
+            Some(UpstreamConfig {
+                base_url,
+                api_key,
+                weight: None,
+            })
+        }
+    };
+
+    capture::replay_capture(&tool_id, &capture_id, upstream)
+        .await
+        .map_err(|e| format!("重放请求失败: {e}"))
+}
+
+/// 删除工具的全部抓包文件，返回删除数量
+#[tauri::command]
+pub async fn clear_proxy_captures(tool_id: String) -> Result<usize, String> {
+    capture::clear_captures(&tool_id).map_err(|e| format!("清理抓包失败: {e}"))
+}
@@ -1,6 +1,9 @@
 // 会话管理 Tauri 命令
 
-use duckcoding::services::session::{SessionListResponse, SESSION_MANAGER};
+use duckcoding::services::session::{
+    ProxyRequestFilter, ProxyRequestListResponse, SessionListResponse, TokenUsageSummary,
+    SESSION_MANAGER,
+};
 
 /// 获取会话列表
 #[tauri::command]
@@ -57,3 +60,43 @@ pub async fn update_session_note(session_id: String, note: Option<String>) -> Re
         .update_session_note(&session_id, note.as_deref())
         .map_err(|e| format!("Failed to update session note: {e}"))
 }
+
+/// 获取单个会话的 token 用量汇总
+#[tauri::command]
+pub async fn get_session_token_usage(session_id: String) -> Result<TokenUsageSummary, String> {
+    SESSION_MANAGER
+        .get_session_token_usage(&session_id)
+        .map_err(|e| format!("Failed to get session token usage: {e}"))
+}
+
+/// 获取工具的 token 用量汇总（since 为 Unix 时间戳，秒）
+#[tauri::command]
+pub async fn get_tool_token_usage(
+    tool_id: String,
+    since: Option<i64>,
+) -> Result<TokenUsageSummary, String> {
+    SESSION_MANAGER
+        .get_tool_token_usage(&tool_id, since)
+        .map_err(|e| format!("Failed to get tool token usage: {e}"))
+}
+
+/// 获取代理请求日志（分页 + 过滤）
+#[tauri::command]
+pub async fn get_proxy_request_log(
+    tool_id: String,
+    page: usize,
+    page_size: usize,
+    filter: Option<ProxyRequestFilter>,
+) -> Result<ProxyRequestListResponse, String> {
+    SESSION_MANAGER
+        .get_proxy_requests(&tool_id, &filter.unwrap_or_default(), page, page_size)
+        .map_err(|e| format!("Failed to get proxy request log: {e}"))
+}
+
+/// 清空指定工具的代理请求日志
+#[tauri::command]
+pub async fn clear_proxy_request_log(tool_id: String) -> Result<(), String> {
+    SESSION_MANAGER
+        .clear_proxy_requests(&tool_id)
+        .map_err(|e| format!("Failed to clear proxy request log: {e}"))
+}
@@ -40,3 +40,31 @@ pub struct ActiveConfig {
     pub base_url: String,
     pub profile_name: Option<String>, // 当前配置的名称
 }
+
+/// 配置文件快照列表
+#[derive(serde::Serialize)]
+pub struct ConfigSnapshots {
+    pub file: String, // 配置文件名（如 settings.json、.env）
+    pub path: String,
+    pub snapshots: Vec<duckcoding::utils::FileSnapshot>,
+}
+
+/// 保存工具配置失败（Schema 校验错误逐字段列出）
+#[derive(Debug, serde::Serialize)]
+pub struct SaveSettingsError {
+    pub message: String,
+    pub errors: Vec<duckcoding::utils::json_schema::SchemaViolation>,
+}
+
+impl From<anyhow::Error> for SaveSettingsError {
+    fn from(err: anyhow::Error) -> Self {
+        let errors = err
+            .downcast_ref::<duckcoding::utils::json_schema::SchemaValidationError>()
+            .map(|e| e.errors.clone())
+            .unwrap_or_default();
+        Self {
+            message: err.to_string(),
+            errors,
+        }
+    }
+}
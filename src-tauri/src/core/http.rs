@@ -1,9 +1,41 @@
 use crate::core::error::{AppError, AppResult};
 use crate::models::GlobalConfig;
+use crate::utils::proxy_bypass::should_bypass_proxy;
 use reqwest::Client;
+use std::time::Duration;
 
 const USER_AGENT: &str = concat!("DuckCoding/", env!("CARGO_PKG_VERSION"));
 
+/// HTTP 客户端可选参数
+#[derive(Debug, Clone)]
+pub struct HttpClientOptions {
+    /// 整体请求超时（None 表示不限制，适用于长时间的流式响应）
+    pub timeout: Option<Duration>,
+    /// 建立连接超时
+    pub connect_timeout: Option<Duration>,
+    /// 两次读取之间的超时
+    pub read_timeout: Option<Duration>,
+    /// HTTP/2 keep-alive ping 间隔
+    pub http2_keep_alive_interval: Option<Duration>,
+    /// 连接池空闲连接保留时间
+    pub pool_idle_timeout: Option<Duration>,
+    /// 代理绕过列表（为空时所有请求都走代理）
+    pub proxy_bypass: Vec<String>,
+}
+
+impl Default for HttpClientOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Some(Duration::from_secs(300)), // 5分钟超时
+            connect_timeout: None,
+            read_timeout: None,
+            http2_keep_alive_interval: None,
+            pool_idle_timeout: None,
+            proxy_bypass: Vec::new(),
+        }
+    }
+}
+
 /// 构建带代理配置的 HTTP 客户端
 ///
 /// # 参数
@@ -12,11 +44,39 @@ const USER_AGENT: &str = concat!("DuckCoding/", env!("CARGO_PKG_VERSION"));
 /// # 返回
 /// - 配置好的 reqwest::Client
 pub fn build_http_client(config: Option<&GlobalConfig>) -> AppResult<Client> {
+    build_http_client_with_options(config, &HttpClientOptions::default())
+}
+
+/// 构建带代理配置和自定义超时参数的 HTTP 客户端
+///
+/// 代理启用且 `options.proxy_bypass` 非空时，匹配绕过列表的请求直连
+pub fn build_http_client_with_options(
+    config: Option<&GlobalConfig>,
+    options: &HttpClientOptions,
+) -> AppResult<Client> {
     let mut builder = Client::builder()
         .user_agent(USER_AGENT)
-        .timeout(std::time::Duration::from_secs(300)) // 5分钟超时
         .redirect(reqwest::redirect::Policy::limited(10)); // 支持重定向
 
+    if let Some(timeout) = options.timeout {
+        builder = builder.timeout(timeout);
+    }
+    if let Some(connect_timeout) = options.connect_timeout {
+        builder = builder.connect_timeout(connect_timeout);
+    }
+    if let Some(read_timeout) = options.read_timeout {
+        builder = builder.read_timeout(read_timeout);
+    }
+    if let Some(interval) = options.http2_keep_alive_interval {
+        builder = builder
+            .http2_keep_alive_interval(interval)
+            .http2_keep_alive_timeout(Duration::from_secs(20))
+            .http2_keep_alive_while_idle(true);
+    }
+    if let Some(idle_timeout) = options.pool_idle_timeout {
+        builder = builder.pool_idle_timeout(idle_timeout);
+    }
+
     // 应用代理配置
     if let Some(cfg) = config {
         if cfg.proxy_enabled {
@@ -26,6 +86,19 @@ pub fn build_http_client(config: Option<&GlobalConfig>) -> AppResult<Client> {
                     reason: format!("代理 URL 无效: {e}"),
                 })?;
 
+            let proxy = if options.proxy_bypass.is_empty() {
+                proxy
+            } else {
+                let bypass_list = options.proxy_bypass.clone();
+                reqwest::Proxy::custom(move |url| {
+                    if should_bypass_proxy(url.as_str(), &bypass_list) {
+                        None
+                    } else {
+                        Some(proxy_url.clone())
+                    }
+                })
+            };
+
             builder = builder.proxy(proxy);
         }
     }
@@ -1,8 +1,12 @@
+use super::log_redact::{RedactedEvent, RedactedFields};
 use crate::models::config::{LogConfig, LogFormat, LogLevel, LogOutput};
 use std::sync::OnceLock;
 use tracing_appender::{non_blocking, rolling};
 use tracing_subscriber::{
-    fmt::{self, format::FmtSpan},
+    fmt::{
+        self,
+        format::{FmtSpan, JsonFields},
+    },
     layer::SubscriberExt,
     reload::{self, Handle},
     util::SubscriberInitExt,
@@ -20,6 +24,8 @@ static LOG_LEVEL_HANDLE: OnceLock<Handle<EnvFilter, Registry>> = OnceLock::new()
 /// - 输出目标（控制台/文件/both）
 /// - 文件路径（用于文件输出）
 ///
+/// 所有输出层在格式化前对 API Key 等敏感信息脱敏（见 `log_redact`）。
+///
 /// # 热重载支持
 /// 日志级别可以通过 `update_log_level` 函数动态调整，无需重启应用。
 /// 其他配置（格式、输出目标、文件路径）需要重启应用后生效。
@@ -118,6 +124,7 @@ where
     S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
 {
     fmt::layer()
+        .fmt_fields(RedactedFields)
         .with_writer(std::io::stdout)
         .with_target(cfg!(debug_assertions))
         .with_thread_ids(false)
@@ -135,11 +142,14 @@ fn create_console_json_layer<S>() -> Box<dyn Layer<S> + Send + Sync + 'static>
 where
     S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
 {
-    fmt::layer()
+    let format = fmt::format()
         .json()
-        .with_writer(std::io::stdout)
         .with_target(cfg!(debug_assertions))
-        .with_thread_ids(false)
+        .with_thread_ids(false);
+    fmt::layer()
+        .fmt_fields(JsonFields::new())
+        .event_format(RedactedEvent(format))
+        .with_writer(std::io::stdout)
         .with_ansi(true)
         .boxed()
 }
@@ -159,6 +169,7 @@ where
     Box::leak(Box::new(guard));
 
     Ok(fmt::layer()
+        .fmt_fields(RedactedFields)
         .with_writer(non_blocking)
         .with_target(cfg!(debug_assertions))
         .with_thread_ids(false)
@@ -180,11 +191,11 @@ where
     // 存储 guard 到全局静态变量（防止被 drop）
     Box::leak(Box::new(guard));
 
+    let format = fmt::format().json().with_target(true).with_thread_ids(true);
     Ok(fmt::layer()
-        .json()
+        .fmt_fields(JsonFields::new())
+        .event_format(RedactedEvent(format))
         .with_writer(non_blocking)
-        .with_target(true)
-        .with_thread_ids(true)
         .with_ansi(false)
         .boxed())
 }
@@ -1,11 +1,15 @@
 pub mod error;
 pub mod http;
+pub mod log_redact;
 pub mod log_utils;
 pub mod logger;
 
 // 导出核心类型
 pub use error::{AppError, AppResult, ErrorContext};
-pub use http::{build_http_client, get_global_client};
+pub use http::{
+    build_http_client, build_http_client_with_options, get_global_client, HttpClientOptions,
+};
+pub use log_redact::{set_log_secrets, set_profile_log_secrets};
 pub use log_utils::{LogContext, Timer};
 #[allow(deprecated)]
 pub use logger::{init_logger, set_log_level, update_log_level};
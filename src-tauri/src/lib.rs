@@ -11,6 +11,7 @@ pub use models::*;
 // Explicitly re-export only selected service types to avoid ambiguous glob re-exports
 pub use models::InstallMethod; // InstallMethod is defined in models (tool.rs) — re-export from models
 pub use services::config::ConfigService;
+pub use services::config_watcher::{ConfigDrift, ConfigWatcher, DriftKind};
 pub use services::downloader::FileDownloader;
 pub use services::installer::InstallerService;
 pub use services::proxy::ProxyService;
@@ -48,6 +49,8 @@ pub use ui::{
     // 托盘管理
     create_tray_menu,
     emit_close_confirm,
+    emit_config_drift,
+    emit_proxy_status,
     emit_single_instance,
     // 窗口管理
     focus_main_window,
@@ -56,6 +59,8 @@ pub use ui::{
     SingleInstancePayload,
     // 事件管理
     CLOSE_CONFIRM_EVENT,
+    CONFIG_DRIFT_EVENT,
+    PROXY_STATUS_EVENT,
     SINGLE_INSTANCE_EVENT,
 };
 
@@ -100,7 +105,10 @@ pub async fn auto_start_proxies(manager: &ProxyManager) {
             "自动启动代理"
         );
 
-        match manager.start_proxy(tool_id, tool_config.clone()).await {
+        match manager
+            .start_proxy(tool_id, tool_config.clone(), &config)
+            .await
+        {
             Ok(_) => {
                 started_count += 1;
                 tracing::info!(tool_id = %tool_id, "代理启动成功");
@@ -15,7 +15,7 @@ mod commands;
 use commands::*;
 
 // 导入透明代理服务
-use duckcoding::{ProxyManager, ToolStatusCache, TransparentProxyService};
+use duckcoding::{ConfigWatcher, ProxyManager, ToolStatusCache, TransparentProxyService};
 use std::sync::Arc;
 use tokio::sync::Mutex as TokioMutex;
 
@@ -135,11 +135,18 @@ fn main() {
     use duckcoding::utils::config::read_global_config;
 
     // 从配置文件读取日志配置，失败则使用默认配置
-    let log_config = read_global_config()
-        .ok()
-        .flatten()
-        .map(|cfg| cfg.log_config)
+    let global_config = read_global_config().ok().flatten();
+    let log_config = global_config
+        .as_ref()
+        .map(|cfg| cfg.log_config.clone())
         .unwrap_or_default();
+    // 日志中按明文替换配置里的密钥
+    if let Some(cfg) = &global_config {
+        duckcoding::core::set_log_secrets(cfg.secret_values());
+    }
+    if let Ok(store) = duckcoding::services::profile_store::ProfileStore::default_store() {
+        duckcoding::core::set_profile_log_secrets(store.secret_values());
+    }
 
     if let Err(e) = init_logger(&log_config) {
         // 日志系统初始化失败时使用 eprintln!（因为 tracing 还不可用）
@@ -149,6 +156,20 @@ fn main() {
 
     tracing::info!("DuckCoding 应用启动");
 
+    // 加载 ~/.duckcoding/tools.d 中的工具描述文件
+    if let Err(e) = duckcoding::services::tool::load_tool_registry() {
+        tracing::warn!(error = ?e, "加载工具注册表失败");
+    }
+
+    // 将旧版分散的配置备份导入配置档案存储（API Key 存入密钥库）
+    let imported = duckcoding::services::profile_store::ProfileStore::default_store()
+        .and_then(|store| duckcoding::ConfigService::import_legacy_profiles(&store));
+    match imported {
+        Ok(0) => {}
+        Ok(count) => tracing::info!(count, "旧版配置备份已导入配置档案存储"),
+        Err(e) => tracing::warn!(error = ?e, "导入旧版配置备份失败"),
+    }
+
     // 创建透明代理服务实例（旧架构，保持兼容）
     let transparent_proxy_port = 8787; // 默认端口,实际会从配置读取
     let transparent_proxy_service = TransparentProxyService::new(transparent_proxy_port);
@@ -168,6 +189,11 @@ fn main() {
         duckcoding::auto_start_proxies(&proxy_manager_for_auto_start).await;
     });
 
+    // 创建配置文件监听器（检测工具配置被外部修改）
+    let config_watcher_state = ConfigWatcherState {
+        watcher: Arc::new(ConfigWatcher::new()),
+    };
+
     let update_service_state = UpdateServiceState::new();
 
     // 创建工具状态缓存
@@ -181,6 +207,7 @@ fn main() {
         .manage(proxy_manager_state)
         .manage(update_service_state)
         .manage(tool_status_cache_state)
+        .manage(config_watcher_state)
         .setup(|app| {
             // 尝试在应用启动时加载全局配置并应用代理设置,确保子进程继承代理 env
             apply_proxy_if_configured();
@@ -289,6 +316,40 @@ fn main() {
                 });
             }
 
+            // 将代理状态变化推送到前端（托盘和仪表盘实时刷新）
+            let app_handle_for_proxy_status = app.handle().clone();
+            let manager_for_status = app.state::<ProxyManagerState>().manager.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut status_rx = manager_for_status.subscribe_status();
+                while status_rx.changed().await.is_ok() {
+                    // 合并短时间内的连续变化，避免高并发请求时频繁推送
+                    tokio::time::sleep(tokio::time::Duration::from_millis(250)).await;
+                    status_rx.borrow_and_update();
+
+                    let status = manager_for_status.get_all_status().await;
+                    if let Err(e) =
+                        duckcoding::emit_proxy_status(&app_handle_for_proxy_status, &status)
+                    {
+                        tracing::error!(error = ?e, "发送代理状态事件失败");
+                    }
+                }
+            });
+
+            // 监听工具配置文件，外部修改导致配置偏移时通知前端
+            let app_handle_for_drift = app.handle().clone();
+            let watcher_for_drift = app.state::<ConfigWatcherState>().watcher.clone();
+            let manager_for_drift = app.state::<ProxyManagerState>().manager.clone();
+            tauri::async_runtime::spawn(async move {
+                watcher_for_drift
+                    .watch(&manager_for_drift, |drift| {
+                        if let Err(e) = duckcoding::emit_config_drift(&app_handle_for_drift, drift)
+                        {
+                            tracing::error!(error = ?e, "发送配置偏移事件失败");
+                        }
+                    })
+                    .await;
+            });
+
             // 启动后延迟检查更新
             let app_handle_for_update = app.handle().clone();
             tauri::async_runtime::spawn(async move {
@@ -354,8 +415,27 @@ fn main() {
             list_profiles,
             switch_profile,
             delete_profile,
+            list_profile_records,
+            rename_profile,
+            duplicate_profile,
+            update_profile_details,
+            search_profiles,
+            check_profile_health,
+            check_all_profiles_health,
             get_active_config,
             get_profile_config,
+            // 配置导入导出
+            export_profile_bundle,
+            inspect_profile_bundle,
+            import_profile_bundle,
+            // 配置快照
+            list_config_snapshots,
+            restore_config_snapshot,
+            // 配置偏移检测
+            get_config_drifts,
+            reconcile_config_drift,
+            // 密钥库
+            get_vault_status,
             save_global_config,
             get_global_config,
             generate_api_key_for_tool,
@@ -384,12 +464,27 @@ fn main() {
             start_tool_proxy,
             stop_tool_proxy,
             get_all_proxy_status,
+            // 本地 API Key 管理命令
+            list_local_api_keys,
+            create_local_api_key,
+            revoke_local_api_key,
+            rotate_local_api_key,
+            get_proxy_tls_certificate,
+            // 请求抓包命令
+            set_proxy_capture_enabled,
+            get_proxy_capture,
+            replay_proxy_capture,
+            clear_proxy_captures,
             // 会话管理命令
             get_session_list,
             delete_session,
             clear_all_sessions,
             update_session_config,
             update_session_note,
+            get_session_token_usage,
+            get_tool_token_usage,
+            get_proxy_request_log,
+            clear_proxy_request_log,
             // 更新管理相关命令
             check_for_app_updates,
             download_app_update,
@@ -1,6 +1,7 @@
 // filepath: e:\DuckCoding\src-tauri\src\models\config.rs
 
 // 全局配置结构，移动到 models 以便在库和二进制之间共享
+use crate::utils::vault::VAULT_REF_PREFIX;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -95,6 +96,295 @@ pub struct ToolProxyConfig {
     pub session_endpoint_config_enabled: bool, // 工具级：是否允许会话自定义端点
     #[serde(default)]
     pub auto_start: bool, // 应用启动时自动运行代理（默认关闭）
+    #[serde(default)]
+    pub upstreams: Vec<UpstreamConfig>, // 备用上游（按顺序故障转移，real_* 为首选）
+    #[serde(default = "default_upstream_cooldown_secs")]
+    pub upstream_cooldown_secs: u64, // 上游失败后的冷却时间（秒）
+    #[serde(default)]
+    pub model_mappings: Vec<ModelMappingRule>, // 模型映射规则（按顺序匹配第一条）
+    #[serde(default)]
+    pub upstream_client: UpstreamClientConfig, // 上游 HTTP 客户端参数
+    #[serde(default = "default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64, // 停止代理时等待活跃连接结束的最长时间（秒）
+    #[serde(default)]
+    pub listener: ListenerConfig, // 本地监听连接参数（HTTP/1.1 与 h2c 自动协商）
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig, // 请求限流（工具级 + 会话级）
+    #[serde(default)]
+    pub local_api_keys: Vec<LocalApiKey>, // 额外的命名本地密钥（按密钥区分使用者）
+    #[serde(default)]
+    pub tls: TlsConfig, // 监听端口 TLS（开放公网访问时加密本地密钥）
+    #[serde(default)]
+    pub ip_filter: IpFilterConfig, // 连接来源过滤（开放公网访问时限制到指定网段）
+    #[serde(default)]
+    pub capture: CaptureConfig, // 请求抓包（调试用，默认关闭）
+}
+
+/// 透明代理请求抓包配置
+///
+/// 启用后每个请求的请求体与响应体（SSE 为完整事件流）脱敏后保存到
+/// ~/.duckcoding/captures/<工具>/，可在请求日志中查看并重放
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct CaptureConfig {
+    pub enabled: bool,
+    pub max_body_bytes: usize, // 单个请求体/响应体最多保存的字节数
+    pub max_files: usize,      // 每个工具最多保留的抓包数量（超出时删除最早的）
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_body_bytes: 1024 * 1024,
+            max_files: 200,
+        }
+    }
+}
+
+/// 透明代理连接来源过滤（CIDR 或单个 IP；本机回环地址始终放行）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct IpFilterConfig {
+    pub allow: Vec<String>, // 非空时只接受这些网段，如 "10.8.0.0/16"
+    pub deny: Vec<String>,  // 拒绝的网段（优先于 allow）
+}
+
+/// 透明代理监听端口的 TLS 配置
+///
+/// 启用后，非本机连接必须使用 TLS；本机（127.0.0.1 / ::1）仍可使用明文 HTTP，
+/// 写入工具配置的 http://127.0.0.1 地址无需改动。
+/// 未指定证书时自动生成自签名证书，保存在 ~/.duckcoding/tls
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct TlsConfig {
+    pub enabled: bool,
+    pub cert_path: Option<String>, // PEM 证书链路径（需与 key_path 同时配置）
+    pub key_path: Option<String>,  // PEM 私钥路径
+}
+
+impl TlsConfig {
+    /// 是否使用自动生成的自签名证书
+    pub fn is_self_signed(&self) -> bool {
+        self.cert_path.is_none() && self.key_path.is_none()
+    }
+}
+
+/// 透明代理的命名本地 API Key
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LocalApiKey {
+    pub id: String,      // 密钥 ID（记录到会话和请求日志）
+    pub key: String,     // 密钥内容
+    pub owner: String,   // 使用者标签
+    pub created_at: i64, // 创建时间（Unix 秒）
+    #[serde(default)]
+    pub expires_at: Option<i64>, // 过期时间（Unix 秒，None 表示永不过期）
+    #[serde(default = "default_true")]
+    pub enabled: bool, // 吊销后为 false
+}
+
+impl LocalApiKey {
+    /// 在指定时间是否可用
+    pub fn is_active(&self, now: i64) -> bool {
+        self.enabled && self.expires_at.is_none_or(|expires_at| now < expires_at)
+    }
+}
+
+/// 透明代理请求限流配置（None 或 0 表示不限制）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub requests_per_minute: Option<u32>,     // 整个代理每分钟请求数
+    pub max_concurrent_requests: Option<u32>, // 整个代理并发请求数
+    pub session_requests_per_minute: Option<u32>, // 单个会话每分钟请求数
+    pub session_max_concurrent_requests: Option<u32>, // 单个会话并发请求数
+}
+
+impl RateLimitConfig {
+    /// 是否配置了会话级限制
+    pub fn has_session_limits(&self) -> bool {
+        [
+            self.session_requests_per_minute,
+            self.session_max_concurrent_requests,
+        ]
+        .iter()
+        .any(|v| v.is_some_and(|v| v > 0))
+    }
+
+    /// 是否配置了任意限制
+    pub fn is_enabled(&self) -> bool {
+        self.has_session_limits()
+            || [self.requests_per_minute, self.max_concurrent_requests]
+                .iter()
+                .any(|v| v.is_some_and(|v| v > 0))
+    }
+}
+
+/// 透明代理本地监听连接参数（对新连接生效，无需重启）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ListenerConfig {
+    pub keep_alive: bool,                            // HTTP/1.1 是否复用连接
+    pub header_read_timeout_secs: u64,               // HTTP/1.1 读取请求头超时（0 表示不限制）
+    pub http2_keep_alive_interval_secs: Option<u64>, // HTTP/2 keep-alive ping 间隔（None 表示关闭）
+    pub http2_keep_alive_timeout_secs: u64,          // HTTP/2 ping 响应超时
+    pub http2_max_concurrent_streams: Option<u32>, // HTTP/2 单连接最大并发流（None 使用 hyper 默认值）
+}
+
+impl Default for ListenerConfig {
+    fn default() -> Self {
+        Self {
+            keep_alive: true,
+            header_read_timeout_secs: 30,
+            http2_keep_alive_interval_secs: Some(30),
+            http2_keep_alive_timeout_secs: 20,
+            http2_max_concurrent_streams: None,
+        }
+    }
+}
+
+/// 透明代理访问上游时的 HTTP 客户端参数
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct UpstreamClientConfig {
+    pub connect_timeout_secs: u64,          // 建立连接超时
+    pub read_timeout_secs: u64,             // 两次读取之间的超时（流式响应按块计算）
+    pub http2_keep_alive_secs: Option<u64>, // HTTP/2 keep-alive ping 间隔（None 表示关闭）
+    pub pool_idle_timeout_secs: u64,        // 空闲连接保留时间
+}
+
+impl Default for UpstreamClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 10,
+            read_timeout_secs: 300,
+            http2_keep_alive_secs: Some(30),
+            pool_idle_timeout_secs: 90,
+        }
+    }
+}
+
+/// 模型映射规则
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ModelMappingRule {
+    pub pattern: String, // glob 模式，如 "claude-opus-*"
+    pub target: String,  // 实际发送给上游的模型
+    #[serde(default)]
+    pub reverse: bool, // 响应中是否还原为客户端请求的模型名称
+}
+
+/// 透明代理的单个上游端点
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UpstreamConfig {
+    pub base_url: String,
+    pub api_key: String,
+    #[serde(default)]
+    pub weight: Option<u32>, // 权重：配置后在带权重的健康上游之间轮询，未配置时仅按顺序故障转移
+}
+
+impl UpstreamConfig {
+    /// 获取有效权重（至少为 1）
+    pub fn effective_weight(&self) -> u32 {
+        self.weight.unwrap_or(1).max(1)
+    }
+}
+
+fn default_upstream_cooldown_secs() -> u64 {
+    30
+}
+
+fn default_shutdown_grace_secs() -> u64 {
+    30
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl ToolProxyConfig {
+    /// 获取按优先级排列的上游列表
+    ///
+    /// real_base_url / real_api_key 作为首选上游，其后是 upstreams 中配置的备用上游，
+    /// 重复的 base_url 只保留第一次出现的条目；密钥仍是密钥库引用（未能解析）的上游不参与转发
+    pub fn upstream_candidates(&self) -> Vec<UpstreamConfig> {
+        let mut candidates: Vec<UpstreamConfig> = Vec::new();
+
+        if let (Some(base_url), Some(api_key)) = (&self.real_base_url, &self.real_api_key) {
+            if !api_key.starts_with(VAULT_REF_PREFIX) {
+                candidates.push(UpstreamConfig {
+                    base_url: base_url.clone(),
+                    api_key: api_key.clone(),
+                    weight: None,
+                });
+            }
+        }
+
+        for upstream in &self.upstreams {
+            if upstream.base_url.trim().is_empty()
+                || upstream.api_key.trim().is_empty()
+                || upstream.api_key.starts_with(VAULT_REF_PREFIX)
+            {
+                continue;
+            }
+            let key = upstream.base_url.trim_end_matches('/');
+            if candidates
+                .iter()
+                .any(|c| c.base_url.trim_end_matches('/') == key)
+            {
+                continue;
+            }
+            candidates.push(upstream.clone());
+        }
+
+        candidates
+    }
+
+    /// 配置中的所有密钥（用于抓包和日志脱敏）
+    pub fn secret_values(&self) -> Vec<String> {
+        let mut secrets: Vec<String> = self
+            .real_api_key
+            .iter()
+            .chain(self.local_api_key.iter())
+            .cloned()
+            .chain(self.upstreams.iter().map(|u| u.api_key.clone()))
+            .chain(self.local_api_keys.iter().map(|k| k.key.clone()))
+            .filter(|s| !s.is_empty())
+            .collect();
+        secrets.sort();
+        secrets.dedup();
+        secrets
+    }
+
+    /// 是否有密钥字段仍是密钥库引用（密钥库无法打开或缺少条目），此时代理不能运行
+    pub fn has_unresolved_secrets(&self) -> bool {
+        self.real_api_key
+            .iter()
+            .chain(self.local_api_key.iter())
+            .chain(self.upstreams.iter().map(|u| &u.api_key))
+            .chain(self.local_api_keys.iter().map(|k| &k.key))
+            .any(|value| value.starts_with(VAULT_REF_PREFIX))
+    }
+
+    /// 可变的密钥字段及其在密钥库中的条目 ID（以 `prefix` 开头）
+    pub fn secret_fields_mut(&mut self, prefix: &str) -> Vec<(String, &mut String)> {
+        let mut fields = Vec::new();
+        if let Some(key) = self.real_api_key.as_mut() {
+            fields.push((format!("{prefix}/real_api_key"), key));
+        }
+        if let Some(key) = self.local_api_key.as_mut() {
+            fields.push((format!("{prefix}/local_api_key"), key));
+        }
+        for (index, upstream) in self.upstreams.iter_mut().enumerate() {
+            fields.push((format!("{prefix}/upstreams/{index}"), &mut upstream.api_key));
+        }
+        for local_key in self.local_api_keys.iter_mut() {
+            fields.push((
+                format!("{prefix}/local_api_keys/{}", local_key.id),
+                &mut local_key.key,
+            ));
+        }
+        fields
+    }
 }
 
 impl Default for ToolProxyConfig {
@@ -110,6 +400,17 @@ impl Default for ToolProxyConfig {
             allow_public: false,
             session_endpoint_config_enabled: false,
             auto_start: false,
+            upstreams: Vec::new(),
+            upstream_cooldown_secs: default_upstream_cooldown_secs(),
+            model_mappings: Vec::new(),
+            upstream_client: UpstreamClientConfig::default(),
+            shutdown_grace_secs: default_shutdown_grace_secs(),
+            listener: ListenerConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            local_api_keys: Vec::new(),
+            tls: TlsConfig::default(),
+            ip_filter: IpFilterConfig::default(),
+            capture: CaptureConfig::default(),
         }
     }
 }
@@ -174,48 +475,24 @@ fn default_proxy_configs() -> HashMap<String, ToolProxyConfig> {
     configs.insert(
         "claude-code".to_string(),
         ToolProxyConfig {
-            enabled: false,
             port: 8787,
-            local_api_key: None,
-            real_api_key: None,
-            real_base_url: None,
-            real_model_provider: None,
-            real_profile_name: None,
-            allow_public: false,
-            session_endpoint_config_enabled: false,
-            auto_start: false,
+            ..Default::default()
         },
     );
 
     configs.insert(
         "codex".to_string(),
         ToolProxyConfig {
-            enabled: false,
             port: 8788,
-            local_api_key: None,
-            real_api_key: None,
-            real_base_url: None,
-            real_model_provider: None,
-            real_profile_name: None,
-            allow_public: false,
-            session_endpoint_config_enabled: false,
-            auto_start: false,
+            ..Default::default()
         },
     );
 
     configs.insert(
         "gemini-cli".to_string(),
         ToolProxyConfig {
-            enabled: false,
             port: 8789,
-            local_api_key: None,
-            real_api_key: None,
-            real_base_url: None,
-            real_model_provider: None,
-            real_profile_name: None,
-            allow_public: false,
-            session_endpoint_config_enabled: false,
-            auto_start: false,
+            ..Default::default()
         },
     );
 
@@ -233,21 +510,60 @@ impl GlobalConfig {
         self.proxy_configs.get_mut(tool_id)
     }
 
+    /// 配置中的所有密钥（系统令牌、代理密码及各工具代理的 API Key）
+    pub fn secret_values(&self) -> Vec<String> {
+        let mut secrets: Vec<String> = [
+            Some(&self.system_token),
+            self.proxy_password.as_ref(),
+            self.transparent_proxy_api_key.as_ref(),
+            self.transparent_proxy_real_api_key.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|s| !s.is_empty())
+        .cloned()
+        .chain(
+            self.proxy_configs
+                .values()
+                .flat_map(ToolProxyConfig::secret_values),
+        )
+        .collect();
+        secrets.sort();
+        secrets.dedup();
+        secrets
+    }
+
+    /// 可变的密钥字段及其在密钥库中的条目 ID（均以 `config/` 开头）
+    pub fn secret_fields_mut(&mut self) -> Vec<(String, &mut String)> {
+        let mut fields = vec![("config/system_token".to_string(), &mut self.system_token)];
+        for (name, value) in [
+            ("proxy_password", self.proxy_password.as_mut()),
+            (
+                "transparent_proxy_api_key",
+                self.transparent_proxy_api_key.as_mut(),
+            ),
+            (
+                "transparent_proxy_real_api_key",
+                self.transparent_proxy_real_api_key.as_mut(),
+            ),
+        ] {
+            if let Some(value) = value {
+                fields.push((format!("config/{name}"), value));
+            }
+        }
+        for (tool_id, proxy_config) in self.proxy_configs.iter_mut() {
+            fields.extend(proxy_config.secret_fields_mut(&format!("config/proxy/{tool_id}")));
+        }
+        fields
+    }
+
     /// 确保工具的代理配置存在（如果不存在则创建默认配置）
     pub fn ensure_proxy_config(&mut self, tool_id: &str, default_port: u16) {
         self.proxy_configs
             .entry(tool_id.to_string())
             .or_insert_with(|| ToolProxyConfig {
-                enabled: false,
                 port: default_port,
-                local_api_key: None,
-                real_api_key: None,
-                real_base_url: None,
-                real_model_provider: None,
-                real_profile_name: None,
-                allow_public: false,
-                session_endpoint_config_enabled: false,
-                auto_start: false,
+                ..Default::default()
             });
     }
 
@@ -1,7 +1,9 @@
 pub mod config;
+pub mod profile;
 pub mod tool;
 pub mod update;
 
 pub use config::*;
+pub use profile::*;
 pub use tool::*;
 pub use update::*;
@@ -0,0 +1,117 @@
+// 配置档案（profile）数据模型
+//
+// 每个工具的配置档案统一保存在 ~/.duckcoding/profiles/<tool_id>.json，
+// API Key 存入密钥库，记录中只保留引用。
+use serde::{Deserialize, Serialize};
+
+/// 配置档案
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProfileRecord {
+    pub id: String, // 唯一 ID（重命名后不变，密钥库条目以此为键）
+    pub tool_id: String,
+    pub name: String,
+    pub base_url: String,
+    pub api_key_ref: String, // 密钥库引用（vault:profile/<tool_id>/<id>）
+    #[serde(default)]
+    pub model: Option<String>, // .env 布局的 *_MODEL 变量、Codex 的 model 字段
+    #[serde(default)]
+    pub provider: Option<String>, // Codex 的 model_provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_settings: Option<serde_json::Map<String, serde_json::Value>>, // Codex 的 model_providers.<provider> 表（wire_api 等）
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub created_at: i64, // 创建时间（Unix 秒）
+    pub updated_at: i64, // 更新时间（Unix 秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_check: Option<ProfileHealth>, // 最近一次连通性检查结果
+}
+
+impl ProfileRecord {
+    pub fn new(tool_id: &str, name: &str) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            id: format!("{:x}{:04x}", now.timestamp_millis(), rand::random::<u16>()),
+            tool_id: tool_id.to_string(),
+            name: name.to_string(),
+            base_url: String::new(),
+            api_key_ref: String::new(),
+            model: None,
+            provider: None,
+            provider_settings: None,
+            notes: String::new(),
+            tags: Vec::new(),
+            created_at: now.timestamp(),
+            updated_at: now.timestamp(),
+            last_check: None,
+        }
+    }
+
+    /// 更新修改时间
+    pub fn touch(&mut self) {
+        self.updated_at = chrono::Utc::now().timestamp();
+    }
+
+    /// 是否匹配搜索关键字（不区分大小写，匹配名称、Base URL、模型、备注和标签）
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return true;
+        }
+        [
+            Some(self.name.as_str()),
+            Some(self.base_url.as_str()),
+            self.model.as_deref(),
+            self.provider.as_deref(),
+            Some(self.notes.as_str()),
+        ]
+        .into_iter()
+        .flatten()
+        .chain(self.tags.iter().map(String::as_str))
+        .any(|field| field.to_lowercase().contains(&query))
+    }
+
+    /// 是否带有指定标签（不区分大小写）
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag.trim()))
+    }
+}
+
+/// 可编辑的配置档案详情
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileDetails {
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// 连通性检查结论
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// 认证通过
+    Healthy,
+    /// 上游返回 401 / 403，API Key 无效或已过期
+    AuthFailed,
+    /// 上游返回其他错误状态码
+    HttpError,
+    /// 网络不可达或请求超时
+    Unreachable,
+}
+
+/// 配置档案连通性检查结果
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProfileHealth {
+    pub status: HealthStatus,
+    pub http_status: Option<u16>,
+    pub latency_ms: Option<u64>,
+    pub auth_valid: Option<bool>, // None 表示无法判断（如网络错误、模型列表接口不存在）
+    #[serde(default)]
+    pub models: Vec<String>, // 模型列表接口返回的可用模型
+    pub message: Option<String>,
+    pub checked_at: i64, // 检查时间（Unix 秒）
+}
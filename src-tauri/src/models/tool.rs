@@ -1,5 +1,11 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// 通过 tools.d 描述文件注册的工具（启动时加载）
+static REGISTERED_TOOLS: RwLock<Vec<Tool>> = RwLock::new(Vec::new());
 
 /// 工具状态
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,9 +26,33 @@ pub struct Tool {
     pub check_command: String,
     pub config_dir: PathBuf,
     pub config_file: String,
+    /// 配置文件布局（决定 API Key / Base URL 的读写方式）
+    #[serde(default)]
+    pub config_layout: ConfigLayout,
     pub env_vars: EnvVars,
+    /// 写入配置时补充的默认值（已存在则保留用户的值；JsonPointer 布局下键为 JSON Pointer）
+    #[serde(default)]
+    pub default_env: BTreeMap<String, String>,
+    /// 写入配置时合并到配置文件的默认设置（仅补充缺失的字段）
+    #[serde(default)]
+    pub default_settings: Option<Value>,
+    /// 透明代理转发时的认证头格式
+    #[serde(default)]
+    pub auth_header: AuthHeaderStyle,
+    /// 上游 API 格式（用于统一错误响应格式）
+    #[serde(default)]
+    pub api_format: ApiFormat,
+    /// 配置文件的内置 JSON Schema（导入配置时校验生成的配置片段）
+    #[serde(default)]
+    pub settings_schema: Option<SettingsSchema>,
+    /// 透明代理默认端口
+    #[serde(default)]
+    pub default_port: u16,
     /// 版本检查是否使用代理（某些工具如Claude Code在代理环境下会出错）
     pub use_proxy_for_version_check: bool,
+    /// 是否为内置工具（false 表示来自 tools.d 描述文件）
+    #[serde(default)]
+    pub builtin: bool,
 }
 
 /// 环境变量配置
@@ -30,6 +60,128 @@ pub struct Tool {
 pub struct EnvVars {
     pub api_key: String,
     pub base_url: String,
+    /// 模型字段（JSON 布局填写 env 变量名或 JSON Pointer，.env 布局未填写时见 `Tool::model_env_key`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+/// 配置文件布局
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigLayout {
+    /// JSON 配置文件的 env 对象（如 Claude Code 的 settings.json）
+    #[default]
+    JsonEnv,
+    /// 配置目录下的 .env 文件，可选合并 default_settings 到配置文件（如 Gemini CLI）
+    Dotenv,
+    /// config.toml + auth.json（CodeX）
+    CodexToml,
+    /// JSON 配置文件中由 JSON Pointer 指定的字段，env_vars 填写 Pointer
+    /// （如 opencode 的 /provider/duckcoding/options/apiKey）
+    JsonPointer,
+}
+
+/// 认证头格式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthHeaderStyle {
+    /// Authorization: Bearer <key>
+    #[default]
+    Bearer,
+    /// x-api-key: <key>
+    XApiKey,
+    /// x-goog-api-key: <key>
+    XGoogApiKey,
+}
+
+/// 上游 API 格式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiFormat {
+    Anthropic,
+    #[default]
+    #[serde(rename = "openai")]
+    OpenAI,
+    Gemini,
+}
+
+/// 内置的配置文件 JSON Schema（resources/ 目录）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingsSchema {
+    /// Claude Code settings.json
+    ClaudeCode,
+    /// CodeX config.toml
+    Codex,
+    /// Gemini CLI settings.json
+    GeminiCli,
+}
+
+/// 工具描述文件（~/.duckcoding/tools.d/*.json 或 *.toml，每个文件一个工具）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolDescriptor {
+    pub id: String,
+    pub name: String,
+    /// 默认为 "<name> 专用分组"
+    #[serde(default)]
+    pub group_name: Option<String>,
+    pub npm_package: String,
+    pub check_command: String,
+    /// 配置目录，支持 ~ 开头表示用户主目录
+    pub config_dir: String,
+    pub config_file: String,
+    pub config_layout: ConfigLayout,
+    pub env_vars: EnvVars,
+    #[serde(default)]
+    pub default_env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub default_settings: Option<Value>,
+    #[serde(default)]
+    pub auth_header: AuthHeaderStyle,
+    #[serde(default)]
+    pub api_format: ApiFormat,
+    #[serde(default)]
+    pub settings_schema: Option<SettingsSchema>,
+    pub default_port: u16,
+    #[serde(default = "default_true")]
+    pub use_proxy_for_version_check: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl ToolDescriptor {
+    /// 转换为工具定义（`home_dir` 用于展开 config_dir 中的 ~）
+    pub fn into_tool(self, home_dir: &Path) -> Tool {
+        let config_dir = match self.config_dir.strip_prefix('~') {
+            Some(rest) => home_dir.join(rest.trim_start_matches(['/', '\\'])),
+            None => PathBuf::from(&self.config_dir),
+        };
+        let group_name = self
+            .group_name
+            .unwrap_or_else(|| format!("{} 专用分组", self.name));
+
+        Tool {
+            id: self.id,
+            name: self.name,
+            group_name,
+            npm_package: self.npm_package,
+            check_command: self.check_command,
+            config_dir,
+            config_file: self.config_file,
+            config_layout: self.config_layout,
+            env_vars: self.env_vars,
+            default_env: self.default_env,
+            default_settings: self.default_settings,
+            auth_header: self.auth_header,
+            api_format: self.api_format,
+            settings_schema: self.settings_schema,
+            default_port: self.default_port,
+            use_proxy_for_version_check: self.use_proxy_for_version_check,
+            builtin: false,
+        }
+    }
 }
 
 /// 安装方法
@@ -41,9 +193,34 @@ pub enum InstallMethod {
 }
 
 impl Tool {
-    /// 获取所有工具
+    /// 获取所有工具（内置工具 + tools.d 注册的工具）
     pub fn all() -> Vec<Tool> {
-        vec![Tool::claude_code(), Tool::codex(), Tool::gemini_cli()]
+        let mut tools = Self::builtin_tools();
+        tools.extend(
+            REGISTERED_TOOLS
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .iter()
+                .cloned(),
+        );
+        tools
+    }
+
+    /// 获取内置工具
+    pub fn builtin_tools() -> Vec<Tool> {
+        vec![
+            Tool::claude_code(),
+            Tool::codex(),
+            Tool::gemini_cli(),
+            Tool::qwen_code(),
+            Tool::opencode(),
+            Tool::crush(),
+        ]
+    }
+
+    /// 替换 tools.d 注册的工具列表
+    pub fn set_registered(tools: Vec<Tool>) {
+        *REGISTERED_TOOLS.write().unwrap_or_else(|e| e.into_inner()) = tools;
     }
 
     /// 根据 ID 获取工具
@@ -63,11 +240,20 @@ impl Tool {
             check_command: "claude --version".to_string(),
             config_dir: home_dir.join(".claude"),
             config_file: "settings.json".to_string(),
+            config_layout: ConfigLayout::JsonEnv,
             env_vars: EnvVars {
                 api_key: "ANTHROPIC_AUTH_TOKEN".to_string(),
                 base_url: "ANTHROPIC_BASE_URL".to_string(),
+                model: Some("ANTHROPIC_MODEL".to_string()),
             },
+            default_env: BTreeMap::new(),
+            default_settings: None,
+            auth_header: AuthHeaderStyle::Bearer,
+            settings_schema: Some(SettingsSchema::ClaudeCode),
+            api_format: ApiFormat::Anthropic,
+            default_port: 8787,
             use_proxy_for_version_check: false, // Claude Code在代理环境下会出现URL协议错误
+            builtin: true,
         }
     }
 
@@ -83,11 +269,20 @@ impl Tool {
             check_command: "codex --version".to_string(),
             config_dir: home_dir.join(".codex"),
             config_file: "config.toml".to_string(),
+            config_layout: ConfigLayout::CodexToml,
             env_vars: EnvVars {
                 api_key: "OPENAI_API_KEY".to_string(),
                 base_url: "base_url".to_string(), // TOML key
+                model: None,
             },
+            default_env: BTreeMap::new(),
+            default_settings: None,
+            auth_header: AuthHeaderStyle::Bearer,
+            settings_schema: Some(SettingsSchema::Codex),
+            api_format: ApiFormat::OpenAI,
+            default_port: 8788,
             use_proxy_for_version_check: true, // CodeX可以使用代理
+            builtin: true,
         }
     }
 
@@ -103,12 +298,189 @@ impl Tool {
             check_command: "gemini --version".to_string(),
             config_dir: home_dir.join(".gemini"),
             config_file: "settings.json".to_string(),
+            config_layout: ConfigLayout::Dotenv,
             env_vars: EnvVars {
                 api_key: "GEMINI_API_KEY".to_string(),
                 base_url: "GOOGLE_GEMINI_BASE_URL".to_string(),
+                model: None,
             },
+            default_env: BTreeMap::from([(
+                "GEMINI_MODEL".to_string(),
+                "gemini-2.5-pro".to_string(),
+            )]),
+            default_settings: Some(serde_json::json!({
+                "ide": {"enabled": true},
+                "security": {"auth": {"selectedType": "gemini-api-key"}}
+            })),
+            auth_header: AuthHeaderStyle::XGoogApiKey,
+            settings_schema: Some(SettingsSchema::GeminiCli),
+            api_format: ApiFormat::Gemini,
+            default_port: 8789,
             use_proxy_for_version_check: true, // Gemini CLI可以使用代理
+            builtin: true,
+        }
+    }
+
+    /// Qwen Code 定义（OpenAI 兼容接口，配置写入 ~/.qwen/.env）
+    pub fn qwen_code() -> Tool {
+        let home_dir = dirs::home_dir().expect("无法获取用户主目录");
+
+        Tool {
+            id: "qwen-code".to_string(),
+            name: "Qwen Code".to_string(),
+            group_name: "Qwen Code 专用分组".to_string(),
+            npm_package: "@qwen-code/qwen-code".to_string(),
+            check_command: "qwen --version".to_string(),
+            config_dir: home_dir.join(".qwen"),
+            config_file: "settings.json".to_string(),
+            config_layout: ConfigLayout::Dotenv,
+            env_vars: EnvVars {
+                api_key: "OPENAI_API_KEY".to_string(),
+                base_url: "OPENAI_BASE_URL".to_string(),
+                model: None,
+            },
+            default_env: BTreeMap::from([(
+                "OPENAI_MODEL".to_string(),
+                "qwen3-coder-plus".to_string(),
+            )]),
+            default_settings: Some(serde_json::json!({
+                "security": {"auth": {"selectedType": "openai"}}
+            })),
+            auth_header: AuthHeaderStyle::Bearer,
+            settings_schema: None,
+            api_format: ApiFormat::OpenAI,
+            default_port: 8790,
+            use_proxy_for_version_check: true,
+            builtin: true,
+        }
+    }
+
+    /// opencode 定义（多 provider JSON 配置，DuckCoding 作为 Anthropic 兼容 provider 写入）
+    pub fn opencode() -> Tool {
+        let home_dir = dirs::home_dir().expect("无法获取用户主目录");
+
+        Tool {
+            id: "opencode".to_string(),
+            name: "opencode".to_string(),
+            group_name: "opencode 专用分组".to_string(),
+            npm_package: "opencode-ai".to_string(),
+            check_command: "opencode --version".to_string(),
+            config_dir: home_dir.join(".config").join("opencode"),
+            config_file: "opencode.json".to_string(),
+            config_layout: ConfigLayout::JsonPointer,
+            env_vars: EnvVars {
+                api_key: "/provider/duckcoding/options/apiKey".to_string(),
+                base_url: "/provider/duckcoding/options/baseURL".to_string(),
+                model: None,
+            },
+            default_env: BTreeMap::new(),
+            default_settings: Some(serde_json::json!({
+                "$schema": "https://opencode.ai/config.json",
+                "model": "duckcoding/claude-sonnet-4-5",
+                "provider": {
+                    "duckcoding": {
+                        "npm": "@ai-sdk/anthropic",
+                        "name": "DuckCoding",
+                        "models": {
+                            "claude-sonnet-4-5": {"name": "Claude Sonnet 4.5"}
+                        }
+                    }
+                }
+            })),
+            auth_header: AuthHeaderStyle::XApiKey,
+            settings_schema: None,
+            api_format: ApiFormat::Anthropic,
+            default_port: 8791,
+            use_proxy_for_version_check: true,
+            builtin: true,
+        }
+    }
+
+    /// Crush 定义（providers JSON 配置，请求由通用处理器转发）
+    pub fn crush() -> Tool {
+        let home_dir = dirs::home_dir().expect("无法获取用户主目录");
+        // Crush 在 Windows 上使用 %LOCALAPPDATA%\crush
+        let config_dir = if cfg!(target_os = "windows") {
+            dirs::data_local_dir()
+                .unwrap_or_else(|| home_dir.join("AppData").join("Local"))
+                .join("crush")
+        } else {
+            home_dir.join(".config").join("crush")
+        };
+
+        Tool {
+            id: "crush".to_string(),
+            name: "Crush".to_string(),
+            group_name: "Crush 专用分组".to_string(),
+            npm_package: "@charmland/crush".to_string(),
+            check_command: "crush --version".to_string(),
+            config_dir,
+            config_file: "crush.json".to_string(),
+            config_layout: ConfigLayout::JsonPointer,
+            env_vars: EnvVars {
+                api_key: "/providers/duckcoding/api_key".to_string(),
+                base_url: "/providers/duckcoding/base_url".to_string(),
+                model: None,
+            },
+            default_env: BTreeMap::new(),
+            default_settings: Some(serde_json::json!({
+                "$schema": "https://charm.land/crush.json",
+                "providers": {
+                    "duckcoding": {
+                        "name": "DuckCoding",
+                        "type": "anthropic",
+                        "models": [{
+                            "id": "claude-sonnet-4-5",
+                            "name": "Claude Sonnet 4.5",
+                            "context_window": 200000,
+                            "default_max_tokens": 32000
+                        }]
+                    }
+                }
+            })),
+            auth_header: AuthHeaderStyle::XApiKey,
+            settings_schema: None,
+            api_format: ApiFormat::Anthropic,
+            default_port: 8792,
+            use_proxy_for_version_check: true,
+            builtin: true,
+        }
+    }
+
+    /// 读取 JSON 配置中的 API 字段
+    ///
+    /// JsonEnv 布局读取 env 对象中的字段，JsonPointer 布局按 JSON Pointer 读取
+    pub fn json_config_value<'a>(&self, settings: &'a Value, key: &str) -> Option<&'a str> {
+        match self.config_layout {
+            ConfigLayout::JsonPointer => settings.pointer(key),
+            _ => settings.get("env").and_then(|env| env.get(key)),
         }
+        .and_then(|v| v.as_str())
+    }
+
+    /// 写入 JSON 配置中的 API 字段（自动创建缺失的中间对象）
+    pub fn set_json_config_value(&self, settings: &mut Value, key: &str, value: &str) {
+        let path: Vec<String> = match self.config_layout {
+            ConfigLayout::JsonPointer => key
+                .split('/')
+                .skip(1)
+                .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+                .collect(),
+            _ => vec!["env".to_string(), key.to_string()],
+        };
+
+        let mut current = settings;
+        for segment in path {
+            if !current.is_object() {
+                *current = Value::Object(serde_json::Map::new());
+            }
+            current = current
+                .as_object_mut()
+                .unwrap()
+                .entry(segment)
+                .or_insert(Value::Null);
+        }
+        *current = Value::String(value.to_string());
     }
 
     /// 获取可用的安装方法
@@ -127,10 +499,8 @@ impl Tool {
                 }
                 methods.push(InstallMethod::Npm);
             }
-            "gemini-cli" => {
-                methods.push(InstallMethod::Npm);
-            }
-            _ => {}
+            // Gemini CLI、Qwen Code、opencode、Crush 及 tools.d 注册的工具使用 npm 安装
+            _ => methods.push(InstallMethod::Npm),
         }
 
         methods
@@ -153,8 +523,7 @@ impl Tool {
                     InstallMethod::Npm
                 }
             }
-            "gemini-cli" => InstallMethod::Npm,
-            _ => InstallMethod::Official,
+            _ => InstallMethod::Npm,
         }
     }
 
@@ -177,6 +546,29 @@ impl Tool {
                 .join(format!("{basename}.{profile_name}.{ext}"))
         }
     }
+
+    /// 表示模型的配置字段（env_vars.model，未填写时取 default_env 中以 _MODEL 结尾的键）
+    pub fn model_env_key(&self) -> Option<&str> {
+        self.env_vars.model.as_deref().or_else(|| {
+            self.default_env
+                .keys()
+                .map(String::as_str)
+                .find(|key| key.ends_with("_MODEL"))
+        })
+    }
+
+    /// 获取当前生效的配置文件路径（不含备份 profile）
+    pub fn config_files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        if self.config_layout == ConfigLayout::Dotenv {
+            files.push(self.config_dir.join(".env"));
+        }
+        files.push(self.config_dir.join(&self.config_file));
+        if self.config_layout == ConfigLayout::CodexToml {
+            files.push(self.config_dir.join("auth.json"));
+        }
+        files
+    }
 }
 
 /// Provider 配置
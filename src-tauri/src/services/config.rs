@@ -1,25 +1,22 @@
-use crate::models::Tool;
+use crate::models::{ConfigLayout, ProfileDetails, ProfileRecord, SettingsSchema, Tool};
+use crate::services::profile_bundle::{
+    decode_bundle, encode_bundle, next_available_name, BundleImportReport, BundleProfile,
+    ConflictStrategy, ImportAction, ImportOutcome, ProfileRef,
+};
+use crate::services::profile_store::{validate_profile_name, ProfileStore};
+use crate::utils::json_schema::{self, SchemaValidationError, SchemaViolation, ValidateOptions};
+use crate::utils::vault::{profile_secret_id, vault_ref_id};
+use crate::utils::{write_atomic, write_atomic_with, SnapshotStore};
 use anyhow::{anyhow, Context, Result};
 use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
-use serde_json::{Map, Value};
-use std::collections::HashMap;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use toml;
 use toml_edit::{DocumentMut, Item, Table};
 
-// Codex provider 配置必需字段
-const CODEX_PROVIDER_REQUIRED_FIELDS: &[&str] =
-    &["name", "base_url", "wire_api", "requires_openai_auth"];
-
-/// 检查 Codex provider 配置是否完整（包含所有必需字段）
-fn is_complete_provider_config(table: &toml_edit::Table) -> bool {
-    CODEX_PROVIDER_REQUIRED_FIELDS
-        .iter()
-        .all(|field| table.contains_key(field))
-}
-
 #[derive(Serialize, Deserialize)]
 pub struct CodexSettingsPayload {
     pub config: Value,
@@ -127,28 +124,38 @@ pub struct ConfigService;
 impl ConfigService {
     /// 应用配置（增量更新）
     pub fn apply_config(
+        store: &ProfileStore,
         tool: &Tool,
         api_key: &str,
         base_url: &str,
         profile_name: Option<&str>,
     ) -> Result<()> {
-        match tool.id.as_str() {
-            "claude-code" => Self::apply_claude_config(tool, api_key, base_url)?,
-            "codex" => Self::apply_codex_config(tool, api_key, base_url)?,
-            "gemini-cli" => Self::apply_gemini_config(tool, api_key, base_url)?,
-            _ => anyhow::bail!("未知工具: {}", tool.id),
+        let snapshots = store.snapshots();
+        match tool.config_layout {
+            ConfigLayout::JsonEnv | ConfigLayout::JsonPointer => {
+                Self::apply_json_config(tool, api_key, base_url, snapshots)?
+            }
+            ConfigLayout::CodexToml => {
+                Self::apply_codex_config(tool, api_key, base_url, snapshots)?
+            }
+            ConfigLayout::Dotenv => Self::apply_dotenv_config(tool, api_key, base_url, snapshots)?,
         }
 
         // 保存命名配置的备份副本
         if let Some(profile) = profile_name {
-            Self::save_backup(tool, profile)?;
+            Self::save_backup(store, tool, profile)?;
         }
 
         Ok(())
     }
 
-    /// Claude Code 配置
-    fn apply_claude_config(tool: &Tool, api_key: &str, base_url: &str) -> Result<()> {
+    /// JSON 布局配置（Claude Code 的 env 对象、opencode / Crush 的 provider 字段）
+    fn apply_json_config(
+        tool: &Tool,
+        api_key: &str,
+        base_url: &str,
+        snapshots: Option<&SnapshotStore>,
+    ) -> Result<()> {
         let config_path = tool.config_dir.join(&tool.config_file);
 
         // 读取现有配置
@@ -159,33 +166,24 @@ impl ConfigService {
             Value::Object(Map::new())
         };
 
-        // 确保有 env 字段
-        if !settings.is_object() {
-            settings = serde_json::json!({});
+        // 只更新 API 相关字段
+        tool.set_json_config_value(&mut settings, &tool.env_vars.api_key, api_key);
+        tool.set_json_config_value(&mut settings, &tool.env_vars.base_url, base_url);
+        for (key, value) in &tool.default_env {
+            if tool.json_config_value(&settings, key).is_none() {
+                tool.set_json_config_value(&mut settings, key, value);
+            }
         }
-
-        let obj = settings.as_object_mut().unwrap();
-        if !obj.contains_key("env") {
-            obj.insert("env".to_string(), Value::Object(Map::new()));
+        if let Some(defaults) = &tool.default_settings {
+            Self::merge_default_settings(&mut settings, defaults);
         }
 
-        // 只更新 API 相关字段
-        let env = obj.get_mut("env").unwrap().as_object_mut().unwrap();
-        env.insert(
-            tool.env_vars.api_key.clone(),
-            Value::String(api_key.to_string()),
-        );
-        env.insert(
-            tool.env_vars.base_url.clone(),
-            Value::String(base_url.to_string()),
-        );
-
         // 确保目录存在
         fs::create_dir_all(&tool.config_dir)?;
 
         // 写入配置
         let json = serde_json::to_string_pretty(&settings)?;
-        fs::write(&config_path, json)?;
+        write_atomic_with(&config_path, json, snapshots)?;
 
         #[cfg(unix)]
         {
@@ -200,7 +198,12 @@ impl ConfigService {
     }
 
     /// CodeX 配置（使用 toml_edit 保留注释和格式）
-    fn apply_codex_config(tool: &Tool, api_key: &str, base_url: &str) -> Result<()> {
+    fn apply_codex_config(
+        tool: &Tool,
+        api_key: &str,
+        base_url: &str,
+        snapshots: Option<&SnapshotStore>,
+    ) -> Result<()> {
         let config_path = tool.config_dir.join(&tool.config_file);
         let auth_path = tool.config_dir.join("auth.json");
 
@@ -286,7 +289,7 @@ impl ConfigService {
         }
 
         // 写入 config.toml（保留注释和格式）
-        fs::write(&config_path, doc.to_string())?;
+        write_atomic_with(&config_path, doc.to_string(), snapshots)?;
 
         // 更新 auth.json（增量）
         let mut auth_data = if auth_path.exists() {
@@ -303,7 +306,11 @@ impl ConfigService {
             );
         }
 
-        fs::write(&auth_path, serde_json::to_string_pretty(&auth_data)?)?;
+        write_atomic_with(
+            &auth_path,
+            serde_json::to_string_pretty(&auth_data)?,
+            snapshots,
+        )?;
 
         #[cfg(unix)]
         {
@@ -321,67 +328,51 @@ impl ConfigService {
         Ok(())
     }
 
-    /// Gemini CLI 配置
-    fn apply_gemini_config(tool: &Tool, api_key: &str, base_url: &str) -> Result<()> {
+    /// .env 布局配置（Gemini CLI 等）
+    fn apply_dotenv_config(
+        tool: &Tool,
+        api_key: &str,
+        base_url: &str,
+        snapshots: Option<&SnapshotStore>,
+    ) -> Result<()> {
         let env_path = tool.config_dir.join(".env");
         let settings_path = tool.config_dir.join(&tool.config_file);
 
         // 确保目录存在
         fs::create_dir_all(&tool.config_dir)?;
 
-        // 读取现有 .env
-        let mut env_vars = HashMap::new();
-        if env_path.exists() {
-            let content = fs::read_to_string(&env_path)?;
-            for line in content.lines() {
-                let trimmed = line.trim();
-                if !trimmed.is_empty() && !trimmed.starts_with('#') {
-                    if let Some((key, value)) = trimmed.split_once('=') {
-                        env_vars.insert(key.trim().to_string(), value.trim().to_string());
-                    }
-                }
-            }
-        }
-
-        // 更新 API 相关字段
-        env_vars.insert("GOOGLE_GEMINI_BASE_URL".to_string(), base_url.to_string());
-        env_vars.insert("GEMINI_API_KEY".to_string(), api_key.to_string());
-        if !env_vars.contains_key("GEMINI_MODEL") {
-            env_vars.insert("GEMINI_MODEL".to_string(), "gemini-2.5-pro".to_string());
+        // 读取现有 .env，只更新 API 相关字段
+        let mut env_vars = Self::read_env_pairs(&env_path)?;
+        env_vars.insert(tool.env_vars.base_url.clone(), base_url.to_string());
+        env_vars.insert(tool.env_vars.api_key.clone(), api_key.to_string());
+        for (key, value) in &tool.default_env {
+            env_vars.entry(key.clone()).or_insert_with(|| value.clone());
         }
+        Self::write_env_pairs(&env_path, &env_vars, snapshots)?;
 
-        // 写入 .env
-        let env_content: Vec<String> = env_vars.iter().map(|(k, v)| format!("{k}={v}")).collect();
-        fs::write(&env_path, env_content.join("\n") + "\n")?;
-
-        // 读取并更新 settings.json
-        let mut settings = if settings_path.exists() {
-            let content = fs::read_to_string(&settings_path)?;
-            serde_json::from_str::<Value>(&content).unwrap_or(Value::Object(Map::new()))
-        } else {
-            Value::Object(Map::new())
-        };
+        let mut written = vec![env_path];
 
-        if let Value::Object(ref mut obj) = settings {
-            if !obj.contains_key("ide") {
-                obj.insert("ide".to_string(), serde_json::json!({"enabled": true}));
-            }
-            if !obj.contains_key("security") {
-                obj.insert(
-                    "security".to_string(),
-                    serde_json::json!({
-                        "auth": {"selectedType": "gemini-api-key"}
-                    }),
-                );
-            }
+        // 补充配置文件中的默认设置
+        if let Some(defaults) = &tool.default_settings {
+            let mut settings = if settings_path.exists() {
+                let content = fs::read_to_string(&settings_path)?;
+                serde_json::from_str::<Value>(&content).unwrap_or(Value::Object(Map::new()))
+            } else {
+                Value::Object(Map::new())
+            };
+            Self::merge_default_settings(&mut settings, defaults);
+            write_atomic_with(
+                &settings_path,
+                serde_json::to_string_pretty(&settings)?,
+                snapshots,
+            )?;
+            written.push(settings_path);
         }
 
-        fs::write(&settings_path, serde_json::to_string_pretty(&settings)?)?;
-
         #[cfg(unix)]
         {
             use std::os::unix::fs::PermissionsExt;
-            for path in [&env_path, &settings_path] {
+            for path in &written {
                 if path.exists() {
                     let metadata = fs::metadata(path)?;
                     let mut perms = metadata.permissions();
@@ -394,161 +385,757 @@ impl ConfigService {
         Ok(())
     }
 
-    /// 保存备份配置
-    pub fn save_backup(tool: &Tool, profile_name: &str) -> Result<()> {
-        match tool.id.as_str() {
-            "claude-code" => Self::backup_claude(tool, profile_name)?,
-            "codex" => Self::backup_codex(tool, profile_name)?,
-            "gemini-cli" => Self::backup_gemini(tool, profile_name)?,
-            _ => anyhow::bail!("未知工具: {}", tool.id),
+    /// 合并默认设置（递归补充缺失的字段，不覆盖用户配置）
+    fn merge_default_settings(settings: &mut Value, defaults: &Value) {
+        if !settings.is_object() {
+            *settings = Value::Object(Map::new());
+        }
+        let (Some(obj), Some(defaults)) = (settings.as_object_mut(), defaults.as_object()) else {
+            return;
+        };
+        for (key, value) in defaults {
+            match obj.get_mut(key) {
+                Some(existing) if existing.is_object() && value.is_object() => {
+                    Self::merge_default_settings(existing, value);
+                }
+                Some(_) => {}
+                None => {
+                    obj.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+
+    /// 把当前生效的配置保存为配置档案（同名档案覆盖，否则新建）
+    pub fn save_backup(store: &ProfileStore, tool: &Tool, profile_name: &str) -> Result<()> {
+        validate_profile_name(profile_name)?;
+        let (api_key, base_url) = Self::read_api_config(tool)?;
+        if api_key.is_empty() || base_url.is_empty() {
+            anyhow::bail!("当前配置缺少 API Key 或 Base URL，无法保存");
         }
+
+        let mut record = store
+            .get(&tool.id, profile_name)?
+            .unwrap_or_else(|| ProfileRecord::new(&tool.id, profile_name));
+        record.base_url = base_url;
+        // API Key 存入密钥库，档案中只保留引用
+        record.api_key_ref = store
+            .vault()
+            .seal(&profile_secret_id(&tool.id, &record.id), &api_key)?;
+        record.last_check = None;
+        // JSON 布局的模型只作记录，保留用户填写的值
+        if !matches!(
+            tool.config_layout,
+            ConfigLayout::JsonEnv | ConfigLayout::JsonPointer
+        ) {
+            (record.model, record.provider) = Self::read_model_config(tool)?;
+        }
+        if tool.config_layout == ConfigLayout::CodexToml {
+            record.provider_settings = Self::codex_provider_settings(&Self::read_codex_doc(tool)?);
+        }
+        record.touch();
+        store.save(&record)?;
+        Self::refresh_log_secrets(store);
         Ok(())
     }
 
-    fn backup_claude(tool: &Tool, profile_name: &str) -> Result<()> {
-        let config_path = tool.config_dir.join(&tool.config_file);
-        let backup_path = tool.backup_path(profile_name);
+    /// 读取当前生效的模型和 provider（.env 布局的 *_MODEL 变量、Codex 的 model / model_provider）
+    fn read_model_config(tool: &Tool) -> Result<(Option<String>, Option<String>)> {
+        let non_empty = |value: Option<&str>| {
+            value
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+        match tool.config_layout {
+            ConfigLayout::JsonEnv | ConfigLayout::JsonPointer => Ok((None, None)),
+            ConfigLayout::CodexToml => {
+                let doc = Self::read_codex_doc(tool)?;
+                Ok((
+                    non_empty(doc.get("model").and_then(|v| v.as_str())),
+                    non_empty(doc.get("model_provider").and_then(|v| v.as_str())),
+                ))
+            }
+            ConfigLayout::Dotenv => {
+                let env_vars = Self::read_env_pairs(&tool.config_dir.join(".env"))?;
+                let model = tool
+                    .model_env_key()
+                    .and_then(|key| env_vars.get(key))
+                    .map(String::as_str);
+                Ok((non_empty(model), None))
+            }
+        }
+    }
 
-        if !config_path.exists() {
-            anyhow::bail!("配置文件不存在，无法备份");
-        }
-
-        // 读取当前配置，只提取 API 相关字段
-        let content = fs::read_to_string(&config_path).context("读取配置文件失败")?;
-        let settings: Value = serde_json::from_str(&content).context("解析配置文件失败")?;
-
-        // 只保存 API 相关字段
-        let backup_data = serde_json::json!({
-            "ANTHROPIC_AUTH_TOKEN": settings
-                .get("env")
-                .and_then(|env| env.get("ANTHROPIC_AUTH_TOKEN"))
-                .and_then(|v| v.as_str())
-                .unwrap_or(""),
-            "ANTHROPIC_BASE_URL": settings
-                .get("env")
-                .and_then(|env| env.get("ANTHROPIC_BASE_URL"))
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-        });
+    /// 列出所有保存的配置名称
+    pub fn list_profiles(store: &ProfileStore, tool: &Tool) -> Result<Vec<String>> {
+        Ok(store.list(&tool.id)?.into_iter().map(|p| p.name).collect())
+    }
+
+    /// 列出所有保存的配置档案
+    pub fn list_profile_records(store: &ProfileStore, tool: &Tool) -> Result<Vec<ProfileRecord>> {
+        store.list(&tool.id)
+    }
 
-        // 写入备份（仅包含 API 字段）
-        fs::write(&backup_path, serde_json::to_string_pretty(&backup_data)?)?;
+    /// 获取指定名称的配置档案
+    pub fn get_profile(
+        store: &ProfileStore,
+        tool: &Tool,
+        profile_name: &str,
+    ) -> Result<ProfileRecord> {
+        store
+            .get(&tool.id, profile_name)?
+            .ok_or_else(|| anyhow!("配置不存在: {profile_name}"))
+    }
 
+    /// 激活指定的配置
+    pub fn activate_profile(store: &ProfileStore, tool: &Tool, profile_name: &str) -> Result<()> {
+        let record = Self::get_profile(store, tool, profile_name)?;
+        let api_key = store.vault().resolve(&record.api_key_ref)?;
+        let snapshots = store.snapshots();
+        match tool.config_layout {
+            ConfigLayout::JsonEnv | ConfigLayout::JsonPointer => {
+                Self::activate_json(tool, &record, &api_key, snapshots)?
+            }
+            ConfigLayout::CodexToml => Self::activate_codex(tool, &record, &api_key, snapshots)?,
+            ConfigLayout::Dotenv => Self::activate_dotenv(tool, &record, &api_key, snapshots)?,
+        }
         Ok(())
     }
 
-    fn backup_codex(tool: &Tool, profile_name: &str) -> Result<()> {
-        let config_path = tool.config_dir.join("config.toml");
-        let auth_path = tool.config_dir.join("auth.json");
+    fn activate_json(
+        tool: &Tool,
+        record: &ProfileRecord,
+        api_key: &str,
+        snapshots: Option<&SnapshotStore>,
+    ) -> Result<()> {
+        let active_path = tool.config_dir.join(&tool.config_file);
 
-        let backup_config = tool.config_dir.join(format!("config.{profile_name}.toml"));
-        let backup_auth = tool.config_dir.join(format!("auth.{profile_name}.json"));
+        // 读取当前配置（保留其他字段）
+        let mut settings = if active_path.exists() {
+            let content = fs::read_to_string(&active_path).context("读取当前配置失败")?;
+            serde_json::from_str::<Value>(&content).unwrap_or(Value::Object(Map::new()))
+        } else {
+            Value::Object(Map::new())
+        };
 
-        // 读取 auth.json 中的 API Key
-        let api_key = if auth_path.exists() {
-            let content = fs::read_to_string(&auth_path)?;
-            let auth: Value = serde_json::from_str(&content)?;
-            auth.get("OPENAI_API_KEY")
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string()
+        // 只更新 API 字段，保留其他配置
+        tool.set_json_config_value(&mut settings, &tool.env_vars.api_key, api_key);
+        tool.set_json_config_value(&mut settings, &tool.env_vars.base_url, &record.base_url);
+
+        fs::create_dir_all(&tool.config_dir)?;
+        write_atomic_with(
+            &active_path,
+            serde_json::to_string_pretty(&settings)?,
+            snapshots,
+        )?;
+
+        Ok(())
+    }
+
+    fn activate_codex(
+        tool: &Tool,
+        record: &ProfileRecord,
+        api_key: &str,
+        snapshots: Option<&SnapshotStore>,
+    ) -> Result<()> {
+        let active_config = tool.config_dir.join(&tool.config_file);
+        let active_auth = tool.config_dir.join("auth.json");
+
+        fs::create_dir_all(&tool.config_dir)?;
+
+        // 增量更新 auth.json（保留其他字段）
+        let mut auth_data = if active_auth.exists() {
+            let content = fs::read_to_string(&active_auth)?;
+            serde_json::from_str::<Value>(&content).unwrap_or(Value::Object(Map::new()))
         } else {
-            String::new()
+            Value::Object(Map::new())
         };
 
-        // 只保存 API 相关字段到备份
-        let backup_auth_data = serde_json::json!({
-            "OPENAI_API_KEY": api_key
-        });
-        fs::write(
-            &backup_auth,
-            serde_json::to_string_pretty(&backup_auth_data)?,
+        if let Value::Object(ref mut auth_obj) = auth_data {
+            auth_obj.insert(
+                "OPENAI_API_KEY".to_string(),
+                Value::String(api_key.to_string()),
+            );
+        }
+
+        write_atomic_with(
+            &active_auth,
+            serde_json::to_string_pretty(&auth_data)?,
+            snapshots,
         )?;
 
-        // 对于 config.toml，只备份当前使用的 provider 的完整配置
-        if config_path.exists() {
-            let content = fs::read_to_string(&config_path)?;
-            if let Ok(doc) = content.parse::<toml_edit::DocumentMut>() {
-                let mut backup_doc = toml_edit::DocumentMut::new();
+        // 更新 config.toml 中的 provider 选择（保留其他字段和注释）
+        let mut doc = Self::read_codex_doc(tool)?;
+        let provider_key = record.provider.clone().unwrap_or_else(|| {
+            if record.base_url.contains("duckcoding") {
+                "duckcoding".to_string()
+            } else {
+                "custom".to_string()
+            }
+        });
 
-                // 获取当前使用的 model_provider
-                let current_provider_name = doc
-                    .get("model_provider")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| anyhow::anyhow!("配置文件缺少 model_provider 字段"))?;
-
-                // 只备份当前 provider 的完整配置
-                if let Some(providers) = doc.get("model_providers").and_then(|p| p.as_table()) {
-                    if let Some(current_provider) = providers.get(current_provider_name) {
-                        tracing::debug!(
-                            provider = %current_provider_name,
-                            profile = %profile_name,
-                            "备份 Codex 配置"
-
-                        );
-                        let mut backup_providers = toml_edit::Table::new();
-                        backup_providers.insert(current_provider_name, current_provider.clone());
-                        backup_doc
-                            .insert("model_providers", toml_edit::Item::Table(backup_providers));
-                    } else {
-                        anyhow::bail!("未找到 model_provider '{current_provider_name}' 的配置");
-                    }
-                } else {
-                    anyhow::bail!("配置文件缺少 model_providers 表");
-                }
+        if !doc
+            .get("model_providers")
+            .map(|item| item.is_table())
+            .unwrap_or(false)
+        {
+            let mut table = Table::new();
+            table.set_implicit(false);
+            doc.insert("model_providers", Item::Table(table));
+        }
+        let providers = doc
+            .get_mut("model_providers")
+            .and_then(|item| item.as_table_mut())
+            .ok_or_else(|| anyhow!("解析 codex 配置失败：model_providers 不是表结构"))?;
+
+        if let Some(settings) = &record.provider_settings {
+            // 档案保存了完整的 provider 表时整体写入（wire_api 等以档案为准）
+            let mut provider = toml::to_string(settings)?
+                .parse::<DocumentMut>()
+                .map_err(|err| anyhow!("转换 provider 配置失败: {err}"))?
+                .as_table()
+                .clone();
+            provider.set_implicit(false);
+            provider.insert("base_url", toml_edit::value(record.base_url.as_str()));
+            providers.insert(&provider_key, Item::Table(provider));
+        } else if let Some(provider) = providers
+            .get_mut(&provider_key)
+            .and_then(|item| item.as_table_mut())
+        {
+            // 已存在时只更新 base_url（保留用户自定义配置）
+            provider.insert("base_url", toml_edit::value(record.base_url.as_str()));
+        } else {
+            let mut provider = Table::new();
+            provider.insert("name", toml_edit::value(provider_key.as_str()));
+            provider.insert("base_url", toml_edit::value(record.base_url.as_str()));
+            provider.insert("wire_api", toml_edit::value("responses"));
+            provider.insert("requires_openai_auth", toml_edit::value(true));
+            providers.insert(&provider_key, Item::Table(provider));
+        }
+
+        set_table_value(
+            doc.as_table_mut(),
+            "model_provider",
+            toml_edit::value(provider_key.as_str()),
+        );
+        if let Some(model) = &record.model {
+            set_table_value(
+                doc.as_table_mut(),
+                "model",
+                toml_edit::value(model.as_str()),
+            );
+        }
+
+        write_atomic_with(&active_config, doc.to_string(), snapshots)?;
+
+        Ok(())
+    }
 
-                // 保存当前的 model_provider 选择
-                backup_doc.insert("model_provider", toml_edit::value(current_provider_name));
+    fn activate_dotenv(
+        tool: &Tool,
+        record: &ProfileRecord,
+        api_key: &str,
+        snapshots: Option<&SnapshotStore>,
+    ) -> Result<()> {
+        let active_env = tool.config_dir.join(".env");
+        fs::create_dir_all(&tool.config_dir)?;
+
+        // 只更新 API 相关字段和模型（保留其他字段）
+        let mut env_vars = Self::read_env_pairs(&active_env)?;
+        env_vars.insert(tool.env_vars.api_key.clone(), api_key.to_string());
+        env_vars.insert(tool.env_vars.base_url.clone(), record.base_url.clone());
+        if let (Some(key), Some(model)) = (tool.model_env_key(), &record.model) {
+            env_vars.insert(key.to_string(), model.clone());
+        }
+
+        Self::write_env_pairs(&active_env, &env_vars, snapshots)?;
+
+        Ok(())
+    }
+
+    /// 删除配置
+    pub fn delete_profile(store: &ProfileStore, tool: &Tool, profile_name: &str) -> Result<()> {
+        let Some(record) = store.remove(&tool.id, profile_name)? else {
+            return Ok(());
+        };
 
-                fs::write(&backup_config, backup_doc.to_string())?;
+        if let Some(id) = vault_ref_id(&record.api_key_ref) {
+            if let Err(e) = store.vault().remove(id) {
+                tracing::warn!(tool_id = %tool.id, profile = %profile_name, error = ?e, "删除密钥库条目失败");
             }
         }
+        Self::refresh_log_secrets(store);
 
         Ok(())
     }
 
-    fn backup_gemini(tool: &Tool, profile_name: &str) -> Result<()> {
-        let env_path = tool.config_dir.join(".env");
-        let backup_env = tool.config_dir.join(format!(".env.{profile_name}"));
+    /// 重命名配置
+    pub fn rename_profile(
+        store: &ProfileStore,
+        tool: &Tool,
+        profile_name: &str,
+        new_name: &str,
+    ) -> Result<ProfileRecord> {
+        store.rename(&tool.id, profile_name, new_name)
+    }
+
+    /// 复制配置（API Key 以新档案 ID 另存一份）
+    pub fn duplicate_profile(
+        store: &ProfileStore,
+        tool: &Tool,
+        profile_name: &str,
+        new_name: &str,
+    ) -> Result<ProfileRecord> {
+        validate_profile_name(new_name)?;
+        let source = Self::get_profile(store, tool, profile_name)?;
+        if store.get(&tool.id, new_name)?.is_some() {
+            anyhow::bail!("配置名称已存在: {new_name}");
+        }
+
+        let mut copy = ProfileRecord::new(&tool.id, new_name);
+        copy.api_key_ref = store.vault().seal(
+            &profile_secret_id(&tool.id, &copy.id),
+            &store.vault().resolve(&source.api_key_ref)?,
+        )?;
+        copy.base_url = source.base_url;
+        copy.model = source.model;
+        copy.provider = source.provider;
+        copy.provider_settings = source.provider_settings;
+        copy.notes = source.notes;
+        copy.tags = source.tags;
+        store.save(&copy)?;
+        Self::refresh_log_secrets(store);
+        Ok(copy)
+    }
+
+    /// 更新配置的模型、备注和标签
+    pub fn update_profile_details(
+        store: &ProfileStore,
+        tool: &Tool,
+        profile_name: &str,
+        details: ProfileDetails,
+    ) -> Result<ProfileRecord> {
+        let mut record = Self::get_profile(store, tool, profile_name)?;
+        record.model = details
+            .model
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty());
+        record.notes = details.notes.trim().to_string();
+        record.tags = Vec::new();
+        for tag in details.tags {
+            let tag = tag.trim();
+            if !tag.is_empty() && !record.has_tag(tag) {
+                record.tags.push(tag.to_string());
+            }
+        }
+        record.touch();
+        store.save(&record)?;
+        Ok(record)
+    }
 
-        if !env_path.exists() {
-            anyhow::bail!("配置文件不存在，无法备份");
+    /// 搜索配置（tool 为 None 时搜索所有工具）
+    pub fn search_profiles(
+        store: &ProfileStore,
+        tool: Option<&Tool>,
+        query: &str,
+        tag: Option<&str>,
+    ) -> Result<Vec<ProfileRecord>> {
+        let tools = match tool {
+            Some(tool) => vec![tool.clone()],
+            None => Tool::all(),
+        };
+        let mut results = Vec::new();
+        for tool in tools {
+            results.extend(
+                store
+                    .list(&tool.id)?
+                    .into_iter()
+                    .filter(|p| p.matches(query) && tag.is_none_or(|tag| p.has_tag(tag))),
+            );
+        }
+        Ok(results)
+    }
+
+    /// 导出配置档案为导出包内容（selection 为空时导出所有工具的全部配置）
+    pub fn export_profiles(
+        store: &ProfileStore,
+        selection: &[ProfileRef],
+        passphrase: Option<&str>,
+    ) -> Result<String> {
+        let records = if selection.is_empty() {
+            Self::search_profiles(store, None, "", None)?
+        } else {
+            selection
+                .iter()
+                .map(|item| {
+                    store
+                        .get(&item.tool_id, &item.name)?
+                        .ok_or_else(|| anyhow!("配置不存在: {}/{}", item.tool_id, item.name))
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let mut profiles = Vec::new();
+        for record in records {
+            profiles.push(BundleProfile {
+                api_key: store.vault().resolve(&record.api_key_ref)?,
+                tool_id: record.tool_id,
+                name: record.name,
+                base_url: record.base_url,
+                model: record.model,
+                provider: record.provider,
+                provider_settings: record.provider_settings,
+                notes: record.notes,
+                tags: record.tags,
+            });
         }
+        encode_bundle(&profiles, passphrase)
+    }
 
-        // 读取 .env 文件，只提取 API 相关字段
-        let content = fs::read_to_string(&env_path)?;
-        let mut api_key = String::new();
-        let mut base_url = String::new();
-        let mut model = String::new();
+    /// 导入导出包中的配置档案（逐个校验，失败的配置不影响其他配置）
+    pub fn import_profiles(
+        store: &ProfileStore,
+        content: &str,
+        passphrase: Option<&str>,
+        strategy: ConflictStrategy,
+    ) -> Result<BundleImportReport> {
+        let profiles = decode_bundle(content, passphrase)?;
+        let report = Self::import_bundle_profiles(store, profiles, strategy);
+        Self::refresh_log_secrets(store);
+        Ok(report)
+    }
 
-        for line in content.lines() {
-            let trimmed = line.trim();
-            if trimmed.is_empty() || trimmed.starts_with('#') {
+    fn import_bundle_profiles(
+        store: &ProfileStore,
+        profiles: Vec<BundleProfile>,
+        strategy: ConflictStrategy,
+    ) -> BundleImportReport {
+        let mut report = BundleImportReport::default();
+        for profile in profiles {
+            let Some(tool) = Tool::by_id(&profile.tool_id) else {
+                report.outcomes.push(ImportOutcome::failed(
+                    &profile,
+                    format!("未知的工具: {}", profile.tool_id),
+                    Vec::new(),
+                ));
+                continue;
+            };
+            let errors = Self::validate_bundle_profile(&tool, &profile);
+            if !errors.is_empty() {
+                report.outcomes.push(ImportOutcome::failed(
+                    &profile,
+                    "配置校验失败".to_string(),
+                    errors,
+                ));
                 continue;
             }
 
-            if let Some((key, value)) = trimmed.split_once('=') {
-                match key.trim() {
-                    "GEMINI_API_KEY" => api_key = value.trim().to_string(),
-                    "GOOGLE_GEMINI_BASE_URL" => base_url = value.trim().to_string(),
-                    "GEMINI_MODEL" => model = value.trim().to_string(),
-                    _ => {}
+            let outcome = Self::import_bundle_profile(store, &tool, &profile, strategy)
+                .unwrap_or_else(|e| ImportOutcome::failed(&profile, e.to_string(), Vec::new()));
+            report.outcomes.push(outcome);
+        }
+        report
+    }
+
+    fn import_bundle_profile(
+        store: &ProfileStore,
+        tool: &Tool,
+        profile: &BundleProfile,
+        strategy: ConflictStrategy,
+    ) -> Result<ImportOutcome> {
+        let existing = store.get(&tool.id, &profile.name)?;
+        let (mut record, action) = match (existing, strategy) {
+            (None, _) => (
+                ProfileRecord::new(&tool.id, &profile.name),
+                ImportAction::Created,
+            ),
+            (Some(_), ConflictStrategy::Skip) => {
+                return Ok(ImportOutcome {
+                    tool_id: tool.id.clone(),
+                    name: profile.name.clone(),
+                    saved_as: None,
+                    action: ImportAction::Skipped,
+                    message: Some("已存在同名配置".to_string()),
+                    errors: Vec::new(),
+                })
+            }
+            (Some(existing), ConflictStrategy::Overwrite) => (existing, ImportAction::Overwritten),
+            (Some(_), ConflictStrategy::Rename) => {
+                let names: Vec<String> =
+                    store.list(&tool.id)?.into_iter().map(|p| p.name).collect();
+                let name = next_available_name(&names, &profile.name);
+                validate_profile_name(&name)?;
+                (ProfileRecord::new(&tool.id, &name), ImportAction::Renamed)
+            }
+        };
+
+        let previous_ref = record.api_key_ref.clone();
+        record.base_url = profile.base_url.clone();
+        record.model = profile.model.clone();
+        record.provider = profile.provider.clone();
+        record.provider_settings = profile.provider_settings.clone();
+        record.notes = profile.notes.clone();
+        record.tags = profile.tags.clone();
+        record.api_key_ref = store
+            .vault()
+            .seal(&profile_secret_id(&tool.id, &record.id), &profile.api_key)?;
+        record.last_check = None;
+        record.touch();
+        store.save(&record)?;
+
+        // 覆盖旧版导入的配置时，旧 ID 下的密钥不再被引用
+        if let Some(id) = vault_ref_id(&previous_ref).filter(|_| previous_ref != record.api_key_ref)
+        {
+            if let Err(e) = store.vault().remove(id) {
+                tracing::warn!(tool_id = %tool.id, error = ?e, "删除密钥库条目失败");
+            }
+        }
+
+        Ok(ImportOutcome {
+            tool_id: tool.id.clone(),
+            name: profile.name.clone(),
+            saved_as: Some(record.name),
+            action,
+            message: None,
+            errors: Vec::new(),
+        })
+    }
+
+    /// 校验导出包中的配置：基本字段，以及按工具声明的 Schema（resources/）校验生成的配置片段
+    pub fn validate_bundle_profile(tool: &Tool, profile: &BundleProfile) -> Vec<SchemaViolation> {
+        let mut errors = Vec::new();
+        let mut invalid = |path: &str, message: String, expected: Option<&str>| {
+            errors.push(SchemaViolation {
+                path: path.to_string(),
+                message,
+                expected: expected.map(str::to_string),
+            })
+        };
+        if let Err(e) = validate_profile_name(&profile.name) {
+            invalid("/name", e.to_string(), None);
+        }
+        if profile.api_key.trim().is_empty() {
+            invalid("/api_key", "API Key 不能为空".to_string(), None);
+        }
+        if !(profile.base_url.starts_with("http://") || profile.base_url.starts_with("https://")) {
+            invalid(
+                "/base_url",
+                "Base URL 应以 http:// 或 https:// 开头".to_string(),
+                Some("uri"),
+            );
+        }
+
+        // 按配置布局生成导入后写入配置文件的片段，再用工具声明的 Schema 校验
+        let fragment = match tool.config_layout {
+            ConfigLayout::JsonEnv | ConfigLayout::JsonPointer => {
+                let mut settings = json!({});
+                tool.set_json_config_value(&mut settings, &tool.env_vars.api_key, &profile.api_key);
+                tool.set_json_config_value(
+                    &mut settings,
+                    &tool.env_vars.base_url,
+                    &profile.base_url,
+                );
+                if let (Some(key), Some(model)) = (tool.model_env_key(), &profile.model) {
+                    tool.set_json_config_value(&mut settings, key, model);
+                }
+                Some(settings)
+            }
+            ConfigLayout::CodexToml => {
+                let provider = profile.provider.as_deref().unwrap_or("custom");
+                let mut table = profile.provider_settings.clone().unwrap_or_default();
+                table.entry("name").or_insert_with(|| json!(provider));
+                table.insert("base_url".to_string(), json!(profile.base_url));
+                let mut config = json!({
+                    "model_provider": provider,
+                    "model_providers": { provider: table }
+                });
+                if let Some(model) = &profile.model {
+                    config["model"] = json!(model);
                 }
+                Some(config)
+            }
+            // .env 中的变量不在配置文件 Schema 的范围内
+            ConfigLayout::Dotenv => None,
+        };
+        if let (Some(schema), Some(fragment)) = (tool.settings_schema, fragment) {
+            match Self::settings_schema(schema) {
+                Ok(schema) => errors.extend(json_schema::validate(&schema, &fragment)),
+                Err(e) => tracing::warn!(tool_id = %tool.id, error = ?e, "加载配置 Schema 失败"),
             }
         }
+        errors
+    }
 
-        // 只保存 API 相关字段
-        let backup_content = format!(
-            "GEMINI_API_KEY={api_key}\nGOOGLE_GEMINI_BASE_URL={base_url}\nGEMINI_MODEL={model}\n"
-        );
+    /// 把旧版分散在工具配置目录中的备份文件导入配置档案存储，返回导入数量
+    ///
+    /// 导入成功的备份文件移入 ~/.duckcoding/legacy-backups/<tool_id>/，失败的保留原样，下次启动时重试
+    pub fn import_legacy_profiles(store: &ProfileStore) -> Result<usize> {
+        let archive_dir = crate::utils::config::config_dir()
+            .map_err(|e| anyhow!(e))?
+            .join("legacy-backups");
+        let mut imported = 0;
+        for tool in Tool::all() {
+            imported += Self::import_legacy_tool(store, &archive_dir, &tool);
+        }
+        Self::refresh_log_secrets(store);
+        Ok(imported)
+    }
+
+    /// 配置档案的密钥变化后刷新日志脱敏列表
+    fn refresh_log_secrets(store: &ProfileStore) {
+        crate::core::set_profile_log_secrets(store.secret_values());
+    }
+
+    fn import_legacy_tool(store: &ProfileStore, archive_dir: &Path, tool: &Tool) -> usize {
+        let mut imported = 0;
+        for profile in Self::legacy_profile_names(tool).unwrap_or_default() {
+            match Self::import_legacy_profile(store, archive_dir, tool, &profile) {
+                Ok(()) => imported += 1,
+                Err(e) => tracing::warn!(
+                    tool_id = %tool.id,
+                    profile = %profile,
+                    error = ?e,
+                    "导入旧版配置备份失败"
+                ),
+            }
+        }
+        imported
+    }
+
+    fn import_legacy_profile(
+        store: &ProfileStore,
+        archive_dir: &Path,
+        tool: &Tool,
+        profile_name: &str,
+    ) -> Result<()> {
+        if store.get(&tool.id, profile_name)?.is_some() {
+            anyhow::bail!("配置名称已存在");
+        }
+        let files = Self::legacy_backup_files(tool, profile_name);
+
+        let mut record = ProfileRecord::new(&tool.id, profile_name);
+        let (api_key, base_url) = match tool.config_layout {
+            ConfigLayout::JsonEnv | ConfigLayout::JsonPointer => {
+                let content = fs::read_to_string(&files[0]).context("读取备份配置失败")?;
+                let data: Value = serde_json::from_str(&content).context("解析备份配置失败")?;
+                // 兼容旧格式：先尝试顶层字段（新格式），再尝试 env 下（旧格式）
+                let field = |key: &str| {
+                    data.get(key)
+                        .or_else(|| data.get("env").and_then(|env| env.get(key)))
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("")
+                        .to_string()
+                };
+                (
+                    field(&tool.env_vars.api_key),
+                    field(&tool.env_vars.base_url),
+                )
+            }
+            ConfigLayout::CodexToml => {
+                // files: config.<name>.toml、auth.<name>.json
+                let content = fs::read_to_string(&files[1]).context("读取备份配置失败")?;
+                let auth: Value = serde_json::from_str(&content).context("解析备份配置失败")?;
+                let api_key = auth
+                    .get("OPENAI_API_KEY")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string();
+
+                let mut base_url = String::new();
+                if files[0].exists() {
+                    let doc = fs::read_to_string(&files[0])?
+                        .parse::<DocumentMut>()
+                        .map_err(|err| anyhow!("解析备份配置失败: {err}"))?;
+                    let field = |key: &str| {
+                        doc.get(key)
+                            .and_then(|v| v.as_str())
+                            .filter(|v| !v.is_empty())
+                            .map(str::to_string)
+                    };
+                    record.model = field("model");
+                    record.provider = field("model_provider");
+                    record.provider_settings = Self::codex_provider_settings(&doc);
+                    base_url = Self::codex_provider_base_url(&doc).unwrap_or_default();
+                }
+                (api_key, base_url)
+            }
+            ConfigLayout::Dotenv => {
+                let env_vars = Self::read_env_pairs(&files[0])?;
+                record.model = tool
+                    .model_env_key()
+                    .and_then(|key| env_vars.get(key))
+                    .filter(|model| !model.is_empty())
+                    .cloned();
+                Self::env_api_fields(tool, &env_vars)
+            }
+        };
 
-        fs::write(&backup_env, backup_content)?;
+        if api_key.is_empty() {
+            anyhow::bail!("备份配置缺少 API Key");
+        }
+        record.base_url = base_url;
+        // 已迁移到密钥库的引用直接沿用，明文存入密钥库
+        record.api_key_ref = store
+            .vault()
+            .seal(&profile_secret_id(&tool.id, &record.id), &api_key)?;
+        store.save(&record)?;
+
+        Self::archive_legacy_files(
+            &files,
+            &archive_dir.join(&tool.id),
+            &api_key,
+            &record.api_key_ref,
+        )?;
+        tracing::info!(tool_id = %tool.id, profile = %profile_name, "已导入旧版配置备份");
+        Ok(())
+    }
 
+    /// 把已导入的旧版备份文件移入归档目录（同名文件已存在时追加时间戳）
+    ///
+    /// 归档副本中的明文 API Key 替换为密钥库引用，原文件随后删除，磁盘上不再保留明文
+    fn archive_legacy_files(
+        files: &[PathBuf],
+        archive_dir: &Path,
+        api_key: &str,
+        api_key_ref: &str,
+    ) -> Result<()> {
+        fs::create_dir_all(archive_dir).context("创建旧版备份归档目录失败")?;
+        for file in files.iter().filter(|f| f.exists()) {
+            let Some(file_name) = file.file_name() else {
+                continue;
+            };
+            let mut target = archive_dir.join(file_name);
+            if target.exists() {
+                let suffix = chrono::Local::now().format("%Y%m%d-%H%M%S");
+                target = archive_dir.join(format!("{}.{suffix}", file_name.to_string_lossy()));
+            }
+            let content = fs::read_to_string(file)
+                .with_context(|| format!("读取旧版备份失败: {file:?}"))?
+                .replace(api_key, api_key_ref);
+            write_atomic_with(&target, content, None)
+                .with_context(|| format!("归档旧版备份失败: {file:?}"))?;
+            fs::remove_file(file).with_context(|| format!("删除旧版备份失败: {file:?}"))?;
+        }
         Ok(())
     }
 
-    /// 列出所有保存的配置
-    pub fn list_profiles(tool: &Tool) -> Result<Vec<String>> {
+    /// 旧版配置备份文件路径
+    fn legacy_backup_files(tool: &Tool, profile_name: &str) -> Vec<PathBuf> {
+        match tool.config_layout {
+            ConfigLayout::JsonEnv | ConfigLayout::JsonPointer => {
+                vec![tool.backup_path(profile_name)]
+            }
+            ConfigLayout::CodexToml => vec![
+                tool.config_dir.join(format!("config.{profile_name}.toml")),
+                tool.config_dir.join(format!("auth.{profile_name}.json")),
+            ],
+            ConfigLayout::Dotenv => vec![tool.config_dir.join(format!(".env.{profile_name}"))],
+        }
+    }
+
+    /// 扫描工具配置目录中的旧版配置备份名称
+    fn legacy_profile_names(tool: &Tool) -> Result<Vec<String>> {
         if !tool.config_dir.exists() {
             return Ok(vec![]);
         }
@@ -564,17 +1151,33 @@ impl ConfigService {
             let filename = entry.file_name();
             let filename_str = filename.to_string_lossy();
 
-            match tool.id.as_str() {
-                "claude-code" => {
+            match tool.config_layout {
+                ConfigLayout::JsonEnv | ConfigLayout::JsonPointer => {
                     // 排除主配置文件本身 (settings.json)
                     if filename_str == tool.config_file {
                         continue;
                     }
 
-                    if filename_str.starts_with("settings.") && filename_str.ends_with(".json") {
+                    let config_file = Path::new(&tool.config_file);
+                    let prefix = format!(
+                        "{}.",
+                        config_file
+                            .file_stem()
+                            .and_then(|s| s.to_str())
+                            .unwrap_or("config")
+                    );
+                    let suffix = format!(
+                        ".{}",
+                        config_file
+                            .extension()
+                            .and_then(|e| e.to_str())
+                            .unwrap_or("json")
+                    );
+
+                    if filename_str.starts_with(&prefix) && filename_str.ends_with(&suffix) {
                         let profile = filename_str
-                            .trim_start_matches("settings.")
-                            .trim_end_matches(".json")
+                            .trim_start_matches(&prefix)
+                            .trim_end_matches(&suffix)
                             .to_string();
 
                         if !profile.is_empty()
@@ -585,7 +1188,7 @@ impl ConfigService {
                         }
                     }
                 }
-                "codex" => {
+                ConfigLayout::CodexToml => {
                     // 排除主配置文件本身 (config.toml、auth.json)
                     if filename_str == tool.config_file || filename_str == "auth.json" {
                         continue;
@@ -620,7 +1223,7 @@ impl ConfigService {
                         }
                     }
                 }
-                "gemini-cli" => {
+                ConfigLayout::Dotenv => {
                     // 排除主配置文件 (.env)
                     if filename_str == tool.config_file {
                         continue;
@@ -637,7 +1240,6 @@ impl ConfigService {
                         }
                     }
                 }
-                _ => {}
             }
         }
 
@@ -646,297 +1248,132 @@ impl ConfigService {
         Ok(profiles)
     }
 
-    /// 激活指定的配置
-    pub fn activate_profile(tool: &Tool, profile_name: &str) -> Result<()> {
-        match tool.id.as_str() {
-            "claude-code" => Self::activate_claude(tool, profile_name)?,
-            "codex" => Self::activate_codex(tool, profile_name)?,
-            "gemini-cli" => Self::activate_gemini(tool, profile_name)?,
-            _ => anyhow::bail!("未知工具: {}", tool.id),
+    /// 读取当前生效的 API Key 和 Base URL
+    pub fn read_api_config(tool: &Tool) -> Result<(String, String)> {
+        match tool.config_layout {
+            ConfigLayout::JsonEnv | ConfigLayout::JsonPointer => {
+                let config_path = tool.config_dir.join(&tool.config_file);
+                let content = fs::read_to_string(&config_path).context("读取配置文件失败")?;
+                let settings: Value = serde_json::from_str(&content).context("解析配置文件失败")?;
+                Ok(Self::json_api_fields(tool, &settings))
+            }
+            ConfigLayout::Dotenv => {
+                let env_vars = Self::read_env_pairs(&tool.config_dir.join(".env"))?;
+                Ok(Self::env_api_fields(tool, &env_vars))
+            }
+            ConfigLayout::CodexToml => {
+                let auth_path = tool.config_dir.join("auth.json");
+                let api_key = if auth_path.exists() {
+                    let content = fs::read_to_string(&auth_path).context("读取认证文件失败")?;
+                    let auth: Value = serde_json::from_str(&content).context("解析认证文件失败")?;
+                    auth.get("OPENAI_API_KEY")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("")
+                        .to_string()
+                } else {
+                    String::new()
+                };
+                let base_url =
+                    Self::codex_provider_base_url(&Self::read_codex_doc(tool)?).unwrap_or_default();
+                Ok((api_key, base_url))
+            }
         }
-        Ok(())
     }
 
-    fn activate_claude(tool: &Tool, profile_name: &str) -> Result<()> {
-        let backup_path = tool.backup_path(profile_name);
-        let active_path = tool.config_dir.join(&tool.config_file);
-
-        if !backup_path.exists() {
-            anyhow::bail!("配置文件不存在: {backup_path:?}");
+    /// 读取配置档案中的 API Key 和 Base URL
+    pub fn read_profile_api_config(
+        store: &ProfileStore,
+        tool: &Tool,
+        profile_name: &str,
+    ) -> Result<(String, String)> {
+        let record = Self::get_profile(store, tool, profile_name)?;
+        if record.api_key_ref.is_empty() || record.base_url.is_empty() {
+            anyhow::bail!("配置缺少必要字段");
         }
+        Ok((store.vault().resolve(&record.api_key_ref)?, record.base_url))
+    }
 
-        // 读取备份的 API 字段（兼容新旧格式）
-        let backup_content = fs::read_to_string(&backup_path).context("读取备份配置失败")?;
-        let backup_data: Value =
-            serde_json::from_str(&backup_content).context("解析备份配置失败")?;
+    /// 查找与给定 API Key 和 Base URL 一致的配置名称
+    pub fn find_matching_profile(
+        store: &ProfileStore,
+        tool: &Tool,
+        api_key: &str,
+        base_url: &str,
+    ) -> Option<String> {
+        let secrets = store.vault().entries().unwrap_or_default();
+        Self::find_matching_profile_with(store, &secrets, tool, api_key, base_url)
+    }
 
-        // 兼容旧格式：先尝试顶层字段（新格式），再尝试 env 下（旧格式）
-        let api_key = backup_data
-            .get("ANTHROPIC_AUTH_TOKEN")
-            .and_then(|v| v.as_str())
-            .or_else(|| {
-                backup_data
-                    .get("env")
-                    .and_then(|env| env.get("ANTHROPIC_AUTH_TOKEN"))
-                    .and_then(|v| v.as_str())
-            })
-            .ok_or_else(|| {
-                anyhow::anyhow!("备份配置格式错误：缺少 API Key\n\n请重新保存配置以更新格式")
-            })?;
-
-        let base_url = backup_data
-            .get("ANTHROPIC_BASE_URL")
-            .and_then(|v| v.as_str())
-            .or_else(|| {
-                backup_data
-                    .get("env")
-                    .and_then(|env| env.get("ANTHROPIC_BASE_URL"))
-                    .and_then(|v| v.as_str())
+    /// 使用已解密的密钥库条目查找配置档案（批量查找时避免重复解密密钥库）
+    pub fn find_matching_profile_with(
+        store: &ProfileStore,
+        secrets: &BTreeMap<String, String>,
+        tool: &Tool,
+        api_key: &str,
+        base_url: &str,
+    ) -> Option<String> {
+        store
+            .list(&tool.id)
+            .ok()?
+            .into_iter()
+            .filter(|p| p.base_url == base_url)
+            .find(|p| match vault_ref_id(&p.api_key_ref) {
+                Some(id) => secrets.get(id).is_some_and(|key| key == api_key),
+                None => p.api_key_ref == api_key,
             })
-            .ok_or_else(|| {
-                anyhow::anyhow!("备份配置格式错误：缺少 Base URL\n\n请重新保存配置以更新格式")
-            })?;
-
-        // 读取当前配置（保留其他字段）
-        let mut settings = if active_path.exists() {
-            let content = fs::read_to_string(&active_path).context("读取当前配置失败")?;
-            serde_json::from_str::<Value>(&content).unwrap_or(Value::Object(Map::new()))
-        } else {
-            Value::Object(Map::new())
-        };
-
-        // 只更新 env 中的 API 字段，保留其他配置
-        if !settings.is_object() {
-            settings = serde_json::json!({});
-        }
-
-        let obj = settings.as_object_mut().unwrap();
-        if !obj.contains_key("env") {
-            obj.insert("env".to_string(), Value::Object(Map::new()));
-        }
-
-        let env = obj.get_mut("env").unwrap().as_object_mut().unwrap();
-        env.insert(
-            "ANTHROPIC_AUTH_TOKEN".to_string(),
-            Value::String(api_key.to_string()),
-        );
-        env.insert(
-            "ANTHROPIC_BASE_URL".to_string(),
-            Value::String(base_url.to_string()),
-        );
-
-        // 写回配置（保留其他字段）
-        fs::write(&active_path, serde_json::to_string_pretty(&settings)?)?;
-
-        Ok(())
+            .map(|p| p.name)
     }
 
-    fn activate_codex(tool: &Tool, profile_name: &str) -> Result<()> {
-        let backup_config = tool.config_dir.join(format!("config.{profile_name}.toml"));
-        let backup_auth = tool.config_dir.join(format!("auth.{profile_name}.json"));
-
-        let active_config = tool.config_dir.join("config.toml");
-        let active_auth = tool.config_dir.join("auth.json");
-
-        if !backup_auth.exists() {
-            anyhow::bail!("配置文件不存在: {backup_auth:?}");
-        }
-
-        // 读取备份的 API Key
-        let backup_auth_content = fs::read_to_string(&backup_auth)?;
-        let backup_auth_data: Value = serde_json::from_str(&backup_auth_content)?;
-        let api_key = backup_auth_data
-            .get("OPENAI_API_KEY")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow::anyhow!("备份配置中缺少 API Key"))?;
-
-        // 增量更新 auth.json（保留其他字段）
-        let mut auth_data = if active_auth.exists() {
-            let content = fs::read_to_string(&active_auth)?;
-            serde_json::from_str::<Value>(&content).unwrap_or(Value::Object(Map::new()))
-        } else {
-            Value::Object(Map::new())
-        };
-
-        if let Value::Object(ref mut auth_obj) = auth_data {
-            auth_obj.insert(
-                "OPENAI_API_KEY".to_string(),
-                Value::String(api_key.to_string()),
-            );
-        }
-
-        fs::write(&active_auth, serde_json::to_string_pretty(&auth_data)?)?;
-
-        // 读取备份的 config.toml（base_url 和 model_provider）
-        if backup_config.exists() {
-            let backup_config_content = fs::read_to_string(&backup_config)?;
-            let backup_doc = backup_config_content.parse::<toml_edit::DocumentMut>()?;
-
-            // 读取当前 config.toml（保留其他配置）
-            let mut active_doc = if active_config.exists() {
-                let content = fs::read_to_string(&active_config)?;
-                content
-                    .parse::<toml_edit::DocumentMut>()
-                    .unwrap_or_else(|_| toml_edit::DocumentMut::new())
-            } else {
-                toml_edit::DocumentMut::new()
-            };
-
-            // 只更新 model_providers 中的配置（保留其他字段）
-            if let Some(backup_providers) =
-                backup_doc.get("model_providers").and_then(|p| p.as_table())
-            {
-                if !active_doc.contains_key("model_providers") {
-                    active_doc["model_providers"] = toml_edit::table();
-                }
-
-                // 获取 model_providers 表的可变引用
-                if let Some(active_providers) = active_doc
-                    .get_mut("model_providers")
-                    .and_then(|p| p.as_table_mut())
-                {
-                    for (key, backup_provider) in backup_providers.iter() {
-                        if let Some(backup_provider_table) = backup_provider.as_table() {
-                            if backup_provider_table.get("base_url").is_some() {
-                                // 如果 provider 不存在，需要创建
-                                if !active_providers.contains_key(key) {
-                                    // 检查备份文件格式：新格式包含完整字段，旧格式只有 base_url
-                                    if is_complete_provider_config(backup_provider_table) {
-                                        // 新格式：完整配置，直接复制
-                                        active_providers.insert(key, backup_provider.clone());
-                                    } else {
-                                        // 旧格式：只有 base_url，需要补全必要字段（向后兼容）
-                                        let mut new_provider = toml_edit::Table::new();
-                                        new_provider.insert("name", toml_edit::value(key));
-                                        new_provider.insert(
-                                            "base_url",
-                                            backup_provider_table.get("base_url").unwrap().clone(),
-                                        );
-                                        new_provider
-                                            .insert("wire_api", toml_edit::value("responses"));
-                                        new_provider
-                                            .insert("requires_openai_auth", toml_edit::value(true));
-                                        active_providers
-                                            .insert(key, toml_edit::Item::Table(new_provider));
-                                    }
-                                } else {
-                                    // 如果已存在，只更新 base_url（保留用户自定义配置）
-                                    if let Some(active_provider) =
-                                        active_providers.get_mut(key).and_then(|p| p.as_table_mut())
-                                    {
-                                        if let Some(base_url) =
-                                            backup_provider_table.get("base_url")
-                                        {
-                                            active_provider.insert("base_url", base_url.clone());
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-
-            // 更新 model_provider 选择（如果备份中有）
-            if let Some(provider) = backup_doc.get("model_provider") {
-                active_doc["model_provider"] = provider.clone();
-            }
-
-            // 写回 config.toml（保留其他字段和注释）
-            fs::write(&active_config, active_doc.to_string())?;
+    /// 读取 Codex config.toml（文件不存在时返回空文档）
+    fn read_codex_doc(tool: &Tool) -> Result<DocumentMut> {
+        let config_path = tool.config_dir.join(&tool.config_file);
+        if !config_path.exists() {
+            return Ok(DocumentMut::new());
         }
-
-        Ok(())
+        fs::read_to_string(&config_path)
+            .context("读取配置文件失败")?
+            .parse::<DocumentMut>()
+            .map_err(|err| anyhow!("解析 Codex config.toml 失败: {err}"))
     }
 
-    fn activate_gemini(tool: &Tool, profile_name: &str) -> Result<()> {
-        let backup_env = tool.config_dir.join(format!(".env.{profile_name}"));
-        let active_env = tool.config_dir.join(".env");
-
-        if !backup_env.exists() {
-            anyhow::bail!("配置文件不存在: {backup_env:?}");
-        }
-
-        // 读取备份的 API 字段
-        let backup_content = fs::read_to_string(&backup_env)?;
-        let mut backup_api_key = String::new();
-        let mut backup_base_url = String::new();
-        let mut backup_model = String::new();
-
-        for line in backup_content.lines() {
-            let trimmed = line.trim();
-            if trimmed.is_empty() || trimmed.starts_with('#') {
-                continue;
-            }
-
-            if let Some((key, value)) = trimmed.split_once('=') {
-                match key.trim() {
-                    "GEMINI_API_KEY" => backup_api_key = value.trim().to_string(),
-                    "GOOGLE_GEMINI_BASE_URL" => backup_base_url = value.trim().to_string(),
-                    "GEMINI_MODEL" => backup_model = value.trim().to_string(),
-                    _ => {}
-                }
-            }
-        }
+    /// Codex 当前 model_provider 对应的 base_url
+    fn codex_provider_base_url(doc: &DocumentMut) -> Option<String> {
+        let provider = doc.get("model_provider")?.as_str()?;
+        doc.get("model_providers")?
+            .get(provider)?
+            .get("base_url")?
+            .as_str()
+            .map(str::to_string)
+    }
 
-        // 读取当前 .env（保留其他字段）
-        let mut env_vars = HashMap::new();
-        if active_env.exists() {
-            let content = fs::read_to_string(&active_env)?;
-            for line in content.lines() {
-                let trimmed = line.trim();
-                if !trimmed.is_empty() && !trimmed.starts_with('#') {
-                    if let Some((key, value)) = trimmed.split_once('=') {
-                        env_vars.insert(key.trim().to_string(), value.trim().to_string());
-                    }
-                }
-            }
+    /// Codex 当前 model_provider 对应的完整 provider 表（转为 JSON 保存到配置档案）
+    fn codex_provider_settings(doc: &DocumentMut) -> Option<Map<String, Value>> {
+        let provider = doc.get("model_provider")?.as_str()?;
+        let config: toml::Value = toml::from_str(&doc.to_string()).ok()?;
+        match serde_json::to_value(config.get("model_providers")?.get(provider)?).ok()? {
+            Value::Object(settings) => Some(settings),
+            _ => None,
         }
-
-        // 只更新 API 相关字段
-        env_vars.insert("GEMINI_API_KEY".to_string(), backup_api_key);
-        env_vars.insert("GOOGLE_GEMINI_BASE_URL".to_string(), backup_base_url);
-        env_vars.insert("GEMINI_MODEL".to_string(), backup_model);
-
-        // 写回 .env（保留其他字段）
-        let env_content: Vec<String> = env_vars.iter().map(|(k, v)| format!("{k}={v}")).collect();
-        fs::write(&active_env, env_content.join("\n") + "\n")?;
-
-        Ok(())
     }
 
-    /// 删除配置
-    pub fn delete_profile(tool: &Tool, profile_name: &str) -> Result<()> {
-        match tool.id.as_str() {
-            "claude-code" => {
-                let backup_path = tool.backup_path(profile_name);
-                if backup_path.exists() {
-                    fs::remove_file(backup_path)?;
-                }
-            }
-            "codex" => {
-                let backup_config = tool.config_dir.join(format!("config.{profile_name}.toml"));
-                let backup_auth = tool.config_dir.join(format!("auth.{profile_name}.json"));
-
-                if backup_config.exists() {
-                    fs::remove_file(backup_config)?;
-                }
-                if backup_auth.exists() {
-                    fs::remove_file(backup_auth)?;
-                }
-            }
-            "gemini-cli" => {
-                let backup_env = tool.config_dir.join(format!(".env.{profile_name}"));
-
-                if backup_env.exists() {
-                    fs::remove_file(backup_env)?;
-                }
-                // 注意：不再删除 settings.json 备份，因为新版本不再备份它
-            }
-            _ => anyhow::bail!("未知工具: {}", tool.id),
-        }
+    fn json_api_fields(tool: &Tool, settings: &Value) -> (String, String) {
+        let field = |key: &str| {
+            tool.json_config_value(settings, key)
+                .unwrap_or("")
+                .to_string()
+        };
+        (
+            field(&tool.env_vars.api_key),
+            field(&tool.env_vars.base_url),
+        )
+    }
 
-        Ok(())
+    fn env_api_fields(tool: &Tool, env_vars: &HashMap<String, String>) -> (String, String) {
+        let field = |key: &str| env_vars.get(key).cloned().unwrap_or_default();
+        (
+            field(&tool.env_vars.api_key),
+            field(&tool.env_vars.base_url),
+        )
     }
 
     /// 读取 Claude Code 完整配置
@@ -964,6 +1401,7 @@ impl ConfigService {
         if !settings.is_object() {
             anyhow::bail!("Claude Code 配置必须是 JSON 对象");
         }
+        Self::ensure_valid_settings(&Self::get_claude_schema()?, settings)?;
 
         let tool = Tool::claude_code();
         let config_dir = &tool.config_dir;
@@ -971,7 +1409,7 @@ impl ConfigService {
 
         fs::create_dir_all(config_dir).context("创建 Claude Code 配置目录失败")?;
         let json = serde_json::to_string_pretty(settings)?;
-        fs::write(&config_path, json).context("写入 Claude Code 配置失败")?;
+        write_atomic(&config_path, json).context("写入 Claude Code 配置失败")?;
 
         #[cfg(unix)]
         {
@@ -985,6 +1423,30 @@ impl ConfigService {
         Ok(())
     }
 
+    /// 写入前按 Schema 校验配置，失败时返回 SchemaValidationError
+    ///
+    /// 未知字段不视为错误：用户自行添加的环境变量或工具新版本的字段可能尚未收录到 Schema。
+    fn ensure_valid_settings(schema: &Value, settings: &Value) -> Result<()> {
+        let options = ValidateOptions {
+            allow_unknown_fields: true,
+        };
+        let errors = json_schema::validate_with(schema, settings, options);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(SchemaValidationError { errors }.into())
+        }
+    }
+
+    /// 加载工具声明的内置配置 Schema
+    pub fn settings_schema(schema: SettingsSchema) -> Result<Value> {
+        match schema {
+            SettingsSchema::ClaudeCode => Self::get_claude_schema(),
+            SettingsSchema::Codex => Self::get_codex_schema(),
+            SettingsSchema::GeminiCli => Self::get_gemini_schema(),
+        }
+    }
+
     /// 获取内置的 Claude Code JSON Schema
     pub fn get_claude_schema() -> Result<Value> {
         static CLAUDE_SCHEMA: OnceCell<Value> = OnceCell::new();
@@ -1034,6 +1496,7 @@ impl ConfigService {
         if !config.is_object() {
             anyhow::bail!("Codex 配置必须是对象结构");
         }
+        Self::ensure_valid_settings(&Self::get_codex_schema()?, config)?;
 
         let tool = Tool::codex();
         let config_path = tool.config_dir.join(&tool.config_file);
@@ -1057,7 +1520,8 @@ impl ConfigService {
 
         merge_toml_tables(existing_doc.as_table_mut(), new_doc.as_table());
 
-        fs::write(&config_path, existing_doc.to_string()).context("写入 Codex config.toml 失败")?;
+        write_atomic(&config_path, existing_doc.to_string())
+            .context("写入 Codex config.toml 失败")?;
 
         if let Some(token) = auth_token {
             let mut auth_data = if auth_path.exists() {
@@ -1071,7 +1535,7 @@ impl ConfigService {
                 obj.insert("OPENAI_API_KEY".to_string(), Value::String(token));
             }
 
-            fs::write(&auth_path, serde_json::to_string_pretty(&auth_data)?)
+            write_atomic(&auth_path, serde_json::to_string_pretty(&auth_data)?)
                 .context("写入 Codex auth.json 失败")?;
 
             #[cfg(unix)]
@@ -1135,6 +1599,7 @@ impl ConfigService {
         if !settings.is_object() {
             anyhow::bail!("Gemini CLI 配置必须是 JSON 对象");
         }
+        Self::ensure_valid_settings(&Self::get_gemini_schema()?, settings)?;
 
         let tool = Tool::gemini_cli();
         let config_dir = &tool.config_dir;
@@ -1143,7 +1608,7 @@ impl ConfigService {
         fs::create_dir_all(config_dir).context("创建 Gemini CLI 配置目录失败")?;
 
         let json = serde_json::to_string_pretty(settings)?;
-        fs::write(&settings_path, json).context("写入 Gemini CLI 配置失败")?;
+        write_atomic(&settings_path, json).context("写入 Gemini CLI 配置失败")?;
 
         let mut env_pairs = Self::read_env_pairs(&env_path)?;
         env_pairs.insert("GEMINI_API_KEY".to_string(), env.api_key.clone());
@@ -1156,7 +1621,12 @@ impl ConfigService {
                 env.model.clone()
             },
         );
-        Self::write_env_pairs(&env_path, &env_pairs).context("写入 Gemini CLI .env 失败")?;
+        Self::write_env_pairs(
+            &env_path,
+            &env_pairs,
+            SnapshotStore::default_store().as_ref(),
+        )
+        .context("写入 Gemini CLI .env 失败")?;
 
         #[cfg(unix)]
         {
@@ -1224,7 +1694,11 @@ impl ConfigService {
         Ok(pairs)
     }
 
-    fn write_env_pairs(path: &Path, pairs: &HashMap<String, String>) -> Result<()> {
+    fn write_env_pairs(
+        path: &Path,
+        pairs: &HashMap<String, String>,
+        snapshots: Option<&SnapshotStore>,
+    ) -> Result<()> {
         let mut items: Vec<_> = pairs.iter().collect();
         items.sort_by(|a, b| a.0.cmp(b.0));
         let mut content = String::new();
@@ -1237,7 +1711,399 @@ impl ConfigService {
             content.push_str(value);
         }
         content.push('\n');
-        fs::write(path, content)?;
+        write_atomic_with(path, content, snapshots)?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::vault::{vault_ref, Vault};
+    use tempfile::TempDir;
+
+    /// 配置档案、密钥库和快照都放在临时目录，不影响 ~/.duckcoding
+    fn store_in(dir: &Path) -> ProfileStore {
+        ProfileStore::new(
+            dir.join("profiles"),
+            Vault::new(dir),
+            Some(SnapshotStore::new(dir.join("snapshots"), 10)),
+        )
+    }
+
+    fn opencode_in(dir: &Path) -> Tool {
+        Tool {
+            config_dir: dir.join("opencode"),
+            ..Tool::opencode()
+        }
+    }
+
+    #[test]
+    fn test_json_pointer_config_profiles() {
+        let dir = TempDir::new().unwrap();
+        let store = store_in(dir.path());
+        let tool = opencode_in(dir.path());
+        let config_path = tool.config_dir.join(&tool.config_file);
+        fs::create_dir_all(&tool.config_dir).unwrap();
+        // 用户已有的其他 provider 和模型选择保持不变
+        fs::write(
+            &config_path,
+            r#"{"model":"openai/gpt-5","provider":{"openai":{"options":{"apiKey":"user"}}}}"#,
+        )
+        .unwrap();
+
+        ConfigService::apply_config(
+            &store,
+            &tool,
+            "key-a",
+            "https://a.example.com/v1",
+            Some("a"),
+        )
+        .unwrap();
+        ConfigService::apply_config(
+            &store,
+            &tool,
+            "key-b",
+            "https://b.example.com/v1",
+            Some("b"),
+        )
+        .unwrap();
+
+        let settings: Value =
+            serde_json::from_str(&fs::read_to_string(&config_path).unwrap()).unwrap();
+        assert_eq!(settings["model"], "openai/gpt-5");
+        assert_eq!(settings["provider"]["openai"]["options"]["apiKey"], "user");
+        let provider = &settings["provider"]["duckcoding"];
+        assert_eq!(provider["npm"], "@ai-sdk/anthropic");
+        assert_eq!(provider["options"]["apiKey"], "key-b");
+        assert_eq!(provider["options"]["baseURL"], "https://b.example.com/v1");
+
+        assert_eq!(
+            ConfigService::list_profiles(&store, &tool).unwrap(),
+            vec!["a", "b"]
+        );
+        assert_eq!(
+            ConfigService::find_matching_profile(
+                &store,
+                &tool,
+                "key-b",
+                "https://b.example.com/v1"
+            ),
+            Some("b".to_string())
+        );
+
+        ConfigService::activate_profile(&store, &tool, "a").unwrap();
+        assert_eq!(
+            ConfigService::read_api_config(&tool).unwrap(),
+            ("key-a".to_string(), "https://a.example.com/v1".to_string())
+        );
+
+        // 档案中只保存密钥库引用，不再写入工具配置目录
+        let record = ConfigService::get_profile(&store, &tool, "b").unwrap();
+        assert_eq!(
+            record.api_key_ref,
+            format!("vault:profile/{}/{}", tool.id, record.id)
+        );
+        assert!(!tool.backup_path("b").exists());
+
+        // 重命名保留密钥，复制另存一份密钥
+        ConfigService::rename_profile(&store, &tool, "b", "c").unwrap();
+        let copy = ConfigService::duplicate_profile(&store, &tool, "c", "d").unwrap();
+        assert_ne!(copy.api_key_ref, record.api_key_ref);
+        assert_eq!(
+            ConfigService::read_profile_api_config(&store, &tool, "d").unwrap(),
+            ("key-b".to_string(), "https://b.example.com/v1".to_string())
+        );
+        assert!(ConfigService::duplicate_profile(&store, &tool, "c", "a").is_err());
+
+        let details = ProfileDetails {
+            model: Some(" claude-sonnet-4-5 ".to_string()),
+            notes: "备用".to_string(),
+            tags: vec!["work".to_string(), "Work".to_string(), " ".to_string()],
+        };
+        let updated = ConfigService::update_profile_details(&store, &tool, "d", details).unwrap();
+        assert_eq!(updated.model.as_deref(), Some("claude-sonnet-4-5"));
+        assert_eq!(updated.tags, vec!["work"]);
+        let found = ConfigService::search_profiles(&store, Some(&tool), "", Some("WORK")).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "d");
+        assert_eq!(
+            ConfigService::search_profiles(&store, Some(&tool), "b.example", None)
+                .unwrap()
+                .len(),
+            2
+        );
+
+        for profile in ["a", "c", "d"] {
+            ConfigService::delete_profile(&store, &tool, profile).unwrap();
+        }
+        assert!(ConfigService::list_profiles(&store, &tool)
+            .unwrap()
+            .is_empty());
+        // 删除配置时一并删除密钥库条目，工具配置覆盖前保存了快照
+        assert!(store.vault().entries().unwrap().is_empty());
+        assert!(!SnapshotStore::new(dir.path().join("snapshots"), 10)
+            .list(&config_path)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_ensure_valid_settings_with_bundled_schemas() {
+        let claude = ConfigService::get_claude_schema().unwrap();
+        let valid = json!({
+            "env": {"ANTHROPIC_BASE_URL": "https://jp.duckcoding.com", "MY_CUSTOM_VAR": "1"},
+            "cleanupPeriodDays": 30,
+            "someFutureField": true
+        });
+        assert!(ConfigService::ensure_valid_settings(&claude, &valid).is_ok());
+
+        let invalid = json!({"env": {"ANTHROPIC_BASE_URL": 1}, "cleanupPeriodDays": -1});
+        let err = ConfigService::ensure_valid_settings(&claude, &invalid).unwrap_err();
+        let errors = &err.downcast_ref::<SchemaValidationError>().unwrap().errors;
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["/cleanupPeriodDays", "/env/ANTHROPIC_BASE_URL"]);
+        assert_eq!(errors[1].expected.as_deref(), Some("string"));
+
+        let codex = ConfigService::get_codex_schema().unwrap();
+        let valid = json!({"model": "gpt-5-codex", "approval_policy": "on-request"});
+        assert!(ConfigService::ensure_valid_settings(&codex, &valid).is_ok());
+        let invalid = json!({"approval_policy": "sometimes"});
+        assert!(ConfigService::ensure_valid_settings(&codex, &invalid).is_err());
+
+        let gemini = ConfigService::get_gemini_schema().unwrap();
+        let valid = json!({"ide": {"enabled": true}});
+        assert!(ConfigService::ensure_valid_settings(&gemini, &valid).is_ok());
+        let invalid = json!({"ide": {"enabled": "yes"}});
+        assert!(ConfigService::ensure_valid_settings(&gemini, &invalid).is_err());
+    }
+
+    #[test]
+    fn test_import_bundle_profiles_with_conflicts() {
+        let dir = TempDir::new().unwrap();
+        let store = store_in(dir.path());
+        let profile = |name: &str, base_url: &str| BundleProfile {
+            tool_id: "codex".to_string(),
+            name: name.to_string(),
+            base_url: base_url.to_string(),
+            api_key: format!("sk-{name}"),
+            model: Some("gpt-5-codex".to_string()),
+            provider: Some("duckcoding".to_string()),
+            provider_settings: None,
+            notes: String::new(),
+            tags: Vec::new(),
+        };
+        let work = profile("work", "https://jp.duckcoding.com/v1");
+
+        let report = ConfigService::import_bundle_profiles(
+            &store,
+            vec![
+                work.clone(),
+                profile("bad", "jp.duckcoding.com"),
+                BundleProfile {
+                    tool_id: "unknown".to_string(),
+                    ..work.clone()
+                },
+            ],
+            ConflictStrategy::Skip,
+        );
+        let actions: Vec<_> = report.outcomes.iter().map(|o| o.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                ImportAction::Created,
+                ImportAction::Failed,
+                ImportAction::Failed
+            ]
+        );
+        assert_eq!(report.outcomes[1].errors[0].path, "/base_url");
+        assert_eq!(report.imported(), 1);
+
+        // 同名配置按策略跳过、重命名或覆盖
+        let changed = profile("work", "https://us.duckcoding.com/v1");
+        let skipped = ConfigService::import_bundle_profiles(
+            &store,
+            vec![changed.clone()],
+            ConflictStrategy::Skip,
+        );
+        assert_eq!(skipped.outcomes[0].action, ImportAction::Skipped);
+        let renamed = ConfigService::import_bundle_profiles(
+            &store,
+            vec![changed.clone()],
+            ConflictStrategy::Rename,
+        );
+        assert_eq!(renamed.outcomes[0].saved_as.as_deref(), Some("work (2)"));
+        let original = store.get("codex", "work").unwrap().unwrap();
+        ConfigService::import_bundle_profiles(&store, vec![changed], ConflictStrategy::Overwrite);
+        let overwritten = store.get("codex", "work").unwrap().unwrap();
+        assert_eq!(overwritten.id, original.id);
+        assert_eq!(overwritten.base_url, "https://us.duckcoding.com/v1");
+
+        for record in store.list("codex").unwrap() {
+            assert!(store
+                .vault()
+                .resolve(&record.api_key_ref)
+                .unwrap()
+                .starts_with("sk-"));
+        }
+    }
+
+    #[test]
+    fn test_validate_bundle_profile_by_layout() {
+        let profile = BundleProfile {
+            tool_id: "claude-code".to_string(),
+            name: "work".to_string(),
+            base_url: "https://jp.duckcoding.com".to_string(),
+            api_key: "sk-work".to_string(),
+            model: Some("claude-sonnet-4-5".to_string()),
+            provider: None,
+            provider_settings: None,
+            notes: String::new(),
+            tags: Vec::new(),
+        };
+        assert!(ConfigService::validate_bundle_profile(&Tool::claude_code(), &profile).is_empty());
+
+        // 字段位置取自工具定义：按 JSON Pointer 写入的模型字段类型不符时报告
+        let mut tool = Tool {
+            config_layout: ConfigLayout::JsonPointer,
+            ..Tool::claude_code()
+        };
+        tool.env_vars.api_key = "/env/ANTHROPIC_AUTH_TOKEN".to_string();
+        tool.env_vars.base_url = "/env/ANTHROPIC_BASE_URL".to_string();
+        tool.env_vars.model = Some("/cleanupPeriodDays".to_string());
+        let errors = ConfigService::validate_bundle_profile(&tool, &profile);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "/cleanupPeriodDays");
+
+        // 没有声明 Schema 的工具只做基本校验
+        tool.settings_schema = None;
+        assert!(ConfigService::validate_bundle_profile(&tool, &profile).is_empty());
+    }
+
+    #[test]
+    fn test_import_legacy_profiles() {
+        let dir = TempDir::new().unwrap();
+        let store = store_in(dir.path());
+        let tool = Tool {
+            config_dir: dir.path().join("gemini"),
+            ..Tool::gemini_cli()
+        };
+        fs::create_dir_all(&tool.config_dir).unwrap();
+        fs::write(tool.config_dir.join(".env"), "GEMINI_API_KEY=active\n").unwrap();
+        fs::write(
+            tool.config_dir.join(".env.work"),
+            "GEMINI_API_KEY=sk-legacy-plaintext\n\
+             GOOGLE_GEMINI_BASE_URL=https://jp.duckcoding.com\n\
+             GEMINI_MODEL=gemini-2.5-flash\n",
+        )
+        .unwrap();
+        // 已迁移到密钥库的备份沿用原引用
+        fs::write(
+            tool.config_dir.join(".env.home"),
+            "GEMINI_API_KEY=vault:profile/gemini-cli/home\n\
+             GOOGLE_GEMINI_BASE_URL=https://home.example.com\n",
+        )
+        .unwrap();
+        // 自动备份和缺少 API Key 的备份不导入
+        fs::write(tool.config_dir.join(".env.20250101-120000"), "").unwrap();
+        fs::write(tool.config_dir.join(".env.broken"), "GEMINI_MODEL=x\n").unwrap();
+
+        let archive = dir.path().join("legacy-backups");
+        assert_eq!(
+            ConfigService::import_legacy_tool(&store, &archive, &tool),
+            2
+        );
+        let record = store.get(&tool.id, "work").unwrap().unwrap();
+        let work_ref = vault_ref(&profile_secret_id(&tool.id, &record.id));
+        assert_eq!(record.api_key_ref, work_ref);
+        assert_eq!(
+            store.vault().resolve(&record.api_key_ref).unwrap(),
+            "sk-legacy-plaintext"
+        );
+        assert_eq!(record.base_url, "https://jp.duckcoding.com");
+        assert_eq!(record.model.as_deref(), Some("gemini-2.5-flash"));
+        let home = store.get(&tool.id, "home").unwrap().unwrap();
+        assert_eq!(home.api_key_ref, "vault:profile/gemini-cli/home");
+
+        // 导入后的备份移入归档目录，归档副本中的 API Key 替换为引用
+        assert!(!tool.config_dir.join(".env.work").exists());
+        let archived = fs::read_to_string(archive.join("gemini-cli/.env.work")).unwrap();
+        assert!(archived.contains(&format!("GEMINI_API_KEY={work_ref}")));
+        assert!(tool.config_dir.join(".env.broken").exists());
+        assert!(tool.config_dir.join(".env").exists());
+
+        // 磁盘上不再有明文 API Key（密钥库文件只有密文）
+        let mut pending = vec![dir.path().to_path_buf()];
+        while let Some(path) = pending.pop() {
+            if path.is_dir() {
+                pending.extend(fs::read_dir(&path).unwrap().map(|e| e.unwrap().path()));
+            } else {
+                let content = fs::read(&path).unwrap();
+                assert!(
+                    !String::from_utf8_lossy(&content).contains("sk-legacy-plaintext"),
+                    "{path:?} 中仍有明文 API Key"
+                );
+            }
+        }
+
+        // 再次导入不会重复
+        assert_eq!(
+            ConfigService::import_legacy_tool(&store, &archive, &tool),
+            0
+        );
+        assert_eq!(store.list(&tool.id).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_import_legacy_codex_profile_keeps_provider_table() {
+        let dir = TempDir::new().unwrap();
+        let store = store_in(dir.path());
+        let archive = dir.path().join("legacy-backups");
+        let tool = Tool {
+            config_dir: dir.path().join("codex"),
+            ..Tool::codex()
+        };
+        fs::create_dir_all(&tool.config_dir).unwrap();
+        fs::write(
+            tool.config_dir.join("config.work.toml"),
+            "model = \"gpt-4.1\"\nmodel_provider = \"relay\"\n\n\
+             [model_providers.relay]\nname = \"relay\"\n\
+             base_url = \"https://relay.example.com/v1\"\nwire_api = \"chat\"\n\
+             env_key = \"RELAY_KEY\"\n\n\
+             [model_providers.relay.http_headers]\nX-Team = \"dev\"\n",
+        )
+        .unwrap();
+        fs::write(
+            tool.config_dir.join("auth.work.json"),
+            r#"{"OPENAI_API_KEY":"vault:profile/codex/work"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            ConfigService::import_legacy_tool(&store, &archive, &tool),
+            1
+        );
+        let record = store.get(&tool.id, "work").unwrap().unwrap();
+        assert_eq!(record.model.as_deref(), Some("gpt-4.1"));
+        assert_eq!(record.provider.as_deref(), Some("relay"));
+        assert_eq!(record.base_url, "https://relay.example.com/v1");
+        let settings = record.provider_settings.clone().unwrap();
+        assert_eq!(settings["wire_api"], "chat");
+        assert_eq!(settings["http_headers"]["X-Team"], "dev");
+        assert!(archive.join("codex/config.work.toml").exists());
+        assert!(archive.join("codex/auth.work.json").exists());
+        assert!(!tool.config_dir.join("config.work.toml").exists());
+
+        // 激活时写回完整的 provider 表，而不是默认的 responses
+        ConfigService::activate_codex(&tool, &record, "sk-work", store.snapshots()).unwrap();
+        let config: toml::Value =
+            toml::from_str(&fs::read_to_string(tool.config_dir.join("config.toml")).unwrap())
+                .unwrap();
+        let provider = &config["model_providers"]["relay"];
+        assert_eq!(config["model"].as_str(), Some("gpt-4.1"));
+        assert_eq!(config["model_provider"].as_str(), Some("relay"));
+        assert_eq!(provider["wire_api"].as_str(), Some("chat"));
+        assert_eq!(provider["env_key"].as_str(), Some("RELAY_KEY"));
+        assert_eq!(provider["http_headers"]["X-Team"].as_str(), Some("dev"));
+    }
+}
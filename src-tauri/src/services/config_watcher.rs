@@ -0,0 +1,458 @@
+// 配置文件监听与偏移检测
+//
+// 定期检查每个工具的配置文件，借助 write_atomic 记录的内容摘要区分 DuckCoding 自身写入与外部修改。
+// 外部修改使工具配置偏离 DuckCoding 最后写入的状态时，按类型归类后通知前端：
+// - proxy_bypassed: 透明代理运行中，但 Base URL 不再指向本地代理（保存的真实配置随之过期）
+// - key_changed: API Key 或 Base URL 被手动修改
+// - profile_changed: 当前配置与另一个已保存的配置一致（手动切换了配置）
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::models::Tool;
+use crate::services::config::ConfigService;
+use crate::services::profile_store::ProfileStore;
+use crate::services::proxy::{ProxyManager, TransparentProxyConfigService};
+use crate::utils::config::{read_global_config, write_global_config};
+use crate::utils::{content_digest, last_written_digest};
+
+/// 配置文件轮询间隔
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 偏移类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftKind {
+    /// 透明代理运行中，工具配置不再指向本地代理
+    ProxyBypassed,
+    /// API Key 或 Base URL 被手动修改
+    KeyChanged,
+    /// 手动切换到了另一个已保存的配置
+    ProfileChanged,
+}
+
+/// 配置偏移（不包含 API Key 明文）
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigDrift {
+    pub tool_id: String,
+    pub tool_name: String,
+    pub kind: DriftKind,
+    pub expected_base_url: String,
+    pub current_base_url: String,
+    pub current_profile: Option<String>, // 与当前配置一致的已保存配置
+    pub detected_at: i64,                // 毫秒时间戳
+}
+
+/// DuckCoding 最后写入的期望状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpectedConfig {
+    /// 透明代理运行中，工具应指向本地代理
+    Proxy {
+        base_url: String,
+        local_api_key: String,
+    },
+    /// 工具直连上游
+    Direct {
+        api_key: String,
+        base_url: String,
+        profile: Option<String>,
+    },
+}
+
+impl ExpectedConfig {
+    fn base_url(&self) -> &str {
+        match self {
+            ExpectedConfig::Proxy { base_url, .. } | ExpectedConfig::Direct { base_url, .. } => {
+                base_url
+            }
+        }
+    }
+}
+
+/// 比较当前配置与期望状态，返回偏移类型
+pub fn classify_drift(
+    expected: &ExpectedConfig,
+    api_key: &str,
+    base_url: &str,
+    current_profile: Option<&str>,
+) -> Option<DriftKind> {
+    match expected {
+        ExpectedConfig::Proxy {
+            base_url: proxy_url,
+            local_api_key,
+        } => {
+            if !points_to(base_url, proxy_url) {
+                Some(DriftKind::ProxyBypassed)
+            } else if api_key != local_api_key {
+                Some(DriftKind::KeyChanged)
+            } else {
+                None
+            }
+        }
+        ExpectedConfig::Direct {
+            api_key: expected_key,
+            base_url: expected_url,
+            profile,
+        } => {
+            if api_key == expected_key
+                && base_url.trim_end_matches('/') == expected_url.trim_end_matches('/')
+            {
+                return None;
+            }
+            match current_profile {
+                Some(current) if Some(current) != profile.as_deref() => {
+                    Some(DriftKind::ProfileChanged)
+                }
+                _ => Some(DriftKind::KeyChanged),
+            }
+        }
+    }
+}
+
+/// Base URL 是否指向本地代理（允许附加 /v1 等路径）
+fn points_to(base_url: &str, proxy_url: &str) -> bool {
+    base_url
+        .strip_prefix(proxy_url)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// 本地代理地址
+fn proxy_base_url(port: u16) -> String {
+    format!("http://127.0.0.1:{port}")
+}
+
+#[derive(Default)]
+struct ToolWatchState {
+    digests: Option<Vec<Option<u64>>>, // 配置文件内容摘要（None 表示尚未观察）
+    baseline: Option<ExpectedConfig>,  // 直连模式下 DuckCoding 最后写入的配置
+}
+
+#[derive(Default)]
+struct WatcherState {
+    tools: HashMap<String, ToolWatchState>,
+    drifts: HashMap<String, ConfigDrift>,
+}
+
+/// 单次轮询内共享的配置档案查找（密钥库最多解密一次，且仅在需要时解密）
+#[derive(Default)]
+struct ProfileLookup {
+    loaded: Option<Option<(ProfileStore, BTreeMap<String, String>)>>,
+}
+
+impl ProfileLookup {
+    /// 与当前配置一致的配置档案名称
+    fn find(&mut self, tool: &Tool, api_key: &str, base_url: &str) -> Option<String> {
+        let (store, secrets) = self
+            .loaded
+            .get_or_insert_with(|| {
+                let store = ProfileStore::default_store().ok()?;
+                let secrets = store.vault().entries().unwrap_or_default();
+                Some((store, secrets))
+            })
+            .as_ref()?;
+        ConfigService::find_matching_profile_with(store, secrets, tool, api_key, base_url)
+    }
+}
+
+/// 配置文件监听器
+#[derive(Default)]
+pub struct ConfigWatcher {
+    state: Mutex<WatcherState>,
+}
+
+impl ConfigWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 持续轮询所有工具的配置文件，检测到新的偏移时调用 `on_drift`
+    ///
+    /// 文件读取与密钥库解密都在阻塞线程池中执行，不占用异步运行时
+    pub async fn watch<F>(self: &Arc<Self>, manager: &ProxyManager, mut on_drift: F)
+    where
+        F: FnMut(&ConfigDrift),
+    {
+        loop {
+            let mut running = Vec::new();
+            for tool in Tool::all() {
+                if manager.is_running(&tool.id).await {
+                    running.push(tool.id);
+                }
+            }
+
+            let watcher = Arc::clone(self);
+            match tokio::task::spawn_blocking(move || watcher.poll(&running)).await {
+                Ok(drifts) => {
+                    for drift in &drifts {
+                        tracing::warn!(
+                            tool_id = %drift.tool_id,
+                            kind = ?drift.kind,
+                            "检测到工具配置被外部修改"
+                        );
+                        on_drift(drift);
+                    }
+                }
+                Err(e) => tracing::error!(error = ?e, "配置文件轮询任务失败"),
+            }
+
+            tokio::time::sleep(WATCH_INTERVAL).await;
+        }
+    }
+
+    /// 检查一轮所有工具的配置文件，返回新检测到的偏移
+    ///
+    /// `running` 为透明代理运行中的工具 ID
+    fn poll(&self, running: &[String]) -> Vec<ConfigDrift> {
+        let global_config = read_global_config().ok().flatten();
+        let mut profiles = ProfileLookup::default();
+
+        Tool::all()
+            .into_iter()
+            .filter_map(|tool| {
+                let proxy = if running.contains(&tool.id) {
+                    global_config
+                        .as_ref()
+                        .and_then(|config| config.get_proxy_config(&tool.id))
+                        .and_then(|config| {
+                            config
+                                .local_api_key
+                                .clone()
+                                .map(|local_api_key| (config.port, local_api_key))
+                        })
+                } else {
+                    None
+                };
+                self.check_tool(&tool, proxy, &mut profiles)
+            })
+            .collect()
+    }
+
+    /// 检查工具配置文件，返回新检测到的偏移
+    ///
+    /// `proxy` 为运行中透明代理的 (端口, 保护密钥)；读取配置期间不持有状态锁
+    fn check_tool(
+        &self,
+        tool: &Tool,
+        proxy: Option<(u16, String)>,
+        profiles: &mut ProfileLookup,
+    ) -> Option<ConfigDrift> {
+        let paths = tool.config_files();
+        let digests: Vec<Option<u64>> = paths
+            .iter()
+            .map(|path| fs::read(path).ok().map(|content| content_digest(&content)))
+            .collect();
+
+        let (previous, baseline) = {
+            let mut state = self.lock_state();
+            let entry = state.tools.entry(tool.id.clone()).or_default();
+            if entry.digests.as_ref() == Some(&digests) {
+                return None;
+            }
+            let previous = entry.digests.replace(digests.clone());
+            (previous, entry.baseline.clone())
+        };
+
+        // 首次观察或变化的文件全部由 DuckCoding 写入：更新基线，不视为偏移
+        let own_write = previous.as_ref().is_some_and(|previous| {
+            paths
+                .iter()
+                .zip(digests.iter().zip(previous))
+                .filter(|(_, (digest, before))| digest != before)
+                .all(|(path, (digest, _))| digest.is_some() && *digest == last_written_digest(path))
+        });
+        if previous.is_none() || own_write {
+            let baseline = Self::direct_baseline(tool, profiles);
+            self.reset_baseline(tool, baseline);
+            return None;
+        }
+
+        // 配置无法读取（文件被删除或格式错误）时不判定偏移
+        let (api_key, base_url) = ConfigService::read_api_config(tool).ok()?;
+        let expected = match proxy {
+            Some((port, local_api_key)) => ExpectedConfig::Proxy {
+                base_url: proxy_base_url(port),
+                local_api_key,
+            },
+            None => match baseline {
+                Some(baseline) => baseline,
+                None => {
+                    let baseline = Self::direct_baseline(tool, profiles);
+                    self.lock_state()
+                        .tools
+                        .entry(tool.id.clone())
+                        .or_default()
+                        .baseline = baseline;
+                    return None;
+                }
+            },
+        };
+
+        let current_profile = profiles.find(tool, &api_key, &base_url);
+        let kind = classify_drift(&expected, &api_key, &base_url, current_profile.as_deref());
+
+        let mut state = self.lock_state();
+        let Some(kind) = kind else {
+            state.drifts.remove(&tool.id);
+            return None;
+        };
+
+        let drift = ConfigDrift {
+            tool_id: tool.id.clone(),
+            tool_name: tool.name.clone(),
+            kind,
+            expected_base_url: expected.base_url().to_string(),
+            current_base_url: base_url,
+            current_profile,
+            detected_at: chrono::Utc::now().timestamp_millis(),
+        };
+        state.drifts.insert(tool.id.clone(), drift.clone());
+        Some(drift)
+    }
+
+    /// 当前未处理的偏移
+    pub fn drifts(&self) -> Vec<ConfigDrift> {
+        let state = self.lock_state();
+        let mut drifts: Vec<ConfigDrift> = state.drifts.values().cloned().collect();
+        drifts.sort_by_key(|drift| drift.detected_at);
+        drifts
+    }
+
+    /// 一键修复偏移
+    ///
+    /// - 透明代理运行中：若 Base URL 被改为其他上游，将其保存为新的真实配置，然后重新指向本地代理
+    /// - 直连模式：接受当前配置作为新的基线
+    pub async fn reconcile(&self, tool: &Tool, manager: &ProxyManager) -> Result<ConfigDrift> {
+        let drift = self
+            .lock_state()
+            .drifts
+            .get(&tool.id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("{} 没有待处理的配置偏移", tool.name))?;
+
+        let mut profiles = ProfileLookup::default();
+        if manager.is_running(&tool.id).await {
+            let mut global_config = read_global_config()
+                .map_err(anyhow::Error::msg)?
+                .context("全局配置不存在")?;
+            let mut proxy_config = global_config
+                .get_proxy_config(&tool.id)
+                .cloned()
+                .with_context(|| format!("{} 的代理配置不存在", tool.id))?;
+            let local_api_key = proxy_config
+                .local_api_key
+                .clone()
+                .context("透明代理保护密钥未设置")?;
+
+            let (api_key, base_url) = ConfigService::read_api_config(tool)?;
+            if drift.kind == DriftKind::ProxyBypassed
+                && !api_key.is_empty()
+                && !points_to(&base_url, &proxy_base_url(proxy_config.port))
+            {
+                TransparentProxyConfigService::update_real_config(
+                    tool,
+                    &mut global_config,
+                    &api_key,
+                    &base_url,
+                )?;
+                let profile = profiles.find(tool, &api_key, &base_url);
+                if let Some(config) = global_config.get_proxy_config_mut(&tool.id) {
+                    config.real_profile_name = profile.clone();
+                }
+                write_global_config(&global_config).map_err(anyhow::Error::msg)?;
+
+                proxy_config.real_api_key = Some(api_key);
+                proxy_config.real_base_url = Some(base_url);
+                proxy_config.real_profile_name = profile;
+                manager
+                    .update_config(&tool.id, proxy_config.clone(), &global_config)
+                    .await?;
+            }
+
+            TransparentProxyConfigService::update_config_to_proxy(
+                tool,
+                proxy_config.port,
+                &local_api_key,
+            )?;
+        }
+
+        let baseline = Self::direct_baseline(tool, &mut profiles);
+        self.reset_baseline(tool, baseline);
+
+        tracing::info!(tool_id = %tool.id, kind = ?drift.kind, "配置偏移已修复");
+        Ok(drift)
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, WatcherState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 更新直连基线并清除该工具的偏移
+    fn reset_baseline(&self, tool: &Tool, baseline: Option<ExpectedConfig>) {
+        let mut state = self.lock_state();
+        state.tools.entry(tool.id.clone()).or_default().baseline = baseline;
+        state.drifts.remove(&tool.id);
+    }
+
+    /// 以当前配置作为直连模式的基线
+    fn direct_baseline(tool: &Tool, profiles: &mut ProfileLookup) -> Option<ExpectedConfig> {
+        let (api_key, base_url) = ConfigService::read_api_config(tool).ok()?;
+        let profile = profiles.find(tool, &api_key, &base_url);
+        Some(ExpectedConfig::Direct {
+            api_key,
+            base_url,
+            profile,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_drift() {
+        let proxy = ExpectedConfig::Proxy {
+            base_url: "http://127.0.0.1:8787".to_string(),
+            local_api_key: "local".to_string(),
+        };
+        assert_eq!(
+            classify_drift(&proxy, "local", "http://127.0.0.1:8787/v1", None),
+            None
+        );
+        assert_eq!(
+            classify_drift(&proxy, "sk-new", "https://api.example.com", None),
+            Some(DriftKind::ProxyBypassed)
+        );
+        assert_eq!(
+            classify_drift(&proxy, "local", "http://127.0.0.1:87870", None),
+            Some(DriftKind::ProxyBypassed)
+        );
+        assert_eq!(
+            classify_drift(&proxy, "sk-new", "http://127.0.0.1:8787", None),
+            Some(DriftKind::KeyChanged)
+        );
+
+        let direct = ExpectedConfig::Direct {
+            api_key: "sk-a".to_string(),
+            base_url: "https://a.example.com".to_string(),
+            profile: Some("work".to_string()),
+        };
+        assert_eq!(
+            classify_drift(&direct, "sk-a", "https://a.example.com/", Some("work")),
+            None
+        );
+        assert_eq!(
+            classify_drift(&direct, "sk-b", "https://b.example.com", Some("personal")),
+            Some(DriftKind::ProfileChanged)
+        );
+        assert_eq!(
+            classify_drift(&direct, "sk-b", "https://a.example.com", None),
+            Some(DriftKind::KeyChanged)
+        );
+    }
+}
@@ -2,12 +2,20 @@
 //
 // 重组后的目录结构：
 // - config: 配置管理（待拆分优化）
+// - config_watcher: 配置文件监听与偏移检测
+// - profile_store: 配置档案存储
+// - profile_bundle: 配置档案导入导出包
+// - profile_health: 配置档案连通性检查
 // - tool: 工具安装、版本检查、下载
 // - proxy: 代理配置和透明代理
 // - update: 应用自身更新
 // - session: 会话管理（透明代理请求追踪）
 
 pub mod config;
+pub mod config_watcher;
+pub mod profile_bundle;
+pub mod profile_health;
+pub mod profile_store;
 pub mod proxy;
 pub mod session;
 pub mod tool;
@@ -15,6 +23,9 @@ pub mod update;
 
 // 重新导出服务
 pub use config::*;
+pub use config_watcher::*;
+pub use profile_bundle::*;
+pub use profile_store::*;
 pub use proxy::*;
 pub use session::*;
 pub use tool::*;
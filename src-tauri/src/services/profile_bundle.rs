@@ -0,0 +1,237 @@
+// 配置档案导入导出包
+//
+// 导出包是单个带版本号的 JSON 文件，包含一个或多个工具的配置档案（含明文 API Key）。
+// 指定口令时配置列表整体以 AES-256-GCM 加密（PBKDF2 派生密钥），文件中只有密文。
+
+use crate::utils::json_schema::SchemaViolation;
+use crate::utils::vault::{decrypt_with_passphrase, encrypt_with_passphrase, PassphraseCipher};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// 导出包格式标识
+pub const BUNDLE_FORMAT: &str = "duckcoding-profile-bundle";
+
+const BUNDLE_VERSION: u32 = 1;
+const BUNDLE_AAD: &[u8] = b"duckcoding-profile-bundle-v1";
+
+/// 导出包中的配置档案
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BundleProfile {
+    pub tool_id: String,
+    pub name: String,
+    pub base_url: String,
+    pub api_key: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_settings: Option<serde_json::Map<String, serde_json::Value>>,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// 导出包文件
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleFile {
+    format: String,
+    version: u32,
+    created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    profiles: Option<Vec<BundleProfile>>,
+    /// 口令加密后的配置列表
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encrypted: Option<PassphraseCipher>,
+}
+
+/// 导出包概要（导入前展示，判断是否需要口令）
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleInfo {
+    pub version: u32,
+    pub created_at: String,
+    pub encrypted: bool,
+    /// 未加密时的配置数量
+    pub profiles: Option<usize>,
+}
+
+/// 指定要导出的配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileRef {
+    pub tool_id: String,
+    pub name: String,
+}
+
+/// 导入时的同名配置处理方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStrategy {
+    /// 跳过
+    #[default]
+    Skip,
+    /// 覆盖已有配置
+    Overwrite,
+    /// 以新名称导入（如 work (2)）
+    Rename,
+}
+
+/// 单个配置的导入结果
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportAction {
+    Created,
+    Overwritten,
+    Renamed,
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportOutcome {
+    pub tool_id: String,
+    pub name: String,
+    /// 实际保存的名称（跳过或失败时为 None）
+    pub saved_as: Option<String>,
+    pub action: ImportAction,
+    pub message: Option<String>,
+    /// 校验错误
+    pub errors: Vec<SchemaViolation>,
+}
+
+impl ImportOutcome {
+    pub fn failed(profile: &BundleProfile, message: String, errors: Vec<SchemaViolation>) -> Self {
+        Self {
+            tool_id: profile.tool_id.clone(),
+            name: profile.name.clone(),
+            saved_as: None,
+            action: ImportAction::Failed,
+            message: Some(message),
+            errors,
+        }
+    }
+}
+
+/// 导入报告
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BundleImportReport {
+    pub outcomes: Vec<ImportOutcome>,
+}
+
+impl BundleImportReport {
+    /// 成功导入（新建、覆盖或重命名）的数量
+    pub fn imported(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|o| {
+                matches!(
+                    o.action,
+                    ImportAction::Created | ImportAction::Overwritten | ImportAction::Renamed
+                )
+            })
+            .count()
+    }
+}
+
+/// 生成导出包内容
+pub fn encode_bundle(profiles: &[BundleProfile], passphrase: Option<&str>) -> Result<String> {
+    let mut file = BundleFile {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        profiles: None,
+        encrypted: None,
+    };
+    match passphrase.filter(|p| !p.is_empty()) {
+        Some(passphrase) => {
+            let plaintext = serde_json::to_vec(profiles)?;
+            file.encrypted = Some(encrypt_with_passphrase(passphrase, BUNDLE_AAD, &plaintext)?);
+        }
+        None => file.profiles = Some(profiles.to_vec()),
+    }
+    Ok(serde_json::to_string_pretty(&file)?)
+}
+
+/// 读取导出包概要
+pub fn bundle_info(content: &str) -> Result<BundleInfo> {
+    let file = parse_bundle(content)?;
+    Ok(BundleInfo {
+        version: file.version,
+        created_at: file.created_at,
+        encrypted: file.encrypted.is_some(),
+        profiles: file.profiles.map(|p| p.len()),
+    })
+}
+
+/// 解析导出包中的配置列表（加密的导出包需要口令）
+pub fn decode_bundle(content: &str, passphrase: Option<&str>) -> Result<Vec<BundleProfile>> {
+    let file = parse_bundle(content)?;
+    match (file.profiles, file.encrypted) {
+        (Some(profiles), _) => Ok(profiles),
+        (None, Some(cipher)) => {
+            let passphrase = passphrase
+                .filter(|p| !p.is_empty())
+                .ok_or_else(|| anyhow::anyhow!("导出包已加密，请输入口令"))?;
+            let plaintext = decrypt_with_passphrase(passphrase, BUNDLE_AAD, &cipher)?;
+            serde_json::from_slice(&plaintext).context("解析导出包配置失败")
+        }
+        (None, None) => Ok(Vec::new()),
+    }
+}
+
+fn parse_bundle(content: &str) -> Result<BundleFile> {
+    let file: BundleFile = serde_json::from_str(content).context("解析导出包失败")?;
+    if file.format != BUNDLE_FORMAT {
+        anyhow::bail!("不是 DuckCoding 配置导出包");
+    }
+    if file.version > BUNDLE_VERSION {
+        anyhow::bail!("不支持的导出包版本: {}（请升级 DuckCoding）", file.version);
+    }
+    Ok(file)
+}
+
+/// 生成不与已有名称冲突的名称（name (2)、name (3) ...）
+pub fn next_available_name(existing: &[String], name: &str) -> String {
+    (2..)
+        .map(|index| format!("{name} ({index})"))
+        .find(|candidate| !existing.contains(candidate))
+        .expect("名称序号耗尽")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_roundtrip_with_and_without_passphrase() {
+        let profiles = vec![BundleProfile {
+            tool_id: "codex".to_string(),
+            name: "work".to_string(),
+            base_url: "https://jp.duckcoding.com/v1".to_string(),
+            api_key: "sk-secret".to_string(),
+            model: Some("gpt-5-codex".to_string()),
+            provider: Some("duckcoding".to_string()),
+            provider_settings: None,
+            notes: String::new(),
+            tags: vec!["team".to_string()],
+        }];
+
+        let plain = encode_bundle(&profiles, None).unwrap();
+        assert_eq!(bundle_info(&plain).unwrap().profiles, Some(1));
+        assert_eq!(decode_bundle(&plain, None).unwrap(), profiles);
+
+        let sealed = encode_bundle(&profiles, Some("onboarding")).unwrap();
+        assert!(!sealed.contains("sk-secret"));
+        assert!(bundle_info(&sealed).unwrap().encrypted);
+        assert!(decode_bundle(&sealed, None).is_err());
+        assert!(decode_bundle(&sealed, Some("wrong")).is_err());
+        assert_eq!(
+            decode_bundle(&sealed, Some("onboarding")).unwrap(),
+            profiles
+        );
+
+        assert!(bundle_info(r#"{"format":"other","version":1,"created_at":""}"#).is_err());
+        let existing = vec!["work".to_string(), "work (2)".to_string()];
+        assert_eq!(next_available_name(&existing, "work"), "work (3)");
+    }
+}
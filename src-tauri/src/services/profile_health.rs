@@ -0,0 +1,361 @@
+// 配置档案连通性检查
+//
+// 以工具 RequestProcessor 的认证方式请求上游的模型列表接口（不消耗 token），
+// 按状态码判断 API Key 是否有效，同时记录延迟和可用模型。检查结果按工具批量写入
+// 检查结果文件（profiles/<tool_id>.health.json）。
+
+use crate::models::{HealthStatus, ProfileHealth, ProfileRecord, Tool};
+use crate::services::profile_store::ProfileStore;
+use crate::services::proxy::error_envelope::ErrorFormat;
+use crate::services::proxy::headers::create_request_processor;
+use crate::utils::redact_secrets;
+use anyhow::{anyhow, Result};
+use futures_util::stream::{self, StreamExt};
+use hyper::header::HeaderValue;
+use hyper::HeaderMap as HyperHeaderMap;
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// 单次检查的超时时间
+const CHECK_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// 批量检查的最大并发数
+const MAX_CONCURRENT_CHECKS: usize = 8;
+
+/// 错误信息最大长度（字符）
+const MAX_MESSAGE_LEN: usize = 200;
+
+/// Anthropic 协议要求的 API 版本头
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// 批量检查中单个配置的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileCheckResult {
+    pub tool_id: String,
+    pub name: String,
+    /// 检查结果（读取 API Key 失败等无法发起请求时为 None）
+    pub health: Option<ProfileHealth>,
+    pub error: Option<String>,
+}
+
+/// 检查单个配置档案并记录结果
+pub async fn check_profile(tool: &Tool, profile_name: &str) -> Result<ProfileHealth> {
+    let store = ProfileStore::default_store()?;
+    let record = {
+        let (store, tool_id, name) = (store.clone(), tool.id.clone(), profile_name.to_string());
+        tokio::task::spawn_blocking(move || store.get(&tool_id, &name)).await??
+    }
+    .ok_or_else(|| anyhow!("配置不存在: {profile_name}"))?;
+    let client = build_check_client()?;
+    let health = check_record(&client, &store, &record).await?;
+    record_checks(store, vec![(record, Ok(health.clone()))]).await?;
+    Ok(health)
+}
+
+/// 并发检查所有工具的全部配置档案并记录结果（按工具、名称排序返回）
+pub async fn check_all_profiles() -> Result<Vec<ProfileCheckResult>> {
+    let store = ProfileStore::default_store()?;
+    let client = build_check_client()?;
+
+    let records = {
+        let store = store.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut records = Vec::new();
+            for tool in Tool::all() {
+                match store.list(&tool.id) {
+                    Ok(profiles) => records.extend(profiles),
+                    Err(e) => {
+                        tracing::warn!(tool_id = %tool.id, error = ?e, "读取配置档案失败，跳过检查")
+                    }
+                }
+            }
+            records
+        })
+        .await?
+    };
+
+    let (client, store_ref) = (&client, &store);
+    let checks: Vec<(ProfileRecord, Result<ProfileHealth>)> = stream::iter(records)
+        .map(|record| async move {
+            let result = check_record(client, store_ref, &record).await;
+            (record, result)
+        })
+        .buffered(MAX_CONCURRENT_CHECKS)
+        .collect()
+        .await;
+
+    let results = checks
+        .iter()
+        .map(|(record, result)| ProfileCheckResult {
+            tool_id: record.tool_id.clone(),
+            name: record.name.clone(),
+            health: result.as_ref().ok().cloned(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+        })
+        .collect();
+    if let Err(e) = record_checks(store, checks).await {
+        tracing::warn!(error = ?e, "记录检查结果失败");
+    }
+    Ok(results)
+}
+
+/// 按工具批量写入检查结果（每个工具写一次，在阻塞线程中执行）
+async fn record_checks(
+    store: ProfileStore,
+    checks: Vec<(ProfileRecord, Result<ProfileHealth>)>,
+) -> Result<()> {
+    let mut by_tool: BTreeMap<String, Vec<(String, ProfileHealth)>> = BTreeMap::new();
+    for (record, result) in checks {
+        if let Ok(health) = result {
+            by_tool
+                .entry(record.tool_id)
+                .or_default()
+                .push((record.id, health));
+        }
+    }
+    tokio::task::spawn_blocking(move || {
+        for (tool_id, results) in by_tool {
+            let recorded = store.record_checks(&tool_id, &results)?;
+            if recorded < results.len() {
+                tracing::debug!(tool_id = %tool_id, "部分配置已删除，不记录检查结果");
+            }
+        }
+        Ok(())
+    })
+    .await?
+}
+
+async fn check_record(
+    client: &Client,
+    store: &ProfileStore,
+    record: &ProfileRecord,
+) -> Result<ProfileHealth> {
+    if record.base_url.is_empty() || record.api_key_ref.is_empty() {
+        anyhow::bail!("配置缺少 API Key 或 Base URL");
+    }
+    let api_key = store.vault().resolve(&record.api_key_ref)?;
+    check_endpoint(client, &record.tool_id, &record.base_url, &api_key).await
+}
+
+/// 以工具的认证方式请求模型列表接口
+///
+/// 网络错误不返回 Err，而是记为 `HealthStatus::Unreachable`
+pub async fn check_endpoint(
+    client: &Client,
+    tool_id: &str,
+    base_url: &str,
+    api_key: &str,
+) -> Result<ProfileHealth> {
+    let processor = create_request_processor(tool_id)?;
+    let mut headers = HyperHeaderMap::new();
+    if processor.error_format() == ErrorFormat::Anthropic {
+        headers.insert(
+            "anthropic-version",
+            HeaderValue::from_static(ANTHROPIC_VERSION),
+        );
+    }
+    let request = processor
+        .process_outgoing_request(
+            base_url,
+            api_key,
+            processor.models_path(),
+            None,
+            &headers,
+            &[],
+        )
+        .await?;
+
+    let started = Instant::now();
+    let response = client
+        .get(&request.target_url)
+        .headers(request.headers)
+        .timeout(CHECK_TIMEOUT)
+        .send()
+        .await;
+    let checked_at = chrono::Utc::now().timestamp();
+
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => {
+            let message = if e.is_timeout() {
+                format!("请求超时（{} 秒）", CHECK_TIMEOUT.as_secs())
+            } else {
+                format!("请求失败: {e}")
+            };
+            return Ok(ProfileHealth {
+                status: HealthStatus::Unreachable,
+                http_status: None,
+                latency_ms: None,
+                auth_valid: None,
+                models: Vec::new(),
+                message: Some(redact_secrets(&message, &[api_key]).into_owned()),
+                checked_at,
+            });
+        }
+    };
+    let latency_ms = started.elapsed().as_millis() as u64;
+    let status = response.status().as_u16();
+    let body = response.bytes().await.unwrap_or_default();
+
+    let (health_status, auth_valid) = classify_status(status);
+    let (models, message) = if health_status == HealthStatus::Healthy {
+        (parse_models(&body), None)
+    } else {
+        (Vec::new(), error_message(&body, api_key))
+    };
+
+    Ok(ProfileHealth {
+        status: health_status,
+        http_status: Some(status),
+        latency_ms: Some(latency_ms),
+        auth_valid,
+        models,
+        message,
+        checked_at,
+    })
+}
+
+fn build_check_client() -> Result<Client> {
+    crate::http_client::build_client().map_err(|e| anyhow!(e))
+}
+
+/// 按状态码判断检查结论和 API Key 是否有效
+fn classify_status(status: u16) -> (HealthStatus, Option<bool>) {
+    match status {
+        200..=299 => (HealthStatus::Healthy, Some(true)),
+        401 | 403 => (HealthStatus::AuthFailed, Some(false)),
+        // 其他状态（如中转服务未实现模型列表接口）无法判断 API Key 是否有效
+        _ => (HealthStatus::HttpError, None),
+    }
+}
+
+/// 解析模型列表（OpenAI / Anthropic 的 data[].id，Gemini 的 models[].name）
+fn parse_models(body: &[u8]) -> Vec<String> {
+    let Ok(json) = serde_json::from_slice::<Value>(body) else {
+        return Vec::new();
+    };
+    let (list, key) = if let Some(data) = json.get("data").and_then(Value::as_array) {
+        (data, "id")
+    } else if let Some(models) = json.get("models").and_then(Value::as_array) {
+        (models, "name")
+    } else {
+        return Vec::new();
+    };
+    list.iter()
+        .filter_map(|model| model.get(key)?.as_str())
+        .map(|id| id.trim_start_matches("models/").to_string())
+        .collect()
+}
+
+/// 提取上游错误信息（优先取 error.message，否则截取响应体），先脱敏再截断
+fn error_message(body: &[u8], api_key: &str) -> Option<String> {
+    let json_message = serde_json::from_slice::<Value>(body).ok().and_then(|json| {
+        json.pointer("/error/message")
+            .or_else(|| json.get("message"))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    });
+    let message = json_message.unwrap_or_else(|| String::from_utf8_lossy(body).trim().to_string());
+    if message.is_empty() {
+        return None;
+    }
+    Some(
+        redact_secrets(&message, &[api_key])
+            .chars()
+            .take(MAX_MESSAGE_LEN)
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// 启动只响应一次的 HTTP 服务，返回地址和收到的请求头
+    async fn serve_once(
+        status: &'static str,
+        body: &'static str,
+    ) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            let response = format!(
+                "HTTP/1.1 {status}\r\ncontent-type: application/json\r\n\
+                 content-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&request).to_lowercase()
+        });
+        (format!("http://{addr}"), handle)
+    }
+
+    #[tokio::test]
+    async fn test_check_endpoint_reports_models_and_auth() {
+        let client = Client::new();
+
+        let (base_url, request) = serve_once(
+            "200 OK",
+            r#"{"data":[{"id":"gpt-5-codex"},{"id":"gpt-5"}]}"#,
+        )
+        .await;
+        let health = check_endpoint(&client, "codex", &format!("{base_url}/v1"), "sk-good")
+            .await
+            .unwrap();
+        let request = request.await.unwrap();
+        assert!(request.starts_with("get /v1/models "));
+        assert!(request.contains("authorization: bearer sk-good"));
+        assert_eq!(health.status, HealthStatus::Healthy);
+        assert_eq!(health.auth_valid, Some(true));
+        assert_eq!(health.http_status, Some(200));
+        assert_eq!(health.models, vec!["gpt-5-codex", "gpt-5"]);
+
+        let (base_url, request) = serve_once(
+            "401 Unauthorized",
+            r#"{"error":{"message":"invalid api key sk-bad"}}"#,
+        )
+        .await;
+        let health = check_endpoint(&client, "gemini-cli", &base_url, "sk-bad")
+            .await
+            .unwrap();
+        let request = request.await.unwrap();
+        assert!(request.starts_with("get /v1beta/models "));
+        assert!(request.contains("x-goog-api-key: sk-bad"));
+        assert_eq!(health.status, HealthStatus::AuthFailed);
+        assert_eq!(health.auth_valid, Some(false));
+        assert!(!health.message.unwrap().contains("sk-bad"));
+
+        // 端口已关闭
+        let health = check_endpoint(&client, "claude-code", &base_url, "sk-any")
+            .await
+            .unwrap();
+        assert_eq!(health.status, HealthStatus::Unreachable);
+        assert_eq!(health.http_status, None);
+    }
+
+    #[test]
+    fn test_parse_models_and_classify_status() {
+        assert_eq!(
+            parse_models(br#"{"models":[{"name":"models/gemini-2.5-pro"}]}"#),
+            vec!["gemini-2.5-pro"]
+        );
+        assert!(parse_models(b"not json").is_empty());
+        assert_eq!(classify_status(404), (HealthStatus::HttpError, None));
+        assert_eq!(
+            error_message(b"  upstream down  ", "").as_deref(),
+            Some("upstream down")
+        );
+    }
+}
@@ -0,0 +1,413 @@
+// 统一配置档案存储
+//
+// 每个工具一个 JSON 文件（~/.duckcoding/profiles/<tool_id>.json），通过 write_atomic 写入。
+// 存储文件存在即表示该工具已完成旧版备份文件的导入。
+// 连通性检查结果单独保存在 <tool_id>.health.json（不保存快照），读取时合并到档案的 last_check。
+
+use crate::models::{ProfileHealth, ProfileRecord};
+use crate::utils::vault::{Vault, PROFILE_SECRET_PREFIX};
+use crate::utils::{write_atomic_with, SnapshotStore};
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// 存储文件格式版本
+const STORE_VERSION: u32 = 1;
+
+/// 配置档案名称最大长度
+const MAX_NAME_LEN: usize = 64;
+
+/// 串行化存储文件的读-改-写
+static STORE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ProfileFile {
+    version: u32,
+    #[serde(default)]
+    profiles: Vec<ProfileRecord>,
+}
+
+/// 连通性检查结果文件（档案 ID -> 最近一次检查结果）
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HealthFile {
+    version: u32,
+    #[serde(default)]
+    checks: BTreeMap<String, ProfileHealth>,
+}
+
+/// 配置档案存储
+///
+/// 同时持有档案 API Key 所在的密钥库和写入时使用的快照存储，测试中可以整体指向临时目录
+#[derive(Debug, Clone)]
+pub struct ProfileStore {
+    dir: PathBuf,
+    vault: Vault,
+    snapshots: Option<SnapshotStore>,
+}
+
+impl ProfileStore {
+    pub fn new(dir: impl Into<PathBuf>, vault: Vault, snapshots: Option<SnapshotStore>) -> Self {
+        Self {
+            dir: dir.into(),
+            vault,
+            snapshots,
+        }
+    }
+
+    /// 默认存储 (~/.duckcoding/profiles，密钥库和快照也在 ~/.duckcoding 下)
+    pub fn default_store() -> Result<Self> {
+        let dir = crate::utils::config::config_dir().map_err(|e| anyhow::anyhow!(e))?;
+        Ok(Self::new(
+            dir.join("profiles"),
+            Vault::new(&dir),
+            SnapshotStore::default_store(),
+        ))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 档案 API Key 所在的密钥库
+    pub fn vault(&self) -> &Vault {
+        &self.vault
+    }
+
+    /// 密钥库中所有配置档案的 API Key（用于日志脱敏，密钥库无法打开时为空）
+    pub fn secret_values(&self) -> Vec<String> {
+        self.vault
+            .entries()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(id, _)| id.starts_with(PROFILE_SECRET_PREFIX))
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    /// 写入配置文件时使用的快照存储
+    pub fn snapshots(&self) -> Option<&SnapshotStore> {
+        self.snapshots.as_ref()
+    }
+
+    fn path(&self, tool_id: &str) -> PathBuf {
+        self.dir.join(format!("{tool_id}.json"))
+    }
+
+    fn health_path(&self, tool_id: &str) -> PathBuf {
+        self.dir.join(format!("{tool_id}.health.json"))
+    }
+
+    /// 工具的存储文件是否已存在
+    pub fn exists(&self, tool_id: &str) -> bool {
+        self.path(tool_id).exists()
+    }
+
+    /// 列出工具的全部配置档案（按名称排序）
+    pub fn list(&self, tool_id: &str) -> Result<Vec<ProfileRecord>> {
+        let mut profiles = self.read(tool_id)?.profiles;
+        profiles.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(profiles)
+    }
+
+    /// 按名称获取配置档案
+    pub fn get(&self, tool_id: &str, name: &str) -> Result<Option<ProfileRecord>> {
+        Ok(self
+            .read(tool_id)?
+            .profiles
+            .into_iter()
+            .find(|p| p.name == name))
+    }
+
+    /// 保存配置档案（按 ID 覆盖或新增），名称不能与其他档案重复
+    pub fn save(&self, record: &ProfileRecord) -> Result<()> {
+        validate_profile_name(&record.name)?;
+        self.modify(&record.tool_id, |profiles| {
+            if profiles
+                .iter()
+                .any(|p| p.name == record.name && p.id != record.id)
+            {
+                anyhow::bail!("配置名称已存在: {}", record.name);
+            }
+            match profiles.iter_mut().find(|p| p.id == record.id) {
+                Some(existing) => *existing = record.clone(),
+                None => profiles.push(record.clone()),
+            }
+            Ok(())
+        })
+    }
+
+    /// 重命名配置档案
+    pub fn rename(&self, tool_id: &str, name: &str, new_name: &str) -> Result<ProfileRecord> {
+        validate_profile_name(new_name)?;
+        self.modify(tool_id, |profiles| {
+            if name != new_name && profiles.iter().any(|p| p.name == new_name) {
+                anyhow::bail!("配置名称已存在: {new_name}");
+            }
+            let record = profiles
+                .iter_mut()
+                .find(|p| p.name == name)
+                .ok_or_else(|| anyhow::anyhow!("配置不存在: {name}"))?;
+            record.name = new_name.to_string();
+            record.touch();
+            Ok(record.clone())
+        })
+    }
+
+    /// 删除配置档案，返回被删除的记录
+    pub fn remove(&self, tool_id: &str, name: &str) -> Result<Option<ProfileRecord>> {
+        self.modify(tool_id, |profiles| {
+            Ok(profiles
+                .iter()
+                .position(|p| p.name == name)
+                .map(|index| profiles.remove(index)))
+        })
+    }
+
+    /// 批量记录连通性检查结果（只写检查结果文件，已删除的档案跳过），返回记录的数量
+    pub fn record_checks(
+        &self,
+        tool_id: &str,
+        results: &[(String, ProfileHealth)],
+    ) -> Result<usize> {
+        let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let profiles = self.read_file(tool_id)?.profiles;
+        let mut checks = self.read_checks(tool_id)?;
+        let mut recorded = 0;
+        for (id, health) in results {
+            if profiles.iter().any(|p| &p.id == id) {
+                checks.insert(id.clone(), health.clone());
+                recorded += 1;
+            }
+        }
+        if recorded > 0 {
+            self.write_checks(tool_id, checks)?;
+        }
+        Ok(recorded)
+    }
+
+    /// 创建空存储文件（已存在时跳过）
+    pub fn init(&self, tool_id: &str) -> Result<()> {
+        self.modify(tool_id, |_| Ok(()))
+    }
+
+    fn read(&self, tool_id: &str) -> Result<ProfileFile> {
+        let mut file = self.read_file(tool_id)?;
+        let checks = self.read_checks(tool_id)?;
+        merge_checks(&mut file.profiles, &checks);
+        Ok(file)
+    }
+
+    fn read_file(&self, tool_id: &str) -> Result<ProfileFile> {
+        let path = self.path(tool_id);
+        if !path.exists() {
+            return Ok(ProfileFile {
+                version: STORE_VERSION,
+                profiles: Vec::new(),
+            });
+        }
+        let content = fs::read_to_string(&path).context("读取配置档案失败")?;
+        serde_json::from_str(&content).with_context(|| format!("解析配置档案失败: {path:?}"))
+    }
+
+    /// 读取检查结果（文件损坏时忽略，下次检查会重新生成）
+    fn read_checks(&self, tool_id: &str) -> Result<BTreeMap<String, ProfileHealth>> {
+        let path = self.health_path(tool_id);
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let content = fs::read_to_string(&path).context("读取检查结果失败")?;
+        match serde_json::from_str::<HealthFile>(&content) {
+            Ok(file) => Ok(file.checks),
+            Err(e) => {
+                tracing::warn!(path = ?path, error = ?e, "解析检查结果失败，已忽略");
+                Ok(BTreeMap::new())
+            }
+        }
+    }
+
+    /// 检查结果频繁更新且可以重新生成，不保存快照，避免挤掉配置档案的快照
+    fn write_checks(&self, tool_id: &str, checks: BTreeMap<String, ProfileHealth>) -> Result<()> {
+        let file = HealthFile {
+            version: STORE_VERSION,
+            checks,
+        };
+        fs::create_dir_all(&self.dir).context("创建配置档案目录失败")?;
+        write_atomic_with(
+            &self.health_path(tool_id),
+            serde_json::to_string_pretty(&file)?,
+            None,
+        )
+        .context("写入检查结果失败")
+    }
+
+    fn modify<R>(
+        &self,
+        tool_id: &str,
+        f: impl FnOnce(&mut Vec<ProfileRecord>) -> Result<R>,
+    ) -> Result<R> {
+        let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = self.read_file(tool_id)?;
+        let checks = self.read_checks(tool_id)?;
+        merge_checks(&mut file.profiles, &checks);
+        let result = f(&mut file.profiles)?;
+        file.version = STORE_VERSION;
+
+        // 检查结果只保存在检查结果文件中（删除档案或清空 last_check 时一并移除）
+        let mut new_checks = BTreeMap::new();
+        for record in &mut file.profiles {
+            if let Some(health) = record.last_check.take() {
+                new_checks.insert(record.id.clone(), health);
+            }
+        }
+
+        fs::create_dir_all(&self.dir).context("创建配置档案目录失败")?;
+        write_atomic_with(
+            &self.path(tool_id),
+            serde_json::to_string_pretty(&file)?,
+            self.snapshots(),
+        )
+        .context("写入配置档案失败")?;
+        if new_checks != checks {
+            self.write_checks(tool_id, new_checks)?;
+        }
+        Ok(result)
+    }
+}
+
+/// 把检查结果合并到档案（兼容旧版直接保存在档案中的 last_check）
+fn merge_checks(profiles: &mut [ProfileRecord], checks: &BTreeMap<String, ProfileHealth>) {
+    for record in profiles {
+        if let Some(health) = checks.get(&record.id) {
+            record.last_check = Some(health.clone());
+        }
+    }
+}
+
+/// 校验配置档案名称
+pub fn validate_profile_name(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        anyhow::bail!("配置名称不能为空");
+    }
+    if name != name.trim() {
+        anyhow::bail!("配置名称首尾不能包含空白字符");
+    }
+    if name.chars().count() > MAX_NAME_LEN {
+        anyhow::bail!("配置名称不能超过 {MAX_NAME_LEN} 个字符");
+    }
+    if name.chars().any(|c| c.is_control()) {
+        anyhow::bail!("配置名称不能包含控制字符");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_profile_store_crud_and_search() {
+        let dir = TempDir::new().unwrap();
+        let store = ProfileStore::new(dir.path(), Vault::new(dir.path()), None);
+        assert!(!store.exists("codex"));
+        assert!(store.list("codex").unwrap().is_empty());
+
+        let mut work = ProfileRecord::new("codex", "work");
+        work.base_url = "https://jp.duckcoding.com/v1".to_string();
+        work.tags = vec!["Team".to_string()];
+        store.save(&work).unwrap();
+        let mut home = ProfileRecord::new("codex", "home");
+        home.notes = "个人账号".to_string();
+        store.save(&home).unwrap();
+        assert!(store.exists("codex"));
+
+        let names: Vec<_> = store
+            .list("codex")
+            .unwrap()
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        assert_eq!(names, vec!["home", "work"]);
+
+        // 名称冲突
+        let duplicate = ProfileRecord::new("codex", "work");
+        assert!(store.save(&duplicate).is_err());
+        assert!(store.rename("codex", "home", "work").is_err());
+        assert!(store.rename("codex", "home", " ").is_err());
+
+        // 重命名保留 ID
+        let renamed = store.rename("codex", "work", "office").unwrap();
+        assert_eq!(renamed.id, work.id);
+        assert!(store.get("codex", "work").unwrap().is_none());
+
+        let office = store.get("codex", "office").unwrap().unwrap();
+        assert!(office.matches("DUCKCODING"));
+        assert!(office.has_tag("team"));
+        assert!(!office.matches("个人"));
+        assert!(store.get("codex", "home").unwrap().unwrap().matches("个人"));
+
+        // 检查结果按 ID 写回，重命名后仍然保留
+        let health = ProfileHealth {
+            status: crate::models::HealthStatus::Healthy,
+            http_status: Some(200),
+            latency_ms: Some(120),
+            auth_valid: Some(true),
+            models: vec!["gpt-5-codex".to_string()],
+            message: None,
+            checked_at: 0,
+        };
+        let results = vec![
+            (work.id.clone(), health.clone()),
+            ("missing".to_string(), health.clone()),
+        ];
+        assert_eq!(store.record_checks("codex", &results).unwrap(), 1);
+        let office = store.get("codex", "office").unwrap().unwrap();
+        assert_eq!(office.last_check, Some(health.clone()));
+        // 检查结果单独保存，不写入档案文件
+        let content = fs::read_to_string(dir.path().join("codex.json")).unwrap();
+        assert!(!content.contains("last_check"));
+        assert!(dir.path().join("codex.health.json").exists());
+
+        // 修改档案不影响检查结果，清空 last_check 时一并移除
+        store.rename("codex", "office", "work").unwrap();
+        assert_eq!(
+            store.get("codex", "work").unwrap().unwrap().last_check,
+            Some(health)
+        );
+        let mut reset = store.get("codex", "work").unwrap().unwrap();
+        reset.last_check = None;
+        store.save(&reset).unwrap();
+        assert!(store
+            .get("codex", "work")
+            .unwrap()
+            .unwrap()
+            .last_check
+            .is_none());
+        store.rename("codex", "work", "office").unwrap();
+
+        assert_eq!(
+            store.remove("codex", "home").unwrap().map(|p| p.id),
+            Some(home.id)
+        );
+        assert!(store.remove("codex", "home").unwrap().is_none());
+        assert_eq!(store.list("codex").unwrap().len(), 1);
+
+        // 日志脱敏只收集配置档案的密钥
+        store
+            .vault()
+            .seal(
+                &crate::utils::vault::profile_secret_id("codex", &work.id),
+                "sk-profile",
+            )
+            .unwrap();
+        store
+            .vault()
+            .seal("config/proxy_password", "proxy-pass")
+            .unwrap();
+        assert_eq!(store.secret_values(), vec!["sk-profile".to_string()]);
+    }
+}
@@ -0,0 +1,174 @@
+// 代理请求审计
+//
+// RequestAudit 在请求进入时创建，随处理流程逐步补全字段，
+// 最终在响应结束（流式响应为流结束或客户端断开）时写入 proxy_requests 表
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::status::ProxyStats;
+use crate::services::session::{ProxyRequestRecord, SessionEvent, SESSION_MANAGER};
+
+/// 审计记录中的错误类型
+pub mod error_kind {
+    pub const UNAUTHORIZED: &str = "unauthorized";
+    pub const RATE_LIMITED: &str = "rate_limited";
+    pub const CONFIGURATION_MISSING: &str = "configuration_missing";
+    pub const LOOP_DETECTED: &str = "loop_detected";
+    pub const CONNECT: &str = "connect";
+    pub const TIMEOUT: &str = "timeout";
+    pub const UPSTREAM_ERROR: &str = "upstream_error";
+    pub const STREAM_INTERRUPTED: &str = "stream_interrupted";
+    pub const INTERNAL: &str = "internal";
+}
+
+/// 单个请求的审计记录
+pub struct RequestAudit {
+    record: ProxyRequestRecord,
+    started_at: Instant,
+    finished: bool,
+    /// 错误描述（只用于运行状态展示，不写入数据库）
+    error_message: Option<String>,
+    stats: Option<Arc<ProxyStats>>,
+}
+
+impl RequestAudit {
+    pub fn new(tool_id: &str, method: &str, path: &str) -> Self {
+        Self {
+            record: ProxyRequestRecord {
+                timestamp: chrono::Utc::now().timestamp_millis(),
+                tool_id: tool_id.to_string(),
+                method: method.to_string(),
+                path: path.to_string(),
+                ..Default::default()
+            },
+            started_at: Instant::now(),
+            finished: false,
+            error_message: None,
+            stats: None,
+        }
+    }
+
+    /// 关联代理实例的运行统计，请求结束时同步更新错误计数
+    pub fn with_stats(mut self, stats: Arc<ProxyStats>) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    pub fn set_session_id(&mut self, session_id: Option<String>) {
+        self.record.session_id = session_id;
+    }
+
+    /// 记录通过验证的命名本地密钥
+    pub fn set_key_id(&mut self, key_id: Option<String>) {
+        self.record.key_id = key_id;
+    }
+
+    /// 关联请求抓包
+    pub fn set_capture_id(&mut self, capture_id: &str) {
+        self.record.capture_id = Some(capture_id.to_string());
+    }
+
+    /// 记录实际使用的上游（只保留主机和端口）
+    pub fn set_upstream(&mut self, base_url: &str) {
+        self.record.upstream_host = url::Url::parse(base_url)
+            .ok()
+            .and_then(|u| {
+                u.host_str().map(|host| match u.port() {
+                    Some(port) => format!("{host}:{port}"),
+                    None => host.to_string(),
+                })
+            })
+            .or_else(|| Some(base_url.to_string()));
+    }
+
+    pub fn set_bytes_in(&mut self, bytes: usize) {
+        self.record.bytes_in = bytes as i64;
+    }
+
+    pub fn add_bytes_out(&mut self, bytes: usize) {
+        self.record.bytes_out += bytes as i64;
+    }
+
+    /// 记录返回给客户端的状态码（>= 400 且未设置错误类型时标记为上游错误）
+    pub fn set_status(&mut self, status: u16) {
+        self.record.status_code = Some(status);
+        if status >= 400 && self.record.error_kind.is_none() {
+            self.record.error_kind = Some(error_kind::UPSTREAM_ERROR.to_string());
+        }
+    }
+
+    pub fn set_error_kind(&mut self, kind: &str) {
+        self.record.error_kind = Some(kind.to_string());
+    }
+
+    /// 记录上游返回的原始错误响应体（改写为客户端格式前）
+    pub fn set_error_body(&mut self, body: String) {
+        self.record.error_body = Some(body);
+    }
+
+    pub fn set_error_message(&mut self, message: impl Into<String>) {
+        self.error_message = Some(message.into());
+    }
+
+    /// 转移审计记录的所有权（用于随流式响应移动），原对象不再写入
+    pub fn take(&mut self) -> RequestAudit {
+        let taken = RequestAudit {
+            record: self.record.clone(),
+            started_at: self.started_at,
+            finished: self.finished,
+            error_message: self.error_message.take(),
+            stats: self.stats.take(),
+        };
+        self.finished = true;
+        taken
+    }
+
+    /// 写入审计记录（只会写入一次）
+    pub fn finish(&mut self) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        self.record.latency_ms = self.started_at.elapsed().as_millis() as i64;
+
+        tracing::debug!(
+            tool_id = %self.record.tool_id,
+            method = %self.record.method,
+            path = %self.record.path,
+            upstream = ?self.record.upstream_host,
+            status = ?self.record.status_code,
+            latency_ms = self.record.latency_ms,
+            error_kind = ?self.record.error_kind,
+            "代理请求完成"
+        );
+
+        if let Some(stats) = &self.stats {
+            stats.request_finished(&self.record, self.error_message.as_deref());
+        }
+
+        let _ = SESSION_MANAGER
+            .send_event(SessionEvent::ProxyRequest(std::mem::take(&mut self.record)));
+    }
+}
+
+impl Drop for RequestAudit {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// 根据错误链判断错误类型
+pub fn classify_error(error: &anyhow::Error) -> &'static str {
+    for cause in error.chain() {
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            if e.is_timeout() {
+                return error_kind::TIMEOUT;
+            }
+            if e.is_connect() {
+                return error_kind::CONNECT;
+            }
+        }
+    }
+    error_kind::INTERNAL
+}
@@ -0,0 +1,576 @@
+// 请求抓包与重放
+//
+// 启用 ToolProxyConfig.capture 后，每个请求在响应结束时保存一份 JSON 抓包文件
+// （~/.duckcoding/captures/<工具>/<抓包 ID>.json）：
+// - 转发给上游的路径、查询参数、客户端请求头与请求体
+// - 上游响应状态、响应头与响应体（SSE 为完整的事件流）
+// 凭据类请求头和常见 API Key 格式在写入前脱敏；抓包 ID 同时写入请求审计记录。
+//
+// 重放时按当前配置（或指定的上游）重新发送抓包中的请求，并与原响应逐行对比
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use hyper::header::{CONTENT_ENCODING, CONTENT_LENGTH, HOST};
+use hyper::HeaderMap;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::headers::{create_request_processor, route_request_processor};
+use super::proxy_instance::build_upstream_client;
+use crate::models::{ToolProxyConfig, UpstreamConfig};
+use crate::services::tool::registry::is_valid_tool_id;
+use crate::utils::config::{config_dir, read_global_config};
+use crate::utils::redact::{is_sensitive_header, redact_secrets, REDACTED};
+use crate::utils::write_atomic_with;
+
+/// 逐行对比时每侧最多比较的行数
+const MAX_DIFF_LINES: usize = 2000;
+
+/// 响应体超过上限时额外保留的字节数（避免截断处残留部分密钥）
+const REDACT_SLACK_BYTES: usize = 256;
+
+/// 单次请求的抓包内容
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CapturedExchange {
+    pub id: String,
+    pub tool_id: String,
+    /// 请求时间（Unix 时间戳，毫秒）
+    pub timestamp: i64,
+    pub method: String,
+    /// 转发给上游的路径（已应用模型映射）
+    pub path: String,
+    pub query: Option<String>,
+    /// 客户端请求头（凭据已脱敏）
+    pub request_headers: BTreeMap<String, String>,
+    /// 转发给上游的请求体（已脱敏）
+    pub request_body: String,
+    pub request_truncated: bool,
+    pub upstream_base_url: Option<String>,
+    pub target_url: Option<String>,
+    pub status: Option<u16>,
+    pub response_headers: BTreeMap<String, String>,
+    /// 上游响应体（SSE 为完整事件流，已脱敏）
+    pub response_body: String,
+    pub response_truncated: bool,
+    pub streamed: bool,
+}
+
+/// 单个请求的抓包记录器
+///
+/// 随请求处理流程补全内容，响应结束（流式响应为流结束或客户端断开）时写入磁盘
+pub struct CaptureRecorder {
+    exchange: CapturedExchange,
+    secrets: Vec<String>,
+    max_body_bytes: usize,
+    max_files: usize,
+    response_body: Vec<u8>,
+    response_encoding: Option<String>,
+    finished: bool,
+}
+
+impl CaptureRecorder {
+    /// 创建抓包记录器（未启用抓包时返回 None）
+    pub fn start(
+        tool_id: &str,
+        config: &ToolProxyConfig,
+        method: &str,
+        headers: &HeaderMap,
+    ) -> Option<Self> {
+        if !config.capture.enabled {
+            return None;
+        }
+
+        let timestamp = chrono::Utc::now().timestamp_millis();
+        let suffix: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(6)
+            .map(|c| char::from(c).to_ascii_lowercase())
+            .collect();
+        let secrets = config.secret_values();
+
+        Some(Self {
+            exchange: CapturedExchange {
+                id: format!("{timestamp}-{suffix}"),
+                tool_id: tool_id.to_string(),
+                timestamp,
+                method: method.to_string(),
+                request_headers: redact_headers(headers, &secrets),
+                ..Default::default()
+            },
+            secrets,
+            max_body_bytes: config.capture.max_body_bytes,
+            max_files: config.capture.max_files,
+            response_body: Vec::new(),
+            response_encoding: None,
+            finished: false,
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.exchange.id
+    }
+
+    /// 记录转发给上游的路径与请求体
+    pub fn set_request(&mut self, path: &str, query: Option<&str>, body: &[u8]) {
+        self.exchange.path = path.to_string();
+        self.exchange.query = query.map(|q| self.redact(q));
+        // 先脱敏再截断，避免截断处残留部分密钥
+        let (body, truncated) = truncate_utf8(
+            self.redact(&String::from_utf8_lossy(body)),
+            self.max_body_bytes,
+        );
+        self.exchange.request_body = body;
+        self.exchange.request_truncated = truncated;
+    }
+
+    /// 记录实际使用的上游（故障转移时以最后一次为准）
+    pub fn set_upstream(&mut self, base_url: &str, target_url: &str) {
+        self.exchange.upstream_base_url = Some(base_url.to_string());
+        // Gemini 等工具可能把 API Key 放在查询参数中
+        self.exchange.target_url = Some(self.redact(target_url));
+    }
+
+    pub fn set_response(&mut self, status: u16, headers: &HeaderMap, streamed: bool) {
+        self.exchange.status = Some(status);
+        self.exchange.response_headers = redact_headers(headers, &self.secrets);
+        self.exchange.streamed = streamed;
+        self.response_encoding = headers
+            .get(CONTENT_ENCODING)
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| !v.eq_ignore_ascii_case("identity"))
+            .map(|v| v.to_string());
+    }
+
+    /// 追加响应数据（超过上限的部分丢弃）
+    pub fn feed(&mut self, chunk: &[u8]) {
+        // 多保留一段数据，脱敏后再截断到上限
+        let limit = self.max_body_bytes + REDACT_SLACK_BYTES;
+        let remaining = limit.saturating_sub(self.response_body.len());
+        if chunk.len() > remaining {
+            self.exchange.response_truncated = true;
+        }
+        self.response_body
+            .extend_from_slice(&chunk[..chunk.len().min(remaining)]);
+    }
+
+    fn redact(&self, text: &str) -> String {
+        let secrets: Vec<&str> = self.secrets.iter().map(|s| s.as_str()).collect();
+        redact_secrets(text, &secrets).into_owned()
+    }
+
+    /// 写入抓包文件（只会写入一次）
+    pub fn finish(&mut self) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+
+        let body = std::mem::take(&mut self.response_body);
+        self.exchange.response_body = match &self.response_encoding {
+            // 压缩过的响应体无法按文本保存
+            Some(encoding) => format!("<{} bytes, content-encoding: {encoding}>", body.len()),
+            None => {
+                let (body, truncated) = truncate_utf8(
+                    self.redact(&String::from_utf8_lossy(&body)),
+                    self.max_body_bytes,
+                );
+                self.exchange.response_truncated |= truncated;
+                body
+            }
+        };
+
+        let exchange = std::mem::take(&mut self.exchange);
+        let max_files = self.max_files;
+        let write = move || {
+            if let Err(e) = write_capture(&exchange, max_files) {
+                tracing::warn!(
+                    tool_id = %exchange.tool_id,
+                    capture_id = %exchange.id,
+                    error = ?e,
+                    "写入请求抓包失败"
+                );
+            }
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(write);
+            }
+            Err(_) => write(),
+        }
+    }
+}
+
+impl Drop for CaptureRecorder {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// 按字节数截断（保持 UTF-8 字符完整），返回是否发生截断
+fn truncate_utf8(mut text: String, max_bytes: usize) -> (String, bool) {
+    if text.len() <= max_bytes {
+        return (text, false);
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    (text, true)
+}
+
+fn redact_headers(headers: &HeaderMap, secrets: &[String]) -> BTreeMap<String, String> {
+    let secrets: Vec<&str> = secrets.iter().map(|s| s.as_str()).collect();
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if is_sensitive_header(name.as_str()) {
+                REDACTED.to_string()
+            } else {
+                redact_secrets(&String::from_utf8_lossy(value.as_bytes()), &secrets).into_owned()
+            };
+            (name.as_str().to_string(), value)
+        })
+        .collect()
+}
+
+/// 抓包 ID 与工具 ID 使用工具描述文件允许的字符集（防止路径穿越）
+fn is_safe_name(name: &str) -> bool {
+    is_valid_tool_id(name)
+}
+
+/// 工具的抓包目录（~/.duckcoding/captures/<工具>）
+pub fn capture_dir(tool_id: &str) -> Result<PathBuf> {
+    if !is_safe_name(tool_id) {
+        anyhow::bail!("无效的工具 ID: {tool_id}");
+    }
+    Ok(config_dir()
+        .map_err(anyhow::Error::msg)?
+        .join("captures")
+        .join(tool_id))
+}
+
+fn write_capture(exchange: &CapturedExchange, max_files: usize) -> Result<()> {
+    let dir = capture_dir(&exchange.tool_id)?;
+    std::fs::create_dir_all(&dir).context("创建抓包目录失败")?;
+    let content = serde_json::to_vec_pretty(exchange).context("序列化抓包失败")?;
+    let path = dir.join(format!("{}.json", exchange.id));
+    write_atomic_with(&path, content, None).context("写入抓包文件失败")?;
+    // 抓包包含完整的提示词和响应，限制为仅本人可读
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+            .context("设置抓包文件权限失败")?;
+    }
+    prune_captures(&dir, max_files)
+}
+
+/// 删除超出数量上限的最早抓包（抓包 ID 以毫秒时间戳开头，按文件名排序即按时间排序）
+fn prune_captures(dir: &Path, max_files: usize) -> Result<()> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    if files.len() <= max_files {
+        return Ok(());
+    }
+
+    files.sort();
+    for path in &files[..files.len() - max_files] {
+        let _ = std::fs::remove_file(path);
+    }
+    Ok(())
+}
+
+/// 读取抓包
+pub fn load_capture(tool_id: &str, capture_id: &str) -> Result<CapturedExchange> {
+    if !is_safe_name(capture_id) {
+        anyhow::bail!("无效的抓包 ID: {capture_id}");
+    }
+    let path = capture_dir(tool_id)?.join(format!("{capture_id}.json"));
+    let content =
+        std::fs::read(&path).with_context(|| format!("抓包不存在或已被清理: {capture_id}"))?;
+    serde_json::from_slice(&content).context("解析抓包文件失败")
+}
+
+/// 清空工具的抓包，返回删除的数量
+pub fn clear_captures(tool_id: &str) -> Result<usize> {
+    let dir = capture_dir(tool_id)?;
+    if !dir.exists() {
+        return Ok(0);
+    }
+
+    let mut deleted = 0;
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") && std::fs::remove_file(&path).is_ok()
+        {
+            deleted += 1;
+        }
+    }
+    Ok(deleted)
+}
+
+/// 对比结果中的一行
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiffLine {
+    pub kind: DiffKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    Same,
+    Added,
+    Removed,
+}
+
+/// 重放结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayResult {
+    pub capture_id: String,
+    pub upstream_base_url: String,
+    pub status: u16,
+    pub original_status: Option<u16>,
+    pub response_headers: BTreeMap<String, String>,
+    /// 重放得到的响应体（已脱敏）
+    pub response_body: String,
+    /// 响应体与原抓包完全一致
+    pub identical: bool,
+    /// 原响应（removed）与重放响应（added）的逐行对比
+    pub diff: Vec<DiffLine>,
+    /// 行数超过上限，只对比了前 MAX_DIFF_LINES 行
+    pub diff_truncated: bool,
+}
+
+/// 重放抓包中的请求
+///
+/// `upstream` 为空时使用当前配置中优先级最高的上游
+pub async fn replay_capture(
+    tool_id: &str,
+    capture_id: &str,
+    upstream: Option<UpstreamConfig>,
+) -> Result<ReplayResult> {
+    let global_config = read_global_config()
+        .map_err(anyhow::Error::msg)?
+        .context("全局配置不存在")?;
+    let config = global_config
+        .get_proxy_config(tool_id)
+        .with_context(|| format!("{tool_id} 尚未配置透明代理"))?;
+    let exchange = load_capture(tool_id, capture_id)?;
+    if exchange.request_truncated {
+        anyhow::bail!("抓包中的请求体已被截断，无法重放");
+    }
+
+    let upstream = match upstream {
+        Some(upstream) => upstream,
+        None => config
+            .upstream_candidates()
+            .into_iter()
+            .next()
+            .context("未配置可用的上游")?,
+    };
+    let base = upstream.base_url.trim_end_matches('/');
+
+    // 还原请求头（跳过已脱敏的凭据，由处理器重新注入上游 API Key）
+    let mut headers = HeaderMap::new();
+    for (name, value) in &exchange.request_headers {
+        if is_sensitive_header(name) || value == REDACTED {
+            continue;
+        }
+        let (Ok(name), Ok(value)) = (
+            hyper::header::HeaderName::from_bytes(name.as_bytes()),
+            hyper::header::HeaderValue::from_str(value),
+        ) else {
+            continue;
+        };
+        if name == HOST || name == CONTENT_LENGTH {
+            continue;
+        }
+        headers.insert(name, value);
+    }
+
+    let processor = create_request_processor(tool_id)?;
+    let processor = route_request_processor(processor.as_ref(), &exchange.path);
+    let processed = processor
+        .process_outgoing_request(
+            base,
+            &upstream.api_key,
+            &exchange.path,
+            exchange.query.as_deref(),
+            &headers,
+            exchange.request_body.as_bytes(),
+        )
+        .await
+        .context("处理重放请求失败")?;
+
+    let method = reqwest::Method::from_bytes(exchange.method.as_bytes())
+        .with_context(|| format!("无效的请求方法: {}", exchange.method))?;
+    let client = build_upstream_client(&global_config, config)?;
+    let mut builder = client
+        .request(method, &processed.target_url)
+        .headers(processed.headers);
+    if !processed.body.is_empty() {
+        builder = builder.body(processed.body.to_vec());
+    }
+
+    tracing::info!(
+        tool_id = %tool_id,
+        capture_id = %capture_id,
+        upstream = %base,
+        "重放抓包请求"
+    );
+    let response = builder.send().await.context("重放请求失败")?;
+    let status = response.status().as_u16();
+    let response_headers = response.headers().clone();
+    let body = response.bytes().await.context("读取重放响应失败")?;
+
+    let secrets = config.secret_values();
+    let secret_refs: Vec<&str> = secrets.iter().map(|s| s.as_str()).collect();
+    let response_body = match response_headers
+        .get(CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.eq_ignore_ascii_case("identity"))
+    {
+        Some(encoding) => format!("<{} bytes, content-encoding: {encoding}>", body.len()),
+        None => {
+            let text = redact_secrets(&String::from_utf8_lossy(&body), &secret_refs).into_owned();
+            truncate_utf8(text, config.capture.max_body_bytes).0
+        }
+    };
+
+    let (diff, diff_truncated) = line_diff(&exchange.response_body, &response_body);
+    Ok(ReplayResult {
+        capture_id: exchange.id,
+        upstream_base_url: base.to_string(),
+        status,
+        original_status: exchange.status,
+        response_headers: redact_headers(&response_headers, &secrets),
+        identical: exchange.response_body == response_body,
+        response_body,
+        diff,
+        diff_truncated,
+    })
+}
+
+/// 逐行对比（最长公共子序列）
+pub fn line_diff(old: &str, new: &str) -> (Vec<DiffLine>, bool) {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let truncated = old_lines.len() > MAX_DIFF_LINES || new_lines.len() > MAX_DIFF_LINES;
+    let a = &old_lines[..old_lines.len().min(MAX_DIFF_LINES)];
+    let b = &new_lines[..new_lines.len().min(MAX_DIFF_LINES)];
+
+    // lcs[i][j] 为 a[i..] 与 b[j..] 的最长公共子序列长度
+    let width = b.len() + 1;
+    let mut lcs = vec![0u16; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i * width + j] = if a[i] == b[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let line = |kind, text: &str| DiffLine {
+        kind,
+        text: text.to_string(),
+    };
+    let mut diff = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            diff.push(line(DiffKind::Same, a[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+            diff.push(line(DiffKind::Removed, a[i]));
+            i += 1;
+        } else {
+            diff.push(line(DiffKind::Added, b[j]));
+            j += 1;
+        }
+    }
+    diff.extend(a[i..].iter().map(|l| line(DiffKind::Removed, l)));
+    diff.extend(b[j..].iter().map(|l| line(DiffKind::Added, l)));
+
+    (diff, truncated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_diff() {
+        let (diff, truncated) = line_diff("a\nb\nc", "a\nx\nc\nd");
+        assert!(!truncated);
+        let kinds: Vec<_> = diff.iter().map(|l| (l.kind, l.text.as_str())).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (DiffKind::Same, "a"),
+                (DiffKind::Removed, "b"),
+                (DiffKind::Added, "x"),
+                (DiffKind::Same, "c"),
+                (DiffKind::Added, "d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recorder_redacts_and_truncates() {
+        let mut config = ToolProxyConfig {
+            real_api_key: Some("relay-secret-0001".to_string()),
+            ..Default::default()
+        };
+        config.capture.enabled = true;
+        config.capture.max_body_bytes = 16;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "authorization",
+            "Bearer dc-codex-abcdefghijklmnop".parse().unwrap(),
+        );
+        headers.insert("user-agent", "codex/1.0".parse().unwrap());
+
+        let mut recorder = CaptureRecorder::start("codex", &config, "POST", &headers).unwrap();
+        // 测试中不写入磁盘
+        recorder.finished = true;
+        recorder.set_request(
+            "/v1/responses",
+            None,
+            b"{\"key\":\"relay-secret-0001\",\"input\":\"hi\"}",
+        );
+
+        let exchange = &recorder.exchange;
+        assert_eq!(exchange.request_headers["authorization"], REDACTED);
+        assert_eq!(exchange.request_headers["user-agent"], "codex/1.0");
+        // 先脱敏再截断
+        assert_eq!(exchange.request_body, "{\"key\":\"***\",\"in");
+        assert!(exchange.request_truncated);
+
+        assert_eq!(
+            truncate_utf8("你好".to_string(), 4),
+            ("你".to_string(), true)
+        );
+    }
+
+    #[test]
+    fn test_disabled_capture_and_unsafe_ids() {
+        let config = ToolProxyConfig::default();
+        assert!(CaptureRecorder::start("codex", &config, "GET", &HeaderMap::new()).is_none());
+        assert!(load_capture("codex", "../config").is_err());
+        assert!(capture_dir("../x").is_err());
+        // tools.d 中的工具 ID 允许下划线
+        assert!(is_safe_name("my_tool"));
+        assert!(!is_safe_name("My.Tool"));
+    }
+}
@@ -0,0 +1,355 @@
+// 错误响应格式统一
+//
+// 中转服务返回 HTML 错误页或非标准 JSON 时，客户端只能显示难以理解的信息。
+// 这里负责从任意错误响应体中提取可读的错误信息，并按客户端期望的格式重新封装：
+// - Anthropic：{"type":"error","error":{"type":"...","message":"..."}}
+// - OpenAI：{"error":{"message":"...","type":"...","code":"..."}}
+// - Gemini：{"error":{"code":400,"message":"...","status":"INVALID_ARGUMENT"}}
+
+use bytes::Bytes;
+use hyper::header::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::{HeaderMap, StatusCode};
+use serde_json::{json, Value};
+
+/// 提取的错误信息最大长度（字符）
+const MAX_MESSAGE_CHARS: usize = 500;
+
+/// 审计日志中保留的原始错误响应体最大长度（字节）
+pub const MAX_AUDIT_BODY_BYTES: usize = 8 * 1024;
+
+/// 客户端期望的错误响应格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    Anthropic,
+    OpenAI,
+    Gemini,
+}
+
+impl ErrorFormat {
+    /// 构造错误响应体
+    ///
+    /// `code` 为代理自身的错误代码（如 CONFIGURATION_MISSING），上游错误时为空
+    pub fn build(&self, status: StatusCode, code: Option<&str>, message: &str) -> Bytes {
+        let message = if message.is_empty() {
+            status
+                .canonical_reason()
+                .unwrap_or("Upstream error")
+                .to_string()
+        } else {
+            message.to_string()
+        };
+
+        let value = match self {
+            ErrorFormat::Anthropic => json!({
+                "type": "error",
+                "error": {
+                    "type": anthropic_error_type(status),
+                    "message": with_code_prefix(code, &message),
+                },
+            }),
+            ErrorFormat::OpenAI => json!({
+                "error": {
+                    "message": message,
+                    "type": openai_error_type(status),
+                    "param": null,
+                    "code": code,
+                },
+            }),
+            ErrorFormat::Gemini => json!({
+                "error": {
+                    "code": status.as_u16(),
+                    "message": with_code_prefix(code, &message),
+                    "status": gemini_error_status(status),
+                },
+            }),
+        };
+
+        Bytes::from(value.to_string())
+    }
+
+    /// 检查响应体是否已经是该格式的错误（无需改写）
+    pub fn is_native(&self, body: &[u8]) -> bool {
+        let Ok(value) = serde_json::from_slice::<Value>(body) else {
+            return false;
+        };
+        // Gemini 流式接口的错误以数组形式返回
+        let value = match &value {
+            Value::Array(items) => match items.first() {
+                Some(first) => first,
+                None => return false,
+            },
+            _ => &value,
+        };
+
+        let error = &value["error"];
+        match self {
+            ErrorFormat::Anthropic => value["type"] == "error" && error["message"].is_string(),
+            ErrorFormat::OpenAI => error["message"].is_string(),
+            ErrorFormat::Gemini => error["code"].is_u64() && error["message"].is_string(),
+        }
+    }
+}
+
+fn with_code_prefix(code: Option<&str>, message: &str) -> String {
+    match code {
+        Some(code) => format!("[{code}] {message}"),
+        None => message.to_string(),
+    }
+}
+
+fn anthropic_error_type(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        400 => "invalid_request_error",
+        401 => "authentication_error",
+        403 => "permission_error",
+        404 => "not_found_error",
+        413 => "request_too_large",
+        429 => "rate_limit_error",
+        529 => "overloaded_error",
+        _ => "api_error",
+    }
+}
+
+fn openai_error_type(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        401 => "authentication_error",
+        403 => "permission_error",
+        429 => "rate_limit_error",
+        s if s >= 500 => "server_error",
+        _ => "invalid_request_error",
+    }
+}
+
+fn gemini_error_status(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        400 => "INVALID_ARGUMENT",
+        401 => "UNAUTHENTICATED",
+        403 => "PERMISSION_DENIED",
+        404 => "NOT_FOUND",
+        409 => "ALREADY_EXISTS",
+        429 => "RESOURCE_EXHAUSTED",
+        499 => "CANCELLED",
+        501 => "UNIMPLEMENTED",
+        502 | 503 => "UNAVAILABLE",
+        504 => "DEADLINE_EXCEEDED",
+        _ => "INTERNAL",
+    }
+}
+
+/// 从任意错误响应体中提取可读的错误信息
+///
+/// JSON 按常见字段查找；HTML 优先取 `<title>`，否则去除标签后取正文
+pub fn extract_error_message(body: &[u8]) -> String {
+    let text = String::from_utf8_lossy(body);
+    let text = text.trim();
+    if text.is_empty() {
+        return String::new();
+    }
+
+    let message = match serde_json::from_str::<Value>(text) {
+        Ok(value) => json_error_message(&value).unwrap_or_else(|| value.to_string()),
+        Err(_) if text.starts_with('<') => html_error_message(text),
+        Err(_) => text.to_string(),
+    };
+
+    truncate_chars(collapse_whitespace(&message), MAX_MESSAGE_CHARS)
+}
+
+fn json_error_message(value: &Value) -> Option<String> {
+    if let Value::Array(items) = value {
+        return items.first().and_then(json_error_message);
+    }
+
+    let candidates = [
+        &value["error"]["message"],
+        &value["error"],
+        &value["message"],
+        &value["detail"],
+        &value["msg"],
+        &value["error_description"],
+        &value["errors"][0]["message"],
+    ];
+    candidates
+        .iter()
+        .find_map(|v| v.as_str().filter(|s| !s.trim().is_empty()))
+        .map(|s| s.to_string())
+}
+
+fn html_error_message(html: &str) -> String {
+    let lower = html.to_ascii_lowercase();
+    if let Some(start) = lower.find("<title>").map(|i| i + "<title>".len()) {
+        if let Some(len) = lower[start..].find("</title>") {
+            let title = &html[start..start + len];
+            if !title.trim().is_empty() {
+                return title.to_string();
+            }
+        }
+    }
+
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn truncate_chars(text: String, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => format!("{}...", &text[..idx]),
+        None => text,
+    }
+}
+
+/// 将非 2xx 的上游响应体改写为客户端期望的错误格式
+///
+/// 已经符合格式的响应体原样返回；改写时同步修正 content-type 等响应头
+pub fn normalize_error_response(
+    format: ErrorFormat,
+    status: StatusCode,
+    headers: &mut HeaderMap,
+    body: Bytes,
+) -> Bytes {
+    if status.is_success() {
+        return body;
+    }
+
+    // 压缩过的响应体无法解析，只能按状态码生成错误信息
+    let encoded = headers
+        .get(CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| !v.eq_ignore_ascii_case("identity"));
+
+    if !encoded && format.is_native(&body) {
+        return body;
+    }
+
+    let message = if encoded {
+        String::new()
+    } else {
+        extract_error_message(&body)
+    };
+
+    headers.remove(CONTENT_ENCODING);
+    headers.remove(CONTENT_LENGTH);
+    headers.insert(
+        CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+    format.build(status, None, &message)
+}
+
+/// 截取原始错误响应体用于审计日志
+pub fn audit_error_body(headers: &HeaderMap, body: &[u8]) -> String {
+    if let Some(encoding) = headers
+        .get(CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.eq_ignore_ascii_case("identity"))
+    {
+        return format!("<{} bytes, content-encoding: {encoding}>", body.len());
+    }
+
+    let end = body.len().min(MAX_AUDIT_BODY_BYTES);
+    let text = String::from_utf8_lossy(&body[..end]);
+    if body.len() > MAX_AUDIT_BODY_BYTES {
+        format!("{text}...")
+    } else {
+        text.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_error_message() {
+        assert_eq!(
+            extract_error_message(br#"{"error":{"message":"quota exceeded"}}"#),
+            "quota exceeded"
+        );
+        assert_eq!(
+            extract_error_message(br#"{"error":"bad key","code":1}"#),
+            "bad key"
+        );
+        assert_eq!(
+            extract_error_message(
+                b"<html><head><title>502 Bad Gateway</title></head><body>nginx</body></html>"
+            ),
+            "502 Bad Gateway"
+        );
+        assert_eq!(
+            extract_error_message(b"<html><body><h1>Service\n  down</h1></body></html>"),
+            "Service down"
+        );
+        assert_eq!(extract_error_message(b""), "");
+    }
+
+    #[test]
+    fn test_normalize_html_to_anthropic() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, "text/html".parse().unwrap());
+        headers.insert(CONTENT_LENGTH, "60".parse().unwrap());
+
+        let body = normalize_error_response(
+            ErrorFormat::Anthropic,
+            StatusCode::BAD_GATEWAY,
+            &mut headers,
+            Bytes::from_static(b"<html><title>502 Bad Gateway</title></html>"),
+        );
+
+        let value: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(value["type"], "error");
+        assert_eq!(value["error"]["type"], "api_error");
+        assert_eq!(value["error"]["message"], "502 Bad Gateway");
+        assert_eq!(headers.get(CONTENT_TYPE).unwrap(), "application/json");
+        assert!(headers.get(CONTENT_LENGTH).is_none());
+    }
+
+    #[test]
+    fn test_native_errors_pass_through() {
+        let mut headers = HeaderMap::new();
+        let openai =
+            Bytes::from_static(br#"{"error":{"message":"x","type":"invalid_request_error"}}"#);
+        let body = normalize_error_response(
+            ErrorFormat::OpenAI,
+            StatusCode::BAD_REQUEST,
+            &mut headers,
+            openai.clone(),
+        );
+        assert_eq!(body, openai);
+
+        let gemini = br#"[{"error":{"code":429,"message":"quota","status":"RESOURCE_EXHAUSTED"}}]"#;
+        assert!(ErrorFormat::Gemini.is_native(gemini));
+        assert!(!ErrorFormat::Anthropic.is_native(gemini));
+    }
+
+    #[test]
+    fn test_build_proxy_errors() {
+        let body =
+            ErrorFormat::Gemini.build(StatusCode::BAD_GATEWAY, Some("PROXY_LOOP_DETECTED"), "loop");
+        let value: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(value["error"]["code"], 502);
+        assert_eq!(value["error"]["status"], "UNAVAILABLE");
+        assert_eq!(value["error"]["message"], "[PROXY_LOOP_DETECTED] loop");
+
+        let body =
+            ErrorFormat::OpenAI.build(StatusCode::TOO_MANY_REQUESTS, Some("RATE_LIMITED"), "");
+        let value: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(value["error"]["type"], "rate_limit_error");
+        assert_eq!(value["error"]["code"], "RATE_LIMITED");
+        assert_eq!(value["error"]["message"], "Too Many Requests");
+    }
+}
@@ -1,6 +1,8 @@
 // Claude Code 请求处理器
 
+use super::openai_chat_processor::{OpenAIChatProcessor, CHAT_COMPLETIONS_PATH};
 use super::{ProcessedRequest, RequestProcessor};
+use crate::services::proxy::error_envelope::ErrorFormat;
 use crate::services::session::{SessionEvent, SESSION_MANAGER};
 use anyhow::Result;
 use async_trait::async_trait;
@@ -14,6 +16,7 @@ use reqwest::header::HeaderMap as ReqwestHeaderMap;
 /// - URL 构建：使用标准拼接（无特殊逻辑）
 /// - 认证方式：Bearer Token
 /// - Authorization header 格式：`Bearer sk-ant-xxx`
+/// - OpenAI 兼容：/v1/chat/completions 请求交给 `OpenAIChatProcessor` 转换协议
 pub struct ClaudeHeadersProcessor;
 
 #[async_trait]
@@ -22,6 +25,10 @@ impl RequestProcessor for ClaudeHeadersProcessor {
         "claude-code"
     }
 
+    fn processor_for_path(&self, path: &str) -> Option<&dyn RequestProcessor> {
+        (path == CHAT_COMPLETIONS_PATH).then_some(&OpenAIChatProcessor as &dyn RequestProcessor)
+    }
+
     async fn process_outgoing_request(
         &self,
         base_url: &str,
@@ -120,6 +127,7 @@ impl RequestProcessor for ClaudeHeadersProcessor {
         })
     }
 
-    // Claude Code 不需要特殊的响应处理
-    // 使用默认实现即可
+    fn error_format(&self) -> ErrorFormat {
+        ErrorFormat::Anthropic
+    }
 }
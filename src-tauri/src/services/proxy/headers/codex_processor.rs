@@ -1,6 +1,7 @@
 // Codex 请求处理器
 
 use super::{ProcessedRequest, RequestProcessor};
+use crate::services::proxy::error_envelope::ErrorFormat;
 use anyhow::Result;
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -87,6 +88,7 @@ impl RequestProcessor for CodexHeadersProcessor {
         })
     }
 
-    // Codex 当前不需要特殊的响应处理
-    // 如果未来需要（例如处理速率限制信息），可以在此实现
+    fn error_format(&self) -> ErrorFormat {
+        ErrorFormat::OpenAI
+    }
 }
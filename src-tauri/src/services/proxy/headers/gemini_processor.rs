@@ -1,6 +1,9 @@
 // Gemini CLI 请求处理器
 
 use super::{ProcessedRequest, RequestProcessor};
+use crate::models::ModelMappingRule;
+use crate::services::proxy::error_envelope::ErrorFormat;
+use crate::services::proxy::model_mapping::{rewrite_path_model, ModelRewrite};
 use anyhow::Result;
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -78,6 +81,17 @@ impl RequestProcessor for GeminiHeadersProcessor {
         })
     }
 
-    // Gemini CLI 当前不需要特殊的响应处理
-    // 如果未来需要（例如处理配额信息），可以在此实现
+    // Gemini API 的模型位于路径中：/v1beta/models/{model}:generateContent
+    fn apply_model_mapping(
+        &self,
+        path: &str,
+        _body: &[u8],
+        rules: &[ModelMappingRule],
+    ) -> Option<ModelRewrite> {
+        rewrite_path_model(path, rules)
+    }
+
+    fn error_format(&self) -> ErrorFormat {
+        ErrorFormat::Gemini
+    }
 }
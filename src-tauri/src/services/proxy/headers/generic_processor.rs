@@ -0,0 +1,99 @@
+// 通用请求处理器（用于 tools.d 注册的工具）
+
+use super::{ProcessedRequest, RequestProcessor};
+use crate::models::{ApiFormat, AuthHeaderStyle, Tool};
+use crate::services::proxy::error_envelope::ErrorFormat;
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use hyper::HeaderMap as HyperHeaderMap;
+use reqwest::header::HeaderMap as ReqwestHeaderMap;
+
+/// 由工具描述文件驱动的请求处理器
+///
+/// - URL 构建：base_url + path + query（base_url 以 /v1 结尾且 path 以 /v1 开头时去掉重复的 /v1）
+/// - 认证方式：按描述文件的 `auth_header` 设置 Bearer / x-api-key / x-goog-api-key
+/// - 错误格式：按描述文件的 `api_format` 统一错误响应
+pub struct GenericHeadersProcessor {
+    tool_id: String,
+    auth_header: AuthHeaderStyle,
+    error_format: ErrorFormat,
+}
+
+impl GenericHeadersProcessor {
+    pub fn new(tool: &Tool) -> Self {
+        Self {
+            tool_id: tool.id.clone(),
+            auth_header: tool.auth_header,
+            error_format: match tool.api_format {
+                ApiFormat::Anthropic => ErrorFormat::Anthropic,
+                ApiFormat::OpenAI => ErrorFormat::OpenAI,
+                ApiFormat::Gemini => ErrorFormat::Gemini,
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl RequestProcessor for GenericHeadersProcessor {
+    fn tool_id(&self) -> &str {
+        &self.tool_id
+    }
+
+    async fn process_outgoing_request(
+        &self,
+        base_url: &str,
+        api_key: &str,
+        path: &str,
+        query: Option<&str>,
+        original_headers: &HyperHeaderMap,
+        body: &[u8],
+    ) -> Result<ProcessedRequest> {
+        // 1. 构建目标 URL（避免 /v1 路径重复）
+        let base = base_url.trim_end_matches('/');
+        let adjusted_path = if base.ends_with("/v1") && path.starts_with("/v1") {
+            &path[3..]
+        } else {
+            path
+        };
+        let query_str = query.map(|q| format!("?{q}")).unwrap_or_default();
+        let target_url = format!("{base}{adjusted_path}{query_str}");
+
+        // 2. 处理 headers（复制非认证 headers）
+        let mut headers = ReqwestHeaderMap::new();
+        for (name, value) in original_headers.iter() {
+            let name_str = name.as_str();
+            if name_str.eq_ignore_ascii_case("host")
+                || name_str.eq_ignore_ascii_case("authorization")
+                || name_str.eq_ignore_ascii_case("x-api-key")
+                || name_str.eq_ignore_ascii_case("x-goog-api-key")
+            {
+                continue;
+            }
+            headers.insert(name.clone(), value.clone());
+        }
+
+        // 3. 按描述文件的认证头格式添加真实 API Key
+        let (header_name, header_value) = match self.auth_header {
+            AuthHeaderStyle::Bearer => ("authorization", format!("Bearer {api_key}")),
+            AuthHeaderStyle::XApiKey => ("x-api-key", api_key.to_string()),
+            AuthHeaderStyle::XGoogApiKey => ("x-goog-api-key", api_key.to_string()),
+        };
+        headers.insert(
+            header_name,
+            header_value
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid {header_name} header: {e}"))?,
+        );
+
+        Ok(ProcessedRequest {
+            target_url,
+            headers,
+            body: Bytes::copy_from_slice(body),
+        })
+    }
+
+    fn error_format(&self) -> ErrorFormat {
+        self.error_format
+    }
+}
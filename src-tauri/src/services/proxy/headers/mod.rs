@@ -3,16 +3,26 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use bytes::Bytes;
-use hyper::HeaderMap as HyperHeaderMap;
+use hyper::{HeaderMap as HyperHeaderMap, StatusCode};
 use reqwest::header::HeaderMap as ReqwestHeaderMap;
 
+use super::error_envelope::{normalize_error_response, ErrorFormat};
+use super::model_mapping::{rewrite_json_model, ModelRewrite};
+use crate::models::{ModelMappingRule, Tool};
+
 mod claude_processor;
 mod codex_processor;
 mod gemini_processor;
+mod generic_processor;
+mod openai_chat_processor;
+mod opencode_processor;
 
 pub use claude_processor::ClaudeHeadersProcessor;
 pub use codex_processor::CodexHeadersProcessor;
 pub use gemini_processor::GeminiHeadersProcessor;
+pub use generic_processor::GenericHeadersProcessor;
+pub use openai_chat_processor::{OpenAIChatProcessor, CHAT_COMPLETIONS_PATH};
+pub use opencode_processor::OpencodeHeadersProcessor;
 
 /// 处理后的请求信息
 #[derive(Debug)]
@@ -25,6 +35,17 @@ pub struct ProcessedRequest {
     pub body: Bytes,
 }
 
+/// 流式响应转换器
+///
+/// 逐块改写 SSE 响应（如还原模型名称、转换协议），输入可能在任意位置被切分
+pub trait StreamTransformer: Send {
+    /// 输入一段上游数据，返回可以立即发送给客户端的数据
+    fn feed(&mut self, chunk: &[u8]) -> Bytes;
+
+    /// 上游流结束时输出剩余数据
+    fn finish(&mut self) -> Bytes;
+}
+
 /// 请求处理器 trait
 ///
 /// 为不同的 AI 编程工具提供独立的请求处理逻辑。
@@ -40,6 +61,16 @@ pub trait RequestProcessor: Send + Sync {
     /// 返回工具标识符
     fn tool_id(&self) -> &str;
 
+    /// 按请求路径选择实际处理请求的处理器（可选）
+    ///
+    /// 同一端口需要兼容其他协议的请求时返回对应的处理器
+    ///
+    /// # 默认实现
+    /// 返回 `None`，由自身处理所有请求
+    fn processor_for_path(&self, _path: &str) -> Option<&dyn RequestProcessor> {
+        None
+    }
+
     /// 处理出站请求（转发到上游前的完整处理）
     ///
     /// # 参数
@@ -63,56 +94,164 @@ pub trait RequestProcessor: Send + Sync {
         body: &[u8],
     ) -> Result<ProcessedRequest>;
 
-    /// 处理响应 headers（返回给客户端前调用，可选）
+    /// 从客户端请求体提取会话 ID（用于会话级限流、用量统计和审计日志）
+    ///
+    /// # 参数
+    /// - `body`: 客户端发送的原始请求体（协议转换前）
+    ///
+    /// # 默认实现
+    /// 读取 Anthropic 协议的 `metadata.user_id`
+    fn session_id(&self, body: &[u8]) -> Option<String> {
+        json_str_at(body, "/metadata/user_id")
+    }
+
+    /// 应用模型映射规则（转发前调用，可选）
+    ///
+    /// # 参数
+    /// - `path`: 原始请求路径
+    /// - `body`: 原始请求体
+    /// - `rules`: 工具配置的模型映射规则
+    ///
+    /// # 默认实现
+    /// 改写 JSON 请求体顶层的 `model` 字段；模型不在请求体中的工具需要覆盖此方法
+    fn apply_model_mapping(
+        &self,
+        _path: &str,
+        body: &[u8],
+        rules: &[ModelMappingRule],
+    ) -> Option<ModelRewrite> {
+        rewrite_json_model(body, rules)
+    }
+
+    /// 客户端期望的错误响应格式
+    ///
+    /// 代理自身产生的错误（配置缺失、回环、鉴权失败等）和改写后的上游错误都使用该格式
+    fn error_format(&self) -> ErrorFormat;
+
+    /// 模型列表接口路径（连通性检查时以该工具的认证方式请求）
+    ///
+    /// # 默认实现
+    /// Gemini 协议使用 `/v1beta/models`，其余使用 `/v1/models`
+    fn models_path(&self) -> &str {
+        match self.error_format() {
+            ErrorFormat::Gemini => "/v1beta/models",
+            _ => "/v1/models",
+        }
+    }
+
+    /// 构造代理自身产生的错误响应体
+    ///
+    /// # 参数
+    /// - `status`: 返回给客户端的状态码
+    /// - `code`: 代理错误代码（如 `CONFIGURATION_MISSING`）
+    /// - `message`: 错误描述
+    fn error_body(&self, status: StatusCode, code: &str, message: &str) -> Bytes {
+        self.error_format().build(status, Some(code), message)
+    }
+
+    /// 处理响应（返回给客户端前调用，可选）
     ///
     /// # 参数
+    /// - `status`: 上游响应状态码
     /// - `headers`: 可修改的响应 headers
-    /// - `body`: 响应体字节数组（如果已读取）
+    /// - `body`: 完整的响应体
+    ///
+    /// # 返回
+    /// - 返回给客户端的响应体
     ///
     /// # 默认实现
-    /// 默认不处理响应 headers，直接透传
+    /// 将中转服务返回的非标准错误改写为 `error_format()` 对应的错误格式
     async fn process_response(
         &self,
-        _headers: &mut HyperHeaderMap,
-        _body: Option<&[u8]>,
-    ) -> Result<()> {
-        Ok(())
+        status: StatusCode,
+        headers: &mut HyperHeaderMap,
+        body: Bytes,
+    ) -> Result<Bytes> {
+        Ok(normalize_error_response(
+            self.error_format(),
+            status,
+            headers,
+            body,
+        ))
     }
 
     /// 是否需要读取响应体
     ///
-    /// 如果返回 `true`，代理服务会先读取完整响应体，
+    /// 如果返回 `true`，代理服务会先读取完整响应体（即使是 SSE），
     /// 然后调用 `process_response`。
     ///
     /// # 注意
-    /// 启用此选项会增加内存使用和延迟，仅在必要时启用。
-    fn should_process_response(&self) -> bool {
-        false
+    /// 启用此选项会增加内存使用和延迟，仅在必要时启用（如只处理错误响应）。
+    ///
+    /// # 默认实现
+    /// 只处理错误响应（非 2xx）
+    fn should_process_response(&self, status: StatusCode) -> bool {
+        !status.is_success()
     }
+
+    /// 创建成功 SSE 响应的流式转换器（可选）
+    ///
+    /// # 参数
+    /// - `request_body`: 转发前的请求体（已应用模型映射）
+    ///
+    /// 返回 `Some` 时 SSE 响应逐块转换后转发，不再调用 `process_response`
+    ///
+    /// # 默认实现
+    /// 返回 `None`，SSE 响应原样透传
+    fn stream_transformer(&self, _request_body: &[u8]) -> Option<Box<dyn StreamTransformer>> {
+        None
+    }
+}
+
+/// 读取 JSON 请求体中指定路径的字符串字段
+pub(crate) fn json_str_at(body: &[u8], pointer: &str) -> Option<String> {
+    if body.is_empty() {
+        return None;
+    }
+    serde_json::from_slice::<serde_json::Value>(body)
+        .ok()?
+        .pointer(pointer)?
+        .as_str()
+        .map(|s| s.to_string())
+}
+
+/// 按请求路径选择处理器（见 `RequestProcessor::processor_for_path`）
+pub fn route_request_processor<'a>(
+    processor: &'a dyn RequestProcessor,
+    path: &str,
+) -> &'a dyn RequestProcessor {
+    processor.processor_for_path(path).unwrap_or(processor)
 }
 
 /// 创建请求处理器工厂函数
 ///
 /// # 参数
-/// - `tool_id`: 工具标识符 ("claude-code", "codex", "gemini-cli")
+/// - `tool_id`: 工具标识符（内置工具或 tools.d 注册的工具）
 ///
 /// # 返回
-/// - 对应工具的 RequestProcessor 实例
+/// - 对应工具的 RequestProcessor 实例；Crush 及 tools.d 注册的工具使用 `GenericHeadersProcessor`
 ///
-/// # Panics
-/// 当 `tool_id` 不被支持时会 panic
-pub fn create_request_processor(tool_id: &str) -> Box<dyn RequestProcessor> {
-    match tool_id {
+/// # Errors
+/// 当 `tool_id` 不是已知工具时返回错误
+pub fn create_request_processor(tool_id: &str) -> Result<Box<dyn RequestProcessor>> {
+    Ok(match tool_id {
         "claude-code" => Box::new(ClaudeHeadersProcessor),
         "codex" => Box::new(CodexHeadersProcessor),
         "gemini-cli" => Box::new(GeminiHeadersProcessor),
-        _ => panic!("Unsupported tool: {tool_id}"),
-    }
+        // Qwen Code 没有特殊逻辑，使用内置工具定义驱动的通用处理器
+        "qwen-code" => Box::new(GenericHeadersProcessor::new(&Tool::qwen_code())),
+        "opencode" => Box::new(OpencodeHeadersProcessor),
+        _ => {
+            let tool =
+                Tool::by_id(tool_id).ok_or_else(|| anyhow::anyhow!("不支持的工具: {tool_id}"))?;
+            Box::new(GenericHeadersProcessor::new(&tool))
+        }
+    })
 }
 
 /// 旧工厂函数名称（向后兼容，已弃用）
 #[deprecated(since = "0.1.0", note = "请使用 create_request_processor")]
-pub fn create_headers_processor(tool_id: &str) -> Box<dyn RequestProcessor> {
+pub fn create_headers_processor(tool_id: &str) -> Result<Box<dyn RequestProcessor>> {
     create_request_processor(tool_id)
 }
 
@@ -122,20 +261,124 @@ mod tests {
 
     #[test]
     fn test_create_request_processor() {
-        let claude = create_request_processor("claude-code");
+        let claude = create_request_processor("claude-code").unwrap();
         assert_eq!(claude.tool_id(), "claude-code");
 
-        let codex = create_request_processor("codex");
+        let codex = create_request_processor("codex").unwrap();
         assert_eq!(codex.tool_id(), "codex");
 
-        let gemini = create_request_processor("gemini-cli");
+        let gemini = create_request_processor("gemini-cli").unwrap();
         assert_eq!(gemini.tool_id(), "gemini-cli");
+
+        let qwen = create_request_processor("qwen-code").unwrap();
+        assert_eq!(qwen.tool_id(), "qwen-code");
+        assert_eq!(qwen.error_format(), ErrorFormat::OpenAI);
+
+        let opencode = create_request_processor("opencode").unwrap();
+        assert_eq!(opencode.error_format(), ErrorFormat::Anthropic);
+
+        // Crush 使用通用处理器
+        let crush = create_request_processor("crush").unwrap();
+        assert_eq!(crush.tool_id(), "crush");
+        assert_eq!(crush.error_format(), ErrorFormat::Anthropic);
+    }
+
+    #[test]
+    fn test_route_chat_completions_to_openai_processor() {
+        let claude = create_request_processor("claude-code").unwrap();
+        let routed = route_request_processor(claude.as_ref(), CHAT_COMPLETIONS_PATH);
+        assert_eq!(routed.error_format(), ErrorFormat::OpenAI);
+
+        let routed = route_request_processor(claude.as_ref(), "/v1/messages");
+        assert_eq!(routed.error_format(), ErrorFormat::Anthropic);
+
+        let codex = create_request_processor("codex").unwrap();
+        let routed = route_request_processor(codex.as_ref(), CHAT_COMPLETIONS_PATH);
+        assert_eq!(routed.error_format(), ErrorFormat::OpenAI);
+        assert!(routed.stream_transformer(b"{}").is_none());
     }
 
     #[test]
-    #[should_panic(expected = "Unsupported tool")]
     fn test_create_invalid_processor() {
-        create_request_processor("invalid-tool");
+        assert!(create_request_processor("invalid-tool").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_opencode_processor_adds_v1_and_api_key() {
+        let mut headers = HyperHeaderMap::new();
+        headers.insert("x-api-key", "dc-opencode-local".parse().unwrap());
+
+        let result = OpencodeHeadersProcessor
+            .process_outgoing_request(
+                "https://relay.example.com",
+                "real-key",
+                "/messages",
+                None,
+                &headers,
+                b"{}",
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.target_url, "https://relay.example.com/v1/messages");
+        assert_eq!(result.headers.get("x-api-key").unwrap(), "real-key");
+        assert_eq!(
+            result.headers.get("anthropic-version").unwrap(),
+            "2023-06-01"
+        );
+
+        let result = OpencodeHeadersProcessor
+            .process_outgoing_request(
+                "https://relay.example.com/v1",
+                "real-key",
+                "/messages",
+                None,
+                &headers,
+                b"{}",
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.target_url, "https://relay.example.com/v1/messages");
+    }
+
+    #[tokio::test]
+    async fn test_generic_processor_auth_header_and_url() {
+        let descriptor: crate::models::ToolDescriptor = serde_json::from_value(serde_json::json!({
+            "id": "kimi-cli",
+            "name": "Kimi CLI",
+            "npm_package": "kimi-cli",
+            "check_command": "kimi --version",
+            "config_dir": "~/.kimi",
+            "config_file": "settings.json",
+            "config_layout": "dotenv",
+            "env_vars": {"api_key": "KIMI_API_KEY", "base_url": "KIMI_BASE_URL"},
+            "auth_header": "x-api-key",
+            "default_port": 8800
+        }))
+        .unwrap();
+        let tool = descriptor.into_tool(std::path::Path::new("/home/test"));
+        let processor = GenericHeadersProcessor::new(&tool);
+        assert_eq!(processor.tool_id(), "kimi-cli");
+        assert_eq!(processor.error_format(), ErrorFormat::OpenAI);
+
+        let mut headers = HyperHeaderMap::new();
+        headers.insert("authorization", "Bearer dc-local".parse().unwrap());
+        let result = processor
+            .process_outgoing_request(
+                "https://relay.example.com/v1/",
+                "real-key",
+                "/v1/chat/completions",
+                None,
+                &headers,
+                b"{}",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            result.target_url,
+            "https://relay.example.com/v1/chat/completions"
+        );
+        assert!(result.headers.get("authorization").is_none());
+        assert_eq!(result.headers.get("x-api-key").unwrap(), "real-key");
     }
 
     #[tokio::test]
@@ -0,0 +1,791 @@
+// OpenAI Chat Completions 兼容处理器
+//
+// 在 Claude Code 代理端口上接受 OpenAI 格式的 /v1/chat/completions 请求，
+// 转换为 Anthropic Messages API 请求转发到 Claude Code 配置的上游，再把响应转换回 OpenAI 格式：
+// - 请求：system/developer 消息、图片、tools/tool_choice、assistant tool_calls、tool 消息
+// - 非流式响应：message → chat.completion
+// - 流式响应：Anthropic SSE 事件 → chat.completion.chunk（含 tool_calls 增量与 [DONE]）
+
+use std::collections::HashMap;
+
+use super::{json_str_at, ProcessedRequest, RequestProcessor, StreamTransformer};
+use crate::services::proxy::error_envelope::{normalize_error_response, ErrorFormat};
+use crate::services::session::{SessionEvent, SESSION_MANAGER};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::{HeaderMap as HyperHeaderMap, StatusCode};
+use reqwest::header::HeaderMap as ReqwestHeaderMap;
+use serde_json::{json, Map, Value};
+
+/// OpenAI 兼容接口路径
+pub const CHAT_COMPLETIONS_PATH: &str = "/v1/chat/completions";
+
+/// 转发到上游的 Anthropic 接口路径
+const MESSAGES_PATH: &str = "/v1/messages";
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// 请求未指定 max_tokens 时使用的默认值（Anthropic 接口要求必填）
+const DEFAULT_MAX_TOKENS: u64 = 4096;
+
+/// OpenAI Chat Completions → Anthropic Messages 协议转换处理器
+///
+/// 由 Claude Code 处理器按路径分发，上游与认证方式与 Claude Code 一致：
+/// - URL 构建：base_url + /v1/messages
+/// - 认证方式：Bearer Token
+pub struct OpenAIChatProcessor;
+
+#[async_trait]
+impl RequestProcessor for OpenAIChatProcessor {
+    fn tool_id(&self) -> &str {
+        "claude-code"
+    }
+
+    /// OpenAI 客户端通过 `user` 字段标识会话（转换后对应 `metadata.user_id`）
+    fn session_id(&self, body: &[u8]) -> Option<String> {
+        json_str_at(body, "/user")
+    }
+
+    async fn process_outgoing_request(
+        &self,
+        base_url: &str,
+        api_key: &str,
+        _path: &str,
+        query: Option<&str>,
+        original_headers: &HyperHeaderMap,
+        body: &[u8],
+    ) -> Result<ProcessedRequest> {
+        // 1. 转换请求体
+        let request: Value = serde_json::from_slice(body).context("请求体不是有效的 JSON")?;
+        let translated = chat_request_to_messages(&request)?;
+
+        // 记录会话事件（与 Claude Code 请求一致）
+        if let Some(user) = request["user"].as_str() {
+            let _ = SESSION_MANAGER.send_event(SessionEvent::NewRequest {
+                session_id: user.to_string(),
+                tool_id: self.tool_id().to_string(),
+                timestamp: chrono::Utc::now().timestamp(),
+            });
+        }
+
+        // 2. 构建目标 URL
+        let base = base_url.trim_end_matches('/');
+        let query_str = query.map(|q| format!("?{q}")).unwrap_or_default();
+        let target_url = format!("{base}{MESSAGES_PATH}{query_str}");
+
+        // 3. 处理 headers（请求体已改写，不复制长度、编码和 OpenAI 认证相关 headers）
+        let mut headers = ReqwestHeaderMap::new();
+        for (name, value) in original_headers.iter() {
+            let name_str = name.as_str();
+            if name_str.eq_ignore_ascii_case("host")
+                || name_str.eq_ignore_ascii_case("authorization")
+                || name_str.eq_ignore_ascii_case("x-api-key")
+                || name_str.eq_ignore_ascii_case("content-length")
+                || name_str.eq_ignore_ascii_case("content-type")
+                || name_str.eq_ignore_ascii_case("accept-encoding")
+                || name_str.starts_with("openai-")
+            {
+                continue;
+            }
+            headers.insert(name.clone(), value.clone());
+        }
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        if !headers.contains_key("anthropic-version") {
+            headers.insert(
+                "anthropic-version",
+                HeaderValue::from_static(ANTHROPIC_VERSION),
+            );
+        }
+
+        // 4. 添加真实的 API Key
+        headers.insert(
+            "authorization",
+            format!("Bearer {api_key}")
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid authorization header: {e}"))?,
+        );
+
+        Ok(ProcessedRequest {
+            target_url,
+            headers,
+            body: Bytes::from(serde_json::to_vec(&translated)?),
+        })
+    }
+
+    fn error_format(&self) -> ErrorFormat {
+        ErrorFormat::OpenAI
+    }
+
+    /// 将 Anthropic 响应转换为 chat.completion，错误改写为 OpenAI 错误格式
+    async fn process_response(
+        &self,
+        status: StatusCode,
+        headers: &mut HyperHeaderMap,
+        body: Bytes,
+    ) -> Result<Bytes> {
+        if !status.is_success() {
+            return Ok(normalize_error_response(
+                self.error_format(),
+                status,
+                headers,
+                body,
+            ));
+        }
+
+        let Ok(message) = serde_json::from_slice::<Value>(&body) else {
+            return Ok(body);
+        };
+        let completion = message_to_chat_completion(&message, chrono::Utc::now().timestamp());
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        Ok(Bytes::from(serde_json::to_vec(&completion)?))
+    }
+
+    fn should_process_response(&self, _status: StatusCode) -> bool {
+        true
+    }
+
+    fn stream_transformer(&self, request_body: &[u8]) -> Option<Box<dyn StreamTransformer>> {
+        let include_usage = serde_json::from_slice::<Value>(request_body)
+            .ok()
+            .and_then(|v| v.pointer("/stream_options/include_usage")?.as_bool())
+            .unwrap_or(false);
+        Some(Box::new(ChatStreamTranslator::new(
+            include_usage,
+            chrono::Utc::now().timestamp(),
+        )))
+    }
+}
+
+/// 将 Chat Completions 请求转换为 Messages 请求
+fn chat_request_to_messages(request: &Value) -> Result<Value> {
+    let messages = request["messages"]
+        .as_array()
+        .context("请求缺少 messages 字段")?;
+
+    let mut system = Vec::new();
+    let mut converted: Vec<Value> = Vec::new();
+    for message in messages {
+        match message["role"].as_str().unwrap_or("user") {
+            "system" | "developer" => {
+                let text = content_text(&message["content"]);
+                if !text.is_empty() {
+                    system.push(text);
+                }
+            }
+            "assistant" => {
+                let mut blocks = content_blocks(&message["content"]);
+                for call in message["tool_calls"].as_array().into_iter().flatten() {
+                    let arguments = call["function"]["arguments"].as_str().unwrap_or("{}");
+                    let input = serde_json::from_str::<Value>(arguments)
+                        .ok()
+                        .filter(Value::is_object)
+                        .unwrap_or_else(|| json!({}));
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call["id"],
+                        "name": call["function"]["name"],
+                        "input": input,
+                    }));
+                }
+                push_message(&mut converted, "assistant", blocks);
+            }
+            "tool" => {
+                let block = json!({
+                    "type": "tool_result",
+                    "tool_use_id": message["tool_call_id"],
+                    "content": content_text(&message["content"]),
+                });
+                push_message(&mut converted, "user", vec![block]);
+            }
+            _ => push_message(&mut converted, "user", content_blocks(&message["content"])),
+        }
+    }
+
+    let mut result = Map::new();
+    result.insert("model".to_string(), request["model"].clone());
+    result.insert("messages".to_string(), Value::Array(converted));
+    if !system.is_empty() {
+        result.insert("system".to_string(), Value::String(system.join("\n\n")));
+    }
+
+    let max_tokens = request["max_completion_tokens"]
+        .as_u64()
+        .or_else(|| request["max_tokens"].as_u64())
+        .unwrap_or(DEFAULT_MAX_TOKENS);
+    result.insert("max_tokens".to_string(), max_tokens.into());
+
+    for key in ["temperature", "top_p", "stream"] {
+        if let Some(value) = request.get(key).filter(|v| !v.is_null()) {
+            result.insert(key.to_string(), value.clone());
+        }
+    }
+    let stop = match &request["stop"] {
+        Value::String(stop) => Some(vec![Value::String(stop.clone())]),
+        Value::Array(stops) => Some(stops.clone()),
+        _ => None,
+    };
+    if let Some(stop) = stop {
+        result.insert("stop_sequences".to_string(), Value::Array(stop));
+    }
+    if let Some(user) = request["user"].as_str() {
+        result.insert("metadata".to_string(), json!({ "user_id": user }));
+    }
+
+    let tools: Vec<Value> = request["tools"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|tool| {
+            let function = &tool["function"];
+            let mut converted = json!({
+                "name": function["name"].as_str()?,
+                "input_schema": match &function["parameters"] {
+                    Value::Object(schema) => Value::Object(schema.clone()),
+                    _ => json!({ "type": "object", "properties": {} }),
+                },
+            });
+            if let Some(description) = function["description"].as_str() {
+                converted["description"] = Value::String(description.to_string());
+            }
+            Some(converted)
+        })
+        .collect();
+    if !tools.is_empty() {
+        result.insert("tools".to_string(), Value::Array(tools));
+
+        let mut tool_choice = match &request["tool_choice"] {
+            Value::String(choice) => match choice.as_str() {
+                "none" => Some(json!({ "type": "none" })),
+                "required" => Some(json!({ "type": "any" })),
+                _ => Some(json!({ "type": "auto" })),
+            },
+            Value::Object(_) => request
+                .pointer("/tool_choice/function/name")
+                .map(|name| json!({ "type": "tool", "name": name })),
+            _ => None,
+        };
+        if request["parallel_tool_calls"] == Value::Bool(false) {
+            let choice = tool_choice.get_or_insert_with(|| json!({ "type": "auto" }));
+            if choice["type"] != "none" {
+                choice["disable_parallel_tool_use"] = Value::Bool(true);
+            }
+        }
+        if let Some(tool_choice) = tool_choice {
+            result.insert("tool_choice".to_string(), tool_choice);
+        }
+    }
+
+    Ok(Value::Object(result))
+}
+
+/// 追加消息（Anthropic 要求角色交替，连续的同角色消息合并为一条）
+fn push_message(messages: &mut Vec<Value>, role: &str, blocks: Vec<Value>) {
+    if blocks.is_empty() {
+        return;
+    }
+    if let Some(last) = messages.last_mut().filter(|m| m["role"] == role) {
+        if let Some(content) = last["content"].as_array_mut() {
+            content.extend(blocks);
+            return;
+        }
+    }
+    messages.push(json!({ "role": role, "content": blocks }));
+}
+
+/// 将 OpenAI 消息内容转换为 Anthropic 内容块
+fn content_blocks(content: &Value) -> Vec<Value> {
+    match content {
+        Value::String(text) if !text.is_empty() => vec![json!({ "type": "text", "text": text })],
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| match part["type"].as_str()? {
+                "text" => Some(json!({ "type": "text", "text": part["text"].as_str()? })),
+                "image_url" => {
+                    let url = part["image_url"]["url"]
+                        .as_str()
+                        .or_else(|| part["image_url"].as_str())?;
+                    Some(image_block(url))
+                }
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// 提取消息内容中的纯文本
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn image_block(url: &str) -> Value {
+    let inline = url
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(','))
+        .and_then(|(meta, data)| Some((meta.strip_suffix(";base64")?, data)));
+    match inline {
+        Some((media_type, data)) => json!({
+            "type": "image",
+            "source": { "type": "base64", "media_type": media_type, "data": data },
+        }),
+        None => json!({
+            "type": "image",
+            "source": { "type": "url", "url": url },
+        }),
+    }
+}
+
+/// 将 Messages 响应转换为 chat.completion
+fn message_to_chat_completion(message: &Value, created: i64) -> Value {
+    let mut text = String::new();
+    let mut tool_calls = Vec::new();
+    for block in message["content"].as_array().into_iter().flatten() {
+        match block["type"].as_str() {
+            Some("text") => text.push_str(block["text"].as_str().unwrap_or_default()),
+            Some("tool_use") => tool_calls.push(json!({
+                "id": block["id"],
+                "type": "function",
+                "function": {
+                    "name": block["name"],
+                    "arguments": block["input"].to_string(),
+                },
+            })),
+            _ => {}
+        }
+    }
+
+    let mut chat_message = json!({
+        "role": "assistant",
+        "content": if text.is_empty() && !tool_calls.is_empty() {
+            Value::Null
+        } else {
+            Value::String(text)
+        },
+    });
+    if !tool_calls.is_empty() {
+        chat_message["tool_calls"] = Value::Array(tool_calls);
+    }
+
+    json!({
+        "id": chat_id(message["id"].as_str()),
+        "object": "chat.completion",
+        "created": created,
+        "model": message["model"],
+        "choices": [{
+            "index": 0,
+            "message": chat_message,
+            "finish_reason": finish_reason(message["stop_reason"].as_str()),
+        }],
+        "usage": chat_usage(prompt_tokens(&message["usage"]), output_tokens(&message["usage"])),
+    })
+}
+
+fn chat_id(message_id: Option<&str>) -> String {
+    format!("chatcmpl-{}", message_id.unwrap_or_default())
+}
+
+fn finish_reason(stop_reason: Option<&str>) -> &'static str {
+    match stop_reason {
+        Some("max_tokens") => "length",
+        Some("tool_use") => "tool_calls",
+        _ => "stop",
+    }
+}
+
+/// 输入 token 数（包含缓存读取与缓存写入）
+fn prompt_tokens(usage: &Value) -> u64 {
+    [
+        "input_tokens",
+        "cache_read_input_tokens",
+        "cache_creation_input_tokens",
+    ]
+    .iter()
+    .filter_map(|key| usage[key].as_u64())
+    .sum()
+}
+
+fn output_tokens(usage: &Value) -> u64 {
+    usage["output_tokens"].as_u64().unwrap_or(0)
+}
+
+fn chat_usage(prompt_tokens: u64, completion_tokens: u64) -> Value {
+    json!({
+        "prompt_tokens": prompt_tokens,
+        "completion_tokens": completion_tokens,
+        "total_tokens": prompt_tokens + completion_tokens,
+    })
+}
+
+/// Anthropic SSE → chat.completion.chunk 流式转换器
+struct ChatStreamTranslator {
+    include_usage: bool,
+    created: i64,
+    id: String,
+    model: Value,
+    line_buffer: Vec<u8>,
+    /// Anthropic 内容块索引 → OpenAI tool_calls 索引
+    tool_indexes: HashMap<u64, usize>,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+}
+
+impl ChatStreamTranslator {
+    fn new(include_usage: bool, created: i64) -> Self {
+        Self {
+            include_usage,
+            created,
+            id: chat_id(None),
+            model: Value::Null,
+            line_buffer: Vec::new(),
+            tool_indexes: HashMap::new(),
+            prompt_tokens: 0,
+            completion_tokens: 0,
+        }
+    }
+
+    fn translate_line(&mut self, line: &[u8], output: &mut Vec<u8>) {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let Some(data) = line.strip_prefix(b"data:") else {
+            return;
+        };
+        let Ok(event) = serde_json::from_slice::<Value>(data.trim_ascii()) else {
+            return;
+        };
+
+        match event["type"].as_str().unwrap_or_default() {
+            "message_start" => {
+                let message = &event["message"];
+                self.id = chat_id(message["id"].as_str());
+                self.model = message["model"].clone();
+                self.prompt_tokens = prompt_tokens(&message["usage"]);
+                self.completion_tokens = output_tokens(&message["usage"]);
+                self.write_chunk(output, json!({ "role": "assistant", "content": "" }), None);
+            }
+            "content_block_start" => {
+                let block = &event["content_block"];
+                if block["type"] != "tool_use" {
+                    return;
+                }
+                let tool_index = self.tool_indexes.len();
+                self.tool_indexes
+                    .insert(event["index"].as_u64().unwrap_or_default(), tool_index);
+                let delta = json!({
+                    "tool_calls": [{
+                        "index": tool_index,
+                        "id": block["id"],
+                        "type": "function",
+                        "function": { "name": block["name"], "arguments": "" },
+                    }],
+                });
+                self.write_chunk(output, delta, None);
+            }
+            "content_block_delta" => {
+                let delta = &event["delta"];
+                match delta["type"].as_str() {
+                    Some("text_delta") => {
+                        self.write_chunk(output, json!({ "content": delta["text"] }), None);
+                    }
+                    Some("input_json_delta") => {
+                        let Some(&tool_index) = event["index"]
+                            .as_u64()
+                            .and_then(|index| self.tool_indexes.get(&index))
+                        else {
+                            return;
+                        };
+                        let delta = json!({
+                            "tool_calls": [{
+                                "index": tool_index,
+                                "function": { "arguments": delta["partial_json"] },
+                            }],
+                        });
+                        self.write_chunk(output, delta, None);
+                    }
+                    _ => {}
+                }
+            }
+            "message_delta" => {
+                if let Some(tokens) = event["usage"]["output_tokens"].as_u64() {
+                    self.completion_tokens = tokens;
+                }
+                let reason = finish_reason(event["delta"]["stop_reason"].as_str());
+                self.write_chunk(output, json!({}), Some(reason));
+            }
+            "message_stop" => {
+                if self.include_usage {
+                    let chunk = json!({
+                        "id": self.id,
+                        "object": "chat.completion.chunk",
+                        "created": self.created,
+                        "model": self.model,
+                        "choices": [],
+                        "usage": chat_usage(self.prompt_tokens, self.completion_tokens),
+                    });
+                    write_event(output, &chunk.to_string());
+                }
+                write_event(output, "[DONE]");
+            }
+            "error" => {
+                let error = json!({
+                    "error": {
+                        "message": event["error"]["message"],
+                        "type": event["error"]["type"],
+                        "param": null,
+                        "code": null,
+                    },
+                });
+                write_event(output, &error.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    fn write_chunk(&self, output: &mut Vec<u8>, delta: Value, finish_reason: Option<&str>) {
+        let chunk = json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }],
+        });
+        write_event(output, &chunk.to_string());
+    }
+}
+
+fn write_event(output: &mut Vec<u8>, data: &str) {
+    output.extend_from_slice(b"data: ");
+    output.extend_from_slice(data.as_bytes());
+    output.extend_from_slice(b"\n\n");
+}
+
+impl StreamTransformer for ChatStreamTranslator {
+    fn feed(&mut self, chunk: &[u8]) -> Bytes {
+        self.line_buffer.extend_from_slice(chunk);
+        let Some(last_newline) = self.line_buffer.iter().rposition(|b| *b == b'\n') else {
+            return Bytes::new();
+        };
+
+        let complete: Vec<u8> = self.line_buffer.drain(..=last_newline).collect();
+        let mut output = Vec::new();
+        for line in complete.split(|b| *b == b'\n') {
+            self.translate_line(line, &mut output);
+        }
+        Bytes::from(output)
+    }
+
+    fn finish(&mut self) -> Bytes {
+        let rest = std::mem::take(&mut self.line_buffer);
+        let mut output = Vec::new();
+        self.translate_line(&rest, &mut output);
+        Bytes::from(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::proxy::headers::ClaudeHeadersProcessor;
+
+    #[test]
+    fn test_session_id_read_from_client_protocol() {
+        let chat = br#"{"user":"session-1","messages":[]}"#;
+        assert_eq!(
+            OpenAIChatProcessor.session_id(chat),
+            Some("session-1".to_string())
+        );
+        assert_eq!(ClaudeHeadersProcessor.session_id(chat), None);
+
+        let messages = br#"{"metadata":{"user_id":"session-2"},"messages":[]}"#;
+        assert_eq!(
+            ClaudeHeadersProcessor.session_id(messages),
+            Some("session-2".to_string())
+        );
+        assert_eq!(OpenAIChatProcessor.session_id(b""), None);
+    }
+
+    #[tokio::test]
+    async fn test_chat_request_translated_to_messages() {
+        let body = json!({
+            "model": "claude-sonnet-4-5",
+            "stream": true,
+            "max_tokens": 1024,
+            "stop": "END",
+            "messages": [
+                { "role": "system", "content": "You are terse." },
+                { "role": "user", "content": [
+                    { "type": "text", "text": "What is in this image?" },
+                    { "type": "image_url", "image_url": { "url": "data:image/png;base64,iVBORw0KGgo=" } },
+                ]},
+                { "role": "assistant", "content": null, "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": { "name": "lookup", "arguments": "{\"q\":\"cat\"}" },
+                }]},
+                { "role": "tool", "tool_call_id": "call_1", "content": "a cat" },
+                { "role": "user", "content": "Thanks" },
+            ],
+            "tools": [{ "type": "function", "function": {
+                "name": "lookup",
+                "description": "Search",
+                "parameters": { "type": "object", "properties": { "q": { "type": "string" } } },
+            }}],
+            "tool_choice": "required",
+        });
+
+        let processed = OpenAIChatProcessor
+            .process_outgoing_request(
+                "https://relay.example.com/",
+                "sk-relay",
+                CHAT_COMPLETIONS_PATH,
+                None,
+                &HyperHeaderMap::new(),
+                body.to_string().as_bytes(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            processed.target_url,
+            "https://relay.example.com/v1/messages"
+        );
+        assert_eq!(processed.headers["authorization"], "Bearer sk-relay");
+        assert_eq!(processed.headers["anthropic-version"], ANTHROPIC_VERSION);
+
+        let request: Value = serde_json::from_slice(&processed.body).unwrap();
+        assert_eq!(request["system"], "You are terse.");
+        assert_eq!(request["max_tokens"], 1024);
+        assert_eq!(request["stream"], true);
+        assert_eq!(request["stop_sequences"], json!(["END"]));
+        assert_eq!(request["tool_choice"], json!({ "type": "any" }));
+        assert_eq!(
+            request["tools"][0]["input_schema"]["properties"]["q"]["type"],
+            "string"
+        );
+
+        let messages = request["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[0]["content"][1]["source"]["media_type"],
+            "image/png"
+        );
+        assert_eq!(messages[1]["content"][0]["type"], "tool_use");
+        assert_eq!(messages[1]["content"][0]["input"], json!({ "q": "cat" }));
+        // tool 结果与随后的用户消息合并为一条 user 消息
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(messages[2]["content"][0]["type"], "tool_result");
+        assert_eq!(messages[2]["content"][0]["tool_use_id"], "call_1");
+        assert_eq!(messages[2]["content"][1]["text"], "Thanks");
+    }
+
+    #[tokio::test]
+    async fn test_message_response_translated_to_completion() {
+        let message = json!({
+            "id": "msg_01",
+            "type": "message",
+            "model": "claude-sonnet-4-5",
+            "content": [
+                { "type": "text", "text": "Let me check." },
+                { "type": "tool_use", "id": "toolu_1", "name": "lookup", "input": { "q": "cat" } },
+            ],
+            "stop_reason": "tool_use",
+            "usage": { "input_tokens": 10, "cache_read_input_tokens": 5, "output_tokens": 7 },
+        });
+
+        let mut headers = HyperHeaderMap::new();
+        let body = OpenAIChatProcessor
+            .process_response(
+                StatusCode::OK,
+                &mut headers,
+                Bytes::from(message.to_string()),
+            )
+            .await
+            .unwrap();
+        let completion: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(completion["id"], "chatcmpl-msg_01");
+        assert_eq!(completion["object"], "chat.completion");
+        let choice = &completion["choices"][0];
+        assert_eq!(choice["finish_reason"], "tool_calls");
+        assert_eq!(choice["message"]["content"], "Let me check.");
+        assert_eq!(
+            choice["message"]["tool_calls"][0]["function"]["arguments"],
+            "{\"q\":\"cat\"}"
+        );
+        assert_eq!(completion["usage"]["prompt_tokens"], 15);
+        assert_eq!(completion["usage"]["total_tokens"], 22);
+
+        // 错误响应改写为 OpenAI 错误格式
+        let body = OpenAIChatProcessor
+            .process_response(
+                StatusCode::BAD_GATEWAY,
+                &mut headers,
+                Bytes::from_static(b"<html>Bad Gateway</html>"),
+            )
+            .await
+            .unwrap();
+        let error: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error["error"]["type"], "server_error");
+    }
+
+    #[test]
+    fn test_sse_stream_translated_to_chunks() {
+        let mut translator = OpenAIChatProcessor
+            .stream_transformer(br#"{"stream":true,"stream_options":{"include_usage":true}}"#)
+            .unwrap();
+
+        let events = concat!(
+            "event: message_start\n",
+            "data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_01\",\"model\":\"claude-sonnet-4-5\",\"usage\":{\"input_tokens\":12,\"output_tokens\":1}}}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hi\"}}\n\n",
+            "event: content_block_start\n",
+            "data: {\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"tool_use\",\"id\":\"toolu_1\",\"name\":\"lookup\",\"input\":{}}}\n\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{\\\"q\\\":\"}}\n\n",
+            "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"tool_use\"},\"usage\":{\"output_tokens\":9}}\n\n",
+            "data: {\"type\":\"message_stop\"}\n\n",
+        );
+
+        // 按任意位置切分输入，结果应一致
+        let (first, second) = events.split_at(50);
+        let mut output = Vec::new();
+        output.extend_from_slice(&translator.feed(first.as_bytes()));
+        output.extend_from_slice(&translator.feed(second.as_bytes()));
+        output.extend_from_slice(&translator.finish());
+        let output = String::from_utf8(output).unwrap();
+
+        let chunks: Vec<&str> = output
+            .split("\n\n")
+            .filter_map(|event| event.strip_prefix("data: "))
+            .collect();
+        assert_eq!(chunks.last(), Some(&"[DONE]"));
+
+        let chunks: Vec<Value> = chunks[..chunks.len() - 1]
+            .iter()
+            .map(|chunk| serde_json::from_str(chunk).unwrap())
+            .collect();
+        assert_eq!(chunks.len(), 6);
+        assert_eq!(chunks[0]["id"], "chatcmpl-msg_01");
+        assert_eq!(chunks[0]["choices"][0]["delta"]["role"], "assistant");
+        assert_eq!(chunks[1]["choices"][0]["delta"]["content"], "Hi");
+        assert_eq!(
+            chunks[2]["choices"][0]["delta"]["tool_calls"][0]["id"],
+            "toolu_1"
+        );
+        assert_eq!(
+            chunks[3]["choices"][0]["delta"]["tool_calls"][0]["function"]["arguments"],
+            "{\"q\":"
+        );
+        assert_eq!(chunks[4]["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(chunks[5]["usage"]["prompt_tokens"], 12);
+        assert_eq!(chunks[5]["usage"]["completion_tokens"], 9);
+    }
+}
@@ -0,0 +1,86 @@
+// opencode 请求处理器
+
+use super::{ProcessedRequest, RequestProcessor};
+use crate::services::proxy::error_envelope::ErrorFormat;
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use hyper::HeaderMap as HyperHeaderMap;
+use reqwest::header::HeaderMap as ReqwestHeaderMap;
+
+/// Anthropic API 版本（客户端未携带时补充）
+const DEFAULT_ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// opencode 专用请求处理器
+///
+/// opencode 的 DuckCoding provider 使用 `@ai-sdk/anthropic`，以 baseURL 拼接 /messages：
+/// - URL 构建：上游 base_url 以 /v1 结尾时去掉 path 中重复的 /v1，否则确保 path 带 /v1 前缀
+/// - 认证方式：x-api-key
+/// - 补充 anthropic-version header
+pub struct OpencodeHeadersProcessor;
+
+#[async_trait]
+impl RequestProcessor for OpencodeHeadersProcessor {
+    fn tool_id(&self) -> &str {
+        "opencode"
+    }
+
+    async fn process_outgoing_request(
+        &self,
+        base_url: &str,
+        api_key: &str,
+        path: &str,
+        query: Option<&str>,
+        original_headers: &HyperHeaderMap,
+        body: &[u8],
+    ) -> Result<ProcessedRequest> {
+        // 1. 构建目标 URL（本地代理地址不含 /v1 时 path 为 /messages）
+        let base = base_url.trim_end_matches('/');
+        let adjusted_path = if base.ends_with("/v1") {
+            path.strip_prefix("/v1").unwrap_or(path).to_string()
+        } else if path.starts_with("/v1") {
+            path.to_string()
+        } else {
+            format!("/v1{path}")
+        };
+        let query_str = query.map(|q| format!("?{q}")).unwrap_or_default();
+        let target_url = format!("{base}{adjusted_path}{query_str}");
+
+        // 2. 处理 headers（复制非认证 headers）
+        let mut headers = ReqwestHeaderMap::new();
+        for (name, value) in original_headers.iter() {
+            let name_str = name.as_str();
+            if name_str.eq_ignore_ascii_case("host")
+                || name_str.eq_ignore_ascii_case("authorization")
+                || name_str.eq_ignore_ascii_case("x-api-key")
+            {
+                continue;
+            }
+            headers.insert(name.clone(), value.clone());
+        }
+
+        // 3. 添加真实的 API Key 和 API 版本
+        headers.insert(
+            "x-api-key",
+            api_key
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid x-api-key header: {e}"))?,
+        );
+        if !headers.contains_key("anthropic-version") {
+            headers.insert(
+                "anthropic-version",
+                DEFAULT_ANTHROPIC_VERSION.parse().unwrap(),
+            );
+        }
+
+        Ok(ProcessedRequest {
+            target_url,
+            headers,
+            body: Bytes::copy_from_slice(body),
+        })
+    }
+
+    fn error_format(&self) -> ErrorFormat {
+        ErrorFormat::Anthropic
+    }
+}
@@ -0,0 +1,132 @@
+// 连接来源过滤
+//
+// 按 ToolProxyConfig.ip_filter 在接受连接时检查对端地址：
+// - 命中 deny 列表的地址直接拒绝
+// - allow 列表非空时，只接受命中 allow 列表的地址
+// - 本机回环地址始终放行（工具自身通过 127.0.0.1 访问代理）
+
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use anyhow::Result;
+use ipnet::IpNet;
+
+use crate::models::IpFilterConfig;
+
+/// 拒绝原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpRejection {
+    /// 命中 deny 列表
+    Denied,
+    /// 未命中 allow 列表
+    NotAllowed,
+}
+
+impl fmt::Display for IpRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpRejection::Denied => write!(f, "命中拒绝列表"),
+            IpRejection::NotAllowed => write!(f, "不在允许列表中"),
+        }
+    }
+}
+
+/// 解析后的来源过滤规则
+#[derive(Debug, Clone, Default)]
+pub struct IpFilter {
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+}
+
+impl IpFilter {
+    /// 解析配置（CIDR 或单个 IP 地址），任一条目无效时返回错误
+    pub fn from_config(config: &IpFilterConfig) -> Result<Self> {
+        Ok(Self {
+            allow: parse_list(&config.allow)?,
+            deny: parse_list(&config.deny)?,
+        })
+    }
+
+    /// 检查对端地址是否允许连接
+    pub fn check(&self, ip: IpAddr) -> Result<(), IpRejection> {
+        // IPv6 监听时 IPv4 客户端表现为 ::ffff:a.b.c.d
+        let ip = ip.to_canonical();
+        if ip.is_loopback() {
+            return Ok(());
+        }
+        if self.deny.iter().any(|net| net.contains(&ip)) {
+            return Err(IpRejection::Denied);
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|net| net.contains(&ip)) {
+            return Err(IpRejection::NotAllowed);
+        }
+        Ok(())
+    }
+}
+
+fn parse_list(entries: &[String]) -> Result<Vec<IpNet>> {
+    entries
+        .iter()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(parse_entry)
+        .collect()
+}
+
+fn parse_entry(entry: &str) -> Result<IpNet> {
+    if let Ok(net) = IpNet::from_str(entry) {
+        // 统一为网络地址，便于展示和比较
+        return Ok(net.trunc());
+    }
+    IpAddr::from_str(entry)
+        .map(IpNet::from)
+        .map_err(|_| anyhow::anyhow!("无效的 IP 或 CIDR: {entry}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(allow: &[&str], deny: &[&str]) -> IpFilter {
+        IpFilter::from_config(&IpFilterConfig {
+            allow: allow.iter().map(|s| s.to_string()).collect(),
+            deny: deny.iter().map(|s| s.to_string()).collect(),
+        })
+        .unwrap()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_empty_filter_allows_all() {
+        let f = IpFilter::default();
+        assert!(f.check(ip("203.0.113.7")).is_ok());
+    }
+
+    #[test]
+    fn test_allow_and_deny_lists() {
+        let f = filter(&["10.8.0.0/16", "192.168.1.20"], &["10.8.3.0/24"]);
+
+        assert!(f.check(ip("10.8.1.5")).is_ok());
+        assert!(f.check(ip("192.168.1.20")).is_ok());
+        assert_eq!(f.check(ip("10.8.3.9")), Err(IpRejection::Denied));
+        assert_eq!(f.check(ip("192.168.1.21")), Err(IpRejection::NotAllowed));
+        // IPv4 映射地址按 IPv4 规则匹配
+        assert!(f.check(ip("::ffff:10.8.1.5")).is_ok());
+        // 回环地址始终放行
+        assert!(f.check(ip("127.0.0.1")).is_ok());
+        assert!(f.check(ip("::1")).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_entry_rejected() {
+        let config = IpFilterConfig {
+            allow: vec!["10.0.0.0/33".to_string()],
+            deny: Vec::new(),
+        };
+        assert!(IpFilter::from_config(&config).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+// 本地 API Key 管理
+//
+// 代理实例同时接受两类本地密钥：
+// - 主密钥 local_api_key：启用代理时写入工具自身配置，与旧版本兼容
+// - 命名密钥 local_api_keys：带使用者标签、过期时间和启用状态，可单独吊销和轮换
+//
+// 命名密钥通过验证后，其 ID 会记录到请求日志和会话上；主密钥不记录 ID
+
+use anyhow::Result;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+use crate::models::{LocalApiKey, ToolProxyConfig};
+use crate::utils::vault::VAULT_REF_PREFIX;
+
+/// 生成的密钥随机部分长度
+const KEY_RANDOM_LEN: usize = 32;
+
+/// 本地密钥验证结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyAuth {
+    /// 未配置任何本地密钥，不需要验证
+    Open,
+    /// 使用主密钥
+    Primary,
+    /// 使用命名密钥（值为密钥 ID）
+    Named(String),
+}
+
+impl KeyAuth {
+    /// 需要记录的密钥 ID
+    pub fn key_id(&self) -> Option<&str> {
+        match self {
+            KeyAuth::Named(id) => Some(id),
+            _ => None,
+        }
+    }
+}
+
+/// 验证客户端提供的本地密钥
+///
+/// 返回 None 表示验证失败；已吊销或已过期的命名密钥视为无效。
+/// 密钥库无法打开时配置中的密钥保持 `vault:` 引用形式，引用字符串可以被猜到，一律拒绝
+pub fn authenticate(config: &ToolProxyConfig, provided: &str, now: i64) -> Option<KeyAuth> {
+    if config.local_api_key.is_none() && config.local_api_keys.is_empty() {
+        return Some(KeyAuth::Open);
+    }
+    if provided.starts_with(VAULT_REF_PREFIX) {
+        return None;
+    }
+    let matches = |key: &str| {
+        !key.starts_with(VAULT_REF_PREFIX) && constant_time_eq(key.as_bytes(), provided.as_bytes())
+    };
+
+    if config.local_api_key.as_deref().is_some_and(matches) {
+        return Some(KeyAuth::Primary);
+    }
+
+    config
+        .local_api_keys
+        .iter()
+        .find(|k| matches(&k.key))
+        .filter(|k| k.is_active(now))
+        .map(|k| KeyAuth::Named(k.id.clone()))
+}
+
+/// 比较密钥时不因前缀匹配长度泄露耗时差异
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+/// 生成本地密钥（格式与前端生成的主密钥一致：dc-<工具>-<随机串>）
+pub fn generate_key(tool_id: &str) -> String {
+    format!(
+        "dc-{}-{}",
+        tool_id.replace('-', ""),
+        random_string(KEY_RANDOM_LEN)
+    )
+}
+
+/// 创建命名密钥
+pub fn create_key(
+    config: &mut ToolProxyConfig,
+    tool_id: &str,
+    owner: &str,
+    expires_at: Option<i64>,
+    now: i64,
+) -> Result<LocalApiKey> {
+    let owner = owner.trim();
+    if owner.is_empty() {
+        anyhow::bail!("使用者标签不能为空");
+    }
+    if expires_at.is_some_and(|expires_at| expires_at <= now) {
+        anyhow::bail!("过期时间必须晚于当前时间");
+    }
+
+    let id = loop {
+        let id = format!("key_{}", random_string(12).to_ascii_lowercase());
+        if !config.local_api_keys.iter().any(|k| k.id == id) {
+            break id;
+        }
+    };
+
+    let key = LocalApiKey {
+        id,
+        key: generate_key(tool_id),
+        owner: owner.to_string(),
+        created_at: now,
+        expires_at,
+        enabled: true,
+    };
+    config.local_api_keys.push(key.clone());
+    Ok(key)
+}
+
+/// 吊销命名密钥（保留记录以便在日志中识别）
+pub fn revoke_key(config: &mut ToolProxyConfig, key_id: &str) -> Result<LocalApiKey> {
+    let key = find_key_mut(config, key_id)?;
+    key.enabled = false;
+    Ok(key.clone())
+}
+
+/// 轮换命名密钥：保留 ID、使用者和过期时间，只替换密钥内容
+pub fn rotate_key(
+    config: &mut ToolProxyConfig,
+    tool_id: &str,
+    key_id: &str,
+) -> Result<LocalApiKey> {
+    let key = find_key_mut(config, key_id)?;
+    if !key.enabled {
+        anyhow::bail!("密钥 {key_id} 已吊销，无法轮换");
+    }
+    key.key = generate_key(tool_id);
+    Ok(key.clone())
+}
+
+fn find_key_mut<'a>(config: &'a mut ToolProxyConfig, key_id: &str) -> Result<&'a mut LocalApiKey> {
+    config
+        .local_api_keys
+        .iter_mut()
+        .find(|k| k.id == key_id)
+        .ok_or_else(|| anyhow::anyhow!("本地密钥不存在: {key_id}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authenticate_without_keys_is_open() {
+        let config = ToolProxyConfig::default();
+        assert_eq!(authenticate(&config, "", 0), Some(KeyAuth::Open));
+    }
+
+    #[test]
+    fn test_authenticate_primary_and_named_keys() {
+        let mut config = ToolProxyConfig {
+            local_api_key: Some("dc-primary".to_string()),
+            ..Default::default()
+        };
+        let alice = create_key(&mut config, "claude-code", "alice", None, 100).unwrap();
+        let bob = create_key(&mut config, "claude-code", "bob", Some(200), 100).unwrap();
+        assert!(alice.key.starts_with("dc-claudecode-"));
+        assert_ne!(alice.id, bob.id);
+
+        assert_eq!(
+            authenticate(&config, "dc-primary", 150),
+            Some(KeyAuth::Primary)
+        );
+        assert_eq!(
+            authenticate(&config, &alice.key, 150),
+            Some(KeyAuth::Named(alice.id.clone()))
+        );
+        assert_eq!(
+            authenticate(&config, &bob.key, 150).and_then(|a| a.key_id().map(String::from)),
+            Some(bob.id.clone())
+        );
+        assert_eq!(authenticate(&config, "wrong", 150), None);
+
+        // 过期
+        assert_eq!(authenticate(&config, &bob.key, 200), None);
+
+        // 吊销
+        revoke_key(&mut config, &alice.id).unwrap();
+        assert_eq!(authenticate(&config, &alice.key, 150), None);
+        assert!(rotate_key(&mut config, "claude-code", &alice.id).is_err());
+    }
+
+    #[test]
+    fn test_rotate_replaces_key() {
+        let mut config = ToolProxyConfig::default();
+        let key = create_key(&mut config, "codex", "ci", None, 0).unwrap();
+        let rotated = rotate_key(&mut config, "codex", &key.id).unwrap();
+
+        assert_eq!(rotated.id, key.id);
+        assert_ne!(rotated.key, key.key);
+        assert_eq!(authenticate(&config, &key.key, 1), None);
+        assert_eq!(
+            authenticate(&config, &rotated.key, 1),
+            Some(KeyAuth::Named(key.id))
+        );
+
+        assert!(create_key(&mut config, "codex", "  ", None, 0).is_err());
+        assert!(create_key(&mut config, "codex", "ci", Some(0), 0).is_err());
+        assert!(revoke_key(&mut config, "missing").is_err());
+    }
+}
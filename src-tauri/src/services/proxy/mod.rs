@@ -2,19 +2,36 @@
 //
 // 包含代理配置、透明代理等功能
 
+pub mod audit;
+pub mod capture;
+pub mod error_envelope;
 pub mod headers;
+pub mod ip_filter;
+pub mod local_keys;
+pub mod model_mapping;
 pub mod proxy_instance;
 pub mod proxy_manager;
 pub mod proxy_service;
+pub mod rate_limit;
+pub mod status;
+pub mod tls;
 pub mod transparent_proxy;
 pub mod transparent_proxy_config;
+pub mod upstream;
+pub mod usage;
 
-pub use headers::{create_request_processor, ProcessedRequest, RequestProcessor};
+pub use headers::{
+    create_request_processor, route_request_processor, ProcessedRequest, RequestProcessor,
+    StreamTransformer,
+};
 // 向后兼容的导出（已弃用）
 #[allow(deprecated)]
 pub use headers::create_headers_processor;
 pub use proxy_instance::ProxyInstance;
 pub use proxy_manager::ProxyManager;
 pub use proxy_service::ProxyService;
+pub use status::{ProxyInstanceStatus, ProxyStats, StatusNotifier};
 pub use transparent_proxy::{ProxyConfig, TransparentProxyService};
 pub use transparent_proxy_config::TransparentProxyConfigService;
+pub use upstream::UpstreamPool;
+pub use usage::{UsageParser, UsageRecorder};
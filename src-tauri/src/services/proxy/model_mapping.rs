@@ -0,0 +1,312 @@
+// 模型映射
+//
+// 按 ToolProxyConfig.model_mappings 中的规则改写请求中的模型名称：
+// - 规则使用 glob 匹配（`*` 匹配任意字符，`?` 匹配单个字符），按顺序取第一条命中的规则
+// - 开启 reverse 的规则会把响应中的模型名称还原为客户端请求的名称
+
+use bytes::Bytes;
+use serde_json::Value;
+
+use super::headers::StreamTransformer;
+use crate::models::ModelMappingRule;
+
+/// 一次模型改写的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelRewrite {
+    /// 改写后的请求路径（模型在路径中时使用，如 Gemini）
+    pub path: Option<String>,
+    /// 改写后的请求体（模型在 JSON 中时使用）
+    pub body: Option<Bytes>,
+    /// 客户端请求的模型
+    pub original: String,
+    /// 实际发送给上游的模型
+    pub mapped: String,
+    /// 是否需要在响应中还原模型名称
+    pub reverse: bool,
+}
+
+/// glob 匹配（大小写敏感）
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<usize> = None;
+    let mut star_ti = 0;
+
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some(pi);
+            star_ti = ti;
+            pi += 1;
+        } else if let Some(star_pi) = star {
+            pi = star_pi + 1;
+            star_ti += 1;
+            ti = star_ti;
+        } else {
+            return false;
+        }
+    }
+
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+/// 查找模型命中的第一条规则
+pub fn find_rule<'a>(rules: &'a [ModelMappingRule], model: &str) -> Option<&'a ModelMappingRule> {
+    rules.iter().find(|rule| {
+        !rule.pattern.is_empty() && !rule.target.is_empty() && glob_match(&rule.pattern, model)
+    })
+}
+
+/// 改写 JSON 请求体顶层的 model 字段
+pub fn rewrite_json_model(body: &[u8], rules: &[ModelMappingRule]) -> Option<ModelRewrite> {
+    if rules.is_empty() || body.is_empty() {
+        return None;
+    }
+
+    let mut json: Value = serde_json::from_slice(body).ok()?;
+    let original = json.get("model")?.as_str()?.to_string();
+    let rule = find_rule(rules, &original)?;
+    if rule.target == original {
+        return None;
+    }
+
+    json["model"] = Value::String(rule.target.clone());
+    let body = serde_json::to_vec(&json).ok()?;
+
+    Some(ModelRewrite {
+        path: None,
+        body: Some(Bytes::from(body)),
+        original,
+        mapped: rule.target.clone(),
+        reverse: rule.reverse,
+    })
+}
+
+/// 改写路径中 `/models/{model}:{method}` 形式的模型（Gemini API）
+pub fn rewrite_path_model(path: &str, rules: &[ModelMappingRule]) -> Option<ModelRewrite> {
+    if rules.is_empty() {
+        return None;
+    }
+
+    let start = path.find("/models/")? + "/models/".len();
+    let rest = &path[start..];
+    let end = rest.find([':', '/']).unwrap_or(rest.len());
+    let original = &rest[..end];
+    let rule = find_rule(rules, original)?;
+    if rule.target == original {
+        return None;
+    }
+
+    let new_path = format!("{}{}{}", &path[..start], rule.target, &rest[end..]);
+    Some(ModelRewrite {
+        path: Some(new_path),
+        body: None,
+        original: original.to_string(),
+        mapped: rule.target.clone(),
+        reverse: rule.reverse,
+    })
+}
+
+/// 将 JSON 中值为 `mapped` 的 model / modelVersion 字段还原为 `original`
+fn restore_model_fields(value: &mut Value, mapped: &str, original: &str) -> bool {
+    let mut changed = false;
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map.iter_mut() {
+                if (key == "model" || key == "modelVersion") && v.as_str() == Some(mapped) {
+                    *v = Value::String(original.to_string());
+                    changed = true;
+                } else {
+                    changed |= restore_model_fields(v, mapped, original);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                changed |= restore_model_fields(item, mapped, original);
+            }
+        }
+        _ => {}
+    }
+    changed
+}
+
+/// 响应模型名称还原器
+///
+/// 非流式响应直接改写完整 JSON；SSE 流按行改写 `data:` 行，未结束的行暂存到下一个 chunk
+pub struct ModelRestorer {
+    original: String,
+    mapped: String,
+    line_buffer: Vec<u8>,
+}
+
+impl ModelRestorer {
+    pub fn new(rewrite: &ModelRewrite) -> Self {
+        Self {
+            original: rewrite.original.clone(),
+            mapped: rewrite.mapped.clone(),
+            line_buffer: Vec::new(),
+        }
+    }
+
+    /// 还原完整 JSON 响应体（无法解析或无需改写时原样返回）
+    pub fn restore_json(&self, body: Bytes) -> Bytes {
+        let Ok(mut json) = serde_json::from_slice::<Value>(&body) else {
+            return body;
+        };
+        if !restore_model_fields(&mut json, &self.mapped, &self.original) {
+            return body;
+        }
+        serde_json::to_vec(&json).map(Bytes::from).unwrap_or(body)
+    }
+
+    /// 输入一段 SSE 数据，返回可以立即发送的完整行
+    pub fn feed(&mut self, chunk: &[u8]) -> Bytes {
+        self.line_buffer.extend_from_slice(chunk);
+        let Some(last_newline) = self.line_buffer.iter().rposition(|b| *b == b'\n') else {
+            return Bytes::new();
+        };
+
+        let complete: Vec<u8> = self.line_buffer.drain(..=last_newline).collect();
+        let mut output = Vec::with_capacity(complete.len());
+        for line in complete.split_inclusive(|b| *b == b'\n') {
+            output.extend_from_slice(&self.restore_line(line));
+        }
+        Bytes::from(output)
+    }
+
+    /// 流结束时输出剩余数据
+    pub fn finish(&mut self) -> Bytes {
+        let rest = std::mem::take(&mut self.line_buffer);
+        if rest.is_empty() {
+            return Bytes::new();
+        }
+        Bytes::from(self.restore_line(&rest))
+    }
+
+    fn restore_line(&self, line: &[u8]) -> Vec<u8> {
+        let Ok(text) = std::str::from_utf8(line) else {
+            return line.to_vec();
+        };
+        let Some(data) = text.strip_prefix("data:") else {
+            return line.to_vec();
+        };
+        if !data.contains(self.mapped.as_str()) {
+            return line.to_vec();
+        }
+
+        let payload = data.trim();
+        let Ok(mut json) = serde_json::from_str::<Value>(payload) else {
+            return line.to_vec();
+        };
+        if !restore_model_fields(&mut json, &self.mapped, &self.original) {
+            return line.to_vec();
+        }
+
+        let line_ending = &text[text.trim_end_matches(['\r', '\n']).len()..];
+        format!("data: {json}{line_ending}").into_bytes()
+    }
+}
+
+impl StreamTransformer for ModelRestorer {
+    fn feed(&mut self, chunk: &[u8]) -> Bytes {
+        ModelRestorer::feed(self, chunk)
+    }
+
+    fn finish(&mut self) -> Bytes {
+        ModelRestorer::finish(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, target: &str, reverse: bool) -> ModelMappingRule {
+        ModelMappingRule {
+            pattern: pattern.to_string(),
+            target: target.to_string(),
+            reverse,
+        }
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("claude-opus-*", "claude-opus-4-1-20250805"));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("gpt-?", "gpt-5"));
+        assert!(glob_match("*-mini", "gpt-5-mini"));
+        assert!(!glob_match("claude-opus-*", "claude-sonnet-4"));
+        assert!(!glob_match("gpt-?", "gpt-51"));
+    }
+
+    #[test]
+    fn test_rewrite_json_model() {
+        let rules = vec![rule("claude-opus-*", "claude-sonnet-4", true)];
+        let body = br#"{"model":"claude-opus-4-1","max_tokens":10}"#;
+
+        let rewrite = rewrite_json_model(body, &rules).unwrap();
+        assert_eq!(rewrite.original, "claude-opus-4-1");
+        assert_eq!(rewrite.mapped, "claude-sonnet-4");
+        let json: Value = serde_json::from_slice(rewrite.body.as_ref().unwrap()).unwrap();
+        assert_eq!(json["model"], "claude-sonnet-4");
+        assert_eq!(json["max_tokens"], 10);
+
+        assert!(rewrite_json_model(br#"{"model":"claude-sonnet-4"}"#, &rules).is_none());
+    }
+
+    #[test]
+    fn test_rewrite_path_model() {
+        let rules = vec![rule("gemini-2.5-pro", "gemini-2.5-flash", false)];
+        let rewrite = rewrite_path_model(
+            "/v1beta/models/gemini-2.5-pro:streamGenerateContent",
+            &rules,
+        )
+        .unwrap();
+        assert_eq!(
+            rewrite.path.as_deref(),
+            Some("/v1beta/models/gemini-2.5-flash:streamGenerateContent")
+        );
+    }
+
+    #[test]
+    fn test_restore_sse_stream() {
+        let rewrite = rewrite_json_model(
+            br#"{"model":"claude-opus-4"}"#,
+            &[rule("claude-opus-*", "claude-sonnet-4", true)],
+        )
+        .unwrap();
+        let mut restorer = ModelRestorer::new(&rewrite);
+
+        let mut output = Vec::new();
+        output.extend_from_slice(
+            &restorer.feed(
+                b"event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"mod",
+            ),
+        );
+        output.extend_from_slice(&restorer.feed(b"el\":\"claude-sonnet-4\"}}\n\n"));
+        output.extend_from_slice(&restorer.finish());
+
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.starts_with("event: message_start\n"));
+        assert!(text.contains("\"model\":\"claude-opus-4\""));
+        assert!(text.ends_with("\n\n"));
+    }
+
+    #[test]
+    fn test_restore_json() {
+        let rewrite = rewrite_json_model(
+            br#"{"model":"gpt-5"}"#,
+            &[rule("gpt-5", "gpt-5-codex", true)],
+        )
+        .unwrap();
+        let restorer = ModelRestorer::new(&rewrite);
+        let body =
+            restorer.restore_json(Bytes::from_static(br#"{"model":"gpt-5-codex","id":"1"}"#));
+        let json: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["model"], "gpt-5");
+    }
+}
@@ -1,55 +1,130 @@
 // 单个代理实例管理
 //
 // ProxyInstance 封装单个工具的透明代理服务实例，负责：
-// - HTTP 服务器的启动和停止
+// - HTTP 服务器的启动和优雅停止（连接排空，HTTP/1.1 与 h2c 自动协商）
 // - 请求的接收和转发
 // - Headers 处理的协调
+// - 多上游故障转移
+// - 模型映射
+// - token 用量统计与请求审计
 
 use anyhow::{Context, Result};
 use bytes::Bytes;
+use futures_util::Stream;
 use http_body_util::BodyExt;
 use hyper::body::{Body, Frame, Incoming};
-use hyper::server::conn::http1;
 use hyper::service::service_fn;
 use hyper::{Method, Request, Response, StatusCode};
-use hyper_util::rt::TokioIo;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto;
 use pin_project_lite::pin_project;
 use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context as TaskContext, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpListener;
-use tokio::sync::RwLock;
-
-use super::headers::RequestProcessor;
-use crate::models::ToolProxyConfig;
+use tokio::sync::{watch, RwLock};
+
+use super::audit::{classify_error, error_kind, RequestAudit};
+use super::capture::CaptureRecorder;
+use super::error_envelope::audit_error_body;
+use super::headers::{route_request_processor, RequestProcessor, StreamTransformer};
+use super::ip_filter::IpFilter;
+use super::local_keys;
+use super::model_mapping::ModelRestorer;
+use super::rate_limit::RateLimiter;
+use super::status::{ProxyInstanceStatus, ProxyStats, StatusNotifier};
+use super::tls::{self, ProxyStream};
+use super::upstream::UpstreamPool;
+use super::usage::UsageRecorder;
+use crate::core::http::{build_http_client_with_options, HttpClientOptions};
+use crate::models::{GlobalConfig, ListenerConfig, ToolProxyConfig};
 
 /// 单个代理实例
 pub struct ProxyInstance {
+    shared: Arc<ProxyShared>,
+    server_handle: Arc<RwLock<Option<ServerHandle>>>,
+}
+
+/// 运行中服务器的句柄
+struct ServerHandle {
+    accept_task: tokio::task::JoinHandle<()>,
+    shutdown_tx: watch::Sender<ShutdownState>,
+}
+
+/// 停止流程的阶段（通过 watch 通道广播给接受循环和所有连接）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShutdownState {
+    Running,
+    /// 停止接受新连接，已有连接处理完当前请求后关闭
+    Draining,
+    /// 超过等待期限，强制关闭剩余连接
+    Force,
+}
+
+/// 代理实例的共享状态（在所有连接和请求之间共享）
+struct ProxyShared {
     tool_id: String,
-    config: Arc<RwLock<ToolProxyConfig>>,
-    processor: Arc<dyn RequestProcessor>,
-    server_handle: Arc<RwLock<Option<tokio::task::JoinHandle<()>>>>,
+    config: RwLock<ToolProxyConfig>,
+    processor: Box<dyn RequestProcessor>,
+    upstream_pool: UpstreamPool,
+    client: RwLock<reqwest::Client>,
+    stats: Arc<ProxyStats>,
+    rate_limiter: RateLimiter,
+    /// 连接来源过滤（在接受连接时同步读取）
+    ip_filter: std::sync::RwLock<IpFilter>,
+}
+
+/// 活跃连接计数守卫（连接任务结束时自动减一）
+struct ConnectionGuard {
+    stats: Arc<ProxyStats>,
+}
+
+impl ConnectionGuard {
+    fn new(stats: Arc<ProxyStats>) -> Self {
+        stats.connection_opened();
+        Self { stats }
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.stats.connection_closed();
+    }
 }
 
 impl ProxyInstance {
     /// 创建新的代理实例
+    ///
+    /// `notifier` 用于在运行状态变化时通知 ProxyManager 的订阅方
     pub fn new(
         tool_id: String,
         config: ToolProxyConfig,
         processor: Box<dyn RequestProcessor>,
+        notifier: StatusNotifier,
     ) -> Self {
         Self {
-            tool_id,
-            config: Arc::new(RwLock::new(config)),
-            processor: Arc::from(processor),
+            shared: Arc::new(ProxyShared {
+                tool_id,
+                config: RwLock::new(config),
+                processor,
+                upstream_pool: UpstreamPool::new(),
+                client: RwLock::new(reqwest::Client::new()),
+                stats: Arc::new(ProxyStats::new(notifier)),
+                rate_limiter: RateLimiter::new(),
+                ip_filter: std::sync::RwLock::new(IpFilter::default()),
+            }),
             server_handle: Arc::new(RwLock::new(None)),
         }
     }
 
     /// 启动代理服务
-    pub async fn start(&self) -> Result<()> {
+    ///
+    /// `global_config` 提供出站代理设置，用于构建上游 HTTP 客户端
+    pub async fn start(&self, global_config: &GlobalConfig) -> Result<()> {
         // 检查是否已经在运行
         {
             let handle = self.server_handle.read().await;
@@ -58,16 +133,33 @@ impl ProxyInstance {
             }
         }
 
-        let config = self.config.read().await.clone();
+        let tool_id = self.shared.tool_id.clone();
+        let config = self.shared.config.read().await.clone();
 
         // 验证配置
-        if config.real_api_key.is_none() || config.real_base_url.is_none() {
+        if config.upstream_candidates().is_empty() {
             tracing::warn!(
-                tool_id = %self.tool_id,
+                tool_id = %tool_id,
                 "代理启动时缺少配置，将在运行时拦截请求"
             );
         }
 
+        // 构建上游 HTTP 客户端（应用全局代理设置）
+        *self.shared.client.write().await = build_upstream_client(global_config, &config)?;
+        *self.shared.ip_filter.write().unwrap() =
+            IpFilter::from_config(&config.ip_filter).context("来源过滤配置无效")?;
+
+        // 加载 TLS 证书（证书无效时拒绝启动，避免静默退回明文）
+        let tls_material = if config.tls.enabled {
+            Some(tls::load(&config.tls).context("加载 TLS 证书失败")?)
+        } else {
+            None
+        };
+        let tls_fingerprint = tls_material
+            .as_ref()
+            .map(|m| m.info.fingerprint_sha256.clone());
+        let acceptor = tls_material.map(|m| m.acceptor);
+
         // 绑定地址
         let addr = if config.allow_public {
             SocketAddr::from(([0, 0, 0, 0], config.port))
@@ -78,54 +170,90 @@ impl ProxyInstance {
         let listener = TcpListener::bind(addr)
             .await
             .context(format!("绑定端口 {} 失败", config.port))?;
+        let bound_addr = listener.local_addr().unwrap_or(addr);
 
         tracing::info!(
-            tool_id = %self.tool_id,
+            tool_id = %tool_id,
             addr = %addr,
             bind_mode = if config.allow_public { "0.0.0.0" } else { "127.0.0.1" },
+            tls = acceptor.is_some(),
             "透明代理启动成功"
         );
 
-        let config_clone = Arc::clone(&self.config);
-        let processor_clone = Arc::clone(&self.processor);
+        let shared = Arc::clone(&self.shared);
         let port = config.port;
-        let tool_id = self.tool_id.clone();
+        let (shutdown_tx, mut shutdown_rx) = watch::channel(ShutdownState::Running);
+        let conn_shutdown_rx = shutdown_rx.clone();
 
         // 启动服务器
-        let handle = tokio::spawn(async move {
+        let accept_task = tokio::spawn(async move {
             loop {
-                match listener.accept().await {
-                    Ok((stream, _addr)) => {
-                        let config = Arc::clone(&config_clone);
-                        let processor = Arc::clone(&processor_clone);
-                        let tool_id_inner = tool_id.clone();
-                        let tool_id_for_error = tool_id.clone();
+                let accepted = tokio::select! {
+                    accepted = listener.accept() => accepted,
+                    // 进入停止流程后退出循环，listener 随之关闭，不再接受新连接
+                    _ = shutdown_rx.changed() => break,
+                };
+
+                match accepted {
+                    Ok((stream, peer)) => {
+                        let rejection = shared.ip_filter.read().unwrap().check(peer.ip());
+                        if let Err(reason) = rejection {
+                            shared.stats.connection_rejected();
+                            tracing::warn!(
+                                tool_id = %shared.tool_id,
+                                peer = %peer,
+                                reason = %reason,
+                                "拒绝来源不允许的连接"
+                            );
+                            continue;
+                        }
+
+                        let guard = ConnectionGuard::new(Arc::clone(&shared.stats));
+                        let shared = Arc::clone(&shared);
+                        let shutdown_rx = conn_shutdown_rx.clone();
+                        let acceptor = acceptor.clone();
 
                         tokio::spawn(async move {
-                            let io = TokioIo::new(stream);
-                            let service = service_fn(move |req| {
-                                let config = Arc::clone(&config);
-                                let processor = Arc::clone(&processor);
-                                let tool_id = tool_id_inner.clone();
-                                async move {
-                                    handle_request(req, config, processor, port, &tool_id).await
+                            let _guard = guard;
+                            let Some(acceptor) = acceptor else {
+                                serve_connection(TokioIo::new(stream), shared, port, shutdown_rx)
+                                    .await;
+                                return;
+                            };
+
+                            match tls::accept(&acceptor, stream, peer).await {
+                                Ok(ProxyStream::Tls(stream)) => {
+                                    serve_connection(
+                                        TokioIo::new(stream),
+                                        shared,
+                                        port,
+                                        shutdown_rx,
+                                    )
+                                    .await
+                                }
+                                Ok(ProxyStream::Plain(stream)) => {
+                                    serve_connection(
+                                        TokioIo::new(stream),
+                                        shared,
+                                        port,
+                                        shutdown_rx,
+                                    )
+                                    .await
+                                }
+                                Err(e) => {
+                                    tracing::warn!(
+                                        tool_id = %shared.tool_id,
+                                        peer = %peer,
+                                        error = %e,
+                                        "拒绝连接"
+                                    );
                                 }
-                            });
-
-                            if let Err(err) =
-                                http1::Builder::new().serve_connection(io, service).await
-                            {
-                                tracing::error!(
-                                    tool_id = %tool_id_for_error,
-                                    error = ?err,
-                                    "处理连接失败"
-                                );
                             }
                         });
                     }
                     Err(e) => {
                         tracing::error!(
-                            tool_id = %tool_id,
+                            tool_id = %shared.tool_id,
                             error = ?e,
                             "接受连接失败"
                         );
@@ -137,32 +265,82 @@ impl ProxyInstance {
         // 保存服务器句柄
         {
             let mut h = self.server_handle.write().await;
-            *h = Some(handle);
+            *h = Some(ServerHandle {
+                accept_task,
+                shutdown_tx,
+            });
         }
+        self.shared.stats.mark_started(bound_addr, tls_fingerprint);
 
         Ok(())
     }
 
-    /// 停止代理服务
+    /// 停止代理服务（优雅停止）
+    ///
+    /// 1. 停止接受新连接
+    /// 2. 通知已有连接在当前请求（包括 SSE 流）结束后关闭
+    /// 3. 等待活跃连接归零，超过 `shutdown_grace_secs` 后强制关闭
     pub async fn stop(&self) -> Result<()> {
         let handle = {
             let mut h = self.server_handle.write().await;
             h.take()
         };
 
-        if let Some(handle) = handle {
-            handle.abort();
-            tracing::info!(tool_id = %self.tool_id, "透明代理已停止");
+        let Some(handle) = handle else {
+            return Ok(());
+        };
+        self.shared.stats.mark_stopped();
+
+        let tool_id = &self.shared.tool_id;
+        let grace = Duration::from_secs(self.shared.config.read().await.shutdown_grace_secs);
+
+        let _ = handle.shutdown_tx.send(ShutdownState::Draining);
+        if let Err(e) = handle.accept_task.await {
+            tracing::warn!(tool_id = %tool_id, error = ?e, "等待接受循环退出失败");
+        }
+
+        let active = self.active_connections();
+        if active > 0 {
+            tracing::info!(
+                tool_id = %tool_id,
+                active_connections = active,
+                grace_secs = grace.as_secs(),
+                "等待活跃连接结束"
+            );
+        }
+
+        let deadline = tokio::time::Instant::now() + grace;
+        while self.active_connections() > 0 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(100)).await;
         }
 
+        let remaining = self.active_connections();
+        if remaining > 0 {
+            tracing::warn!(
+                tool_id = %tool_id,
+                active_connections = remaining,
+                "等待超时，强制关闭剩余连接"
+            );
+            let _ = handle.shutdown_tx.send(ShutdownState::Force);
+        }
+
+        tracing::info!(tool_id = %tool_id, "透明代理已停止");
         Ok(())
     }
 
-    /// 检查服务是否在运行
+    /// 当前活跃连接数
+    pub fn active_connections(&self) -> usize {
+        self.shared.stats.active_connections()
+    }
+
+    /// 获取运行状态快照
+    pub fn status(&self) -> ProxyInstanceStatus {
+        self.shared.stats.snapshot()
+    }
+
+    /// 检查服务是否在运行（开始停止后即返回 false）
     pub fn is_running(&self) -> bool {
-        // 使用 blocking 方式读取，因为这是同步方法
-        // 在实际使用中，ProxyManager 会使用异步版本
-        false // 临时实现，将在异步上下文中使用 try_read
+        self.shared.stats.is_running()
     }
 
     /// 异步检查是否运行
@@ -172,23 +350,154 @@ impl ProxyInstance {
     }
 
     /// 更新配置（无需重启）
-    pub async fn update_config(&self, new_config: ToolProxyConfig) -> Result<()> {
-        let mut config = self.config.write().await;
-        *config = new_config;
-        tracing::info!(tool_id = %self.tool_id, "透明代理配置已更新");
+    ///
+    /// 同时按新配置和全局代理设置重建上游 HTTP 客户端
+    pub async fn update_config(
+        &self,
+        new_config: ToolProxyConfig,
+        global_config: &GlobalConfig,
+    ) -> Result<()> {
+        let client = build_upstream_client(global_config, &new_config)?;
+        let ip_filter = IpFilter::from_config(&new_config.ip_filter).context("来源过滤配置无效")?;
+        *self.shared.config.write().await = new_config;
+        *self.shared.client.write().await = client;
+        *self.shared.ip_filter.write().unwrap() = ip_filter;
+        tracing::info!(tool_id = %self.shared.tool_id, "透明代理配置已更新");
         Ok(())
     }
 }
 
+/// 在已建立的连接（明文或 TLS）上提供 HTTP 服务，并响应停止流程
+async fn serve_connection<I>(
+    io: TokioIo<I>,
+    shared: Arc<ProxyShared>,
+    port: u16,
+    mut shutdown_rx: watch::Receiver<ShutdownState>,
+) where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service_shared = Arc::clone(&shared);
+    let service = service_fn(move |req| {
+        let shared = Arc::clone(&service_shared);
+        async move { handle_request(req, shared, port).await }
+    });
+
+    // 每个连接读取最新的监听参数，update_config 后对新连接立即生效
+    let listener_config = shared.config.read().await.listener.clone();
+    let builder = build_connection_builder(&listener_config);
+    let conn = builder.serve_connection(io, service);
+    tokio::pin!(conn);
+
+    // 已处于停止流程时（连接在通知前被接受）立即进入优雅关闭
+    let mut draining = false;
+    let result = loop {
+        let state = *shutdown_rx.borrow_and_update();
+        match state {
+            ShutdownState::Force => return,
+            ShutdownState::Draining if !draining => {
+                draining = true;
+                conn.as_mut().graceful_shutdown();
+            }
+            _ => {}
+        }
+
+        tokio::select! {
+            result = conn.as_mut() => break result,
+            changed = shutdown_rx.changed() => {
+                if changed.is_err() {
+                    // 发送端已释放（实例被丢弃），等待连接自然结束
+                    break conn.as_mut().await;
+                }
+            }
+        }
+    };
+
+    if let Err(err) = result {
+        tracing::error!(
+            tool_id = %shared.tool_id,
+            error = ?err,
+            "处理连接失败"
+        );
+    }
+}
+
+/// 构建本地连接的服务端配置
+///
+/// 自动识别 HTTP/1.1 与 h2c（HTTP/2 prior knowledge），支持多路复用的客户端只需一个连接
+fn build_connection_builder(config: &ListenerConfig) -> auto::Builder<TokioExecutor> {
+    let mut builder = auto::Builder::new(TokioExecutor::new());
+
+    builder
+        .http1()
+        .timer(TokioTimer::new())
+        .keep_alive(config.keep_alive)
+        .header_read_timeout(
+            Some(config.header_read_timeout_secs)
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+        );
+
+    builder
+        .http2()
+        .timer(TokioTimer::new())
+        .keep_alive_interval(
+            config
+                .http2_keep_alive_interval_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+        )
+        .keep_alive_timeout(Duration::from_secs(config.http2_keep_alive_timeout_secs))
+        .max_concurrent_streams(config.http2_max_concurrent_streams);
+
+    builder
+}
+
+/// 构建访问上游的 HTTP 客户端
+///
+/// 复用 core::http 的客户端构建逻辑，应用全局代理（含绕过列表）与工具级超时参数。
+/// 全局配置由调用方传入：代理设置无效时返回错误，不会静默退回直连
+pub(super) fn build_upstream_client(
+    global_config: &GlobalConfig,
+    config: &ToolProxyConfig,
+) -> Result<reqwest::Client> {
+    let client_config = &config.upstream_client;
+
+    let options = HttpClientOptions {
+        // 流式响应可能持续很久，不设置整体超时，改用读取超时
+        timeout: None,
+        connect_timeout: Some(Duration::from_secs(client_config.connect_timeout_secs)),
+        read_timeout: Some(Duration::from_secs(client_config.read_timeout_secs)),
+        http2_keep_alive_interval: client_config
+            .http2_keep_alive_secs
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs),
+        pool_idle_timeout: Some(Duration::from_secs(client_config.pool_idle_timeout_secs)),
+        // 上游可能位于内网，遵守全局配置中的代理绕过列表
+        proxy_bypass: global_config
+            .proxy_bypass_urls
+            .iter()
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .collect(),
+    };
+
+    build_http_client_with_options(Some(global_config), &options)
+        .map_err(|e| anyhow::anyhow!("构建上游 HTTP 客户端失败: {e}"))
+}
+
 /// 处理单个请求
 async fn handle_request(
     req: Request<Incoming>,
-    config: Arc<RwLock<ToolProxyConfig>>,
-    processor: Arc<dyn RequestProcessor>,
+    shared: Arc<ProxyShared>,
     own_port: u16,
-    tool_id: &str,
 ) -> Result<Response<BoxBody>, Infallible> {
-    match handle_request_inner(req, config, processor, own_port, tool_id).await {
+    let tool_id = shared.tool_id.as_str();
+    let mut audit = RequestAudit::new(tool_id, req.method().as_str(), req.uri().path())
+        .with_stats(Arc::clone(&shared.stats));
+    shared.stats.request_started();
+    let processor = route_request_processor(shared.processor.as_ref(), req.uri().path());
+
+    match handle_request_inner(req, &shared, processor, own_port, &mut audit).await {
         Ok(res) => Ok(res),
         Err(e) => {
             tracing::error!(
@@ -196,40 +505,60 @@ async fn handle_request(
                 error = ?e,
                 "请求处理失败"
             );
-            Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(box_body(http_body_util::Full::new(Bytes::from(format!(
-                    "代理错误: {e}"
-                )))))
-                .unwrap())
+            audit.set_error_kind(classify_error(&e));
+            audit.set_error_message(e.to_string());
+            audit.set_status(StatusCode::INTERNAL_SERVER_ERROR.as_u16());
+            Ok(proxy_error_response(
+                processor,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "PROXY_ERROR",
+                &format!("代理错误: {e}"),
+            ))
         }
     }
 }
 
+/// 构造代理自身产生的错误响应（按工具期望的错误格式封装）
+fn proxy_error_response(
+    processor: &dyn RequestProcessor,
+    status: StatusCode,
+    code: &str,
+    message: &str,
+) -> Response<BoxBody> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(box_body(http_body_util::Full::new(
+            processor.error_body(status, code, message),
+        )))
+        .unwrap()
+}
+
 async fn handle_request_inner(
     req: Request<Incoming>,
-    config: Arc<RwLock<ToolProxyConfig>>,
-    processor: Arc<dyn RequestProcessor>,
+    shared: &ProxyShared,
+    processor: &dyn RequestProcessor,
     own_port: u16,
-    tool_id: &str,
+    audit: &mut RequestAudit,
 ) -> Result<Response<BoxBody>> {
+    let tool_id = shared.tool_id.as_str();
+    let upstream_pool = &shared.upstream_pool;
+
     // 获取配置
-    let proxy_config = {
-        let cfg = config.read().await;
-        if cfg.real_api_key.is_none() || cfg.real_base_url.is_none() {
-            return Ok(Response::builder()
-                .status(StatusCode::BAD_GATEWAY)
-                .header("content-type", "application/json")
-                .body(box_body(http_body_util::Full::new(Bytes::from(format!(
-                    r#"{{
-  "error": "CONFIGURATION_MISSING",
-  "message": "{tool_id} 透明代理配置不完整",
-  "details": "请先配置有效的 API Key 和 Base URL"
-}}"#
-                )))))
-                .unwrap());
+    let (proxy_config, candidates) = {
+        let cfg = shared.config.read().await;
+        let candidates = cfg.upstream_candidates();
+        if candidates.is_empty() {
+            audit.set_error_kind(error_kind::CONFIGURATION_MISSING);
+            audit.set_status(StatusCode::BAD_GATEWAY.as_u16());
+            return Ok(proxy_error_response(
+                processor,
+                StatusCode::BAD_GATEWAY,
+                "CONFIGURATION_MISSING",
+                &format!("{tool_id} 透明代理配置不完整，请先配置有效的 API Key 和 Base URL"),
+            ));
         }
-        cfg.clone()
+        (cfg.clone(), candidates)
     };
 
     // 验证本地 API Key
@@ -248,14 +577,17 @@ async fn handle_request_inner(
         auth_header
     };
 
-    if let Some(local_key) = &proxy_config.local_api_key {
-        if provided_key != local_key {
-            return Ok(Response::builder()
-                .status(StatusCode::UNAUTHORIZED)
-                .body(box_body(http_body_util::Full::new(Bytes::from(
-                    "Unauthorized: Invalid API Key",
-                ))))
-                .unwrap());
+    match local_keys::authenticate(&proxy_config, provided_key, chrono::Utc::now().timestamp()) {
+        Some(auth) => audit.set_key_id(auth.key_id().map(|id| id.to_string())),
+        None => {
+            audit.set_error_kind(error_kind::UNAUTHORIZED);
+            audit.set_status(StatusCode::UNAUTHORIZED.as_u16());
+            return Ok(proxy_error_response(
+                processor,
+                StatusCode::UNAUTHORIZED,
+                "UNAUTHORIZED",
+                "Unauthorized: Invalid API Key",
+            ));
         }
     }
 
@@ -265,79 +597,201 @@ async fn handle_request_inner(
     let method = req.method().clone();
     let headers = req.headers().clone();
 
-    let base = proxy_config
-        .real_base_url
-        .as_ref()
-        .unwrap()
-        .trim_end_matches('/');
-
     // 读取请求体（消费 req）
     let body_bytes = if method != Method::GET && method != Method::HEAD {
         req.collect().await?.to_bytes()
     } else {
         Bytes::new()
     };
+    let session_id = processor.session_id(&body_bytes);
+    audit.set_bytes_in(body_bytes.len());
+    audit.set_session_id(session_id.clone());
+
+    // 限流（许可随响应移动，响应结束时释放并发名额）
+    let rate_permit = match shared
+        .rate_limiter
+        .try_acquire(session_id.as_deref(), &proxy_config.rate_limit)
+    {
+        Ok(permit) => permit,
+        Err(limited) => {
+            tracing::warn!(
+                tool_id = %tool_id,
+                session_id = ?session_id,
+                scope = ?limited.scope,
+                kind = ?limited.kind,
+                retry_after = limited.retry_after_secs,
+                "请求被限流"
+            );
+            audit.set_error_kind(error_kind::RATE_LIMITED);
+            audit.set_status(StatusCode::TOO_MANY_REQUESTS.as_u16());
+            let mut response = proxy_error_response(
+                processor,
+                StatusCode::TOO_MANY_REQUESTS,
+                "RATE_LIMITED",
+                &limited.message(),
+            );
+            response.headers_mut().insert(
+                hyper::header::RETRY_AFTER,
+                hyper::header::HeaderValue::from(limited.retry_after_secs),
+            );
+            return Ok(response);
+        }
+    };
 
-    // 使用 RequestProcessor 统一处理请求（URL + headers + body）
-    let processed = processor
-        .process_outgoing_request(
-            base,
-            proxy_config.real_api_key.as_ref().unwrap(),
-            &path,
-            query.as_deref(),
-            &headers,
-            &body_bytes,
-        )
-        .await
-        .context("处理出站请求失败")?;
+    // 模型映射（所有上游共用同一份改写结果）
+    let model_rewrite =
+        processor.apply_model_mapping(&path, &body_bytes, &proxy_config.model_mappings);
+    let (forward_path, forward_body) = match &model_rewrite {
+        Some(rewrite) => {
+            tracing::debug!(
+                tool_id = %tool_id,
+                original = %rewrite.original,
+                mapped = %rewrite.mapped,
+                "应用模型映射"
+            );
+            (
+                rewrite.path.clone().unwrap_or_else(|| path.clone()),
+                rewrite.body.clone().unwrap_or_else(|| body_bytes.clone()),
+            )
+        }
+        None => (path.clone(), body_bytes.clone()),
+    };
+    let mut model_restorer = model_rewrite
+        .as_ref()
+        .filter(|rewrite| rewrite.reverse)
+        .map(ModelRestorer::new);
+
+    // 请求抓包（响应结束时写入磁盘）
+    let mut capture = CaptureRecorder::start(tool_id, &proxy_config, method.as_str(), &headers);
+    if let Some(capture) = capture.as_mut() {
+        capture.set_request(&forward_path, query.as_deref(), &forward_body);
+        audit.set_capture_id(capture.id());
+    }
 
     // 回环检测
-    let loop_urls = vec![
+    let loop_urls = [
         format!("http://127.0.0.1:{}", own_port),
         format!("https://127.0.0.1:{}", own_port),
         format!("http://localhost:{}", own_port),
         format!("https://localhost:{}", own_port),
     ];
 
-    for loop_url in &loop_urls {
-        if processed.target_url.starts_with(loop_url) {
-            return Ok(Response::builder()
-                .status(StatusCode::BAD_GATEWAY)
-                .header("content-type", "application/json")
-                .body(box_body(http_body_util::Full::new(Bytes::from(format!(
-                    r#"{{
-  "error": "PROXY_LOOP_DETECTED",
-  "message": "{tool_id} 透明代理配置错误导致回环",
-  "details": "请检查代理配置，确保 Base URL 不指向本地代理端口"
-}}"#
-                )))))
-                .unwrap());
+    let cooldown = Duration::from_secs(proxy_config.upstream_cooldown_secs);
+    let ordered = upstream_pool.ordered(&candidates);
+    let client = shared.client.read().await.clone();
+    let mut loop_detected = false;
+
+    // 依次尝试上游：连接失败、5xx、429 时切换到下一个上游（响应流开始前）
+    let mut attempts = ordered.iter().enumerate().peekable();
+    let upstream_res = loop {
+        let Some((attempt, upstream)) = attempts.next() else {
+            if loop_detected {
+                audit.set_error_kind(error_kind::LOOP_DETECTED);
+                audit.set_status(StatusCode::BAD_GATEWAY.as_u16());
+                return Ok(proxy_error_response(
+                    processor,
+                    StatusCode::BAD_GATEWAY,
+                    "PROXY_LOOP_DETECTED",
+                    &format!(
+                        "{tool_id} 透明代理配置错误导致回环，请检查代理配置，确保 Base URL 不指向本地代理端口"
+                    ),
+                ));
+            }
+            anyhow::bail!("没有可用的上游");
+        };
+        let has_next = attempts.peek().is_some();
+        let base = upstream.base_url.trim_end_matches('/');
+        audit.set_upstream(base);
+
+        // 使用 RequestProcessor 统一处理请求（URL + headers + body）
+        let processed = processor
+            .process_outgoing_request(
+                base,
+                &upstream.api_key,
+                &forward_path,
+                query.as_deref(),
+                &headers,
+                &forward_body,
+            )
+            .await
+            .context("处理出站请求失败")?;
+
+        if loop_urls
+            .iter()
+            .any(|loop_url| processed.target_url.starts_with(loop_url))
+        {
+            tracing::warn!(
+                tool_id = %tool_id,
+                upstream = %base,
+                "上游指向本地代理端口，已跳过"
+            );
+            loop_detected = true;
+            continue;
+        }
+        if let Some(capture) = capture.as_mut() {
+            capture.set_upstream(base, &processed.target_url);
         }
-    }
 
-    tracing::debug!(
-        tool_id = %tool_id,
-        method = %method,
-        path = %path,
-        target_url = %processed.target_url,
-        "代理请求"
-    );
+        tracing::debug!(
+            tool_id = %tool_id,
+            method = %method,
+            path = %path,
+            upstream = %base,
+            attempt = attempt + 1,
+            target_url = %processed.target_url,
+            "代理请求"
+        );
 
-    // 构建上游请求（使用处理后的信息）
-    let mut reqwest_builder = reqwest::Client::new().request(method.clone(), &processed.target_url);
+        // 构建上游请求（使用处理后的信息）
+        let mut reqwest_builder = client.request(method.clone(), &processed.target_url);
 
-    // 应用处理后的 headers
-    for (name, value) in processed.headers.iter() {
-        reqwest_builder = reqwest_builder.header(name, value);
-    }
+        // 应用处理后的 headers
+        for (name, value) in processed.headers.iter() {
+            reqwest_builder = reqwest_builder.header(name, value);
+        }
 
-    // 添加请求体
-    if !processed.body.is_empty() {
-        reqwest_builder = reqwest_builder.body(processed.body.to_vec());
-    }
+        // 添加请求体
+        if !processed.body.is_empty() {
+            reqwest_builder = reqwest_builder.body(processed.body.to_vec());
+        }
 
-    // 发送请求
-    let upstream_res = reqwest_builder.send().await.context("上游请求失败")?;
+        // 发送请求
+        match reqwest_builder.send().await {
+            Ok(res) if is_retryable_status(res.status().as_u16()) && has_next => {
+                tracing::warn!(
+                    tool_id = %tool_id,
+                    upstream = %base,
+                    status = res.status().as_u16(),
+                    "上游返回可重试状态，切换到下一个上游"
+                );
+                upstream_pool.mark_failure(upstream, cooldown);
+            }
+            Ok(res) => {
+                if is_retryable_status(res.status().as_u16()) {
+                    upstream_pool.mark_failure(upstream, cooldown);
+                } else {
+                    upstream_pool.mark_success(upstream);
+                }
+                shared.stats.set_current_upstream(&upstream.base_url);
+                break res;
+            }
+            Err(e) if (e.is_connect() || e.is_timeout()) && has_next => {
+                tracing::warn!(
+                    tool_id = %tool_id,
+                    upstream = %base,
+                    error = %e,
+                    "上游连接失败，切换到下一个上游"
+                );
+                upstream_pool.mark_failure(upstream, cooldown);
+            }
+            Err(e) => {
+                if e.is_connect() || e.is_timeout() {
+                    upstream_pool.mark_failure(upstream, cooldown);
+                }
+                return Err(anyhow::Error::new(e).context("上游请求失败"));
+            }
+        }
+    };
 
     // 构建响应
     let status = StatusCode::from_u16(upstream_res.status().as_u16())
@@ -351,18 +805,64 @@ async fn handle_request_inner(
         .map(|v| v.contains("text/event-stream"))
         .unwrap_or(false);
 
+    audit.set_status(status.as_u16());
     let mut response = Response::builder().status(status);
 
-    // 复制响应 headers
-    for (name, value) in upstream_res.headers().iter() {
-        response = response.header(name.as_str(), value.as_bytes());
+    // 成功的 SSE 响应需要转换时逐块转换；
+    // 其他需要处理响应体的情况（如改写错误格式）读取完整响应，不再按流转发
+    let stream_transformer = if is_sse && status.is_success() {
+        processor.stream_transformer(&forward_body)
+    } else {
+        None
+    };
+    let process_body = stream_transformer.is_none() && processor.should_process_response(status);
+    if let Some(capture) = capture.as_mut() {
+        capture.set_response(
+            status.as_u16(),
+            upstream_res.headers(),
+            is_sse && !process_body,
+        );
     }
 
-    if is_sse {
+    if is_sse && !process_body {
         tracing::debug!(tool_id = %tool_id, "SSE 流式响应");
         use futures_util::StreamExt;
 
-        let stream = upstream_res.bytes_stream();
+        // 复制响应 headers（改写响应流会改变响应体长度，此时由 hyper 重新计算 content-length）
+        let body_rewritten = model_restorer.is_some() || stream_transformer.is_some();
+        for (name, value) in upstream_res.headers().iter() {
+            if body_rewritten && name == hyper::header::CONTENT_LENGTH {
+                continue;
+            }
+            response = response.header(name.as_str(), value.as_bytes());
+        }
+
+        // 用量记录器与审计记录随流移动，流结束或客户端断开时写入数据库
+        let mut usage_recorder = UsageRecorder::new(tool_id, session_id);
+        let mut stream_audit = audit.take();
+        let tapped_stream = upstream_res.bytes_stream().map(move |result| {
+            // 持有限流许可直到流结束
+            let _ = &rate_permit;
+            match &result {
+                Ok(chunk) => {
+                    usage_recorder.feed(chunk);
+                    stream_audit.add_bytes_out(chunk.len());
+                    if let Some(capture) = capture.as_mut() {
+                        capture.feed(chunk);
+                    }
+                }
+                Err(_) => stream_audit.set_error_kind(error_kind::STREAM_INTERRUPTED),
+            }
+            result
+        });
+
+        let mut stream: UpstreamStream = Box::pin(tapped_stream);
+        if let Some(restorer) = model_restorer {
+            stream = Box::pin(transform_stream(stream, Box::new(restorer)));
+        }
+        if let Some(transformer) = stream_transformer {
+            stream = Box::pin(transform_stream(stream, transformer));
+        }
         let mapped_stream = stream.map(|result| {
             result
                 .map(Frame::data)
@@ -373,13 +873,92 @@ async fn handle_request_inner(
         Ok(response.body(box_body(body)).unwrap())
     } else {
         // 普通响应
-        let body_bytes = upstream_res.bytes().await.context("读取响应体失败")?;
+        let mut response_headers = upstream_res.headers().clone();
+        let mut body_bytes = upstream_res.bytes().await.context("读取响应体失败")?;
+        let mut body_changed = false;
+        if let Some(capture) = capture.as_mut() {
+            capture.feed(&body_bytes);
+        }
+
+        if status.is_success() {
+            UsageRecorder::record_json(tool_id, session_id, &body_bytes);
+            if let Some(restorer) = model_restorer.take() {
+                body_bytes = restorer.restore_json(body_bytes);
+                body_changed = true;
+            }
+        } else {
+            // 审计日志保留上游的原始错误响应体
+            audit.set_error_body(audit_error_body(&response_headers, &body_bytes));
+        }
+
+        if process_body {
+            body_bytes = processor
+                .process_response(status, &mut response_headers, body_bytes)
+                .await
+                .context("处理响应失败")?;
+            body_changed = true;
+        }
+
+        // 响应体被改写时由 hyper 重新计算 content-length
+        for (name, value) in response_headers.iter() {
+            if body_changed
+                && (name == hyper::header::CONTENT_LENGTH
+                    || name == hyper::header::TRANSFER_ENCODING)
+            {
+                continue;
+            }
+            response = response.header(name, value);
+        }
+
+        audit.add_bytes_out(body_bytes.len());
         Ok(response
             .body(box_body(http_body_util::Full::new(body_bytes)))
             .unwrap())
     }
 }
 
+type UpstreamStream = Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>;
+
+/// 在 SSE 流上应用转换器（如还原模型名称、转换协议），流结束时输出剩余数据
+fn transform_stream(
+    stream: UpstreamStream,
+    transformer: Box<dyn StreamTransformer>,
+) -> impl Stream<Item = reqwest::Result<Bytes>> + Send {
+    use futures_util::StreamExt;
+
+    futures_util::stream::unfold(
+        (stream, transformer, false),
+        |(mut stream, mut transformer, done)| async move {
+            if done {
+                return None;
+            }
+            loop {
+                match stream.next().await {
+                    Some(Ok(chunk)) => {
+                        let output = transformer.feed(&chunk);
+                        if !output.is_empty() {
+                            return Some((Ok(output), (stream, transformer, false)));
+                        }
+                    }
+                    Some(Err(e)) => return Some((Err(e), (stream, transformer, true))),
+                    None => {
+                        let rest = transformer.finish();
+                        if rest.is_empty() {
+                            return None;
+                        }
+                        return Some((Ok(rest), (stream, transformer, true)));
+                    }
+                }
+            }
+        },
+    )
+}
+
+/// 是否为可切换上游重试的状态码（5xx 与 429）
+fn is_retryable_status(status: u16) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
 // Body 类型定义
 pin_project! {
     pub struct BoxBody {
@@ -8,15 +8,17 @@
 use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
 
 use super::headers::create_request_processor;
 use super::proxy_instance::ProxyInstance;
-use crate::models::ToolProxyConfig;
+use super::status::{ProxyInstanceStatus, StatusNotifier};
+use crate::models::{GlobalConfig, ToolProxyConfig};
 
 /// 代理管理器
 pub struct ProxyManager {
-    instances: Arc<RwLock<HashMap<String, ProxyInstance>>>,
+    instances: Arc<RwLock<HashMap<String, Arc<ProxyInstance>>>>,
+    status_notifier: StatusNotifier,
 }
 
 impl ProxyManager {
@@ -24,19 +26,33 @@ impl ProxyManager {
     pub fn new() -> Self {
         Self {
             instances: Arc::new(RwLock::new(HashMap::new())),
+            status_notifier: StatusNotifier::new(),
         }
     }
 
+    /// 订阅代理运行状态变化（启停、连接数、请求数、错误、上游切换）
+    pub fn subscribe_status(&self) -> watch::Receiver<()> {
+        self.status_notifier.subscribe()
+    }
+
     /// 启动指定工具的代理
     ///
     /// # 参数
     /// - `tool_id`: 工具标识符 ("claude-code", "codex", "gemini-cli")
     /// - `config`: 工具的代理配置
+    /// - `global_config`: 全局配置（上游请求使用其中的出站代理设置）
     ///
     /// # 返回
     /// - `Ok(())`: 启动成功
     /// - `Err`: 启动失败（如端口已占用、配置无效等）
-    pub async fn start_proxy(&self, tool_id: &str, config: ToolProxyConfig) -> Result<()> {
+    pub async fn start_proxy(
+        &self,
+        tool_id: &str,
+        config: ToolProxyConfig,
+        global_config: &GlobalConfig,
+    ) -> Result<()> {
+        ensure_secrets_resolved(tool_id, &config)?;
+
         // 检查是否已经在运行
         {
             let instances = self.instances.read().await;
@@ -59,19 +75,24 @@ impl ProxyManager {
         }
 
         // 创建 RequestProcessor
-        let processor = create_request_processor(tool_id);
+        let processor = create_request_processor(tool_id)?;
 
         // 创建并启动代理实例
-        let instance = ProxyInstance::new(tool_id.to_string(), config, processor);
+        let instance = ProxyInstance::new(
+            tool_id.to_string(),
+            config,
+            processor,
+            self.status_notifier.clone(),
+        );
         instance
-            .start()
+            .start(global_config)
             .await
             .context(format!("启动 {tool_id} 代理失败"))?;
 
         // 存入 HashMap
         {
             let mut instances = self.instances.write().await;
-            instances.insert(tool_id.to_string(), instance);
+            instances.insert(tool_id.to_string(), Arc::new(instance));
         }
 
         Ok(())
@@ -79,6 +100,9 @@ impl ProxyManager {
 
     /// 停止指定工具的代理
     ///
+    /// 等待活跃连接结束期间不持有实例表的锁，排空中的实例仍可通过
+    /// `get_all_status` 查询剩余连接数
+    ///
     /// # 参数
     /// - `tool_id`: 工具标识符
     ///
@@ -86,78 +110,93 @@ impl ProxyManager {
     /// - `Ok(())`: 停止成功（或代理未运行）
     /// - `Err`: 停止失败
     pub async fn stop_proxy(&self, tool_id: &str) -> Result<()> {
-        let mut instances = self.instances.write().await;
+        let instance = {
+            let instances = self.instances.read().await;
+            instances.get(tool_id).cloned()
+        };
 
-        if let Some(instance) = instances.remove(tool_id) {
-            instance
-                .stop()
-                .await
-                .context(format!("停止 {tool_id} 代理失败"))?;
-        } else {
+        let Some(instance) = instance else {
             tracing::warn!(tool_id = %tool_id, "代理未运行或不存在");
-        }
+            return Ok(());
+        };
 
-        Ok(())
+        let result = instance
+            .stop()
+            .await
+            .context(format!("停止 {tool_id} 代理失败"));
+        self.remove_instance(tool_id, &instance).await;
+
+        result
     }
 
-    /// 停止所有运行中的代理
+    /// 停止所有运行中的代理（并行排空）
     pub async fn stop_all(&self) -> Result<()> {
-        let mut instances = self.instances.write().await;
-        let tool_ids: Vec<String> = instances.keys().cloned().collect();
-
-        for tool_id in tool_ids {
-            if let Some(instance) = instances.remove(&tool_id) {
-                if let Err(e) = instance.stop().await {
-                    tracing::error!(
-                        tool_id = %tool_id,
-                        error = ?e,
-                        "停止代理失败"
-                    );
-                }
+        let instances: Vec<(String, Arc<ProxyInstance>)> = {
+            let instances = self.instances.read().await;
+            instances
+                .iter()
+                .map(|(id, i)| (id.clone(), Arc::clone(i)))
+                .collect()
+        };
+
+        let stops = instances.into_iter().map(|(tool_id, instance)| async move {
+            if let Err(e) = instance.stop().await {
+                tracing::error!(
+                    tool_id = %tool_id,
+                    error = ?e,
+                    "停止代理失败"
+                );
             }
-        }
+            self.remove_instance(&tool_id, &instance).await;
+        });
+        futures_util::future::join_all(stops).await;
 
         Ok(())
     }
 
+    /// 从实例表移除已停止的实例（排空期间同一工具已重新启动时保留新实例）
+    async fn remove_instance(&self, tool_id: &str, instance: &Arc<ProxyInstance>) {
+        let mut instances = self.instances.write().await;
+        if instances
+            .get(tool_id)
+            .is_some_and(|current| Arc::ptr_eq(current, instance))
+        {
+            instances.remove(tool_id);
+            self.status_notifier.notify();
+        }
+    }
+
     /// 检查指定工具的代理是否在运行
     pub async fn is_running(&self, tool_id: &str) -> bool {
         let instances = self.instances.read().await;
-        instances
-            .get(tool_id)
-            .map(|i| {
-                // 使用 blocking 获取状态（在异步上下文中）
-                // 实际实现中应该使用异步版本
-                tokio::task::block_in_place(|| {
-                    tokio::runtime::Handle::current().block_on(async { i.is_running_async().await })
-                })
-            })
-            .unwrap_or(false)
+        instances.get(tool_id).is_some_and(|i| i.is_running())
     }
 
     /// 获取所有工具的代理运行状态
     ///
     /// # 返回
-    /// - HashMap<tool_id, is_running>
-    pub async fn get_all_status(&self) -> HashMap<String, bool> {
+    /// - HashMap<tool_id, 运行状态快照>（包含正在排空连接的实例）
+    pub async fn get_all_status(&self) -> HashMap<String, ProxyInstanceStatus> {
         let instances = self.instances.read().await;
-        let mut status_map = HashMap::new();
-
-        for (tool_id, instance) in instances.iter() {
-            let running = instance.is_running_async().await;
-            status_map.insert(tool_id.clone(), running);
-        }
-
-        status_map
+        instances
+            .iter()
+            .map(|(tool_id, instance)| (tool_id.clone(), instance.status()))
+            .collect()
     }
 
     /// 更新指定工具的代理配置（无需重启）
-    pub async fn update_config(&self, tool_id: &str, config: ToolProxyConfig) -> Result<()> {
+    pub async fn update_config(
+        &self,
+        tool_id: &str,
+        config: ToolProxyConfig,
+        global_config: &GlobalConfig,
+    ) -> Result<()> {
+        ensure_secrets_resolved(tool_id, &config)?;
         let instances = self.instances.read().await;
 
         if let Some(instance) = instances.get(tool_id) {
             instance
-                .update_config(config)
+                .update_config(config, global_config)
                 .await
                 .context(format!("更新 {tool_id} 代理配置失败"))?;
         } else {
@@ -168,6 +207,14 @@ impl ProxyManager {
     }
 }
 
+/// 密钥仍是密钥库引用时拒绝运行代理（否则引用字符串会被当作本地密钥或上游密钥使用）
+fn ensure_secrets_resolved(tool_id: &str, config: &ToolProxyConfig) -> Result<()> {
+    if config.has_unresolved_secrets() {
+        anyhow::bail!("{tool_id} 代理配置中的密钥无法从密钥库读取，请检查密钥库状态");
+    }
+    Ok(())
+}
+
 impl Default for ProxyManager {
     fn default() -> Self {
         Self::new()
@@ -193,5 +240,21 @@ mod tests {
         assert!(status.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_refuses_unresolved_vault_refs() {
+        let manager = ProxyManager::new();
+        let config = ToolProxyConfig {
+            local_api_key: Some("vault:config/proxy/claude-code/local_api_key".to_string()),
+            ..Default::default()
+        };
+        let global_config: GlobalConfig =
+            serde_json::from_value(serde_json::json!({"user_id": "", "system_token": ""})).unwrap();
+        assert!(manager
+            .start_proxy("claude-code", config, &global_config)
+            .await
+            .is_err());
+        assert!(!manager.is_running("claude-code").await);
+    }
+
     // 更多测试需要 mock 或集成测试环境
 }
@@ -1,6 +1,6 @@
+use crate::utils::proxy_bypass;
 use crate::GlobalConfig;
 use std::env;
-use url::Url;
 
 /// 代理服务 - 负责应用代理配置到环境变量
 pub struct ProxyService;
@@ -8,99 +8,7 @@ pub struct ProxyService;
 impl ProxyService {
     /// 检查给定的URL是否应该绕过代理
     pub fn should_bypass_proxy(url: &str, bypass_list: &[String]) -> bool {
-        // 如果没有过滤规则，不绕过
-        if bypass_list.is_empty() {
-            return false;
-        }
-
-        let parsed_url = match Url::parse(url) {
-            Ok(url) => url,
-            Err(_) => {
-                // 如果URL解析失败，尝试作为主机名处理
-                return Self::should_bypass_host(url, bypass_list);
-            }
-        };
-
-        // 获取主机名进行匹配
-        let host = parsed_url.host_str().unwrap_or("");
-
-        Self::should_bypass_host(host, bypass_list)
-    }
-
-    /// 检查主机名是否应该绕过代理
-    fn should_bypass_host(host: &str, bypass_list: &[String]) -> bool {
-        for pattern in bypass_list {
-            if Self::matches_pattern(host, pattern) {
-                return true;
-            }
-        }
-        false
-    }
-
-    /// 检查主机名是否匹配给定的模式
-    fn matches_pattern(host: &str, pattern: &str) -> bool {
-        let pattern = pattern.trim();
-        let host = host.trim().to_lowercase();
-        let pattern = pattern.to_lowercase();
-
-        // 精确匹配
-        if host == pattern {
-            return true;
-        }
-
-        // 通配符匹配
-        if let Some(domain) = pattern.strip_prefix("*.") {
-            if host.ends_with(domain) || host == domain {
-                return true;
-            }
-        }
-
-        // 简单的通配符匹配（支持 *）
-        if pattern.contains('*') {
-            return Self::wildcard_match(&host, &pattern);
-        }
-
-        // IP段匹配（例如 192.168.*）
-        if pattern.contains('*') && !pattern.starts_with("*.") {
-            let parts: Vec<&str> = pattern.split('.').collect();
-            let host_parts: Vec<&str> = host.split('.').collect();
-
-            if parts.len() == host_parts.len() {
-                for (i, part) in parts.iter().enumerate() {
-                    let host_part = host_parts.get(i).unwrap_or(&"");
-                    if *part != "*" && *part != *host_part {
-                        return false;
-                    }
-                }
-                return true;
-            }
-        }
-
-        false
-    }
-
-    /// 简单的通配符匹配
-    fn wildcard_match(text: &str, pattern: &str) -> bool {
-        if pattern == "*" {
-            return true;
-        }
-
-        if !pattern.contains('*') {
-            return text == pattern;
-        }
-
-        // 将模式按 * 分割
-        let parts: Vec<&str> = pattern.split('*').collect();
-
-        if parts.len() == 2 {
-            let prefix = parts[0];
-            let suffix = parts[1];
-
-            return text.starts_with(prefix) && text.ends_with(suffix);
-        }
-
-        // 更复杂的模式，简化处理
-        text.contains(&pattern.replace('*', ""))
+        proxy_bypass::should_bypass_proxy(url, bypass_list)
     }
 
     /// 从全局配置应用代理到环境变量
@@ -0,0 +1,277 @@
+// 请求限流
+//
+// 按 ToolProxyConfig.rate_limit 对单个代理实例做两级限制：
+// - 工具级：整个代理的每分钟请求数与并发请求数
+// - 会话级：按 metadata.user_id 区分的每分钟请求数与并发请求数
+//
+// 每分钟请求数使用 60 秒滑动窗口；并发数由 RatePermit 持有，
+// 响应结束（流式响应为流结束或客户端断开）时释放
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::models::RateLimitConfig;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// 会话表超过该数量时清理空闲会话
+const SESSION_PRUNE_THRESHOLD: usize = 1024;
+
+/// 触发限流的范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitScope {
+    Tool,
+    Session,
+}
+
+/// 触发限流的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitKind {
+    RequestsPerMinute,
+    Concurrency,
+}
+
+/// 限流结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimited {
+    pub scope: LimitScope,
+    pub kind: LimitKind,
+    /// 建议客户端重试前等待的秒数
+    pub retry_after_secs: u64,
+}
+
+impl RateLimited {
+    pub fn message(&self) -> String {
+        let scope = match self.scope {
+            LimitScope::Tool => "代理",
+            LimitScope::Session => "会话",
+        };
+        match self.kind {
+            LimitKind::RequestsPerMinute => format!(
+                "{scope}每分钟请求数已达上限，请在 {} 秒后重试",
+                self.retry_after_secs
+            ),
+            LimitKind::Concurrency => format!("{scope}并发请求数已达上限，请稍后重试"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Counter {
+    window: VecDeque<Instant>,
+    active: u32,
+}
+
+impl Counter {
+    fn trim(&mut self, now: Instant) {
+        while self
+            .window
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= WINDOW)
+        {
+            self.window.pop_front();
+        }
+    }
+
+    fn check(
+        &self,
+        now: Instant,
+        rpm: Option<u32>,
+        max_concurrent: Option<u32>,
+        scope: LimitScope,
+    ) -> Result<(), RateLimited> {
+        if let Some(rpm) = rpm.filter(|v| *v > 0) {
+            if self.window.len() >= rpm as usize {
+                let oldest = self.window.front().copied().unwrap_or(now);
+                let wait = WINDOW.saturating_sub(now.duration_since(oldest));
+                return Err(RateLimited {
+                    scope,
+                    kind: LimitKind::RequestsPerMinute,
+                    retry_after_secs: wait.as_secs_f64().ceil().max(1.0) as u64,
+                });
+            }
+        }
+        if let Some(max) = max_concurrent.filter(|v| *v > 0) {
+            if self.active >= max {
+                return Err(RateLimited {
+                    scope,
+                    kind: LimitKind::Concurrency,
+                    retry_after_secs: 1,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, now: Instant) {
+        self.window.push_back(now);
+        self.active += 1;
+    }
+
+    fn is_idle(&self) -> bool {
+        self.window.is_empty() && self.active == 0
+    }
+}
+
+#[derive(Default)]
+struct LimiterState {
+    tool: Counter,
+    sessions: HashMap<String, Counter>,
+}
+
+/// 单个代理实例的限流器
+#[derive(Default)]
+pub struct RateLimiter {
+    state: Arc<Mutex<LimiterState>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 尝试获取请求许可
+    ///
+    /// 未配置任何限制时直接放行；没有会话 ID 的请求只受工具级限制
+    pub fn try_acquire(
+        &self,
+        session_id: Option<&str>,
+        config: &RateLimitConfig,
+    ) -> Result<RatePermit, RateLimited> {
+        self.try_acquire_at(Instant::now(), session_id, config)
+    }
+
+    fn try_acquire_at(
+        &self,
+        now: Instant,
+        session_id: Option<&str>,
+        config: &RateLimitConfig,
+    ) -> Result<RatePermit, RateLimited> {
+        if !config.is_enabled() {
+            return Ok(RatePermit {
+                state: None,
+                session_id: None,
+            });
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if state.sessions.len() > SESSION_PRUNE_THRESHOLD {
+            state.sessions.retain(|_, counter| {
+                counter.trim(now);
+                !counter.is_idle()
+            });
+        }
+
+        state.tool.trim(now);
+        state.tool.check(
+            now,
+            config.requests_per_minute,
+            config.max_concurrent_requests,
+            LimitScope::Tool,
+        )?;
+
+        let session_id = session_id.filter(|_| config.has_session_limits());
+        if let Some(session_id) = session_id {
+            let counter = state.sessions.entry(session_id.to_string()).or_default();
+            counter.trim(now);
+            counter.check(
+                now,
+                config.session_requests_per_minute,
+                config.session_max_concurrent_requests,
+                LimitScope::Session,
+            )?;
+            counter.record(now);
+        }
+        state.tool.record(now);
+
+        Ok(RatePermit {
+            state: Some(Arc::clone(&self.state)),
+            session_id: session_id.map(|s| s.to_string()),
+        })
+    }
+}
+
+/// 请求许可（释放时归还并发名额）
+pub struct RatePermit {
+    state: Option<Arc<Mutex<LimiterState>>>,
+    session_id: Option<String>,
+}
+
+impl Drop for RatePermit {
+    fn drop(&mut self) {
+        let Some(state) = self.state.take() else {
+            return;
+        };
+        let mut state = state.lock().unwrap();
+        state.tool.active = state.tool.active.saturating_sub(1);
+        if let Some(session_id) = &self.session_id {
+            if let Some(counter) = state.sessions.get_mut(session_id) {
+                counter.active = counter.active.saturating_sub(1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_limits_always_pass() {
+        let limiter = RateLimiter::new();
+        let config = RateLimitConfig::default();
+        let permits: Vec<_> = (0..100)
+            .map(|_| limiter.try_acquire(Some("s"), &config).unwrap())
+            .collect();
+        assert_eq!(permits.len(), 100);
+    }
+
+    #[test]
+    fn test_requests_per_minute_window() {
+        let limiter = RateLimiter::new();
+        let config = RateLimitConfig {
+            requests_per_minute: Some(2),
+            ..Default::default()
+        };
+        let start = Instant::now();
+
+        drop(limiter.try_acquire_at(start, None, &config).unwrap());
+        drop(
+            limiter
+                .try_acquire_at(start + Duration::from_secs(10), None, &config)
+                .unwrap(),
+        );
+        let limited = limiter
+            .try_acquire_at(start + Duration::from_secs(20), None, &config)
+            .err()
+            .unwrap();
+        assert_eq!(limited.kind, LimitKind::RequestsPerMinute);
+        assert_eq!(limited.retry_after_secs, 40);
+
+        // 第一个请求滑出窗口后恢复
+        assert!(limiter
+            .try_acquire_at(start + Duration::from_secs(61), None, &config)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_session_concurrency_released_on_drop() {
+        let limiter = RateLimiter::new();
+        let config = RateLimitConfig {
+            session_max_concurrent_requests: Some(1),
+            ..Default::default()
+        };
+
+        let permit = limiter.try_acquire(Some("a"), &config).unwrap();
+        let limited = limiter.try_acquire(Some("a"), &config).err().unwrap();
+        assert_eq!(limited.scope, LimitScope::Session);
+        assert_eq!(limited.kind, LimitKind::Concurrency);
+
+        // 其他会话不受影响
+        assert!(limiter.try_acquire(Some("b"), &config).is_ok());
+
+        drop(permit);
+        assert!(limiter.try_acquire(Some("a"), &config).is_ok());
+    }
+}
@@ -0,0 +1,268 @@
+// 代理实例运行状态
+//
+// ProxyStats 在代理实例的所有连接和请求之间共享，记录：
+// - 运行状态、监听地址与启动时间
+// - 活跃连接数、请求总数、错误数与最近一次错误
+// - 当前使用的上游
+//
+// 状态变化时通过 StatusNotifier 通知订阅方（由 main.rs 转发为 Tauri 事件）
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use serde::Serialize;
+use tokio::sync::watch;
+
+use crate::services::session::ProxyRequestRecord;
+
+/// 代理实例运行状态快照
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProxyInstanceStatus {
+    pub running: bool,
+    /// 实际监听地址（如 127.0.0.1:8787）
+    pub bound_address: Option<String>,
+    /// 启动时间（Unix 秒）
+    pub started_at: Option<i64>,
+    pub uptime_secs: u64,
+    pub active_connections: usize,
+    /// 被来源过滤拒绝的连接数
+    pub rejected_connections: u64,
+    pub total_requests: u64,
+    pub error_count: u64,
+    pub last_error: Option<String>,
+    /// 最近一次错误时间（Unix 秒）
+    pub last_error_at: Option<i64>,
+    /// 最近一次成功使用的上游 base_url
+    pub current_upstream: Option<String>,
+    /// 启用 TLS 时的证书 SHA-256 指纹
+    pub tls_fingerprint: Option<String>,
+}
+
+/// 状态变化通知器（多个代理实例共享同一个通知通道）
+#[derive(Clone)]
+pub struct StatusNotifier {
+    tx: Arc<watch::Sender<()>>,
+}
+
+impl StatusNotifier {
+    pub fn new() -> Self {
+        let (tx, _) = watch::channel(());
+        Self { tx: Arc::new(tx) }
+    }
+
+    /// 通知订阅方状态已变化
+    pub fn notify(&self) {
+        // send_modify 在没有订阅者时也不会失败
+        self.tx.send_modify(|_| {});
+    }
+
+    /// 订阅状态变化
+    pub fn subscribe(&self) -> watch::Receiver<()> {
+        self.tx.subscribe()
+    }
+}
+
+impl Default for StatusNotifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Default)]
+struct StatsInner {
+    bound_address: Option<SocketAddr>,
+    started_at: Option<(Instant, i64)>,
+    last_error: Option<String>,
+    last_error_at: Option<i64>,
+    current_upstream: Option<String>,
+    tls_fingerprint: Option<String>,
+}
+
+/// 单个代理实例的运行统计
+pub struct ProxyStats {
+    running: AtomicBool,
+    active_connections: AtomicUsize,
+    rejected_connections: AtomicU64,
+    total_requests: AtomicU64,
+    error_count: AtomicU64,
+    inner: Mutex<StatsInner>,
+    notifier: StatusNotifier,
+}
+
+impl ProxyStats {
+    pub fn new(notifier: StatusNotifier) -> Self {
+        Self {
+            running: AtomicBool::new(false),
+            active_connections: AtomicUsize::new(0),
+            rejected_connections: AtomicU64::new(0),
+            total_requests: AtomicU64::new(0),
+            error_count: AtomicU64::new(0),
+            inner: Mutex::new(StatsInner::default()),
+            notifier,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    pub fn active_connections(&self) -> usize {
+        self.active_connections.load(Ordering::SeqCst)
+    }
+
+    /// 监听成功后调用
+    pub fn mark_started(&self, addr: SocketAddr, tls_fingerprint: Option<String>) {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.bound_address = Some(addr);
+            inner.tls_fingerprint = tls_fingerprint;
+            inner.started_at = Some((Instant::now(), chrono::Utc::now().timestamp()));
+        }
+        self.running.store(true, Ordering::SeqCst);
+        self.notifier.notify();
+    }
+
+    /// 开始停止流程时调用（排空期间连接数仍会继续更新）
+    pub fn mark_stopped(&self) {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.bound_address = None;
+            inner.started_at = None;
+            inner.tls_fingerprint = None;
+        }
+        self.running.store(false, Ordering::SeqCst);
+        self.notifier.notify();
+    }
+
+    pub fn connection_opened(&self) {
+        self.active_connections.fetch_add(1, Ordering::SeqCst);
+        self.notifier.notify();
+    }
+
+    pub fn connection_closed(&self) {
+        self.active_connections.fetch_sub(1, Ordering::SeqCst);
+        self.notifier.notify();
+    }
+
+    /// 连接被来源过滤拒绝
+    pub fn connection_rejected(&self) {
+        self.rejected_connections.fetch_add(1, Ordering::SeqCst);
+        self.notifier.notify();
+    }
+
+    pub fn request_started(&self) {
+        self.total_requests.fetch_add(1, Ordering::SeqCst);
+        self.notifier.notify();
+    }
+
+    /// 记录成功使用的上游
+    pub fn set_current_upstream(&self, base_url: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.current_upstream.as_deref() != Some(base_url) {
+            inner.current_upstream = Some(base_url.to_string());
+            drop(inner);
+            self.notifier.notify();
+        }
+    }
+
+    /// 请求结束时调用（由 RequestAudit 在写入审计记录前触发）
+    pub fn request_finished(&self, record: &ProxyRequestRecord, message: Option<&str>) {
+        let Some(kind) = record.error_kind.as_deref() else {
+            return;
+        };
+
+        self.error_count.fetch_add(1, Ordering::SeqCst);
+        let mut description = match record.status_code {
+            Some(status) => format!("{kind} (HTTP {status})"),
+            None => kind.to_string(),
+        };
+        if let Some(message) = message.filter(|m| !m.is_empty()) {
+            description.push_str(": ");
+            description.push_str(message);
+        }
+
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.last_error = Some(description);
+            inner.last_error_at = Some(record.timestamp / 1000);
+        }
+        self.notifier.notify();
+    }
+
+    /// 生成状态快照
+    pub fn snapshot(&self) -> ProxyInstanceStatus {
+        let inner = self.inner.lock().unwrap();
+        ProxyInstanceStatus {
+            running: self.is_running(),
+            bound_address: inner.bound_address.map(|addr| addr.to_string()),
+            started_at: inner.started_at.map(|(_, ts)| ts),
+            uptime_secs: inner
+                .started_at
+                .map(|(instant, _)| instant.elapsed().as_secs())
+                .unwrap_or(0),
+            active_connections: self.active_connections(),
+            rejected_connections: self.rejected_connections.load(Ordering::SeqCst),
+            total_requests: self.total_requests.load(Ordering::SeqCst),
+            error_count: self.error_count.load(Ordering::SeqCst),
+            last_error: inner.last_error.clone(),
+            last_error_at: inner.last_error_at,
+            current_upstream: inner.current_upstream.clone(),
+            tls_fingerprint: inner.tls_fingerprint.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_snapshot() {
+        let notifier = StatusNotifier::new();
+        let rx = notifier.subscribe();
+        let stats = ProxyStats::new(notifier);
+
+        stats.mark_started(
+            SocketAddr::from(([127, 0, 0, 1], 8787)),
+            Some("AB:CD".to_string()),
+        );
+        stats.connection_opened();
+        stats.connection_rejected();
+        stats.request_started();
+        stats.request_finished(
+            &ProxyRequestRecord {
+                status_code: Some(502),
+                error_kind: Some("upstream_error".to_string()),
+                timestamp: 1_700_000_000_000,
+                ..Default::default()
+            },
+            None,
+        );
+        stats.set_current_upstream("https://relay.example.com");
+        assert!(rx.has_changed().unwrap());
+
+        let status = stats.snapshot();
+        assert!(status.running);
+        assert_eq!(status.bound_address.as_deref(), Some("127.0.0.1:8787"));
+        assert_eq!(status.active_connections, 1);
+        assert_eq!(status.rejected_connections, 1);
+        assert_eq!(status.total_requests, 1);
+        assert_eq!(status.error_count, 1);
+        assert_eq!(
+            status.last_error.as_deref(),
+            Some("upstream_error (HTTP 502)")
+        );
+        assert_eq!(status.last_error_at, Some(1_700_000_000));
+        assert_eq!(status.tls_fingerprint.as_deref(), Some("AB:CD"));
+
+        stats.connection_closed();
+        stats.mark_stopped();
+        let status = stats.snapshot();
+        assert!(!status.running);
+        assert_eq!(status.active_connections, 0);
+        assert!(status.bound_address.is_none());
+        assert!(status.tls_fingerprint.is_none());
+    }
+}
@@ -0,0 +1,320 @@
+// 透明代理 TLS
+//
+// 开放公网访问（allow_public）时，本地密钥会以明文在局域网中传输。
+// 启用 TLS 后：
+// - 使用用户提供的证书/私钥，或自动生成保存在 ~/.duckcoding/tls 的自签名证书
+// - 非本机连接必须使用 TLS；本机连接仍可使用明文 HTTP（工具配置中的 127.0.0.1 地址无需改动）
+// - 证书 SHA-256 指纹可导出，供客户端固定（pinning）
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::ServerConfig;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::net::TcpStream;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+
+use crate::models::TlsConfig;
+use crate::utils::config::config_dir;
+
+const SELF_SIGNED_CERT_FILE: &str = "proxy-cert.pem";
+const SELF_SIGNED_KEY_FILE: &str = "proxy-key.pem";
+const SELF_SIGNED_COMMON_NAME: &str = "DuckCoding Transparent Proxy";
+
+/// 自签名证书有效期（年）
+const SELF_SIGNED_VALID_YEARS: i32 = 10;
+
+/// 识别协议及完成 TLS 握手的超时
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// TLS 记录类型：握手（ClientHello 的第一个字节）
+const TLS_HANDSHAKE_RECORD: u8 = 0x16;
+
+/// 证书信息（用于导出指纹）
+#[derive(Debug, Clone, Serialize)]
+pub struct TlsCertificateInfo {
+    pub cert_path: String,
+    pub self_signed: bool,
+    /// 叶子证书 DER 的 SHA-256 指纹（大写十六进制，冒号分隔）
+    pub fingerprint_sha256: String,
+    /// 证书文件内容（PEM，用户证书可能包含完整证书链）
+    pub certificate_pem: String,
+}
+
+/// 已加载的 TLS 配置
+pub struct TlsMaterial {
+    pub acceptor: TlsAcceptor,
+    pub info: TlsCertificateInfo,
+}
+
+/// 接受的连接
+pub enum ProxyStream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+/// 自签名证书保存目录（~/.duckcoding/tls）
+pub fn tls_dir() -> Result<PathBuf> {
+    let dir = config_dir().map_err(anyhow::Error::msg)?.join("tls");
+    // 目录中有私钥，Unix 下仅所有者可访问（已存在的目录同样收紧权限）
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&dir)
+            .context("创建 TLS 证书目录失败")?;
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))
+            .context("设置 TLS 证书目录权限失败")?;
+    }
+    #[cfg(not(unix))]
+    std::fs::create_dir_all(&dir).context("创建 TLS 证书目录失败")?;
+    Ok(dir)
+}
+
+/// 确定证书和私钥路径（自签名证书不存在时生成）
+fn resolve_paths(config: &TlsConfig) -> Result<(PathBuf, PathBuf, bool)> {
+    match (&config.cert_path, &config.key_path) {
+        (Some(cert), Some(key)) => Ok((PathBuf::from(cert), PathBuf::from(key), false)),
+        (None, None) => {
+            let dir = tls_dir()?;
+            let cert = dir.join(SELF_SIGNED_CERT_FILE);
+            let key = dir.join(SELF_SIGNED_KEY_FILE);
+            if !cert.exists() || !key.exists() {
+                generate_self_signed(&cert, &key)?;
+                tracing::info!(cert = %cert.display(), "已生成透明代理自签名证书");
+            }
+            Ok((cert, key, true))
+        }
+        _ => anyhow::bail!("TLS 证书和私钥路径需要同时配置"),
+    }
+}
+
+/// 加载 TLS 证书并构建 acceptor
+pub fn load(config: &TlsConfig) -> Result<TlsMaterial> {
+    let (cert_path, key_path, self_signed) = resolve_paths(config)?;
+    load_from_paths(&cert_path, &key_path, self_signed)
+}
+
+/// 获取证书信息（未启用 TLS 时也可导出，便于提前固定指纹）
+pub fn certificate_info(config: &TlsConfig) -> Result<TlsCertificateInfo> {
+    let (cert_path, _, self_signed) = resolve_paths(config)?;
+    let certs = read_certs(&cert_path)?;
+    build_info(&cert_path, &certs, self_signed)
+}
+
+fn load_from_paths(cert_path: &Path, key_path: &Path, self_signed: bool) -> Result<TlsMaterial> {
+    let certs = read_certs(cert_path)?;
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .with_context(|| format!("读取私钥失败: {}", key_path.display()))?;
+    let info = build_info(cert_path, &certs, self_signed)?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut server_config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .context("初始化 TLS 配置失败")?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("证书与私钥不匹配")?;
+    // 与明文监听一致，同时支持 HTTP/2 和 HTTP/1.1
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(TlsMaterial {
+        acceptor: TlsAcceptor::from(Arc::new(server_config)),
+        info,
+    })
+}
+
+fn read_certs(cert_path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .with_context(|| format!("读取证书失败: {}", cert_path.display()))?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .with_context(|| format!("解析证书失败: {}", cert_path.display()))?;
+    if certs.is_empty() {
+        anyhow::bail!("证书文件中没有证书: {}", cert_path.display());
+    }
+    Ok(certs)
+}
+
+fn build_info(
+    cert_path: &Path,
+    certs: &[CertificateDer<'_>],
+    self_signed: bool,
+) -> Result<TlsCertificateInfo> {
+    let certificate_pem = std::fs::read_to_string(cert_path)
+        .with_context(|| format!("读取证书失败: {}", cert_path.display()))?;
+    Ok(TlsCertificateInfo {
+        cert_path: cert_path.to_string_lossy().to_string(),
+        self_signed,
+        fingerprint_sha256: fingerprint_sha256(&certs[0]),
+        certificate_pem,
+    })
+}
+
+/// 计算证书 SHA-256 指纹（格式与浏览器和 openssl 输出一致）
+pub fn fingerprint_sha256(cert: &[u8]) -> String {
+    Sha256::digest(cert)
+        .iter()
+        .map(|b| format!("{b:02X}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// 生成自签名证书（包含 localhost 与本机回环地址）
+fn generate_self_signed(cert_path: &Path, key_path: &Path) -> Result<()> {
+    use chrono::Datelike;
+
+    let mut params = rcgen::CertificateParams::new(vec![
+        "localhost".to_string(),
+        "127.0.0.1".to_string(),
+        "::1".to_string(),
+    ])
+    .context("生成证书参数失败")?;
+    params
+        .distinguished_name
+        .push(rcgen::DnType::CommonName, SELF_SIGNED_COMMON_NAME);
+    let today = chrono::Utc::now().date_naive();
+    params.not_before = rcgen::date_time_ymd(today.year(), today.month() as u8, 1);
+    params.not_after = rcgen::date_time_ymd(
+        today.year() + SELF_SIGNED_VALID_YEARS,
+        today.month() as u8,
+        1,
+    );
+
+    let key_pair = rcgen::KeyPair::generate().context("生成私钥失败")?;
+    let cert = params
+        .self_signed(&key_pair)
+        .context("生成自签名证书失败")?;
+
+    write_private(key_path, key_pair.serialize_pem().as_bytes())?;
+    std::fs::write(cert_path, cert.pem())
+        .with_context(|| format!("写入证书失败: {}", cert_path.display()))?;
+    Ok(())
+}
+
+/// 写入私钥（Unix 下创建时即为仅所有者可读写，不存在可被他人读取的窗口）
+fn write_private(path: &Path, content: &[u8]) -> Result<()> {
+    use std::io::Write;
+
+    // 证书缺失而私钥仍在时重新生成，旧私钥的权限不可信，先删除再新建
+    match std::fs::remove_file(path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(e).with_context(|| format!("删除旧私钥失败: {}", path.display()));
+        }
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .and_then(|mut file| {
+            file.write_all(content)?;
+            file.sync_all()
+        })
+        .with_context(|| format!("写入私钥失败: {}", path.display()))
+}
+
+/// 识别连接协议并完成 TLS 握手
+///
+/// 以 TLS 握手开头的连接走 TLS；明文连接只接受来自本机的
+pub async fn accept(
+    acceptor: &TlsAcceptor,
+    stream: TcpStream,
+    peer: SocketAddr,
+) -> Result<ProxyStream> {
+    tokio::time::timeout(HANDSHAKE_TIMEOUT, async {
+        let mut first = [0u8; 1];
+        let n = stream.peek(&mut first).await.context("读取连接数据失败")?;
+        if n == 0 {
+            anyhow::bail!("连接已关闭");
+        }
+
+        if first[0] == TLS_HANDSHAKE_RECORD {
+            let tls = acceptor.accept(stream).await.context("TLS 握手失败")?;
+            Ok(ProxyStream::Tls(Box::new(tls)))
+        } else if peer.ip().is_loopback() {
+            Ok(ProxyStream::Plain(stream))
+        } else {
+            anyhow::bail!("已启用 TLS，拒绝来自 {peer} 的明文连接")
+        }
+    })
+    .await
+    .context("TLS 握手超时")?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_self_signed_certificate_loads() {
+        let dir = tempdir().unwrap();
+        let cert = dir.path().join(SELF_SIGNED_CERT_FILE);
+        let key = dir.path().join(SELF_SIGNED_KEY_FILE);
+        generate_self_signed(&cert, &key).unwrap();
+
+        let material = load_from_paths(&cert, &key, true).unwrap();
+        assert!(material.info.self_signed);
+        assert!(material
+            .info
+            .certificate_pem
+            .starts_with("-----BEGIN CERTIFICATE-----"));
+
+        // 32 字节，每字节两位十六进制，冒号分隔
+        let fingerprint = &material.info.fingerprint_sha256;
+        assert_eq!(fingerprint.len(), 32 * 3 - 1);
+        assert_eq!(fingerprint.split(':').count(), 32);
+
+        // 重新读取证书时指纹不变
+        let certs = read_certs(&cert).unwrap();
+        assert_eq!(&fingerprint_sha256(&certs[0]), fingerprint);
+
+        // 私钥仅所有者可读写，已存在的私钥可被重新生成覆盖
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&key, std::fs::Permissions::from_mode(0o644)).unwrap();
+            std::fs::remove_file(&cert).unwrap();
+            generate_self_signed(&cert, &key).unwrap();
+            let mode = std::fs::metadata(&key).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_mismatched_key_rejected() {
+        let dir = tempdir().unwrap();
+        let (cert_a, key_a) = (dir.path().join("a.pem"), dir.path().join("a.key"));
+        let (cert_b, key_b) = (dir.path().join("b.pem"), dir.path().join("b.key"));
+        generate_self_signed(&cert_a, &key_a).unwrap();
+        generate_self_signed(&cert_b, &key_b).unwrap();
+
+        assert!(load_from_paths(&cert_a, &key_b, false).is_err());
+    }
+
+    #[test]
+    fn test_cert_and_key_paths_required_together() {
+        let config = TlsConfig {
+            enabled: true,
+            cert_path: Some("/tmp/cert.pem".to_string()),
+            key_path: None,
+        };
+        assert!(load(&config).is_err());
+    }
+}
@@ -1,5 +1,8 @@
 // 透明代理配置管理服务
-use crate::models::{GlobalConfig, Tool, ToolProxyConfig};
+use crate::models::{ConfigLayout, GlobalConfig, Tool, ToolProxyConfig};
+use crate::services::config::ConfigService;
+use crate::services::profile_store::ProfileStore;
+use crate::utils::write_atomic;
 use anyhow::{Context, Result};
 use serde_json::{Map, Value};
 use std::collections::HashMap;
@@ -186,22 +189,22 @@ impl TransparentProxyConfigService {
 
     /// 读取工具的当前配置（API Key 和 Base URL）
     fn read_tool_config(tool: &Tool) -> Result<(String, String)> {
-        match tool.id.as_str() {
-            "claude-code" => Self::read_claude_config(tool),
-            "codex" => Self::read_codex_config(tool),
-            "gemini-cli" => Self::read_gemini_config(tool),
-            _ => anyhow::bail!("不支持的工具: {}", tool.id),
+        match tool.config_layout {
+            ConfigLayout::JsonEnv | ConfigLayout::JsonPointer => Self::read_json_config(tool),
+            ConfigLayout::CodexToml => Self::read_codex_config(tool),
+            ConfigLayout::Dotenv => Self::read_dotenv_config(tool),
         }
     }
 
     /// 写入代理配置到工具
     fn write_proxy_config(tool: &Tool, port: u16, api_key: &str) -> Result<()> {
         let base_url = format!("http://127.0.0.1:{port}");
-        match tool.id.as_str() {
-            "claude-code" => Self::write_claude_config(tool, api_key, &base_url),
-            "codex" => Self::write_codex_config(tool, api_key, &base_url),
-            "gemini-cli" => Self::write_gemini_config(tool, api_key, &base_url),
-            _ => anyhow::bail!("不支持的工具: {}", tool.id),
+        match tool.config_layout {
+            ConfigLayout::JsonEnv | ConfigLayout::JsonPointer => {
+                Self::write_json_config(tool, api_key, &base_url)
+            }
+            ConfigLayout::CodexToml => Self::write_codex_config(tool, api_key, &base_url),
+            ConfigLayout::Dotenv => Self::write_dotenv_config(tool, api_key, &base_url),
         }
     }
 
@@ -212,50 +215,45 @@ impl TransparentProxyConfigService {
         base_url: &str,
         model_provider: Option<&str>,
     ) -> Result<()> {
-        match tool.id.as_str() {
-            "claude-code" => Self::write_claude_config(tool, api_key, base_url),
-            "codex" => {
+        match tool.config_layout {
+            ConfigLayout::JsonEnv | ConfigLayout::JsonPointer => {
+                Self::write_json_config(tool, api_key, base_url)
+            }
+            ConfigLayout::CodexToml => {
                 Self::write_codex_config_with_provider(tool, api_key, base_url, model_provider)
             }
-            "gemini-cli" => Self::write_gemini_config(tool, api_key, base_url),
-            _ => anyhow::bail!("不支持的工具: {}", tool.id),
+            ConfigLayout::Dotenv => Self::write_dotenv_config(tool, api_key, base_url),
         }
     }
 
-    // ==================== Claude Code ====================
+    // ==================== JSON（Claude Code、opencode、Crush 等） ====================
 
-    fn read_claude_config(tool: &Tool) -> Result<(String, String)> {
+    fn read_json_config(tool: &Tool) -> Result<(String, String)> {
         let config_path = tool.config_dir.join(&tool.config_file);
 
         if !config_path.exists() {
-            anyhow::bail!("Claude Code 配置文件不存在，请先配置 API");
+            anyhow::bail!("{} 配置文件不存在，请先配置 API", tool.name);
         }
 
-        let content = fs::read_to_string(&config_path).context("读取 Claude Code 配置失败")?;
-        let settings: Value =
-            serde_json::from_str(&content).context("解析 Claude Code 配置失败")?;
-
-        let env = settings
-            .get("env")
-            .and_then(|v| v.as_object())
-            .ok_or_else(|| anyhow::anyhow!("配置文件缺少 env 字段"))?;
+        let content = fs::read_to_string(&config_path)
+            .with_context(|| format!("读取 {} 配置失败", tool.name))?;
+        let settings: Value = serde_json::from_str(&content)
+            .with_context(|| format!("解析 {} 配置失败", tool.name))?;
 
-        let api_key = env
-            .get("ANTHROPIC_AUTH_TOKEN")
-            .and_then(|v| v.as_str())
+        let api_key = tool
+            .json_config_value(&settings, &tool.env_vars.api_key)
             .ok_or_else(|| anyhow::anyhow!("未找到 API Key"))?
             .to_string();
 
-        let base_url = env
-            .get("ANTHROPIC_BASE_URL")
-            .and_then(|v| v.as_str())
+        let base_url = tool
+            .json_config_value(&settings, &tool.env_vars.base_url)
             .ok_or_else(|| anyhow::anyhow!("未找到 Base URL"))?
             .to_string();
 
         Ok((api_key, base_url))
     }
 
-    fn write_claude_config(tool: &Tool, api_key: &str, base_url: &str) -> Result<()> {
+    fn write_json_config(tool: &Tool, api_key: &str, base_url: &str) -> Result<()> {
         let config_path = tool.config_dir.join(&tool.config_file);
 
         let mut settings = if config_path.exists() {
@@ -265,27 +263,11 @@ impl TransparentProxyConfigService {
             Value::Object(Map::new())
         };
 
-        if !settings.is_object() {
-            settings = serde_json::json!({});
-        }
-
-        let obj = settings.as_object_mut().unwrap();
-        if !obj.contains_key("env") {
-            obj.insert("env".to_string(), Value::Object(Map::new()));
-        }
-
-        let env = obj.get_mut("env").unwrap().as_object_mut().unwrap();
-        env.insert(
-            "ANTHROPIC_AUTH_TOKEN".to_string(),
-            Value::String(api_key.to_string()),
-        );
-        env.insert(
-            "ANTHROPIC_BASE_URL".to_string(),
-            Value::String(base_url.to_string()),
-        );
+        tool.set_json_config_value(&mut settings, &tool.env_vars.api_key, api_key);
+        tool.set_json_config_value(&mut settings, &tool.env_vars.base_url, base_url);
 
         let json = serde_json::to_string_pretty(&settings)?;
-        fs::write(&config_path, json)?;
+        write_atomic(&config_path, json)?;
 
         Ok(())
     }
@@ -395,7 +377,7 @@ impl TransparentProxyConfigService {
             );
         }
 
-        fs::write(&auth_path, serde_json::to_string_pretty(&auth_data)?)?;
+        write_atomic(&auth_path, serde_json::to_string_pretty(&auth_data)?)?;
 
         // 更新 config.toml（使用 toml_edit 保留注释）
         let mut doc = if config_path.exists() {
@@ -470,21 +452,22 @@ impl TransparentProxyConfigService {
             provider_table.insert("requires_openai_auth", toml_edit::value(true));
         }
 
-        fs::write(&config_path, doc.to_string())?;
+        write_atomic(&config_path, doc.to_string())?;
 
         Ok(())
     }
 
-    // ==================== Gemini CLI ====================
+    // ==================== .env（Gemini CLI 等） ====================
 
-    fn read_gemini_config(tool: &Tool) -> Result<(String, String)> {
+    fn read_dotenv_config(tool: &Tool) -> Result<(String, String)> {
         let env_path = tool.config_dir.join(".env");
 
         if !env_path.exists() {
-            anyhow::bail!("Gemini CLI .env 不存在，请先配置 API");
+            anyhow::bail!("{} .env 不存在，请先配置 API", tool.name);
         }
 
-        let content = fs::read_to_string(&env_path).context("读取 Gemini .env 失败")?;
+        let content = fs::read_to_string(&env_path)
+            .with_context(|| format!("读取 {} .env 失败", tool.name))?;
 
         let mut env_vars = HashMap::new();
         for line in content.lines() {
@@ -497,19 +480,19 @@ impl TransparentProxyConfigService {
         }
 
         let api_key = env_vars
-            .get("GEMINI_API_KEY")
-            .ok_or_else(|| anyhow::anyhow!("Gemini .env 中未找到 GEMINI_API_KEY"))?
+            .get(&tool.env_vars.api_key)
+            .ok_or_else(|| anyhow::anyhow!(".env 中未找到 {}", tool.env_vars.api_key))?
             .clone();
 
         let base_url = env_vars
-            .get("GOOGLE_GEMINI_BASE_URL")
-            .ok_or_else(|| anyhow::anyhow!("Gemini .env 中未找到 GOOGLE_GEMINI_BASE_URL"))?
+            .get(&tool.env_vars.base_url)
+            .ok_or_else(|| anyhow::anyhow!(".env 中未找到 {}", tool.env_vars.base_url))?
             .clone();
 
         Ok((api_key, base_url))
     }
 
-    fn write_gemini_config(tool: &Tool, api_key: &str, base_url: &str) -> Result<()> {
+    fn write_dotenv_config(tool: &Tool, api_key: &str, base_url: &str) -> Result<()> {
         let env_path = tool.config_dir.join(".env");
 
         // 确保目录存在
@@ -530,8 +513,8 @@ impl TransparentProxyConfigService {
         }
 
         // 更新 API 相关字段
-        env_vars.insert("GEMINI_API_KEY".to_string(), api_key.to_string());
-        env_vars.insert("GOOGLE_GEMINI_BASE_URL".to_string(), base_url.to_string());
+        env_vars.insert(tool.env_vars.api_key.clone(), api_key.to_string());
+        env_vars.insert(tool.env_vars.base_url.clone(), base_url.to_string());
 
         // 写入 .env
         let mut env_content = String::new();
@@ -539,54 +522,16 @@ impl TransparentProxyConfigService {
             env_content.push_str(&format!("{key}={value}\n"));
         }
 
-        fs::write(&env_path, env_content)?;
+        write_atomic(&env_path, env_content)?;
 
         Ok(())
     }
 
-    /// 从备份配置文件读取真实的 API 配置（仅 Claude Code）
+    /// 从配置档案读取真实的 API 配置
     pub fn read_real_config_from_backup(
         tool: &Tool,
         profile_name: &str,
     ) -> Result<(String, String)> {
-        if tool.id != "claude-code" {
-            anyhow::bail!("从备份读取配置目前仅支持 Claude Code");
-        }
-
-        let backup_path = tool.backup_path(profile_name);
-
-        if !backup_path.exists() {
-            anyhow::bail!("备份配置文件不存在: {backup_path:?}");
-        }
-
-        let content = fs::read_to_string(&backup_path).context("读取备份配置失败")?;
-        let backup_data: Value = serde_json::from_str(&content).context("解析备份配置失败")?;
-
-        // 兼容新旧格式
-        let api_key = backup_data
-            .get("ANTHROPIC_AUTH_TOKEN")
-            .and_then(|v| v.as_str())
-            .or_else(|| {
-                backup_data
-                    .get("env")
-                    .and_then(|env| env.get("ANTHROPIC_AUTH_TOKEN"))
-                    .and_then(|v| v.as_str())
-            })
-            .ok_or_else(|| anyhow::anyhow!("备份配置中未找到 API Key"))?
-            .to_string();
-
-        let base_url = backup_data
-            .get("ANTHROPIC_BASE_URL")
-            .and_then(|v| v.as_str())
-            .or_else(|| {
-                backup_data
-                    .get("env")
-                    .and_then(|env| env.get("ANTHROPIC_BASE_URL"))
-                    .and_then(|v| v.as_str())
-            })
-            .ok_or_else(|| anyhow::anyhow!("备份配置中未找到 Base URL"))?
-            .to_string();
-
-        Ok((api_key, base_url))
+        ConfigService::read_profile_api_config(&ProfileStore::default_store()?, tool, profile_name)
     }
 }
@@ -0,0 +1,187 @@
+// 上游健康状态与选择
+//
+// UpstreamPool 负责：
+// - 记录失败的上游并在冷却期内暂停使用
+// - 在健康上游之间按权重平滑轮询，决定首选上游
+// - 生成本次请求的尝试顺序（健康上游优先，冷却中的上游兜底）
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::models::UpstreamConfig;
+
+#[derive(Default)]
+struct PoolState {
+    /// base_url -> 冷却截止时间
+    benched_until: HashMap<String, Instant>,
+    /// base_url -> 平滑加权轮询的当前权重
+    current_weights: HashMap<String, i64>,
+}
+
+/// 单个代理实例的上游池
+#[derive(Default)]
+pub struct UpstreamPool {
+    state: Mutex<PoolState>,
+}
+
+fn pool_key(upstream: &UpstreamConfig) -> String {
+    upstream.base_url.trim_end_matches('/').to_string()
+}
+
+impl UpstreamPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 生成本次请求的上游尝试顺序
+    ///
+    /// 健康上游中按平滑加权轮询选出首选，其余健康上游按配置顺序排列；
+    /// 冷却中的上游按剩余冷却时间排在最后，保证全部失败时仍有上游可尝试
+    pub fn ordered(&self, candidates: &[UpstreamConfig]) -> Vec<UpstreamConfig> {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        state.benched_until.retain(|_, until| *until > now);
+
+        let (healthy, mut benched): (Vec<&UpstreamConfig>, Vec<&UpstreamConfig>) = candidates
+            .iter()
+            .partition(|u| !state.benched_until.contains_key(&pool_key(u)));
+
+        let mut ordered: Vec<UpstreamConfig> = Vec::with_capacity(candidates.len());
+
+        if let Some(first) = Self::pick_weighted(&mut state, &healthy) {
+            ordered.push(healthy[first].clone());
+            ordered.extend(
+                healthy
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| *i != first)
+                    .map(|(_, u)| (*u).clone()),
+            );
+        }
+
+        benched.sort_by_key(|u| state.benched_until.get(&pool_key(u)).copied());
+        ordered.extend(benched.into_iter().cloned());
+        ordered
+    }
+
+    /// 平滑加权轮询（nginx 算法），返回选中项的下标
+    ///
+    /// 只有显式配置了 weight 的上游参与轮询；少于两个时按配置顺序取第一个
+    fn pick_weighted(state: &mut PoolState, healthy: &[&UpstreamConfig]) -> Option<usize> {
+        let weighted: Vec<usize> = (0..healthy.len())
+            .filter(|&i| healthy[i].weight.is_some())
+            .collect();
+        if weighted.len() <= 1 {
+            return if healthy.is_empty() { None } else { Some(0) };
+        }
+
+        let total: i64 = weighted
+            .iter()
+            .map(|&i| healthy[i].effective_weight() as i64)
+            .sum();
+        let mut best: Option<(usize, i64)> = None;
+
+        for &i in &weighted {
+            let upstream = healthy[i];
+            let current = state.current_weights.entry(pool_key(upstream)).or_insert(0);
+            *current += upstream.effective_weight() as i64;
+            if best.is_none_or(|(_, w)| *current > w) {
+                best = Some((i, *current));
+            }
+        }
+
+        let (index, _) = best?;
+        if let Some(current) = state.current_weights.get_mut(&pool_key(healthy[index])) {
+            *current -= total;
+        }
+        Some(index)
+    }
+
+    /// 标记上游失败，在冷却期内暂停使用
+    pub fn mark_failure(&self, upstream: &UpstreamConfig, cooldown: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .benched_until
+            .insert(pool_key(upstream), Instant::now() + cooldown);
+    }
+
+    /// 标记上游成功，立即解除冷却
+    pub fn mark_success(&self, upstream: &UpstreamConfig) {
+        let mut state = self.state.lock().unwrap();
+        state.benched_until.remove(&pool_key(upstream));
+    }
+
+    /// 检查上游是否处于冷却中
+    pub fn is_benched(&self, upstream: &UpstreamConfig) -> bool {
+        let state = self.state.lock().unwrap();
+        state
+            .benched_until
+            .get(&pool_key(upstream))
+            .is_some_and(|until| *until > Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upstream(url: &str, weight: Option<u32>) -> UpstreamConfig {
+        UpstreamConfig {
+            base_url: url.to_string(),
+            api_key: "sk-test".to_string(),
+            weight,
+        }
+    }
+
+    #[test]
+    fn test_ordered_keeps_config_order_without_failures() {
+        let pool = UpstreamPool::new();
+        let candidates = vec![upstream("https://a", None), upstream("https://b", None)];
+
+        let ordered = pool.ordered(&candidates);
+        assert_eq!(ordered.len(), 2);
+        assert_eq!(ordered[0].base_url, "https://a");
+        assert_eq!(pool.ordered(&candidates)[0].base_url, "https://a");
+    }
+
+    #[test]
+    fn test_benched_upstream_moves_to_end() {
+        let pool = UpstreamPool::new();
+        let candidates = vec![upstream("https://a", None), upstream("https://b", None)];
+
+        pool.mark_failure(&candidates[0], Duration::from_secs(60));
+        assert!(pool.is_benched(&candidates[0]));
+
+        let ordered = pool.ordered(&candidates);
+        assert_eq!(ordered[0].base_url, "https://b");
+        assert_eq!(ordered[1].base_url, "https://a");
+
+        pool.mark_success(&candidates[0]);
+        assert!(!pool.is_benched(&candidates[0]));
+    }
+
+    #[test]
+    fn test_cooldown_expires() {
+        let pool = UpstreamPool::new();
+        let candidates = [upstream("https://a", None)];
+
+        pool.mark_failure(&candidates[0], Duration::from_millis(0));
+        assert!(!pool.is_benched(&candidates[0]));
+    }
+
+    #[test]
+    fn test_weighted_round_robin() {
+        let pool = UpstreamPool::new();
+        let candidates = vec![
+            upstream("https://a", Some(3)),
+            upstream("https://b", Some(1)),
+        ];
+
+        let firsts: Vec<String> = (0..4)
+            .map(|_| pool.ordered(&candidates)[0].base_url.clone())
+            .collect();
+        assert_eq!(firsts.iter().filter(|u| *u == "https://a").count(), 3);
+        assert_eq!(firsts.iter().filter(|u| *u == "https://b").count(), 1);
+    }
+}
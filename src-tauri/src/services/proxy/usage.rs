@@ -0,0 +1,278 @@
+// Token 用量解析
+//
+// 从上游响应中提取 token 用量，支持：
+// - Anthropic：message_start / message_delta 事件与非流式 usage
+// - OpenAI：Chat Completions 与 Responses API 的 usage
+// - Gemini：usageMetadata
+//
+// SSE 流按行增量解析，只保留未结束的最后一行，不缓存整个响应
+
+use serde_json::Value;
+
+use crate::services::session::{SessionEvent, TokenUsage, SESSION_MANAGER};
+
+/// 增量 token 用量解析器
+///
+/// 各家 API 在流式响应中上报的都是累计值，因此按字段取最大值合并
+#[derive(Debug, Default)]
+pub struct UsageParser {
+    usage: TokenUsage,
+    line_buffer: Vec<u8>,
+}
+
+impl UsageParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从完整的 JSON 响应体解析用量
+    pub fn from_json(body: &[u8]) -> Option<TokenUsage> {
+        let value = serde_json::from_slice::<Value>(body).ok()?;
+        let mut parser = Self::new();
+        match &value {
+            // Gemini 非 SSE 的 streamGenerateContent 返回 JSON 数组
+            Value::Array(items) => items.iter().for_each(|item| parser.absorb(item)),
+            _ => parser.absorb(&value),
+        }
+        parser.finish()
+    }
+
+    /// 输入一段 SSE 数据
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.line_buffer.extend_from_slice(chunk);
+
+        while let Some(pos) = self.line_buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.line_buffer.drain(..=pos).collect();
+            self.parse_line(&line);
+        }
+    }
+
+    /// 结束解析，返回用量（无任何用量数据时返回 None）
+    pub fn finish(mut self) -> Option<TokenUsage> {
+        if !self.line_buffer.is_empty() {
+            let line = std::mem::take(&mut self.line_buffer);
+            self.parse_line(&line);
+        }
+        if self.usage.is_empty() {
+            None
+        } else {
+            Some(self.usage)
+        }
+    }
+
+    fn parse_line(&mut self, line: &[u8]) {
+        let Ok(line) = std::str::from_utf8(line) else {
+            return;
+        };
+        let Some(data) = line.trim().strip_prefix("data:") else {
+            return;
+        };
+        let data = data.trim();
+        if data.is_empty() || data == "[DONE]" {
+            return;
+        }
+        if let Ok(value) = serde_json::from_str::<Value>(data) {
+            self.absorb(&value);
+        }
+    }
+
+    /// 从单个 JSON 事件/响应中提取用量
+    fn absorb(&mut self, value: &Value) {
+        match value["type"].as_str() {
+            // Anthropic 流式：message_start 携带输入与缓存用量
+            Some("message_start") => {
+                self.absorb_model(&value["message"]["model"]);
+                self.absorb_usage(&value["message"]["usage"]);
+            }
+            // OpenAI Responses API 流式：完成事件携带最终用量
+            Some(t) if t.starts_with("response.") => {
+                self.absorb_model(&value["response"]["model"]);
+                self.absorb_usage(&value["response"]["usage"]);
+            }
+            _ => {
+                self.absorb_model(&value["model"]);
+                self.absorb_model(&value["modelVersion"]);
+                self.absorb_usage(&value["usage"]);
+                self.absorb_gemini_usage(&value["usageMetadata"]);
+            }
+        }
+    }
+
+    fn absorb_model(&mut self, model: &Value) {
+        if self.usage.model.is_none() {
+            if let Some(model) = model.as_str().filter(|m| !m.is_empty()) {
+                self.usage.model = Some(model.to_string());
+            }
+        }
+    }
+
+    /// Anthropic / OpenAI 格式的 usage 对象
+    fn absorb_usage(&mut self, usage: &Value) {
+        if !usage.is_object() {
+            return;
+        }
+
+        let input = first_u64(usage, &["input_tokens", "prompt_tokens"]);
+        let output = first_u64(usage, &["output_tokens", "completion_tokens"]);
+        let cache_creation = first_u64(usage, &["cache_creation_input_tokens"]);
+        let cache_read = first_u64(usage, &["cache_read_input_tokens"])
+            .or_else(|| usage["prompt_tokens_details"]["cached_tokens"].as_u64())
+            .or_else(|| usage["input_tokens_details"]["cached_tokens"].as_u64());
+
+        self.merge(input, output, cache_creation, cache_read);
+    }
+
+    /// Gemini 格式的 usageMetadata 对象
+    fn absorb_gemini_usage(&mut self, usage: &Value) {
+        if !usage.is_object() {
+            return;
+        }
+
+        let input = usage["promptTokenCount"].as_u64();
+        let candidates = usage["candidatesTokenCount"].as_u64();
+        let thoughts = usage["thoughtsTokenCount"].as_u64();
+        let output = match (candidates, thoughts) {
+            (None, None) => None,
+            (c, t) => Some(c.unwrap_or(0) + t.unwrap_or(0)),
+        };
+        let cache_read = usage["cachedContentTokenCount"].as_u64();
+
+        self.merge(input, output, None, cache_read);
+    }
+
+    fn merge(
+        &mut self,
+        input: Option<u64>,
+        output: Option<u64>,
+        cache_creation: Option<u64>,
+        cache_read: Option<u64>,
+    ) {
+        let usage = &mut self.usage;
+        usage.input_tokens = usage.input_tokens.max(input.unwrap_or(0));
+        usage.output_tokens = usage.output_tokens.max(output.unwrap_or(0));
+        usage.cache_creation_tokens = usage.cache_creation_tokens.max(cache_creation.unwrap_or(0));
+        usage.cache_read_tokens = usage.cache_read_tokens.max(cache_read.unwrap_or(0));
+    }
+}
+
+fn first_u64(value: &Value, keys: &[&str]) -> Option<u64> {
+    keys.iter().find_map(|key| value[*key].as_u64())
+}
+
+/// 用量记录器
+///
+/// 随响应流一起移动，流结束或被丢弃（客户端断开）时把解析到的用量写入会话数据库
+pub struct UsageRecorder {
+    parser: Option<UsageParser>,
+    tool_id: String,
+    session_id: Option<String>,
+}
+
+impl UsageRecorder {
+    pub fn new(tool_id: &str, session_id: Option<String>) -> Self {
+        Self {
+            parser: Some(UsageParser::new()),
+            tool_id: tool_id.to_string(),
+            session_id,
+        }
+    }
+
+    /// 输入一段 SSE 数据
+    pub fn feed(&mut self, chunk: &[u8]) {
+        if let Some(parser) = self.parser.as_mut() {
+            parser.feed(chunk);
+        }
+    }
+
+    /// 记录非流式响应的用量
+    pub fn record_json(tool_id: &str, session_id: Option<String>, body: &[u8]) {
+        if let Some(usage) = UsageParser::from_json(body) {
+            send_usage_event(tool_id, session_id, usage);
+        }
+    }
+}
+
+impl Drop for UsageRecorder {
+    fn drop(&mut self) {
+        if let Some(usage) = self.parser.take().and_then(UsageParser::finish) {
+            send_usage_event(&self.tool_id, self.session_id.take(), usage);
+        }
+    }
+}
+
+fn send_usage_event(tool_id: &str, session_id: Option<String>, usage: TokenUsage) {
+    tracing::debug!(
+        tool_id = %tool_id,
+        input_tokens = usage.input_tokens,
+        output_tokens = usage.output_tokens,
+        cache_creation_tokens = usage.cache_creation_tokens,
+        cache_read_tokens = usage.cache_read_tokens,
+        "记录 token 用量"
+    );
+    let _ = SESSION_MANAGER.send_event(SessionEvent::TokenUsage {
+        session_id,
+        tool_id: tool_id.to_string(),
+        usage,
+        timestamp: chrono::Utc::now().timestamp(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anthropic_sse_usage() {
+        let mut parser = UsageParser::new();
+        parser.feed(b"event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"model\":\"claude-sonnet-4\",\"usage\":{\"input_tokens\":12,\"cache_creation_input_tokens\":100,\"cache_read_input_tokens\":50,\"output_tokens\":1}}}\n\n");
+        // 事件被拆分到两个 chunk 中
+        parser.feed(b"event: message_delta\ndata: {\"type\":\"message_delta\",\"usage\":{\"outp");
+        parser.feed(b"ut_tokens\":42}}\n\n");
+
+        let usage = parser.finish().unwrap();
+        assert_eq!(usage.model.as_deref(), Some("claude-sonnet-4"));
+        assert_eq!(usage.input_tokens, 12);
+        assert_eq!(usage.output_tokens, 42);
+        assert_eq!(usage.cache_creation_tokens, 100);
+        assert_eq!(usage.cache_read_tokens, 50);
+    }
+
+    #[test]
+    fn test_openai_json_usage() {
+        let body = br#"{"model":"gpt-5","usage":{"prompt_tokens":30,"completion_tokens":7,"prompt_tokens_details":{"cached_tokens":10}}}"#;
+        let usage = UsageParser::from_json(body).unwrap();
+        assert_eq!(usage.input_tokens, 30);
+        assert_eq!(usage.output_tokens, 7);
+        assert_eq!(usage.cache_read_tokens, 10);
+    }
+
+    #[test]
+    fn test_openai_responses_sse_usage() {
+        let mut parser = UsageParser::new();
+        parser.feed(b"data: {\"type\":\"response.completed\",\"response\":{\"model\":\"gpt-5-codex\",\"usage\":{\"input_tokens\":20,\"output_tokens\":5,\"input_tokens_details\":{\"cached_tokens\":4}}}}\n\ndata: [DONE]\n");
+
+        let usage = parser.finish().unwrap();
+        assert_eq!(usage.model.as_deref(), Some("gpt-5-codex"));
+        assert_eq!(usage.input_tokens, 20);
+        assert_eq!(usage.output_tokens, 5);
+        assert_eq!(usage.cache_read_tokens, 4);
+    }
+
+    #[test]
+    fn test_gemini_usage() {
+        let body = br#"[{"usageMetadata":{"promptTokenCount":8,"candidatesTokenCount":2}},{"modelVersion":"gemini-2.5-pro","usageMetadata":{"promptTokenCount":8,"candidatesTokenCount":6,"thoughtsTokenCount":3,"cachedContentTokenCount":1}}]"#;
+        let usage = UsageParser::from_json(body).unwrap();
+        assert_eq!(usage.model.as_deref(), Some("gemini-2.5-pro"));
+        assert_eq!(usage.input_tokens, 8);
+        assert_eq!(usage.output_tokens, 9);
+        assert_eq!(usage.cache_read_tokens, 1);
+    }
+
+    #[test]
+    fn test_no_usage() {
+        assert!(UsageParser::from_json(br#"{"id":"x"}"#).is_none());
+        let mut parser = UsageParser::new();
+        parser.feed(b"data: {\"type\":\"ping\"}\n");
+        assert!(parser.finish().is_none());
+    }
+}
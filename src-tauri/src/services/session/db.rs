@@ -1,8 +1,12 @@
 // SQLite 数据库管理
 
-use crate::services::session::models::{ProxySession, SessionListResponse};
+use crate::services::session::models::{
+    ProxyRequestFilter, ProxyRequestListResponse, ProxyRequestRecord, ProxySession,
+    SessionListResponse, TokenUsage, TokenUsageSummary,
+};
 use anyhow::Result;
-use rusqlite::{params, Connection, OptionalExtension};
+use rusqlite::types::ToSql;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
@@ -44,6 +48,7 @@ impl SessionDatabase {
                 url TEXT NOT NULL,
                 api_key TEXT NOT NULL,
                 note TEXT,
+                key_id TEXT,
                 first_seen_at INTEGER NOT NULL,
                 last_seen_at INTEGER NOT NULL,
                 request_count INTEGER NOT NULL DEFAULT 0,
@@ -59,6 +64,10 @@ impl SessionDatabase {
             [],
         );
         let _ = conn.execute("ALTER TABLE claude_proxy_sessions ADD COLUMN note TEXT", []);
+        let _ = conn.execute(
+            "ALTER TABLE claude_proxy_sessions ADD COLUMN key_id TEXT",
+            [],
+        );
 
         // 创建索引
         conn.execute(
@@ -74,6 +83,63 @@ impl SessionDatabase {
             [],
         )?;
 
+        // token 用量表（每个请求一条记录）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS session_token_usage (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT,
+                tool_id TEXT NOT NULL,
+                model TEXT,
+                input_tokens INTEGER NOT NULL DEFAULT 0,
+                output_tokens INTEGER NOT NULL DEFAULT 0,
+                cache_creation_tokens INTEGER NOT NULL DEFAULT 0,
+                cache_read_tokens INTEGER NOT NULL DEFAULT 0,
+                created_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_usage_session_id ON session_token_usage(session_id)",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_usage_tool_created ON session_token_usage(tool_id, created_at)",
+            [],
+        )?;
+
+        // 代理请求审计表（每个请求一条记录）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS proxy_requests (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp INTEGER NOT NULL,
+                tool_id TEXT NOT NULL,
+                session_id TEXT,
+                method TEXT NOT NULL,
+                path TEXT NOT NULL,
+                upstream_host TEXT,
+                status_code INTEGER,
+                latency_ms INTEGER NOT NULL DEFAULT 0,
+                bytes_in INTEGER NOT NULL DEFAULT 0,
+                bytes_out INTEGER NOT NULL DEFAULT 0,
+                error_kind TEXT,
+                error_body TEXT,
+                key_id TEXT,
+                capture_id TEXT
+            )",
+            [],
+        )?;
+        let _ = conn.execute("ALTER TABLE proxy_requests ADD COLUMN error_body TEXT", []);
+        let _ = conn.execute("ALTER TABLE proxy_requests ADD COLUMN key_id TEXT", []);
+        let _ = conn.execute("ALTER TABLE proxy_requests ADD COLUMN capture_id TEXT", []);
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_requests_tool_time ON proxy_requests(tool_id, timestamp)",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_requests_session_id ON proxy_requests(session_id)",
+            [],
+        )?;
+
         Ok(())
     }
 
@@ -125,7 +191,7 @@ impl SessionDatabase {
             "SELECT session_id, display_id, tool_id, config_name, custom_profile_name,
                     url, api_key, note,
                     first_seen_at, last_seen_at, request_count,
-                    created_at, updated_at
+                    created_at, updated_at, key_id
              FROM claude_proxy_sessions
              WHERE tool_id = ?1
              ORDER BY last_seen_at DESC
@@ -148,6 +214,7 @@ impl SessionDatabase {
                     request_count: row.get(10)?,
                     created_at: row.get(11)?,
                     updated_at: row.get(12)?,
+                    key_id: row.get(13)?,
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
@@ -167,6 +234,10 @@ impl SessionDatabase {
             "DELETE FROM claude_proxy_sessions WHERE session_id = ?1",
             params![session_id],
         )?;
+        conn.execute(
+            "DELETE FROM session_token_usage WHERE session_id = ?1",
+            params![session_id],
+        )?;
         Ok(())
     }
 
@@ -177,6 +248,10 @@ impl SessionDatabase {
             "DELETE FROM claude_proxy_sessions WHERE tool_id = ?1",
             params![tool_id],
         )?;
+        conn.execute(
+            "DELETE FROM session_token_usage WHERE tool_id = ?1",
+            params![tool_id],
+        )?;
         Ok(())
     }
 
@@ -222,6 +297,271 @@ impl SessionDatabase {
         Ok(deleted_by_age + deleted_by_count)
     }
 
+    /// 清理过期的 token 用量记录
+    pub fn cleanup_old_token_usage(&self, tool_id: &str, max_age_days: i64) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let cutoff_time = chrono::Utc::now().timestamp() - (max_age_days * 24 * 3600);
+
+        let deleted = conn.execute(
+            "DELETE FROM session_token_usage WHERE tool_id = ?1 AND created_at < ?2",
+            params![tool_id, cutoff_time],
+        )?;
+
+        Ok(deleted)
+    }
+
+    /// 记录单次请求的 token 用量
+    pub fn insert_token_usage(
+        &self,
+        session_id: Option<&str>,
+        tool_id: &str,
+        usage: &TokenUsage,
+        timestamp: i64,
+    ) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT INTO session_token_usage (
+                session_id, tool_id, model, input_tokens, output_tokens,
+                cache_creation_tokens, cache_read_tokens, created_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                session_id,
+                tool_id,
+                usage.model,
+                usage.input_tokens as i64,
+                usage.output_tokens as i64,
+                usage.cache_creation_tokens as i64,
+                usage.cache_read_tokens as i64,
+                timestamp
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// 汇总单个会话的 token 用量
+    pub fn get_session_token_usage(&self, session_id: &str) -> Result<TokenUsageSummary> {
+        let conn = self.conn.lock().unwrap();
+        let summary = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(input_tokens), 0), COALESCE(SUM(output_tokens), 0),
+                    COALESCE(SUM(cache_creation_tokens), 0), COALESCE(SUM(cache_read_tokens), 0)
+             FROM session_token_usage
+             WHERE session_id = ?1",
+            params![session_id],
+            Self::map_usage_summary,
+        )?;
+        Ok(summary)
+    }
+
+    /// 汇总工具的 token 用量（可选起始时间）
+    pub fn get_tool_token_usage(
+        &self,
+        tool_id: &str,
+        since: Option<i64>,
+    ) -> Result<TokenUsageSummary> {
+        let conn = self.conn.lock().unwrap();
+        let summary = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(input_tokens), 0), COALESCE(SUM(output_tokens), 0),
+                    COALESCE(SUM(cache_creation_tokens), 0), COALESCE(SUM(cache_read_tokens), 0)
+             FROM session_token_usage
+             WHERE tool_id = ?1 AND created_at >= ?2",
+            params![tool_id, since.unwrap_or(0)],
+            Self::map_usage_summary,
+        )?;
+        Ok(summary)
+    }
+
+    fn map_usage_summary(row: &rusqlite::Row<'_>) -> rusqlite::Result<TokenUsageSummary> {
+        Ok(TokenUsageSummary {
+            request_count: row.get::<_, i64>(0)? as u64,
+            input_tokens: row.get::<_, i64>(1)? as u64,
+            output_tokens: row.get::<_, i64>(2)? as u64,
+            cache_creation_tokens: row.get::<_, i64>(3)? as u64,
+            cache_read_tokens: row.get::<_, i64>(4)? as u64,
+        })
+    }
+
+    /// 写入代理请求审计记录
+    pub fn insert_proxy_request(&self, record: &ProxyRequestRecord) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT INTO proxy_requests (
+                timestamp, tool_id, session_id, method, path, upstream_host,
+                status_code, latency_ms, bytes_in, bytes_out, error_kind, error_body, key_id,
+                capture_id
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                record.timestamp,
+                record.tool_id,
+                record.session_id,
+                record.method,
+                record.path,
+                record.upstream_host,
+                record.status_code,
+                record.latency_ms,
+                record.bytes_in,
+                record.bytes_out,
+                record.error_kind,
+                record.error_body,
+                record.key_id,
+                record.capture_id
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// 查询代理请求日志（分页 + 过滤，按时间降序）
+    pub fn get_proxy_requests(
+        &self,
+        tool_id: &str,
+        filter: &ProxyRequestFilter,
+        page: usize,
+        page_size: usize,
+    ) -> Result<ProxyRequestListResponse> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut conditions = vec!["tool_id = ?".to_string()];
+        let mut values: Vec<Box<dyn ToSql>> = vec![Box::new(tool_id.to_string())];
+
+        if let Some(session_id) = &filter.session_id {
+            conditions.push("session_id = ?".to_string());
+            values.push(Box::new(session_id.clone()));
+        }
+        if let Some(key_id) = &filter.key_id {
+            conditions.push("key_id = ?".to_string());
+            values.push(Box::new(key_id.clone()));
+        }
+        if let Some(status_min) = filter.status_min {
+            conditions.push("status_code >= ?".to_string());
+            values.push(Box::new(status_min));
+        }
+        if let Some(status_max) = filter.status_max {
+            conditions.push("status_code <= ?".to_string());
+            values.push(Box::new(status_max));
+        }
+        if filter.errors_only {
+            conditions.push("(status_code >= 400 OR error_kind IS NOT NULL)".to_string());
+        }
+        if let Some(since) = filter.since {
+            conditions.push("timestamp >= ?".to_string());
+            values.push(Box::new(since));
+        }
+        if let Some(until) = filter.until {
+            conditions.push("timestamp <= ?".to_string());
+            values.push(Box::new(until));
+        }
+        if let Some(keyword) = filter.path_contains.as_ref().filter(|k| !k.is_empty()) {
+            conditions.push("instr(path, ?) > 0".to_string());
+            values.push(Box::new(keyword.clone()));
+        }
+
+        let where_clause = conditions.join(" AND ");
+
+        // 查询总数
+        let total: usize = conn.query_row(
+            &format!("SELECT COUNT(*) FROM proxy_requests WHERE {where_clause}"),
+            params_from_iter(values.iter()),
+            |row| row.get(0),
+        )?;
+
+        // 查询分页数据
+        let offset = (page.saturating_sub(1)) * page_size;
+        values.push(Box::new(page_size));
+        values.push(Box::new(offset));
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, timestamp, tool_id, session_id, method, path, upstream_host,
+                    status_code, latency_ms, bytes_in, bytes_out, error_kind, error_body, key_id,
+                    capture_id
+             FROM proxy_requests
+             WHERE {where_clause}
+             ORDER BY timestamp DESC, id DESC
+             LIMIT ? OFFSET ?"
+        ))?;
+
+        let requests = stmt
+            .query_map(params_from_iter(values.iter()), |row| {
+                Ok(ProxyRequestRecord {
+                    id: row.get(0)?,
+                    timestamp: row.get(1)?,
+                    tool_id: row.get(2)?,
+                    session_id: row.get(3)?,
+                    method: row.get(4)?,
+                    path: row.get(5)?,
+                    upstream_host: row.get(6)?,
+                    status_code: row.get(7)?,
+                    latency_ms: row.get(8)?,
+                    bytes_in: row.get(9)?,
+                    bytes_out: row.get(10)?,
+                    error_kind: row.get(11)?,
+                    error_body: row.get(12)?,
+                    key_id: row.get(13)?,
+                    capture_id: row.get(14)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(ProxyRequestListResponse {
+            requests,
+            total,
+            page,
+            page_size,
+        })
+    }
+
+    /// 清空指定工具的代理请求日志
+    pub fn clear_proxy_requests(&self, tool_id: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM proxy_requests WHERE tool_id = ?1",
+            params![tool_id],
+        )?;
+        Ok(())
+    }
+
+    /// 清理过期的代理请求日志（超过保留天数 + 超过条数限制）
+    pub fn cleanup_old_proxy_requests(
+        &self,
+        tool_id: &str,
+        max_count: usize,
+        max_age_days: i64,
+    ) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let cutoff_time = (chrono::Utc::now().timestamp() - (max_age_days * 24 * 3600)) * 1000;
+
+        // 1. 删除超过保留天数的记录
+        let deleted_by_age = conn.execute(
+            "DELETE FROM proxy_requests WHERE tool_id = ?1 AND timestamp < ?2",
+            params![tool_id, cutoff_time],
+        )?;
+
+        // 2. 超过条数限制时删除最旧的记录
+        let current_count: usize = conn.query_row(
+            "SELECT COUNT(*) FROM proxy_requests WHERE tool_id = ?1",
+            params![tool_id],
+            |row| row.get(0),
+        )?;
+
+        let deleted_by_count = if current_count > max_count {
+            let to_delete = current_count - max_count;
+            conn.execute(
+                "DELETE FROM proxy_requests WHERE id IN (
+                    SELECT id FROM proxy_requests
+                    WHERE tool_id = ?1
+                    ORDER BY timestamp ASC, id ASC
+                    LIMIT ?2
+                )",
+                params![tool_id, to_delete],
+            )?
+        } else {
+            0
+        };
+
+        Ok(deleted_by_age + deleted_by_count)
+    }
+
     /// 获取会话详情
     pub fn get_session(&self, session_id: &str) -> Result<Option<ProxySession>> {
         let conn = self.conn.lock().unwrap();
@@ -230,7 +570,7 @@ impl SessionDatabase {
                 "SELECT session_id, display_id, tool_id, config_name, custom_profile_name,
                         url, api_key, note,
                         first_seen_at, last_seen_at, request_count,
-                        created_at, updated_at
+                        created_at, updated_at, key_id
                  FROM claude_proxy_sessions
                  WHERE session_id = ?1",
                 params![session_id],
@@ -249,6 +589,7 @@ impl SessionDatabase {
                         request_count: row.get(10)?,
                         created_at: row.get(11)?,
                         updated_at: row.get(12)?,
+                        key_id: row.get(13)?,
                     })
                 },
             )
@@ -307,6 +648,18 @@ impl SessionDatabase {
         Ok(())
     }
 
+    /// 记录会话使用的本地 API Key
+    pub fn update_session_key(&self, session_id: &str, key_id: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "UPDATE claude_proxy_sessions SET key_id = ?1 WHERE session_id = ?2",
+            params![key_id, session_id],
+        )?;
+
+        Ok(())
+    }
+
     /// 更新会话备注
     pub fn update_session_note(&self, session_id: &str, note: Option<&str>) -> Result<()> {
         let conn = self.conn.lock().unwrap();
@@ -362,5 +715,124 @@ mod tests {
         // 验证请求次数
         let session = db.get_session("test_session_1").unwrap().unwrap();
         assert_eq!(session.request_count, 2);
+        assert!(session.key_id.is_none());
+
+        // 记录会话使用的本地密钥
+        db.update_session_key("test_session_1", "key_alice")
+            .unwrap();
+        let session = db.get_session("test_session_1").unwrap().unwrap();
+        assert_eq!(session.key_id.as_deref(), Some("key_alice"));
+    }
+
+    #[test]
+    fn test_token_usage_summary() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = SessionDatabase::new(db_path).unwrap();
+
+        let timestamp = chrono::Utc::now().timestamp();
+        let usage = TokenUsage {
+            model: Some("claude-sonnet-4".to_string()),
+            input_tokens: 10,
+            output_tokens: 20,
+            cache_creation_tokens: 30,
+            cache_read_tokens: 40,
+        };
+
+        db.insert_token_usage(Some("test_session_1"), "claude-code", &usage, timestamp)
+            .unwrap();
+        db.insert_token_usage(Some("test_session_1"), "claude-code", &usage, timestamp)
+            .unwrap();
+        db.insert_token_usage(None, "claude-code", &usage, timestamp)
+            .unwrap();
+
+        let session_summary = db.get_session_token_usage("test_session_1").unwrap();
+        assert_eq!(session_summary.request_count, 2);
+        assert_eq!(session_summary.input_tokens, 20);
+        assert_eq!(session_summary.cache_read_tokens, 80);
+
+        let tool_summary = db.get_tool_token_usage("claude-code", None).unwrap();
+        assert_eq!(tool_summary.request_count, 3);
+        assert_eq!(tool_summary.output_tokens, 60);
+
+        let future_summary = db
+            .get_tool_token_usage("claude-code", Some(timestamp + 1))
+            .unwrap();
+        assert_eq!(future_summary.request_count, 0);
+    }
+
+    #[test]
+    fn test_proxy_request_log_filter_and_cleanup() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = SessionDatabase::new(db_path).unwrap();
+
+        let now = chrono::Utc::now().timestamp_millis();
+        for (i, status) in [200u16, 200, 429, 502].iter().enumerate() {
+            db.insert_proxy_request(&ProxyRequestRecord {
+                timestamp: now + i as i64,
+                tool_id: "claude-code".to_string(),
+                session_id: Some(format!("session_{}", i % 2)),
+                method: "POST".to_string(),
+                path: "/v1/messages".to_string(),
+                upstream_host: Some("api.example.com".to_string()),
+                status_code: Some(*status),
+                latency_ms: 100,
+                bytes_in: 10,
+                bytes_out: 20,
+                error_kind: None,
+                error_body: (*status == 502).then(|| "<html>Bad Gateway</html>".to_string()),
+                key_id: (i == 0).then(|| "key_alice".to_string()),
+                capture_id: (i == 3).then(|| "1700000000000-abcdef".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        }
+
+        let all = db
+            .get_proxy_requests("claude-code", &ProxyRequestFilter::default(), 1, 10)
+            .unwrap();
+        assert_eq!(all.total, 4);
+        assert_eq!(all.requests[0].status_code, Some(502));
+        assert_eq!(
+            all.requests[0].error_body.as_deref(),
+            Some("<html>Bad Gateway</html>")
+        );
+        assert_eq!(
+            all.requests[0].capture_id.as_deref(),
+            Some("1700000000000-abcdef")
+        );
+
+        let errors = ProxyRequestFilter {
+            errors_only: true,
+            ..Default::default()
+        };
+        let result = db
+            .get_proxy_requests("claude-code", &errors, 1, 10)
+            .unwrap();
+        assert_eq!(result.total, 2);
+
+        let by_session = ProxyRequestFilter {
+            session_id: Some("session_1".to_string()),
+            ..Default::default()
+        };
+        let result = db
+            .get_proxy_requests("claude-code", &by_session, 1, 1)
+            .unwrap();
+        assert_eq!(result.total, 2);
+        assert_eq!(result.requests.len(), 1);
+
+        let by_key = ProxyRequestFilter {
+            key_id: Some("key_alice".to_string()),
+            ..Default::default()
+        };
+        let result = db
+            .get_proxy_requests("claude-code", &by_key, 1, 10)
+            .unwrap();
+        assert_eq!(result.total, 1);
+        assert_eq!(result.requests[0].key_id.as_deref(), Some("key_alice"));
+
+        let deleted = db.cleanup_old_proxy_requests("claude-code", 3, 30).unwrap();
+        assert_eq!(deleted, 1);
     }
 }
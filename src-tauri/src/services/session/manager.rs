@@ -1,7 +1,11 @@
 // SessionManager 单例 - 会话管理核心模块
 
+use crate::models::Tool;
 use crate::services::session::db::SessionDatabase;
-use crate::services::session::models::{ProxySession, SessionEvent, SessionListResponse};
+use crate::services::session::models::{
+    ProxyRequestFilter, ProxyRequestListResponse, ProxySession, SessionEvent, SessionListResponse,
+    TokenUsageSummary,
+};
 use anyhow::Result;
 use lazy_static::lazy_static;
 use std::path::PathBuf;
@@ -86,9 +90,11 @@ impl SessionManager {
             loop {
                 cleanup_interval.tick().await;
 
-                // 清理三个工具的过期会话
-                for tool_id in &["claude-code", "codex", "gemini-cli"] {
-                    let _ = db_clone.cleanup_old_sessions(tool_id, 1000, 30);
+                // 清理所有工具（含 tools.d 注册的工具）的过期会话
+                for tool in Tool::all() {
+                    let _ = db_clone.cleanup_old_sessions(&tool.id, 1000, 30);
+                    let _ = db_clone.cleanup_old_token_usage(&tool.id, 30);
+                    let _ = db_clone.cleanup_old_proxy_requests(&tool.id, 10000, 30);
                 }
             }
         });
@@ -108,6 +114,22 @@ impl SessionManager {
                         let _ = db.upsert_session(&session_id, &display_id, &tool_id, timestamp);
                     }
                 }
+                SessionEvent::TokenUsage {
+                    session_id,
+                    tool_id,
+                    usage,
+                    timestamp,
+                } => {
+                    let _ =
+                        db.insert_token_usage(session_id.as_deref(), &tool_id, &usage, timestamp);
+                }
+                SessionEvent::ProxyRequest(record) => {
+                    let _ = db.insert_proxy_request(&record);
+                    // 会话记录在同一批次的 NewRequest 中已写入
+                    if let (Some(session_id), Some(key_id)) = (&record.session_id, &record.key_id) {
+                        let _ = db.update_session_key(session_id, key_id);
+                    }
+                }
             }
         }
     }
@@ -164,6 +186,36 @@ impl SessionManager {
             .update_session_config(session_id, config_name, custom_profile_name, url, api_key)
     }
 
+    /// 获取会话 token 用量汇总（公共 API）
+    pub fn get_session_token_usage(&self, session_id: &str) -> Result<TokenUsageSummary> {
+        self.db.get_session_token_usage(session_id)
+    }
+
+    /// 获取工具 token 用量汇总（公共 API）
+    pub fn get_tool_token_usage(
+        &self,
+        tool_id: &str,
+        since: Option<i64>,
+    ) -> Result<TokenUsageSummary> {
+        self.db.get_tool_token_usage(tool_id, since)
+    }
+
+    /// 查询代理请求日志（公共 API）
+    pub fn get_proxy_requests(
+        &self,
+        tool_id: &str,
+        filter: &ProxyRequestFilter,
+        page: usize,
+        page_size: usize,
+    ) -> Result<ProxyRequestListResponse> {
+        self.db.get_proxy_requests(tool_id, filter, page, page_size)
+    }
+
+    /// 清空工具的代理请求日志（公共 API）
+    pub fn clear_proxy_requests(&self, tool_id: &str) -> Result<()> {
+        self.db.clear_proxy_requests(tool_id)
+    }
+
     /// 更新会话备注（公共 API）
     pub fn update_session_note(&self, session_id: &str, note: Option<&str>) -> Result<()> {
         self.db.update_session_note(session_id, note)
@@ -5,4 +5,7 @@ pub mod manager;
 pub mod models;
 
 pub use manager::SESSION_MANAGER;
-pub use models::{ProxySession, SessionEvent, SessionListResponse};
+pub use models::{
+    ProxyRequestFilter, ProxyRequestListResponse, ProxyRequestRecord, ProxySession, SessionEvent,
+    SessionListResponse, TokenUsage, TokenUsageSummary,
+};
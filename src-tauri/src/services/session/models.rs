@@ -31,6 +31,118 @@ pub struct ProxySession {
     pub created_at: i64,
     /// 更新时间（Unix 时间戳，秒）
     pub updated_at: i64,
+    /// 最近一次请求使用的本地 API Key ID（未使用命名密钥时为空）
+    pub key_id: Option<String>,
+}
+
+/// 单次请求的 token 用量
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    /// 响应中报告的模型名称
+    pub model: Option<String>,
+    /// 输入 token 数
+    pub input_tokens: u64,
+    /// 输出 token 数
+    pub output_tokens: u64,
+    /// 缓存写入 token 数
+    pub cache_creation_tokens: u64,
+    /// 缓存读取 token 数
+    pub cache_read_tokens: u64,
+}
+
+impl TokenUsage {
+    /// 是否没有任何用量数据
+    pub fn is_empty(&self) -> bool {
+        self.input_tokens == 0
+            && self.output_tokens == 0
+            && self.cache_creation_tokens == 0
+            && self.cache_read_tokens == 0
+    }
+}
+
+/// token 用量汇总
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenUsageSummary {
+    /// 有用量记录的请求数
+    pub request_count: u64,
+    /// 输入 token 总数
+    pub input_tokens: u64,
+    /// 输出 token 总数
+    pub output_tokens: u64,
+    /// 缓存写入 token 总数
+    pub cache_creation_tokens: u64,
+    /// 缓存读取 token 总数
+    pub cache_read_tokens: u64,
+}
+
+/// 代理请求审计记录（proxy_requests 表）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProxyRequestRecord {
+    /// 自增 ID（写入前为 0）
+    pub id: i64,
+    /// 请求时间（Unix 时间戳，毫秒）
+    pub timestamp: i64,
+    /// 工具ID
+    pub tool_id: String,
+    /// 会话ID（来自 metadata.user_id，可能为空）
+    pub session_id: Option<String>,
+    /// HTTP 方法
+    pub method: String,
+    /// 请求路径（不含查询字符串）
+    pub path: String,
+    /// 实际使用的上游主机
+    pub upstream_host: Option<String>,
+    /// 返回给客户端的状态码（连接中断等情况下为空）
+    pub status_code: Option<u16>,
+    /// 从收到请求到响应结束的耗时（毫秒）
+    pub latency_ms: i64,
+    /// 请求体字节数
+    pub bytes_in: i64,
+    /// 响应体字节数
+    pub bytes_out: i64,
+    /// 错误类型（成功时为空）
+    pub error_kind: Option<String>,
+    /// 上游返回的原始错误响应体（截断，改写为客户端错误格式前的内容）
+    pub error_body: Option<String>,
+    /// 请求使用的本地 API Key ID（未使用命名密钥时为空）
+    pub key_id: Option<String>,
+    /// 请求抓包 ID（未启用抓包时为空）
+    pub capture_id: Option<String>,
+}
+
+/// 代理请求日志过滤条件
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxyRequestFilter {
+    /// 按会话过滤
+    pub session_id: Option<String>,
+    /// 最小状态码（含）
+    pub status_min: Option<u16>,
+    /// 最大状态码（含）
+    pub status_max: Option<u16>,
+    /// 仅显示失败请求（状态码 >= 400 或存在错误类型）
+    pub errors_only: bool,
+    /// 起始时间（Unix 时间戳，毫秒，含）
+    pub since: Option<i64>,
+    /// 截止时间（Unix 时间戳，毫秒，含）
+    pub until: Option<i64>,
+    /// 路径包含的关键字
+    pub path_contains: Option<String>,
+    /// 按本地 API Key 过滤
+    pub key_id: Option<String>,
+}
+
+/// 代理请求日志分页响应
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProxyRequestListResponse {
+    /// 请求记录
+    pub requests: Vec<ProxyRequestRecord>,
+    /// 总数
+    pub total: usize,
+    /// 当前页
+    pub page: usize,
+    /// 每页大小
+    pub page_size: usize,
 }
 
 /// 会话事件（异步队列传递）
@@ -42,6 +154,15 @@ pub enum SessionEvent {
         tool_id: String,
         timestamp: i64,
     },
+    /// token 用量事件（响应结束时发送）
+    TokenUsage {
+        session_id: Option<String>,
+        tool_id: String,
+        usage: TokenUsage,
+        timestamp: i64,
+    },
+    /// 代理请求审计事件（响应结束时发送）
+    ProxyRequest(ProxyRequestRecord),
 }
 
 /// 会话列表响应
@@ -178,8 +178,8 @@ impl InstallerService {
 
                 Some(InstallMethod::Official)
             }
-            "gemini-cli" => Some(InstallMethod::Npm),
-            _ => None,
+            // Gemini CLI 及 tools.d 注册的工具仅支持 npm 安装
+            _ => Some(InstallMethod::Npm),
         }
     }
 
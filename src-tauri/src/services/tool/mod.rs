@@ -5,9 +5,11 @@
 pub mod cache;
 pub mod downloader;
 pub mod installer;
+pub mod registry;
 pub mod version;
 
 pub use cache::ToolStatusCache;
 pub use downloader::FileDownloader;
 pub use installer::InstallerService;
+pub use registry::load_tool_registry;
 pub use version::VersionService;
@@ -0,0 +1,207 @@
+// 工具注册表
+//
+// 内置工具之外，启动时从 ~/.duckcoding/tools.d 加载工具描述文件（*.json / *.toml，每个文件一个工具），
+// 无需发布新版本即可接入新的 AI 编程工具。无效的描述文件记录警告后跳过。
+
+use crate::models::{ConfigLayout, Tool, ToolDescriptor};
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 工具描述文件目录名
+const TOOLS_DIR_NAME: &str = "tools.d";
+
+/// 工具描述文件目录 (~/.duckcoding/tools.d)
+pub fn tools_dir() -> Result<PathBuf> {
+    let config_dir = crate::utils::config::config_dir().map_err(|e| anyhow::anyhow!(e))?;
+    Ok(config_dir.join(TOOLS_DIR_NAME))
+}
+
+/// 解析单个工具描述文件
+pub fn parse_tool_descriptor(path: &Path) -> Result<ToolDescriptor> {
+    let content = fs::read_to_string(path).context("读取工具描述文件失败")?;
+    let descriptor: ToolDescriptor = match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str(&content).context("解析 TOML 描述文件失败")?,
+        _ => serde_json::from_str(&content).context("解析 JSON 描述文件失败")?,
+    };
+    validate_descriptor(&descriptor)?;
+    Ok(descriptor)
+}
+
+/// 工具 ID 只允许小写字母、数字、- 和 _（工具 ID 会用作目录和文件名）
+pub fn is_valid_tool_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+fn validate_descriptor(descriptor: &ToolDescriptor) -> Result<()> {
+    let id = &descriptor.id;
+    if !is_valid_tool_id(id) {
+        anyhow::bail!("工具 ID 只能包含小写字母、数字、- 和 _: {id:?}");
+    }
+    for (field, value) in [
+        ("name", &descriptor.name),
+        ("npm_package", &descriptor.npm_package),
+        ("check_command", &descriptor.check_command),
+        ("config_dir", &descriptor.config_dir),
+        ("config_file", &descriptor.config_file),
+        ("env_vars.api_key", &descriptor.env_vars.api_key),
+        ("env_vars.base_url", &descriptor.env_vars.base_url),
+    ] {
+        if value.trim().is_empty() {
+            anyhow::bail!("字段 {field} 不能为空");
+        }
+    }
+    if descriptor.config_layout == ConfigLayout::JsonPointer {
+        for pointer in [&descriptor.env_vars.api_key, &descriptor.env_vars.base_url] {
+            if !pointer.starts_with('/') {
+                anyhow::bail!(
+                    "json_pointer 布局的 env_vars 必须是以 / 开头的 JSON Pointer: {pointer}"
+                );
+            }
+        }
+    }
+    if descriptor.default_port == 0 {
+        anyhow::bail!("default_port 不能为 0");
+    }
+    Ok(())
+}
+
+/// 加载目录下的所有工具描述文件（按文件名排序）
+///
+/// 返回成功加载的工具及每个无效文件的错误；与内置工具或先加载的工具 ID/端口重复的文件视为无效
+pub fn load_tool_descriptors(
+    dir: &Path,
+    home_dir: &Path,
+) -> (Vec<Tool>, Vec<(PathBuf, anyhow::Error)>) {
+    let mut tools = Vec::new();
+    let mut errors = Vec::new();
+
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.is_file()
+                    && matches!(
+                        path.extension().and_then(|e| e.to_str()),
+                        Some("json") | Some("toml")
+                    )
+            })
+            .collect(),
+        Err(_) => return (tools, errors),
+    };
+    paths.sort();
+
+    let builtin = Tool::builtin_tools();
+    let mut ids: HashSet<String> = builtin.iter().map(|t| t.id.clone()).collect();
+    let mut ports: HashSet<u16> = builtin.iter().map(|t| t.default_port).collect();
+
+    for path in paths {
+        let result = parse_tool_descriptor(&path).and_then(|descriptor| {
+            if ids.contains(&descriptor.id) {
+                anyhow::bail!("工具 ID {} 已存在", descriptor.id);
+            }
+            if ports.contains(&descriptor.default_port) {
+                anyhow::bail!("默认端口 {} 已被其他工具使用", descriptor.default_port);
+            }
+            Ok(descriptor)
+        });
+        match result {
+            Ok(descriptor) => {
+                ids.insert(descriptor.id.clone());
+                ports.insert(descriptor.default_port);
+                tools.push(descriptor.into_tool(home_dir));
+            }
+            Err(e) => errors.push((path, e)),
+        }
+    }
+
+    (tools, errors)
+}
+
+/// 从 ~/.duckcoding/tools.d 加载工具并注册（应用启动时调用）
+///
+/// 返回注册的工具数量
+pub fn load_tool_registry() -> Result<usize> {
+    let dir = tools_dir()?;
+    let home_dir = dirs::home_dir().context("无法获取用户主目录")?;
+
+    let (tools, errors) = load_tool_descriptors(&dir, &home_dir);
+    for (path, error) in &errors {
+        tracing::warn!(path = ?path, error = ?error, "跳过无效的工具描述文件");
+    }
+    for tool in &tools {
+        tracing::info!(tool_id = %tool.id, name = %tool.name, "已注册工具");
+    }
+
+    let count = tools.len();
+    Tool::set_registered(tools);
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AuthHeaderStyle;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_tool_descriptors() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("kimi.toml"),
+            r#"
+id = "kimi-cli"
+name = "Kimi CLI"
+npm_package = "kimi-cli"
+check_command = "kimi --version"
+config_dir = "~/.kimi"
+config_file = "settings.json"
+config_layout = "dotenv"
+auth_header = "bearer"
+default_port = 8800
+
+[env_vars]
+api_key = "KIMI_API_KEY"
+base_url = "KIMI_BASE_URL"
+
+[default_env]
+KIMI_MODEL = "kimi-k2"
+"#,
+        )
+        .unwrap();
+        // 与内置工具 ID 冲突
+        fs::write(
+            dir.path().join("claude.json"),
+            r#"{"id":"claude-code","name":"Claude","npm_package":"x","check_command":"x",
+                "config_dir":"~/.x","config_file":"x.json","config_layout":"json_env",
+                "env_vars":{"api_key":"K","base_url":"U"},"default_port":9000}"#,
+        )
+        .unwrap();
+        // 无效文件
+        fs::write(dir.path().join("broken.json"), "{").unwrap();
+        fs::write(dir.path().join("README.md"), "ignored").unwrap();
+
+        let home = Path::new("/home/test");
+        let (tools, errors) = load_tool_descriptors(dir.path(), home);
+
+        assert_eq!(tools.len(), 1);
+        let kimi = &tools[0];
+        assert_eq!(kimi.id, "kimi-cli");
+        assert_eq!(kimi.group_name, "Kimi CLI 专用分组");
+        assert_eq!(kimi.config_dir, home.join(".kimi"));
+        assert_eq!(kimi.config_layout, ConfigLayout::Dotenv);
+        assert_eq!(kimi.auth_header, AuthHeaderStyle::Bearer);
+        assert_eq!(kimi.default_env["KIMI_MODEL"], "kimi-k2");
+        assert!(kimi.use_proxy_for_version_check);
+        assert!(!kimi.builtin);
+
+        assert_eq!(errors.len(), 2);
+        assert!(errors
+            .iter()
+            .any(|(path, e)| path.ends_with("claude.json") && e.to_string().contains("已存在")));
+    }
+}
@@ -106,7 +106,8 @@ impl VersionService {
                 });
             }
             Err(e) => {
-                tracing::warn!(error = ?e, "镜像站 API 不可用");
+                // 镜像站未收录的工具（如 Qwen Code、opencode、Crush）同样回退到 npm 查询
+                tracing::warn!(tool_id = %tool.id, error = ?e, "镜像站 API 不可用，回退到 npm 查询");
             }
         }
 
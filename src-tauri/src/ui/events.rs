@@ -2,9 +2,14 @@
 //!
 //! 用于统一管理应用内部事件名称，避免拼写错误
 
+use std::collections::HashMap;
+
 use serde::Serialize;
 use tauri::{AppHandle, Emitter, Runtime};
 
+use crate::services::config_watcher::ConfigDrift;
+use crate::services::proxy::ProxyInstanceStatus;
+
 /// 关闭确认事件
 ///
 /// 当用户尝试关闭窗口时，发送此事件到前端，
@@ -17,6 +22,18 @@ pub const CLOSE_CONFIRM_EVENT: &str = "duckcoding://request-close-action";
 /// 携带新的启动参数和工作目录
 pub const SINGLE_INSTANCE_EVENT: &str = "single-instance";
 
+/// 代理状态变化事件
+///
+/// 透明代理启停、连接数、请求数、错误或上游切换时发送，
+/// 负载为 `HashMap<tool_id, ProxyInstanceStatus>`
+pub const PROXY_STATUS_EVENT: &str = "duckcoding://proxy-status-changed";
+
+/// 配置偏移事件
+///
+/// 工具配置文件被外部修改、偏离 DuckCoding 最后写入的状态时发送，
+/// 负载为 `ConfigDrift`，前端可调用 `reconcile_config_drift` 一键修复
+pub const CONFIG_DRIFT_EVENT: &str = "duckcoding://config-drift";
+
 /// 单实例事件负载
 ///
 /// 包含第二次启动时的参数信息
@@ -69,3 +86,32 @@ pub fn emit_single_instance<R: Runtime>(
     );
     app.emit(SINGLE_INSTANCE_EVENT, payload)
 }
+
+/// 发送代理状态变化事件
+///
+/// # 参数
+/// - `app`: Tauri 应用句柄
+/// - `status`: 所有运行中（或正在排空连接）的代理实例状态
+///
+/// # 返回
+/// - 成功或错误
+pub fn emit_proxy_status<R: Runtime>(
+    app: &AppHandle<R>,
+    status: &HashMap<String, ProxyInstanceStatus>,
+) -> tauri::Result<()> {
+    tracing::trace!(count = status.len(), "发送代理状态变化事件");
+    app.emit(PROXY_STATUS_EVENT, status)
+}
+
+/// 发送配置偏移事件
+///
+/// # 参数
+/// - `app`: Tauri 应用句柄
+/// - `drift`: 检测到的配置偏移
+///
+/// # 返回
+/// - 成功或错误
+pub fn emit_config_drift<R: Runtime>(app: &AppHandle<R>, drift: &ConfigDrift) -> tauri::Result<()> {
+    tracing::debug!(tool_id = %drift.tool_id, kind = ?drift.kind, "发送配置偏移事件");
+    app.emit(CONFIG_DRIFT_EVENT, drift)
+}
@@ -10,6 +10,7 @@ pub use tray::create_tray_menu;
 
 // 导出事件常量和函数
 pub use events::{
-    emit_close_confirm, emit_single_instance, SingleInstancePayload, CLOSE_CONFIRM_EVENT,
+    emit_close_confirm, emit_config_drift, emit_proxy_status, emit_single_instance,
+    SingleInstancePayload, CLOSE_CONFIRM_EVENT, CONFIG_DRIFT_EVENT, PROXY_STATUS_EVENT,
     SINGLE_INSTANCE_EVENT,
 };
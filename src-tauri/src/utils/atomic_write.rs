@@ -0,0 +1,338 @@
+// 原子写入与配置快照
+//
+// 工具配置文件（settings.json、config.toml、auth.json、.env 等）统一通过 write_atomic 写入：
+// 先写入同目录下的临时文件并 fsync，沿用原文件权限后 rename 覆盖，中途崩溃不会留下写了一半的文件。
+// 覆盖前把原内容保存到 ~/.duckcoding/snapshots，每个文件保留最近 SNAPSHOT_LIMIT 个版本，可随时恢复。
+// 写入成功后记录内容摘要，配置监听据此区分 DuckCoding 自身写入与外部修改。
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+/// 每个文件保留的快照数量
+pub const SNAPSHOT_LIMIT: usize = 10;
+
+/// 临时文件序号（同一进程内并发写入同一文件时避免临时文件冲突）
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// 每个文件最近一次由 write_atomic 写入的内容摘要
+static WRITTEN_DIGESTS: Lazy<Mutex<HashMap<PathBuf, u64>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 文件快照
+#[derive(Debug, Clone, Serialize)]
+pub struct FileSnapshot {
+    /// 快照 ID（保存时间，如 20250101-120000.123）
+    pub id: String,
+    pub size: u64,
+    /// 保存时间（毫秒时间戳）
+    pub created_at: i64,
+}
+
+/// 快照存储，每个被写入的文件对应一个子目录
+#[derive(Debug, Clone)]
+pub struct SnapshotStore {
+    root: PathBuf,
+    limit: usize,
+}
+
+impl SnapshotStore {
+    pub fn new(root: impl Into<PathBuf>, limit: usize) -> Self {
+        Self {
+            root: root.into(),
+            limit,
+        }
+    }
+
+    /// 默认快照存储 (~/.duckcoding/snapshots)
+    pub fn default_store() -> Option<Self> {
+        crate::utils::config::config_dir()
+            .ok()
+            .map(|dir| Self::new(dir.join("snapshots"), SNAPSHOT_LIMIT))
+    }
+
+    /// 文件对应的快照目录（由绝对路径转换而来）
+    fn dir_for(&self, target: &Path) -> PathBuf {
+        let absolute = if target.is_absolute() {
+            target.to_path_buf()
+        } else {
+            std::env::current_dir()
+                .map(|dir| dir.join(target))
+                .unwrap_or_else(|_| target.to_path_buf())
+        };
+        let key: String = absolute
+            .to_string_lossy()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.root.join(key.trim_start_matches('_'))
+    }
+
+    /// 保存文件当前内容的快照（文件不存在时跳过），并清理超出数量的旧快照
+    pub fn snapshot(&self, target: &Path) -> io::Result<Option<FileSnapshot>> {
+        if !target.is_file() {
+            return Ok(None);
+        }
+
+        let dir = self.dir_for(target);
+        fs::create_dir_all(&dir)?;
+
+        // 快照 ID 按名称排序即时间顺序：同一毫秒内（或时钟回拨时）在最新快照的 ID 上递增序号，
+        // 不能复用已被清理的 ID，否则新快照会排在最旧的位置
+        let mut id = chrono::Local::now().format("%Y%m%d-%H%M%S%.3f").to_string();
+        if let Some(latest) = Self::snapshot_ids(&dir)?
+            .pop()
+            .filter(|latest| *latest >= id)
+        {
+            let (base, seq) = match latest
+                .split_once('.')
+                .and_then(|(_, ms)| ms.split_once('-'))
+            {
+                Some((_, seq)) => (
+                    &latest[..latest.len() - seq.len() - 1],
+                    seq.parse::<u32>().unwrap_or(0),
+                ),
+                None => (latest.as_str(), 0),
+            };
+            id = format!("{base}-{:03}", seq + 1);
+        }
+
+        let path = dir.join(&id);
+        fs::copy(target, &path)?;
+        self.prune(&dir)?;
+
+        Ok(Some(Self::describe(id, &path)?))
+    }
+
+    /// 列出文件的快照（最新的在前）
+    pub fn list(&self, target: &Path) -> io::Result<Vec<FileSnapshot>> {
+        let dir = self.dir_for(target);
+        let mut snapshots = Vec::new();
+        for id in Self::snapshot_ids(&dir)?.into_iter().rev() {
+            snapshots.push(Self::describe(id.clone(), &dir.join(&id))?);
+        }
+        Ok(snapshots)
+    }
+
+    /// 用快照恢复文件（恢复前的当前内容同样会保存快照）
+    pub fn restore(&self, target: &Path, snapshot_id: &str) -> io::Result<()> {
+        if snapshot_id.is_empty()
+            || snapshot_id.starts_with('.')
+            || snapshot_id.contains(['/', '\\'])
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("无效的快照 ID: {snapshot_id}"),
+            ));
+        }
+        let content = fs::read(self.dir_for(target).join(snapshot_id))?;
+        write_atomic_with(target, content, Some(self))
+    }
+
+    /// 目录下的快照 ID（按时间升序）
+    fn snapshot_ids(dir: &Path) -> io::Result<Vec<String>> {
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                ids.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    fn prune(&self, dir: &Path) -> io::Result<()> {
+        let ids = Self::snapshot_ids(dir)?;
+        let excess = ids.len().saturating_sub(self.limit);
+        for id in &ids[..excess] {
+            fs::remove_file(dir.join(id))?;
+        }
+        Ok(())
+    }
+
+    fn describe(id: String, path: &Path) -> io::Result<FileSnapshot> {
+        let metadata = fs::metadata(path)?;
+        let created_at = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_millis() as i64)
+            .unwrap_or_default();
+        Ok(FileSnapshot {
+            id,
+            size: metadata.len(),
+            created_at,
+        })
+    }
+}
+
+/// 原子写入文件，覆盖前保存快照到默认快照存储
+pub fn write_atomic(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    write_atomic_with(
+        path.as_ref(),
+        contents,
+        SnapshotStore::default_store().as_ref(),
+    )
+}
+
+/// 原子写入文件
+///
+/// `snapshots` 为 `Some` 时先保存原内容的快照（快照失败只记录警告，不阻止写入）
+pub fn write_atomic_with(
+    path: &Path,
+    contents: impl AsRef<[u8]>,
+    snapshots: Option<&SnapshotStore>,
+) -> io::Result<()> {
+    if let Some(store) = snapshots {
+        if let Err(e) = store.snapshot(path) {
+            tracing::warn!(path = ?path, error = ?e, "保存配置快照失败");
+        }
+    }
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "无效的文件路径"))?
+        .to_string_lossy();
+    let dir = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let temp_path = dir.join(format!(
+        ".{file_name}.{}-{}.tmp",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = (|| {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(contents.as_ref())?;
+        // 沿用原文件权限（如 0600）
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temp_path, metadata.permissions())?;
+        }
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(&temp_path, path)?;
+        sync_dir(dir);
+        WRITTEN_DIGESTS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(path.to_path_buf(), content_digest(contents.as_ref()));
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// 计算文件内容摘要（仅用于进程内比较）
+pub fn content_digest(contents: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 文件最近一次由 write_atomic 写入的内容摘要（本进程未写入过时返回 None）
+pub fn last_written_digest(path: &Path) -> Option<u64> {
+    WRITTEN_DIGESTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(path)
+        .copied()
+}
+
+/// 同步目录项，确保 rename 在断电后仍然生效
+fn sync_dir(dir: &Path) {
+    #[cfg(unix)]
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_atomic_keeps_permissions_and_rolling_snapshots() {
+        let dir = TempDir::new().unwrap();
+        let store = SnapshotStore::new(dir.path().join("snapshots"), 3);
+        let target = dir.path().join("settings.json");
+
+        // 首次写入没有可保存的快照
+        write_atomic_with(&target, "v0", Some(&store)).unwrap();
+        assert!(store.list(&target).unwrap().is_empty());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&target, fs::Permissions::from_mode(0o600)).unwrap();
+        }
+
+        for version in 1..=5 {
+            write_atomic_with(&target, format!("v{version}"), Some(&store)).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&target).unwrap(), "v5");
+        assert_eq!(last_written_digest(&target), Some(content_digest(b"v5")));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&target).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // 只保留最近 3 个版本（v2、v3、v4），最新的在前
+        let snapshots = store.list(&target).unwrap();
+        assert_eq!(snapshots.len(), 3);
+        assert_eq!(snapshots[0].size, 2);
+
+        // 恢复 v3，恢复前的 v5 也进入快照
+        store.restore(&target, &snapshots[1].id).unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "v3");
+        let ids: Vec<String> = store
+            .list(&target)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(ids.len(), 3);
+        let latest = fs::read_to_string(store.dir_for(&target).join(&ids[0])).unwrap();
+        assert_eq!(latest, "v5");
+
+        assert!(store.restore(&target, "../settings.json").is_err());
+
+        // 没有遗留临时文件
+        let leftovers: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty());
+    }
+}
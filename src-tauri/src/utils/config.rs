@@ -1,5 +1,8 @@
 use crate::services::proxy::ProxyService;
+use crate::utils::atomic_write::write_atomic_with;
+use crate::utils::vault::{vault_ref, vault_ref_id, Vault};
 use crate::GlobalConfig;
+use std::collections::HashSet;
 use std::fs;
 use std::path::PathBuf;
 
@@ -37,9 +40,85 @@ pub fn read_global_config() -> Result<Option<GlobalConfig>, String> {
     // 自动迁移全局会话配置到工具级
     migrate_session_config(&mut config)?;
 
+    // 从密钥库解析密钥引用；仍有明文密钥时（旧版本配置）迁移到密钥库。
+    // 密钥库无法打开时保留引用原样返回配置（状态见 get_vault_status），不影响其他功能读取配置
+    let vault = Vault::default_vault().map_err(|e| format!("读取密钥库失败: {e}"))?;
+    match open_secrets(&mut config, &vault) {
+        Ok(true) => {
+            tracing::info!("检测到明文密钥，正在迁移到密钥库");
+            if let Err(e) = write_global_config(&config) {
+                tracing::warn!(error = %e, "迁移明文密钥失败，下次读取配置时重试");
+            }
+        }
+        Ok(false) => {}
+        Err(e) => tracing::warn!(error = %e, "密钥库无法打开，密钥保持引用形式"),
+    }
+
     Ok(Some(config))
 }
 
+/// 把配置中的密钥引用替换为密钥库中的值，返回是否存在明文密钥
+///
+/// 密钥库无法打开时返回错误且不修改配置；缺少的条目保留引用，避免下次保存时被置空
+fn open_secrets(config: &mut GlobalConfig, vault: &Vault) -> Result<bool, String> {
+    let mut fields = config.secret_fields_mut();
+    let has_plaintext = fields
+        .iter()
+        .any(|(_, value)| !value.is_empty() && vault_ref_id(value).is_none());
+    if !fields
+        .iter()
+        .any(|(_, value)| vault_ref_id(value).is_some())
+    {
+        return Ok(has_plaintext);
+    }
+
+    let entries = vault
+        .entries()
+        .map_err(|e| format!("读取密钥库失败: {e}"))?;
+    for (_, value) in fields.iter_mut() {
+        if let Some(id) = vault_ref_id(value) {
+            match entries.get(id) {
+                Some(secret) => **value = secret.clone(),
+                None => tracing::warn!(id = %id, "密钥库中缺少条目，保留引用"),
+            }
+        }
+    }
+    Ok(has_plaintext)
+}
+
+/// 把配置中的密钥存入密钥库，返回只包含引用的配置
+fn seal_secrets(config: &GlobalConfig, vault: &Vault) -> Result<GlobalConfig, String> {
+    let mut sealed = config.clone();
+    vault
+        .update(|entries| {
+            let mut fields = sealed.secret_fields_mut();
+            // 仍是引用的字段（包括密钥库中缺少条目的）原样保留，其条目不能被其他字段覆盖
+            let mut ids: HashSet<String> = fields
+                .iter()
+                .filter_map(|(_, value)| vault_ref_id(value))
+                .map(str::to_string)
+                .collect();
+            for (id, value) in fields.iter_mut() {
+                if value.is_empty() || vault_ref_id(value).is_some() {
+                    continue;
+                }
+                let mut entry_id = id.clone();
+                let mut suffix = 2;
+                while ids.contains(&entry_id) {
+                    entry_id = format!("{id}-{suffix}");
+                    suffix += 1;
+                }
+                entries.insert(entry_id.clone(), value.clone());
+                **value = vault_ref(&entry_id);
+                ids.insert(entry_id);
+            }
+            // 清理已从配置中移除的密钥（如删除的上游或吊销的本地密钥）
+            entries.retain(|id, _| !id.starts_with("config/") || ids.contains(id));
+        })
+        .map_err(|e| format!("写入密钥库失败: {e}"))?;
+    Ok(sealed)
+}
+
 /// 迁移旧的透明代理配置到新的多工具架构
 ///
 /// 将旧的 `transparent_proxy_*` 字段迁移到 `proxy_configs["claude-code"]`
@@ -80,7 +159,8 @@ fn migrate_proxy_config(config: &mut GlobalConfig) -> Result<(), String> {
         let config_path = global_config_path()?;
         let json = serde_json::to_string_pretty(config)
             .map_err(|e| format!("Failed to serialize config: {e}"))?;
-        fs::write(&config_path, json).map_err(|e| format!("Failed to write config: {e}"))?;
+        write_atomic_with(&config_path, json, None)
+            .map_err(|e| format!("Failed to write config: {e}"))?;
 
         #[cfg(unix)]
         {
@@ -126,7 +206,8 @@ fn migrate_session_config(config: &mut GlobalConfig) -> Result<(), String> {
         let config_path = global_config_path()?;
         let json = serde_json::to_string_pretty(config)
             .map_err(|e| format!("Failed to serialize config: {e}"))?;
-        fs::write(&config_path, json).map_err(|e| format!("Failed to write config: {e}"))?;
+        write_atomic_with(&config_path, json, None)
+            .map_err(|e| format!("Failed to write config: {e}"))?;
 
         #[cfg(unix)]
         {
@@ -159,10 +240,12 @@ fn migrate_session_config(config: &mut GlobalConfig) -> Result<(), String> {
 /// 写入全局配置，同时设置权限并更新当前进程代理
 pub fn write_global_config(config: &GlobalConfig) -> Result<(), String> {
     let config_path = global_config_path()?;
-    let json = serde_json::to_string_pretty(config)
+    let vault = Vault::default_vault().map_err(|e| format!("读取密钥库失败: {e}"))?;
+    let json = serde_json::to_string_pretty(&seal_secrets(config, &vault)?)
         .map_err(|e| format!("Failed to serialize config: {e}"))?;
 
-    fs::write(&config_path, json).map_err(|e| format!("Failed to write config: {e}"))?;
+    write_atomic_with(&config_path, json, None)
+        .map_err(|e| format!("Failed to write config: {e}"))?;
 
     #[cfg(unix)]
     {
@@ -176,6 +259,7 @@ pub fn write_global_config(config: &GlobalConfig) -> Result<(), String> {
     }
 
     ProxyService::apply_proxy_from_config(config);
+    crate::core::set_log_secrets(config.secret_values());
     Ok(())
 }
 
@@ -185,3 +269,82 @@ pub fn apply_proxy_if_configured() {
         ProxyService::apply_proxy_from_config(&config);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_secrets_keep_refs_when_vault_unavailable() {
+        let dir = TempDir::new().unwrap();
+        let vault = Vault::new(dir.path());
+        let mut config: GlobalConfig = serde_json::from_value(serde_json::json!({
+            "user_id": "u",
+            "system_token": "token-123",
+            "proxy_password": "vault:config/missing",
+        }))
+        .unwrap();
+
+        // 缺少的条目保留引用，保存时不会被置空，也不会被其他字段占用
+        let sealed = seal_secrets(&config, &vault).unwrap();
+        assert_eq!(sealed.system_token, "vault:config/system_token");
+        assert_eq!(
+            sealed.proxy_password.as_deref(),
+            Some("vault:config/missing")
+        );
+        config = sealed;
+        assert!(!open_secrets(&mut config, &vault).unwrap());
+        assert_eq!(config.system_token, "token-123");
+        assert_eq!(
+            config.proxy_password.as_deref(),
+            Some("vault:config/missing")
+        );
+
+        // 密钥库无法打开时返回错误且不修改配置
+        fs::write(dir.path().join("vault.key"), [7u8; 32]).unwrap();
+        let mut locked = seal_secrets(&config, &Vault::new(dir.path().join("other"))).unwrap();
+        assert!(open_secrets(&mut locked, &vault).is_err());
+        assert_eq!(locked.system_token, "vault:config/system_token");
+    }
+
+    #[test]
+    fn test_proxy_rejects_refs_when_vault_locked() {
+        use crate::models::ToolProxyConfig;
+        use crate::services::proxy::local_keys::authenticate;
+
+        let dir = TempDir::new().unwrap();
+        let vault = Vault::new(dir.path());
+        let mut config: GlobalConfig =
+            serde_json::from_value(serde_json::json!({"user_id": "u", "system_token": ""}))
+                .unwrap();
+        config.proxy_configs.insert(
+            "claude-code".to_string(),
+            ToolProxyConfig {
+                local_api_key: Some("dc-claude-local".to_string()),
+                real_api_key: Some("sk-real-upstream".to_string()),
+                real_base_url: Some("https://api.example.com".to_string()),
+                allow_public: true,
+                ..Default::default()
+            },
+        );
+        let sealed = seal_secrets(&config, &vault).unwrap();
+        let local_ref = "vault:config/proxy/claude-code/local_api_key";
+
+        // vault.key 被替换或丢失时密钥库无法解密，配置中保留引用
+        for wrong_key in [Some([7u8; 32]), None] {
+            match wrong_key {
+                Some(key) => fs::write(dir.path().join("vault.key"), key).unwrap(),
+                None => fs::remove_file(dir.path().join("vault.key")).unwrap(),
+            }
+            let mut opened = sealed.clone();
+            assert!(open_secrets(&mut opened, &vault).is_err());
+
+            let proxy = &opened.proxy_configs["claude-code"];
+            assert_eq!(proxy.local_api_key.as_deref(), Some(local_ref));
+            assert_eq!(authenticate(proxy, local_ref, 0), None);
+            assert!(proxy.upstream_candidates().is_empty());
+            assert!(proxy.has_unresolved_secrets());
+        }
+    }
+}
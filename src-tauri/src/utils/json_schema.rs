@@ -0,0 +1,511 @@
+// JSON Schema 校验
+//
+// 只实现 resources/ 中工具配置 Schema 用到的关键字：
+// type、enum、const、properties、patternProperties、additionalProperties、required、
+// items、uniqueItems、minItems/maxItems、minLength/maxLength、pattern、
+// minimum/maximum/exclusiveMinimum/exclusiveMaximum、anyOf/oneOf/allOf 以及文档内 $ref。
+// 其余关键字（format、description 等）忽略。
+// 嵌套层级超过 MAX_DEPTH（通常是 $ref 循环引用）时停止向下校验；编译后的正则按模式缓存。
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Schema 最大嵌套层级
+const MAX_DEPTH: usize = 64;
+
+/// 已编译的正则（无效的模式缓存为 None）
+static REGEX_CACHE: Lazy<Mutex<HashMap<String, Option<Regex>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 单个字段的校验错误
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SchemaViolation {
+    /// 字段路径（JSON Pointer，如 /env/ANTHROPIC_BASE_URL，根为空字符串）
+    pub path: String,
+    pub message: String,
+    /// 期望的类型或取值（如 string、integer | null）
+    pub expected: Option<String>,
+}
+
+/// 配置未通过 Schema 校验
+#[derive(Debug, Clone)]
+pub struct SchemaValidationError {
+    pub errors: Vec<SchemaViolation>,
+}
+
+impl std::fmt::Display for SchemaValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "配置校验失败（{} 处错误）", self.errors.len())?;
+        if let Some(first) = self.errors.first() {
+            let path = if first.path.is_empty() {
+                "/"
+            } else {
+                &first.path
+            };
+            write!(f, "：{path} {}", first.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SchemaValidationError {}
+
+/// 校验选项
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidateOptions {
+    /// 忽略 additionalProperties: false 产生的未知字段错误
+    /// （工具新增的字段或用户自定义的环境变量，Schema 可能尚未收录）
+    pub allow_unknown_fields: bool,
+}
+
+/// 按 Schema 校验值，返回所有错误（为空表示通过）
+pub fn validate(schema: &Value, value: &Value) -> Vec<SchemaViolation> {
+    validate_with(schema, value, ValidateOptions::default())
+}
+
+/// 按 Schema 和选项校验值
+pub fn validate_with(
+    schema: &Value,
+    value: &Value,
+    options: ValidateOptions,
+) -> Vec<SchemaViolation> {
+    let mut errors = Vec::new();
+    Validator {
+        root: schema,
+        options,
+        depth: Cell::new(0),
+    }
+    .check(schema, value, "", &mut errors);
+    // 同一字段可能同时命中 properties 和 patternProperties，去掉重复的错误
+    let mut unique: Vec<SchemaViolation> = Vec::with_capacity(errors.len());
+    for error in errors {
+        if !unique.contains(&error) {
+            unique.push(error);
+        }
+    }
+    unique
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+    options: ValidateOptions,
+    depth: Cell<usize>,
+}
+
+impl Validator<'_> {
+    fn check(&self, schema: &Value, value: &Value, path: &str, errors: &mut Vec<SchemaViolation>) {
+        let depth = self.depth.get();
+        if depth >= MAX_DEPTH {
+            tracing::warn!(path, "Schema 嵌套过深，可能存在循环引用，停止校验");
+            return;
+        }
+        self.depth.set(depth + 1);
+        self.check_schema(schema, value, path, errors);
+        self.depth.set(depth);
+    }
+
+    fn check_schema(
+        &self,
+        schema: &Value,
+        value: &Value,
+        path: &str,
+        errors: &mut Vec<SchemaViolation>,
+    ) {
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => {
+                errors.push(violation(path, "不允许该字段", None));
+                return;
+            }
+            Value::Object(schema) => schema,
+            _ => return,
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            match self.resolve(reference) {
+                Some(target) => self.check(target, value, path, errors),
+                None => tracing::warn!(reference, "无法解析 Schema 引用"),
+            }
+        }
+
+        if let Some(expected) = schema.get("type") {
+            let types: Vec<&str> = match expected {
+                Value::String(t) => vec![t.as_str()],
+                Value::Array(list) => list.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !types.is_empty() && !types.iter().any(|t| type_matches(t, value)) {
+                let expected = types.join(" | ");
+                errors.push(violation(
+                    path,
+                    &format!("类型错误：应为 {expected}，实际为 {}", type_name(value)),
+                    Some(expected),
+                ));
+                // 类型不符时其余关键字没有意义
+                return;
+            }
+        }
+
+        if let Some(Value::Array(options)) = schema.get("enum") {
+            if !options.contains(value) {
+                let expected = options
+                    .iter()
+                    .map(Value::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" | ");
+                errors.push(violation(
+                    path,
+                    &format!("取值无效：应为 {expected} 之一"),
+                    Some(expected),
+                ));
+            }
+        }
+        if let Some(constant) = schema.get("const") {
+            if constant != value {
+                errors.push(violation(
+                    path,
+                    &format!("取值无效：应为 {constant}"),
+                    Some(constant.to_string()),
+                ));
+            }
+        }
+
+        match value {
+            Value::Object(object) => self.check_object(schema, object, path, errors),
+            Value::Array(items) => self.check_array(schema, items, path, errors),
+            Value::String(text) => check_string(schema, text, path, errors),
+            Value::Number(_) => check_number(schema, value, path, errors),
+            _ => {}
+        }
+
+        if let Some(Value::Array(all)) = schema.get("allOf") {
+            for sub in all {
+                self.check(sub, value, path, errors);
+            }
+        }
+        if let Some(Value::Array(any)) = schema.get("anyOf") {
+            let matched = any.iter().any(|sub| self.is_valid(sub, value, path));
+            if !matched {
+                errors.push(violation(path, "不符合任何允许的格式", None));
+            }
+        }
+        if let Some(Value::Array(one)) = schema.get("oneOf") {
+            let matched = one
+                .iter()
+                .filter(|sub| self.is_valid(sub, value, path))
+                .count();
+            if matched != 1 {
+                errors.push(violation(path, "应恰好符合一种允许的格式", None));
+            }
+        }
+    }
+
+    fn is_valid(&self, schema: &Value, value: &Value, path: &str) -> bool {
+        let mut errors = Vec::new();
+        self.check(schema, value, path, &mut errors);
+        errors.is_empty()
+    }
+
+    fn check_object(
+        &self,
+        schema: &Map<String, Value>,
+        object: &Map<String, Value>,
+        path: &str,
+        errors: &mut Vec<SchemaViolation>,
+    ) {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(key) {
+                    errors.push(violation(&child_path(path, key), "缺少必填字段", None));
+                }
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let patterns: Vec<(Regex, &Value)> = schema
+            .get("patternProperties")
+            .and_then(Value::as_object)
+            .map(|patterns| {
+                patterns
+                    .iter()
+                    .filter_map(|(pattern, sub)| Some((compiled_regex(pattern)?, sub)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        for (key, field) in object {
+            let field_path = child_path(path, key);
+            let mut matched = false;
+            if let Some(sub) = properties.and_then(|p| p.get(key)) {
+                matched = true;
+                self.check(sub, field, &field_path, errors);
+            }
+            for (pattern, sub) in &patterns {
+                if pattern.is_match(key) {
+                    matched = true;
+                    self.check(sub, field, &field_path, errors);
+                }
+            }
+            if !matched {
+                match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) if !self.options.allow_unknown_fields => {
+                        errors.push(violation(&field_path, "未知字段", None));
+                    }
+                    Some(sub @ Value::Object(_)) => self.check(sub, field, &field_path, errors),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn check_array(
+        &self,
+        schema: &Map<String, Value>,
+        items: &[Value],
+        path: &str,
+        errors: &mut Vec<SchemaViolation>,
+    ) {
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+            if (items.len() as u64) < min {
+                errors.push(violation(path, &format!("至少需要 {min} 项"), None));
+            }
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+            if items.len() as u64 > max {
+                errors.push(violation(path, &format!("最多允许 {max} 项"), None));
+            }
+        }
+        if schema.get("uniqueItems") == Some(&Value::Bool(true)) {
+            for (index, item) in items.iter().enumerate() {
+                if items[..index].contains(item) {
+                    errors.push(violation(
+                        &child_path(path, &index.to_string()),
+                        "重复的项",
+                        None,
+                    ));
+                }
+            }
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (index, item) in items.iter().enumerate() {
+                self.check(
+                    item_schema,
+                    item,
+                    &child_path(path, &index.to_string()),
+                    errors,
+                );
+            }
+        }
+    }
+
+    /// 解析文档内引用（#/$defs/xxx、#/definitions/xxx）
+    fn resolve(&self, reference: &str) -> Option<&Value> {
+        self.root.pointer(reference.strip_prefix('#')?)
+    }
+}
+
+fn check_string(
+    schema: &Map<String, Value>,
+    text: &str,
+    path: &str,
+    errors: &mut Vec<SchemaViolation>,
+) {
+    let length = text.chars().count() as u64;
+    if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+        if length < min {
+            let message = if min == 1 {
+                "不能为空".to_string()
+            } else {
+                format!("长度不能少于 {min} 个字符")
+            };
+            errors.push(violation(path, &message, None));
+        }
+    }
+    if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+        if length > max {
+            errors.push(violation(path, &format!("长度不能超过 {max} 个字符"), None));
+        }
+    }
+    if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+        if let Some(re) = compiled_regex(pattern) {
+            if !re.is_match(text) {
+                errors.push(violation(
+                    path,
+                    &format!("格式不正确：应匹配 {pattern}"),
+                    Some(pattern.to_string()),
+                ));
+            }
+        }
+    }
+}
+
+fn check_number(
+    schema: &Map<String, Value>,
+    value: &Value,
+    path: &str,
+    errors: &mut Vec<SchemaViolation>,
+) {
+    let Some(number) = value.as_f64() else {
+        return;
+    };
+    let bound = |key: &str| schema.get(key).and_then(Value::as_f64);
+    if let Some(min) = bound("minimum").filter(|min| number < *min) {
+        errors.push(violation(path, &format!("不能小于 {min}"), None));
+    }
+    if let Some(min) = bound("exclusiveMinimum").filter(|min| number <= *min) {
+        errors.push(violation(path, &format!("必须大于 {min}"), None));
+    }
+    if let Some(max) = bound("maximum").filter(|max| number > *max) {
+        errors.push(violation(path, &format!("不能大于 {max}"), None));
+    }
+    if let Some(max) = bound("exclusiveMaximum").filter(|max| number >= *max) {
+        errors.push(violation(path, &format!("必须小于 {max}"), None));
+    }
+}
+
+/// 从缓存获取编译后的正则（模式无效时返回 None）
+fn compiled_regex(pattern: &str) -> Option<Regex> {
+    let mut cache = REGEX_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .entry(pattern.to_string())
+        .or_insert_with(|| Regex::new(pattern).ok())
+        .clone()
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// 拼接 JSON Pointer 路径（按 RFC 6901 转义 ~ 和 /）
+fn child_path(path: &str, key: &str) -> String {
+    format!("{path}/{}", key.replace('~', "~0").replace('/', "~1"))
+}
+
+fn violation(path: &str, message: &str, expected: Option<String>) -> SchemaViolation {
+    SchemaViolation {
+        path: path.to_string(),
+        message: message.to_string(),
+        expected,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_reports_field_errors() {
+        let schema = json!({
+            "type": "object",
+            "$defs": {"rule": {"type": "string", "minLength": 1}},
+            "properties": {
+                "cleanupPeriodDays": {"type": "integer", "minimum": 0},
+                "env": {
+                    "type": "object",
+                    "additionalProperties": false,
+                    "properties": {"ANTHROPIC_BASE_URL": {"type": "string"}}
+                },
+                "permissions": {
+                    "type": "object",
+                    "properties": {
+                        "allow": {"type": "array", "items": {"$ref": "#/$defs/rule"}, "uniqueItems": true}
+                    }
+                },
+                "forceLoginMethod": {"enum": ["claudeai", "console"]},
+                "statusLine": {
+                    "type": "object",
+                    "required": ["type"],
+                    "properties": {"type": {"const": "command"}}
+                }
+            }
+        });
+
+        let valid = json!({
+            "cleanupPeriodDays": 30,
+            "env": {"ANTHROPIC_BASE_URL": "https://jp.duckcoding.com"},
+            "permissions": {"allow": ["Bash(ls)"]},
+            "unknown": true
+        });
+        assert!(validate(&schema, &valid).is_empty());
+
+        let invalid = json!({
+            "cleanupPeriodDays": -1,
+            "env": {"ANTHROPIC_BASE_URL": 1, "TYPO": "x"},
+            "permissions": {"allow": ["", "a", "a"]},
+            "forceLoginMethod": "sso",
+            "statusLine": {}
+        });
+        let errors = validate(&schema, &invalid);
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/cleanupPeriodDays",
+                "/env/ANTHROPIC_BASE_URL",
+                "/env/TYPO",
+                "/forceLoginMethod",
+                "/permissions/allow/2",
+                "/permissions/allow/0",
+                "/statusLine/type",
+            ]
+        );
+        assert_eq!(errors[1].expected.as_deref(), Some("string"));
+        assert_eq!(errors[1].message, "类型错误：应为 string，实际为 integer");
+
+        let lenient = ValidateOptions {
+            allow_unknown_fields: true,
+        };
+        let errors = validate_with(&schema, &json!({"env": {"TYPO": "x"}}), lenient);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_validate_stops_on_ref_cycle() {
+        let schema = json!({
+            "$defs": {
+                "loop": {"$ref": "#/$defs/loop"},
+                "name": {"type": "string", "pattern": "^[a-z]+$"}
+            },
+            "properties": {
+                "a": {"$ref": "#/$defs/loop"},
+                "b": {"$ref": "#/$defs/name"}
+            }
+        });
+
+        let errors = validate(&schema, &json!({"a": 1, "b": "ok"}));
+        assert!(errors.is_empty());
+        let errors = validate(&schema, &json!({"a": 1, "b": "Not OK"}));
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["/b"]);
+    }
+}
@@ -1,7 +1,15 @@
+pub mod atomic_write;
 pub mod command;
 pub mod config;
+pub mod json_schema;
 pub mod platform;
+pub mod proxy_bypass;
+pub mod redact;
+pub mod vault;
 
+pub use atomic_write::*;
 pub use command::*;
 pub use config::*;
 pub use platform::*;
+pub use redact::*;
+pub use vault::*;
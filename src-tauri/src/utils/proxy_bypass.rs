@@ -0,0 +1,87 @@
+// 代理绕过列表匹配
+//
+// 支持精确主机名、*.domain 与 192.168.* 形式的通配模式，
+// core::http 与 ProxyService 共用同一套匹配规则
+
+use url::Url;
+
+/// 检查给定的URL是否应该绕过代理
+pub fn should_bypass_proxy(url: &str, bypass_list: &[String]) -> bool {
+    // 如果没有过滤规则，不绕过
+    if bypass_list.is_empty() {
+        return false;
+    }
+
+    let parsed_url = match Url::parse(url) {
+        Ok(url) => url,
+        Err(_) => {
+            // 如果URL解析失败，尝试作为主机名处理
+            return should_bypass_host(url, bypass_list);
+        }
+    };
+
+    // 获取主机名进行匹配
+    let host = parsed_url.host_str().unwrap_or("");
+
+    should_bypass_host(host, bypass_list)
+}
+
+/// 检查主机名是否应该绕过代理
+fn should_bypass_host(host: &str, bypass_list: &[String]) -> bool {
+    for pattern in bypass_list {
+        if matches_pattern(host, pattern) {
+            return true;
+        }
+    }
+    false
+}
+
+/// 检查主机名是否匹配给定的模式
+fn matches_pattern(host: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim();
+    let host = host.trim().to_lowercase();
+    let pattern = pattern.to_lowercase();
+
+    // 精确匹配
+    if host == pattern {
+        return true;
+    }
+
+    // 通配符匹配
+    if let Some(domain) = pattern.strip_prefix("*.") {
+        if host.ends_with(domain) || host == domain {
+            return true;
+        }
+    }
+
+    // 简单的通配符匹配（支持 *，包括 192.168.* 这类 IP 段）
+    if pattern.contains('*') {
+        return wildcard_match(&host, &pattern);
+    }
+
+    false
+}
+
+/// 简单的通配符匹配
+fn wildcard_match(text: &str, pattern: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+
+    if !pattern.contains('*') {
+        return text == pattern;
+    }
+
+    // 将模式按 * 分割
+    let parts: Vec<&str> = pattern.split('*').collect();
+
+    if parts.len() == 2 {
+        let prefix = parts[0];
+        let suffix = parts[1];
+
+        return text.starts_with(prefix) && text.ends_with(suffix);
+    }
+
+    // 更复杂的模式，简化处理
+    text.contains(&pattern.replace('*', ""))
+}
@@ -0,0 +1,92 @@
+// 敏感信息脱敏
+//
+// 用于请求抓包等需要把请求/响应内容写入磁盘的场景，替换常见的 API Key 格式：
+// - Anthropic：sk-ant-...
+// - OpenAI 及兼容中转：sk-...
+// - Google：AIza...
+// - DuckCoding 本地代理密钥：dc-<工具>-...
+// - Authorization 中的 Bearer Token
+
+use std::borrow::Cow;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// 脱敏后的占位符
+pub const REDACTED: &str = "***";
+
+static SECRET_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?x)
+        \b(?P<prefix>sk-ant-|sk-|AIza|dc-[a-z]+-)[A-Za-z0-9_\-]{8,}
+        | \b(?P<bearer>(?i:bearer)\s+)[A-Za-z0-9._~+/=\-]{8,}
+        ",
+    )
+    .expect("invalid secret regex")
+});
+
+/// 替换文本中的 API Key 等敏感信息
+///
+/// `extra` 为额外需要替换的明文（如配置中的真实 API Key），空字符串会被忽略
+pub fn redact_secrets<'a>(text: &'a str, extra: &[&str]) -> Cow<'a, str> {
+    let mut result = SECRET_PATTERN.replace_all(text, |caps: &regex::Captures| {
+        let prefix = caps
+            .name("prefix")
+            .or_else(|| caps.name("bearer"))
+            .map(|m| m.as_str())
+            .unwrap_or_default();
+        format!("{prefix}{REDACTED}")
+    });
+
+    for secret in extra.iter().filter(|s| !s.is_empty()) {
+        if result.contains(secret) {
+            result = Cow::Owned(result.replace(secret, REDACTED));
+        }
+    }
+    result
+}
+
+/// 是否为携带凭据的请求/响应头
+pub fn is_sensitive_header(name: &str) -> bool {
+    matches!(
+        name.to_ascii_lowercase().as_str(),
+        "authorization"
+            | "proxy-authorization"
+            | "x-api-key"
+            | "x-goog-api-key"
+            | "api-key"
+            | "cookie"
+            | "set-cookie"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_known_key_formats() {
+        let text = r#"{"key":"sk-ant-api03-abcdefghijkl","openai":"sk-proj-1234567890","g":"AIzaSyA1234567890abcdef","local":"dc-claudecode-AbCdEf1234567890"}"#;
+        let redacted = redact_secrets(text, &[]);
+        assert_eq!(
+            redacted,
+            r#"{"key":"sk-ant-***","openai":"sk-***","g":"AIza***","local":"dc-claudecode-***"}"#
+        );
+
+        assert_eq!(
+            redact_secrets("Authorization: Bearer eyJhbGciOi.payload", &[]),
+            "Authorization: Bearer ***"
+        );
+    }
+
+    #[test]
+    fn test_redact_extra_secrets_and_plain_text() {
+        assert_eq!(
+            redact_secrets("token=relay-secret-value", &["relay-secret-value", ""]),
+            "token=***"
+        );
+        // 普通文本不变且不分配
+        let text = "hello sk- world, risk-assessment-framework";
+        assert!(matches!(redact_secrets(text, &[]), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,379 @@
+// 本地密钥库
+//
+// 全局配置和配置备份中的 API Key、令牌、代理密码等不再以明文保存，而是集中加密存放在
+// ~/.duckcoding/vault.json，原位置只保存形如 `vault:<id>` 的引用。
+// 加密使用 AES-256-GCM，密钥来自本机密钥文件（~/.duckcoding/vault.key）。
+// 密钥库无法打开（密钥文件损坏、解密失败）时，引用保持原样，不会被置空或覆盖。
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+use super::atomic_write::write_atomic_with;
+
+/// 密钥引用前缀
+pub const VAULT_REF_PREFIX: &str = "vault:";
+
+const VAULT_VERSION: u32 = 1;
+const VAULT_AAD: &[u8] = b"duckcoding-vault-v1";
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+/// 串行化读改写，避免并发写入丢失条目
+static VAULT_LOCK: Mutex<()> = Mutex::new(());
+
+/// 密钥库状态
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultStatus {
+    pub locked: bool, // 密钥库无法打开（密钥文件损坏、解密失败等）
+    pub message: Option<String>,
+    pub entries: usize,
+}
+
+/// 密钥库文件（只有密文，不包含明文条目）
+#[derive(Serialize, Deserialize)]
+struct VaultFile {
+    version: u32,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// 本地密钥库
+#[derive(Debug, Clone)]
+pub struct Vault {
+    dir: PathBuf,
+}
+
+impl Vault {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// 默认密钥库 (~/.duckcoding/vault.json)
+    pub fn default_vault() -> Result<Self> {
+        let dir = super::config::config_dir().map_err(anyhow::Error::msg)?;
+        Ok(Self::new(dir))
+    }
+
+    fn vault_path(&self) -> PathBuf {
+        self.dir.join("vault.json")
+    }
+
+    fn key_path(&self) -> PathBuf {
+        self.dir.join("vault.key")
+    }
+
+    /// 密钥库状态（无法打开时返回 locked 而不是错误）
+    pub fn status(&self) -> VaultStatus {
+        match self.entries() {
+            Ok(entries) => VaultStatus {
+                locked: false,
+                message: None,
+                entries: entries.len(),
+            },
+            Err(e) => VaultStatus {
+                locked: true,
+                message: Some(format!("{e:#}")),
+                entries: 0,
+            },
+        }
+    }
+
+    /// 读取全部条目
+    pub fn entries(&self) -> Result<BTreeMap<String, String>> {
+        match self.read_file()? {
+            Some(file) => self.decrypt(&file),
+            None => Ok(BTreeMap::new()),
+        }
+    }
+
+    /// 读取单个条目
+    pub fn get(&self, id: &str) -> Result<Option<String>> {
+        Ok(self.entries()?.remove(id))
+    }
+
+    /// 读改写条目（内容未变化时不写盘）
+    pub fn update<R>(&self, f: impl FnOnce(&mut BTreeMap<String, String>) -> R) -> Result<R> {
+        let _guard = VAULT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let original = self.entries()?;
+
+        let mut entries = original.clone();
+        let result = f(&mut entries);
+        if entries != original {
+            self.write_file(&entries)?;
+        }
+        Ok(result)
+    }
+
+    /// 解析密钥引用（明文原样返回）
+    pub fn resolve(&self, value: &str) -> Result<String> {
+        match vault_ref_id(value) {
+            Some(id) => self
+                .get(id)?
+                .ok_or_else(|| anyhow::anyhow!("密钥库中缺少条目: {id}")),
+            None => Ok(value.to_string()),
+        }
+    }
+
+    /// 存入密钥并返回引用（空值和已是引用的值原样返回）
+    pub fn seal(&self, id: &str, value: &str) -> Result<String> {
+        if value.is_empty() || vault_ref_id(value).is_some() {
+            return Ok(value.to_string());
+        }
+        self.update(|entries| {
+            entries.insert(id.to_string(), value.to_string());
+        })?;
+        Ok(vault_ref(id))
+    }
+
+    /// 删除条目
+    pub fn remove(&self, id: &str) -> Result<()> {
+        self.update(|entries| {
+            entries.remove(id);
+        })
+    }
+
+    fn read_file(&self) -> Result<Option<VaultFile>> {
+        let path = self.vault_path();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path).context("读取密钥库失败")?;
+        let file: VaultFile = serde_json::from_str(&content).context("解析密钥库失败")?;
+        if file.version > VAULT_VERSION {
+            anyhow::bail!("不支持的密钥库版本: {}", file.version);
+        }
+        Ok(Some(file))
+    }
+
+    fn decrypt(&self, file: &VaultFile) -> Result<BTreeMap<String, String>> {
+        let key = self.machine_key(false)?;
+        let nonce: [u8; NONCE_LEN] = BASE64
+            .decode(&file.nonce)?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("密钥库 nonce 长度错误"))?;
+        let mut buffer = BASE64.decode(&file.ciphertext)?;
+
+        let plaintext = aead_key(&key)?
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(VAULT_AAD),
+                &mut buffer,
+            )
+            .map_err(|_| anyhow::anyhow!("解密密钥库失败：密钥文件与密钥库不匹配"))?;
+        serde_json::from_slice(plaintext).context("解析密钥库条目失败")
+    }
+
+    fn write_file(&self, entries: &BTreeMap<String, String>) -> Result<()> {
+        let key = self.machine_key(true)?;
+
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| anyhow::anyhow!("生成随机数失败"))?;
+        let mut buffer = serde_json::to_vec(entries)?;
+        aead_key(&key)?
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(VAULT_AAD),
+                &mut buffer,
+            )
+            .map_err(|_| anyhow::anyhow!("加密密钥库失败"))?;
+
+        let file = VaultFile {
+            version: VAULT_VERSION,
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(buffer),
+        };
+        let path = self.vault_path();
+        write_atomic_with(&path, serde_json::to_string_pretty(&file)?, None)
+            .context("写入密钥库失败")?;
+        restrict_permissions(&path)
+    }
+
+    /// 读取本机密钥（`create` 为 true 时在密钥文件不存在时生成）
+    fn machine_key(&self, create: bool) -> Result<[u8; KEY_LEN]> {
+        let path = self.key_path();
+        if path.exists() {
+            let bytes = fs::read(&path).context("读取密钥文件失败")?;
+            return bytes
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("密钥文件已损坏: {path:?}"));
+        }
+        if !create {
+            anyhow::bail!("密钥文件不存在: {path:?}");
+        }
+
+        let mut key = [0u8; KEY_LEN];
+        SystemRandom::new()
+            .fill(&mut key)
+            .map_err(|_| anyhow::anyhow!("生成随机数失败"))?;
+        fs::create_dir_all(&self.dir)?;
+        write_atomic_with(&path, key, None).context("写入密钥文件失败")?;
+        restrict_permissions(&path)?;
+        Ok(key)
+    }
+}
+
+fn aead_key(key: &[u8; KEY_LEN]) -> Result<LessSafeKey> {
+    let key = UnboundKey::new(&AES_256_GCM, key).map_err(|_| anyhow::anyhow!("无效的密钥"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+fn pbkdf2_key(passphrase: &str, salt: &str) -> [u8; KEY_LEN] {
+    let mut key = [0u8; KEY_LEN];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(PBKDF2_ITERATIONS).unwrap(),
+        salt.as_bytes(),
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    key
+}
+
+fn restrict_permissions(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .context("设置密钥库权限失败")?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// 口令加密的数据（用于导出文件等密钥库之外的场景）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PassphraseCipher {
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+/// 用口令加密数据（每次生成新的 salt 和 nonce，派生密钥不缓存）
+pub fn encrypt_with_passphrase(
+    passphrase: &str,
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<PassphraseCipher> {
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|_| rng.fill(&mut nonce))
+        .map_err(|_| anyhow::anyhow!("生成随机数失败"))?;
+    let salt = BASE64.encode(salt);
+
+    let mut buffer = plaintext.to_vec();
+    aead_key(&pbkdf2_key(passphrase, &salt))?
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(aad),
+            &mut buffer,
+        )
+        .map_err(|_| anyhow::anyhow!("加密失败"))?;
+
+    Ok(PassphraseCipher {
+        salt,
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(buffer),
+    })
+}
+
+/// 解密口令加密的数据
+pub fn decrypt_with_passphrase(
+    passphrase: &str,
+    aad: &[u8],
+    cipher: &PassphraseCipher,
+) -> Result<Vec<u8>> {
+    let nonce: [u8; NONCE_LEN] = BASE64
+        .decode(&cipher.nonce)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("nonce 长度错误"))?;
+    let mut buffer = BASE64.decode(&cipher.ciphertext)?;
+    let plaintext = aead_key(&pbkdf2_key(passphrase, &cipher.salt))?
+        .open_in_place(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(aad),
+            &mut buffer,
+        )
+        .map_err(|_| anyhow::anyhow!("解密失败：口令不正确或数据已损坏"))?;
+    Ok(plaintext.to_vec())
+}
+
+/// 生成密钥引用
+pub fn vault_ref(id: &str) -> String {
+    format!("{VAULT_REF_PREFIX}{id}")
+}
+
+/// 解析密钥引用中的条目 ID（不是引用时返回 None）
+pub fn vault_ref_id(value: &str) -> Option<&str> {
+    value.strip_prefix(VAULT_REF_PREFIX)
+}
+
+/// 配置档案密钥条目 ID 的前缀
+pub const PROFILE_SECRET_PREFIX: &str = "profile/";
+
+/// 配置档案中密钥的条目 ID
+pub fn profile_secret_id(tool_id: &str, profile_id: &str) -> String {
+    format!("{PROFILE_SECRET_PREFIX}{tool_id}/{profile_id}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_vault_roundtrip_and_locked_status() {
+        let dir = TempDir::new().unwrap();
+        let vault = Vault::new(dir.path());
+        assert!(vault.entries().unwrap().is_empty());
+
+        vault
+            .update(|entries| {
+                entries.insert("config/system_token".to_string(), "token-123".to_string());
+            })
+            .unwrap();
+        assert_eq!(
+            vault.get("config/system_token").unwrap().as_deref(),
+            Some("token-123")
+        );
+        // 文件中只有密文
+        let raw = fs::read_to_string(dir.path().join("vault.json")).unwrap();
+        assert!(!raw.contains("token-123"));
+        assert_eq!(
+            fs::read(dir.path().join("vault.key")).unwrap().len(),
+            KEY_LEN
+        );
+
+        assert_eq!(Vault::new(dir.path()).status().entries, 1);
+
+        // 密钥文件与密钥库不匹配时报告锁定状态
+        fs::write(dir.path().join("vault.key"), [7u8; KEY_LEN]).unwrap();
+        let broken = Vault::new(dir.path());
+        assert!(broken.entries().is_err());
+        let status = broken.status();
+        assert!(status.locked);
+        assert!(status.message.is_some());
+
+        assert_eq!(
+            vault_ref_id(&vault_ref("profile/codex/work")),
+            Some("profile/codex/work")
+        );
+        assert_eq!(vault_ref_id("sk-plain"), None);
+    }
+}
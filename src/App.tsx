@@ -14,13 +14,18 @@ import { useToast } from '@/hooks/use-toast';
 import { useAppEvents } from '@/hooks/useAppEvents';
 import { useCloseAction } from '@/hooks/useCloseAction';
 import { Toaster } from '@/components/ui/toaster';
+import { ToastAction } from '@/components/ui/toast';
 import {
   checkInstallations,
   checkForAppUpdates,
   getGlobalConfig,
   getUserQuota,
+  getVaultStatus,
   getUsageStats,
+  reconcileConfigDrift,
+  CONFIG_DRIFT_EVENT,
   type CloseAction,
+  type ConfigDrift,
   type ToolStatus,
   type GlobalConfig,
   type UserQuotaResult,
@@ -152,6 +157,22 @@ function App() {
     loadGlobalConfig();
   }, [loadTools, loadGlobalConfig]);
 
+  // 启动时检查密钥库：无法打开时配置中的密钥仍是 vault: 引用，提示用户
+  useEffect(() => {
+    getVaultStatus()
+      .then((vault) => {
+        if (vault.locked) {
+          toast({
+            title: '密钥库无法打开',
+            description: `已保存的密钥暂不可用：${vault.message ?? '未知错误'}`,
+            variant: 'destructive',
+            duration: 10000,
+          });
+        }
+      })
+      .catch((error) => console.error('Failed to read vault status:', error));
+  }, [toast]);
+
   // 应用启动时检查更新（延迟1秒，避免影响启动速度）
   useEffect(() => {
     const timer = setTimeout(() => {
@@ -200,6 +221,49 @@ function App() {
     };
   }, [toast]);
 
+  // 监听工具配置被外部修改（配置偏移），提供一键修复
+  useEffect(() => {
+    const driftMessages: Record<ConfigDrift['kind'], string> = {
+      proxy_bypassed: '配置已不再指向透明代理，保存的真实配置可能已过期',
+      key_changed: 'API Key 或 Base URL 被手动修改',
+      profile_changed: '已手动切换到其他配置',
+    };
+
+    const unlistenDrift = listen<ConfigDrift>(CONFIG_DRIFT_EVENT, (event) => {
+      const drift = event.payload;
+      const profileHint = drift.current_profile ? `（${drift.current_profile}）` : '';
+
+      toast({
+        title: `${drift.tool_name} 配置被外部修改`,
+        description: `${driftMessages[drift.kind]}${profileHint}`,
+        action: (
+          <ToastAction
+            altText="一键修复"
+            onClick={() => {
+              reconcileConfigDrift(drift.tool_id)
+                .then(() => {
+                  toast({ title: '配置已同步', description: drift.tool_name });
+                })
+                .catch((error) => {
+                  toast({
+                    variant: 'destructive',
+                    title: '修复配置失败',
+                    description: String(error),
+                  });
+                });
+            }}
+          >
+            一键修复
+          </ToastAction>
+        ),
+      });
+    });
+
+    return () => {
+      unlistenDrift.then((fn) => fn());
+    };
+  }, [toast]);
+
   // 智能预加载：只要有凭证就立即预加载统计数据
   useEffect(() => {
     // 条件：配置已加载 + 有凭证 + 还没有统计数据 + 不在加载中 + 没有失败过
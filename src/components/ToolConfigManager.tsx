@@ -28,6 +28,7 @@ import {
   saveClaudeSettings,
   saveCodexSettings,
   saveGeminiSettings,
+  SaveSettingsError,
   type GeminiEnvConfig,
   type CodexSettingsPayload,
   type GeminiSettingsPayload,
@@ -241,7 +242,17 @@ export function ToolConfigManager({
         description: '配置已写入目标文件。',
       });
     } catch (err) {
-      const message = err instanceof Error ? err.message : String(err);
+      let message = err instanceof Error ? err.message : String(err);
+      if (err instanceof SaveSettingsError && err.errors.length > 0) {
+        // 逐字段列出校验错误（最多 5 条）
+        const lines = err.errors
+          .slice(0, 5)
+          .map((violation) => `${violation.path || '/'}：${violation.message}`);
+        if (err.errors.length > 5) {
+          lines.push(`…… 共 ${err.errors.length} 处错误`);
+        }
+        message = lines.join('\n');
+      }
       toast({
         title: '保存失败',
         description: message,
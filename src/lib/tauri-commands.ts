@@ -34,6 +34,142 @@ export interface ActiveConfig {
   profile_name?: string;
 }
 
+export interface ProfileRecord {
+  id: string;
+  tool_id: string;
+  name: string;
+  base_url: string;
+  api_key_ref: string; // 密钥库引用
+  model: string | null;
+  provider: string | null; // Codex 的 model_provider
+  provider_settings?: Record<string, unknown>; // Codex 的 model_providers.<provider> 表
+  notes: string;
+  tags: string[];
+  created_at: number; // Unix 秒
+  updated_at: number; // Unix 秒
+  last_check?: ProfileHealth; // 最近一次连通性检查结果
+}
+
+export type HealthStatus = 'healthy' | 'auth_failed' | 'http_error' | 'unreachable';
+
+export interface ProfileHealth {
+  status: HealthStatus;
+  http_status: number | null;
+  latency_ms: number | null;
+  auth_valid: boolean | null; // null 表示无法判断
+  models: string[];
+  message: string | null;
+  checked_at: number; // Unix 秒
+}
+
+export interface ProfileCheckResult {
+  tool_id: string;
+  name: string;
+  health: ProfileHealth | null;
+  error: string | null; // 无法发起请求（如读取 API Key 失败）
+}
+
+export interface ProfileDetails {
+  model: string | null;
+  notes: string;
+  tags: string[];
+}
+
+export interface SchemaViolation {
+  path: string; // JSON Pointer，如 /env/ANTHROPIC_BASE_URL
+  message: string;
+  expected: string | null; // 期望的类型或取值
+}
+
+// 保存工具配置失败时后端返回的错误（errors 为 Schema 校验错误）
+export interface SaveSettingsErrorPayload {
+  message: string;
+  errors: SchemaViolation[];
+}
+
+export class SaveSettingsError extends Error {
+  errors: SchemaViolation[];
+
+  constructor(payload: SaveSettingsErrorPayload) {
+    super(payload.message);
+    this.name = 'SaveSettingsError';
+    this.errors = payload.errors ?? [];
+  }
+}
+
+async function invokeSaveSettings(command: string, args: Record<string, unknown>): Promise<void> {
+  try {
+    await invoke<void>(command, args);
+  } catch (err) {
+    if (err && typeof err === 'object' && 'message' in err && 'errors' in err) {
+      throw new SaveSettingsError(err as SaveSettingsErrorPayload);
+    }
+    throw err;
+  }
+}
+
+export interface ProfileRef {
+  tool_id: string;
+  name: string;
+}
+
+export interface BundleInfo {
+  version: number;
+  created_at: string;
+  encrypted: boolean;
+  profiles: number | null; // 未加密时的配置数量
+}
+
+export type ConflictStrategy = 'skip' | 'overwrite' | 'rename';
+
+export type ImportAction = 'created' | 'overwritten' | 'renamed' | 'skipped' | 'failed';
+
+export interface ImportOutcome {
+  tool_id: string;
+  name: string;
+  saved_as: string | null; // 实际保存的名称
+  action: ImportAction;
+  message: string | null;
+  errors: SchemaViolation[];
+}
+
+export interface BundleImportReport {
+  outcomes: ImportOutcome[];
+}
+
+export interface FileSnapshot {
+  id: string;
+  size: number;
+  created_at: number; // 毫秒时间戳
+}
+
+export interface ConfigSnapshots {
+  file: string;
+  path: string;
+  snapshots: FileSnapshot[]; // 最新的在前
+}
+
+// 配置偏移事件（工具配置文件被外部修改）
+export const CONFIG_DRIFT_EVENT = 'duckcoding://config-drift';
+
+export type DriftKind = 'proxy_bypassed' | 'key_changed' | 'profile_changed';
+
+export interface ConfigDrift {
+  tool_id: string;
+  tool_name: string;
+  kind: DriftKind;
+  expected_base_url: string;
+  current_base_url: string;
+  current_profile: string | null; // 与当前配置一致的已保存配置
+  detected_at: number; // 毫秒时间戳
+}
+
+export interface VaultStatus {
+  locked: boolean; // 密钥库无法打开（密钥文件损坏、解密失败等），配置中的密钥保持 vault: 引用
+  message: string | null;
+  entries: number;
+}
+
 export interface GlobalConfig {
   user_id: string;
   system_token: string;
@@ -237,6 +373,82 @@ export async function deleteProfile(tool: string, profile: string): Promise<void
   return await invoke<void>('delete_profile', { tool, profile });
 }
 
+export async function listProfileRecords(tool: string): Promise<ProfileRecord[]> {
+  return await invoke<ProfileRecord[]>('list_profile_records', { tool });
+}
+
+export async function renameProfile(
+  tool: string,
+  profile: string,
+  newName: string,
+): Promise<ProfileRecord> {
+  return await invoke<ProfileRecord>('rename_profile', { tool, profile, newName });
+}
+
+export async function duplicateProfile(
+  tool: string,
+  profile: string,
+  newName: string,
+): Promise<ProfileRecord> {
+  return await invoke<ProfileRecord>('duplicate_profile', { tool, profile, newName });
+}
+
+export async function updateProfileDetails(
+  tool: string,
+  profile: string,
+  details: ProfileDetails,
+): Promise<ProfileRecord> {
+  return await invoke<ProfileRecord>('update_profile_details', { tool, profile, details });
+}
+
+/**
+ * 搜索配置
+ * @param tool - 工具 ID（为 null 时搜索所有工具）
+ * @param query - 关键字（匹配名称、Base URL、模型、备注和标签）
+ * @param tag - 只返回带有该标签的配置
+ */
+export async function searchProfiles(
+  tool: string | null,
+  query: string,
+  tag: string | null = null,
+): Promise<ProfileRecord[]> {
+  return await invoke<ProfileRecord[]>('search_profiles', { tool, query, tag });
+}
+
+export async function checkProfileHealth(tool: string, profile: string): Promise<ProfileHealth> {
+  return await invoke<ProfileHealth>('check_profile_health', { tool, profile });
+}
+
+export async function checkAllProfilesHealth(): Promise<ProfileCheckResult[]> {
+  return await invoke<ProfileCheckResult[]>('check_all_profiles_health');
+}
+
+/**
+ * 导出配置到导出包文件
+ * @param path - 导出文件路径
+ * @param profiles - 要导出的配置（为空时导出全部）
+ * @param passphrase - 加密口令（为 null 时不加密，文件中包含明文 API Key）
+ */
+export async function exportProfileBundle(
+  path: string,
+  profiles: ProfileRef[],
+  passphrase: string | null,
+): Promise<number> {
+  return await invoke<number>('export_profile_bundle', { path, profiles, passphrase });
+}
+
+export async function inspectProfileBundle(path: string): Promise<BundleInfo> {
+  return await invoke<BundleInfo>('inspect_profile_bundle', { path });
+}
+
+export async function importProfileBundle(
+  path: string,
+  passphrase: string | null,
+  strategy: ConflictStrategy,
+): Promise<BundleImportReport> {
+  return await invoke<BundleImportReport>('import_profile_bundle', { path, passphrase, strategy });
+}
+
 export async function getActiveConfig(tool: string): Promise<ActiveConfig> {
   return await invoke<ActiveConfig>('get_active_config', { tool });
 }
@@ -250,6 +462,50 @@ export async function getProfileConfig(tool: string, profile: string): Promise<A
   return await invoke<ActiveConfig>('get_profile_config', { tool, profile });
 }
 
+/**
+ * 列出工具配置文件的快照（每次写入前自动保存）
+ * @param tool - 工具 ID
+ */
+export async function listConfigSnapshots(tool: string): Promise<ConfigSnapshots[]> {
+  return await invoke<ConfigSnapshots[]>('list_config_snapshots', { tool });
+}
+
+/**
+ * 用快照恢复工具配置文件
+ * @param tool - 工具 ID
+ * @param file - 配置文件名（如 settings.json）
+ * @param snapshotId - 快照 ID
+ */
+export async function restoreConfigSnapshot(
+  tool: string,
+  file: string,
+  snapshotId: string,
+): Promise<void> {
+  return await invoke<void>('restore_config_snapshot', { tool, file, snapshotId });
+}
+
+/**
+ * 获取未处理的配置偏移（工具配置被外部修改）
+ */
+export async function getConfigDrifts(): Promise<ConfigDrift[]> {
+  return await invoke<ConfigDrift[]>('get_config_drifts');
+}
+
+/**
+ * 一键修复配置偏移
+ * @param tool - 工具 ID
+ */
+export async function reconcileConfigDrift(tool: string): Promise<ConfigDrift> {
+  return await invoke<ConfigDrift>('reconcile_config_drift', { tool });
+}
+
+/**
+ * 获取密钥库状态
+ */
+export async function getVaultStatus(): Promise<VaultStatus> {
+  return await invoke<VaultStatus>('get_vault_status');
+}
+
 export async function saveGlobalConfig(config: GlobalConfig): Promise<void> {
   return await invoke<void>('save_global_config', { config });
 }
@@ -316,7 +572,7 @@ export async function getClaudeSettings(): Promise<JsonObject> {
 }
 
 export async function saveClaudeSettings(settings: JsonObject): Promise<void> {
-  return await invoke<void>('save_claude_settings', { settings });
+  return await invokeSaveSettings('save_claude_settings', { settings });
 }
 
 export async function getClaudeSchema(): Promise<JsonSchema> {
@@ -331,7 +587,7 @@ export async function saveCodexSettings(
   settings: JsonObject,
   authToken?: string | null,
 ): Promise<void> {
-  return await invoke<void>('save_codex_settings', { settings, authToken });
+  return await invokeSaveSettings('save_codex_settings', { settings, authToken });
 }
 
 export async function getCodexSchema(): Promise<JsonSchema> {
@@ -360,7 +616,7 @@ export async function saveGeminiSettings(
   settings: JsonObject,
   env: GeminiEnvConfig,
 ): Promise<void> {
-  return await invoke<void>('save_gemini_settings', { settings, env });
+  return await invokeSaveSettings('save_gemini_settings', { settings, env });
 }
 
 export async function getGeminiSchema(): Promise<JsonSchema> {
@@ -381,12 +637,148 @@ export interface ToolProxyConfig {
   allow_public: boolean;
   session_endpoint_config_enabled: boolean; // 工具级：是否允许会话自定义端点
   auto_start: boolean; // 应用启动时自动运行代理（默认关闭）
+  upstreams?: UpstreamConfig[]; // 备用上游（按顺序故障转移，real_* 为首选）
+  upstream_cooldown_secs?: number; // 上游失败后的冷却时间（秒）
+  model_mappings?: ModelMappingRule[]; // 模型映射规则（按顺序匹配第一条）
+  upstream_client?: UpstreamClientConfig; // 上游 HTTP 客户端参数
+  shutdown_grace_secs?: number; // 停止代理时等待活跃连接结束的最长时间（秒）
+  listener?: ListenerConfig; // 本地监听连接参数
+  rate_limit?: RateLimitConfig; // 请求限流
+  local_api_keys?: LocalApiKey[]; // 额外的命名本地密钥
+  tls?: TlsConfig; // 监听端口 TLS
+  ip_filter?: IpFilterConfig; // 连接来源过滤
+  capture?: CaptureConfig; // 请求抓包
+}
+
+// 请求抓包配置（抓包保存在 ~/.duckcoding/captures/<工具>/）
+export interface CaptureConfig {
+  enabled: boolean;
+  max_body_bytes: number; // 单个请求体/响应体最多保存的字节数
+  max_files: number; // 每个工具最多保留的抓包数量
+}
+
+// 单次请求的抓包内容（凭据已脱敏）
+export interface CapturedExchange {
+  id: string;
+  tool_id: string;
+  timestamp: number; // Unix 时间戳，毫秒
+  method: string;
+  path: string;
+  query: string | null;
+  request_headers: Record<string, string>;
+  request_body: string;
+  request_truncated: boolean;
+  upstream_base_url: string | null;
+  target_url: string | null;
+  status: number | null;
+  response_headers: Record<string, string>;
+  response_body: string; // SSE 为完整事件流
+  response_truncated: boolean;
+  streamed: boolean;
+}
+
+export interface DiffLine {
+  kind: 'same' | 'added' | 'removed';
+  text: string;
+}
+
+// 抓包重放结果
+export interface ReplayResult {
+  capture_id: string;
+  upstream_base_url: string;
+  status: number;
+  original_status: number | null;
+  response_headers: Record<string, string>;
+  response_body: string;
+  identical: boolean; // 响应体与原抓包完全一致
+  diff: DiffLine[]; // 原响应（removed）与重放响应（added）的逐行对比
+  diff_truncated: boolean;
+}
+
+// 连接来源过滤（CIDR 或单个 IP；本机回环地址始终放行）
+export interface IpFilterConfig {
+  allow: string[]; // 非空时只接受这些网段，如 "10.8.0.0/16"
+  deny: string[]; // 拒绝的网段（优先于 allow）
+}
+
+// 监听端口 TLS 配置（启用后非本机连接必须使用 TLS；未指定证书时使用自动生成的自签名证书）
+export interface TlsConfig {
+  enabled: boolean;
+  cert_path: string | null; // PEM 证书链路径（需与 key_path 同时配置）
+  key_path: string | null; // PEM 私钥路径
+}
+
+// TLS 证书信息（用于客户端固定证书）
+export interface TlsCertificateInfo {
+  cert_path: string;
+  self_signed: boolean;
+  fingerprint_sha256: string; // 大写十六进制，冒号分隔
+  certificate_pem: string;
+}
+
+// 命名本地密钥（通过验证的密钥 ID 会记录到会话和请求日志）
+export interface LocalApiKey {
+  id: string;
+  key: string;
+  owner: string; // 使用者标签
+  created_at: number; // 创建时间（Unix 秒）
+  expires_at: number | null; // 过期时间（Unix 秒，null 表示永不过期）
+  enabled: boolean; // 吊销后为 false
+}
+
+// 限流配置（null 或 0 表示不限制；会话按 metadata.user_id 区分）
+export interface RateLimitConfig {
+  requests_per_minute: number | null;
+  max_concurrent_requests: number | null;
+  session_requests_per_minute: number | null;
+  session_max_concurrent_requests: number | null;
+}
+
+export interface ListenerConfig {
+  keep_alive: boolean; // HTTP/1.1 是否复用连接
+  header_read_timeout_secs: number; // 读取请求头超时（0 表示不限制）
+  http2_keep_alive_interval_secs: number | null; // HTTP/2 keep-alive ping 间隔（null 表示关闭）
+  http2_keep_alive_timeout_secs: number; // HTTP/2 ping 响应超时
+  http2_max_concurrent_streams: number | null; // HTTP/2 单连接最大并发流
+}
+
+export interface UpstreamClientConfig {
+  connect_timeout_secs: number; // 建立连接超时
+  read_timeout_secs: number; // 两次读取之间的超时
+  http2_keep_alive_secs: number | null; // HTTP/2 keep-alive ping 间隔（null 表示关闭）
+  pool_idle_timeout_secs: number; // 空闲连接保留时间
+}
+
+export interface ModelMappingRule {
+  pattern: string; // glob 模式，如 "claude-opus-*"
+  target: string; // 实际发送给上游的模型
+  reverse?: boolean; // 响应中是否还原为客户端请求的模型名称
+}
+
+export interface UpstreamConfig {
+  base_url: string;
+  api_key: string;
+  weight?: number | null; // 配置后在带权重的健康上游之间轮询
 }
 
 export interface TransparentProxyStatus {
   running: boolean;
   port: number;
-}
+  bound_address: string | null; // 实际监听地址
+  started_at: number | null; // 启动时间（Unix 秒）
+  uptime_secs: number;
+  active_connections: number; // 活跃连接数（停止过程中为正在排空的连接）
+  rejected_connections: number; // 被来源过滤拒绝的连接数
+  total_requests: number;
+  error_count: number;
+  last_error: string | null;
+  last_error_at: number | null; // 最近一次错误时间（Unix 秒）
+  current_upstream: string | null; // 最近一次使用的上游 base_url
+  tls_fingerprint: string | null; // 启用 TLS 时的证书 SHA-256 指纹
+}
+
+// 代理状态变化事件（负载为运行中实例的状态，不含端口）
+export const PROXY_STATUS_EVENT = 'duckcoding://proxy-status-changed';
 
 // 多工具代理状态映射
 export type AllProxyStatus = Record<string, TransparentProxyStatus>;
@@ -439,6 +831,105 @@ export async function getAllProxyStatus(): Promise<AllProxyStatus> {
   return await invoke<AllProxyStatus>('get_all_proxy_status');
 }
 
+/**
+ * 获取工具的命名本地密钥列表
+ * @param toolId - 工具 ID
+ */
+export async function listLocalApiKeys(toolId: string): Promise<LocalApiKey[]> {
+  return await invoke<LocalApiKey[]>('list_local_api_keys', { toolId });
+}
+
+/**
+ * 创建命名本地密钥（代理运行中时立即生效）
+ * @param toolId - 工具 ID
+ * @param owner - 使用者标签
+ * @param expiresAt - 过期时间（Unix 秒，null 表示永不过期）
+ */
+export async function createLocalApiKey(
+  toolId: string,
+  owner: string,
+  expiresAt: number | null = null,
+): Promise<LocalApiKey> {
+  return await invoke<LocalApiKey>('create_local_api_key', { toolId, owner, expiresAt });
+}
+
+/**
+ * 吊销命名本地密钥
+ * @param toolId - 工具 ID
+ * @param keyId - 密钥 ID
+ */
+export async function revokeLocalApiKey(toolId: string, keyId: string): Promise<LocalApiKey> {
+  return await invoke<LocalApiKey>('revoke_local_api_key', { toolId, keyId });
+}
+
+/**
+ * 轮换命名本地密钥（旧密钥立即失效）
+ * @param toolId - 工具 ID
+ * @param keyId - 密钥 ID
+ */
+export async function rotateLocalApiKey(toolId: string, keyId: string): Promise<LocalApiKey> {
+  return await invoke<LocalApiKey>('rotate_local_api_key', { toolId, keyId });
+}
+
+/**
+ * 获取工具代理的 TLS 证书信息（自签名证书不存在时会先生成）
+ * @param toolId - 工具 ID
+ */
+export async function getProxyTlsCertificate(toolId: string): Promise<TlsCertificateInfo> {
+  return await invoke<TlsCertificateInfo>('get_proxy_tls_certificate', { toolId });
+}
+
+/**
+ * 启用或关闭工具代理的请求抓包（运行中的代理立即生效）
+ * @param toolId - 工具 ID
+ * @param enabled - 是否启用
+ */
+export async function setProxyCaptureEnabled(toolId: string, enabled: boolean): Promise<void> {
+  return await invoke<void>('set_proxy_capture_enabled', { toolId, enabled });
+}
+
+/**
+ * 读取抓包内容
+ * @param toolId - 工具 ID
+ * @param captureId - 请求记录中的 capture_id
+ */
+export async function getProxyCapture(
+  toolId: string,
+  captureId: string,
+): Promise<CapturedExchange> {
+  return await invoke<CapturedExchange>('get_proxy_capture', { toolId, captureId });
+}
+
+/**
+ * 重放抓包请求并与原始响应对比
+ * @param toolId - 工具 ID
+ * @param captureId - 抓包 ID
+ * @param baseUrl - 重放目标上游（留空使用当前配置的上游）
+ * @param apiKey - 目标上游的 API Key（留空时复用配置中相同地址上游的密钥）
+ */
+export async function replayProxyCapture(
+  toolId: string,
+  captureId: string,
+  baseUrl?: string,
+  apiKey?: string,
+): Promise<ReplayResult> {
+  return await invoke<ReplayResult>('replay_proxy_capture', {
+    toolId,
+    captureId,
+    baseUrl: baseUrl ?? null,
+    apiKey: apiKey ?? null,
+  });
+}
+
+/**
+ * 删除工具的全部抓包文件
+ * @param toolId - 工具 ID
+ * @returns 删除的抓包数量
+ */
+export async function clearProxyCaptures(toolId: string): Promise<number> {
+  return await invoke<number>('clear_proxy_captures', { toolId });
+}
+
 // 更新管理相关函数
 export async function checkForAppUpdates(): Promise<UpdateInfo> {
   return await invoke<UpdateInfo>('check_for_app_updates');
@@ -497,6 +988,8 @@ export interface SessionRecord {
   request_count: number;
   created_at: number;
   updated_at: number;
+  /** 最近一次请求使用的本地密钥 ID（未使用命名密钥时为 null） */
+  key_id: string | null;
 }
 
 /**
@@ -579,6 +1072,121 @@ export async function updateSessionNote(sessionId: string, note: string | null):
   });
 }
 
+/**
+ * token 用量汇总
+ */
+export interface TokenUsageSummary {
+  request_count: number;
+  input_tokens: number;
+  output_tokens: number;
+  cache_creation_tokens: number;
+  cache_read_tokens: number;
+}
+
+/**
+ * 获取单个会话的 token 用量汇总
+ * @param sessionId - 会话 ID
+ */
+export async function getSessionTokenUsage(sessionId: string): Promise<TokenUsageSummary> {
+  return await invoke<TokenUsageSummary>('get_session_token_usage', { sessionId });
+}
+
+/**
+ * 获取工具的 token 用量汇总
+ * @param toolId - 工具 ID
+ * @param since - 起始时间（Unix 时间戳，秒；null 表示全部）
+ */
+export async function getToolTokenUsage(
+  toolId: string,
+  since: number | null = null,
+): Promise<TokenUsageSummary> {
+  return await invoke<TokenUsageSummary>('get_tool_token_usage', { toolId, since });
+}
+
+/**
+ * 代理请求审计记录
+ */
+export interface ProxyRequestRecord {
+  id: number;
+  /** 请求时间（Unix 时间戳，毫秒） */
+  timestamp: number;
+  tool_id: string;
+  session_id: string | null;
+  method: string;
+  path: string;
+  upstream_host: string | null;
+  status_code: number | null;
+  /** 从收到请求到响应结束的耗时（毫秒） */
+  latency_ms: number;
+  bytes_in: number;
+  bytes_out: number;
+  /** 错误类型（成功时为 null） */
+  error_kind: string | null;
+  /** 上游返回的原始错误响应体（截断） */
+  error_body: string | null;
+  /** 使用的本地密钥 ID（未使用命名密钥时为 null） */
+  key_id: string | null;
+  /** 请求抓包 ID（未启用抓包时为 null） */
+  capture_id: string | null;
+}
+
+/**
+ * 代理请求日志过滤条件
+ */
+export interface ProxyRequestFilter {
+  session_id?: string | null;
+  status_min?: number | null;
+  status_max?: number | null;
+  /** 仅显示失败请求 */
+  errors_only?: boolean;
+  /** 起始时间（Unix 时间戳，毫秒） */
+  since?: number | null;
+  /** 截止时间（Unix 时间戳，毫秒） */
+  until?: number | null;
+  path_contains?: string | null;
+  /** 按本地密钥过滤 */
+  key_id?: string | null;
+}
+
+/**
+ * 代理请求日志分页响应
+ */
+export interface ProxyRequestListResponse {
+  requests: ProxyRequestRecord[];
+  total: number;
+  page: number;
+  page_size: number;
+}
+
+/**
+ * 获取代理请求日志
+ * @param toolId - 工具 ID
+ * @param page - 页码（从 1 开始）
+ * @param pageSize - 每页数量
+ * @param filter - 过滤条件
+ */
+export async function getProxyRequestLog(
+  toolId: string,
+  page: number,
+  pageSize: number,
+  filter: ProxyRequestFilter | null = null,
+): Promise<ProxyRequestListResponse> {
+  return await invoke<ProxyRequestListResponse>('get_proxy_request_log', {
+    toolId,
+    page,
+    pageSize,
+    filter,
+  });
+}
+
+/**
+ * 清空指定工具的代理请求日志
+ * @param toolId - 工具 ID
+ */
+export async function clearProxyRequestLog(toolId: string): Promise<void> {
+  return await invoke<void>('clear_proxy_request_log', { toolId });
+}
+
 // ==================== 日志配置管理 ====================
 
 /**
@@ -15,6 +15,9 @@ export function useInstallation(_tools: ToolStatus[]) {
       'claude-code': 'official',
       codex: isMac ? 'brew' : 'npm',
       'gemini-cli': 'npm',
+      'qwen-code': 'npm',
+      opencode: 'npm',
+      crush: 'npm',
     };
   });
   const [mirrorStaleDialog, setMirrorStaleDialog] = useState({
@@ -56,10 +59,9 @@ export function useInstallation(_tools: ToolStatus[]) {
           methods.unshift({ value: 'brew', label: 'Homebrew (推荐)', disabled: false });
         }
         return methods;
-      } else if (toolId === 'gemini-cli') {
-        return [{ value: 'npm', label: 'npm 安装 (推荐)', disabled: !nodeEnv?.npm_available }];
       }
-      return [];
+      // Gemini CLI、Qwen Code、opencode、Crush 等仅支持 npm 安装
+      return [{ value: 'npm', label: 'npm 安装 (推荐)', disabled: !nodeEnv?.npm_available }];
     },
     [nodeEnv],
   );
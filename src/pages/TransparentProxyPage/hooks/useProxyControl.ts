@@ -2,10 +2,12 @@
 // 封装代理启停控制逻辑，复用 Tauri 命令
 
 import { useState, useEffect, useCallback } from 'react';
+import { listen } from '@tauri-apps/api/event';
 import {
   startToolProxy,
   stopToolProxy,
   getAllProxyStatus,
+  PROXY_STATUS_EVENT,
   type AllProxyStatus,
 } from '@/lib/tauri-commands';
 import type { ToolId } from '../types/proxy-history';
@@ -118,6 +120,16 @@ export function useProxyControl() {
     refreshProxyStatus();
   }, [refreshProxyStatus]);
 
+  // 后端状态变化时刷新（连接数、请求数、错误等）
+  useEffect(() => {
+    const unlisten = listen(PROXY_STATUS_EVENT, () => {
+      refreshProxyStatus();
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [refreshProxyStatus]);
+
   return {
     proxyStatus,
     startProxy,
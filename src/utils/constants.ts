@@ -14,6 +14,9 @@ export const descriptionMap: Record<string, string> = {
   'claude-code': 'Anthropic 官方 CLI - AI 代码助手',
   codex: 'OpenAI 代码助手 - GPT-5 Codex',
   'gemini-cli': 'Google Gemini 命令行工具',
+  'qwen-code': '通义千问 Qwen 命令行代码助手',
+  opencode: '开源终端 AI 编程助手（多 Provider）',
+  crush: 'Charm 出品的终端 AI 编程助手',
 };
 
 // 工具组名映射
@@ -21,6 +24,9 @@ export const groupNameMap: Record<string, string> = {
   'claude-code': 'Claude Code 专用分组',
   codex: 'CodeX 专用分组',
   'gemini-cli': 'Gemini CLI 专用分组',
+  'qwen-code': 'Qwen Code 专用分组',
+  opencode: 'opencode 专用分组',
+  crush: 'Crush 专用分组',
 };
 
 // 获取工具显示名称
@@ -32,6 +38,12 @@ export function getToolDisplayName(toolId: string): string {
       return 'CodeX';
     case 'gemini-cli':
       return 'Gemini CLI';
+    case 'qwen-code':
+      return 'Qwen Code';
+    case 'opencode':
+      return 'opencode';
+    case 'crush':
+      return 'Crush';
     default:
       return toolId;
   }